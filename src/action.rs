@@ -0,0 +1,51 @@
+use std::path::PathBuf;
+
+use crate::atlas::WormSide;
+use crate::region::RegionFilter;
+use crate::roi::Roi;
+
+/// A change to the app state. The interface records what the user did as actions, which are
+/// applied together at the end of the frame; embedding apps and scripts queue their own with
+/// [`crate::MyApp::dispatch`].
+#[derive(Clone, Debug, PartialEq)]
+pub enum Action {
+    /// Replace the search text: name prefixes separated by spaces or commas, `*` for all.
+    SetFilter(String),
+    SetSide(WormSide),
+    /// Step to the next body side, as the side button does.
+    ToggleSide,
+    /// Add a neuron to the selection.
+    SelectNeuron(String),
+    /// Remove a neuron from the selection.
+    DeselectNeuron(String),
+    SetSelection(Vec<String>),
+    ClearSelection,
+    /// Replace the quick filters on body region, pharynx, pairing and functional type.
+    SetRegionFilter(RegionFilter),
+    /// Step from all neurons to the pharyngeal ones, to the others and back, as the pharynx
+    /// button does.
+    CyclePharyngeal,
+    /// Show the selected neurons alone, or again all those passing the filters.
+    SetSelectionOnly(bool),
+    /// Hide the neurons outside the region of interest, or show them again.
+    SetRoiCrop(bool),
+    /// Replace the region of interest and select the neurons inside it; an empty one clears
+    /// it and stops cropping.
+    SetRoi(Roi),
+    /// Move the keyboard focus to a neuron, centering the slices on it, or take it away.
+    SetFocus(Option<String>),
+    /// Move the slices through the focused neuron by this many slab thicknesses.
+    MoveSlices(f64),
+    /// Parse a dataset file in the background and add it to the loaded datasets. `path` is
+    /// where it came from on disk, if anywhere, so it can be watched.
+    LoadDataset {
+        name: String,
+        bytes: Vec<u8>,
+        path: Option<PathBuf>,
+    },
+    /// Read a neuron×time activity matrix for playback, replacing the one loaded.
+    LoadActivity {
+        name: String,
+        bytes: Vec<u8>,
+    },
+}
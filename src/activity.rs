@@ -0,0 +1,230 @@
+use std::cell::OnceCell;
+use std::collections::BTreeMap;
+
+use crate::colormap::{Colorbar, Colormap};
+use crate::dataset;
+use crate::hdf5;
+use crate::i18n::tr;
+use crate::mapping::{self, Delimiter};
+use crate::nwb;
+
+/// Activity of neurons over time, such as calcium ΔF/F, read from a neuron×time matrix.
+pub struct Activity {
+    pub name: String,
+    /// Time of each frame, from the header of the matrix or else the frame number.
+    pub times: Vec<f32>,
+    /// One value per frame for each neuron, NaN where it was not measured.
+    pub traces: BTreeMap<String, Vec<f32>>,
+    /// Smallest and largest value measured.
+    pub range: (f32, f32),
+    /// Correlation of each pair of traces, computed when first needed; see
+    /// [`Activity::correlated`].
+    correlations: OnceCell<Vec<f32>>,
+}
+
+impl Activity {
+    /// Read the traces of an NWB file, or else a table as [`Activity::from_bytes`] does.
+    pub fn read(name: &str, bytes: &[u8]) -> Result<Self, String> {
+        match hdf5::is_hdf5(bytes) {
+            true => nwb::read_activity(name, bytes),
+            false => Self::from_bytes(name, bytes),
+        }
+    }
+
+    /// Read a table with a row per neuron: its name and then its value at each frame. A first
+    /// row whose first field is empty or not a neuron name, such as `name` or `time`, is a
+    /// header giving the time of each frame.
+    pub fn from_bytes(name: &str, bytes: &[u8]) -> Result<Self, String> {
+        let delimiter = Delimiter::detect(bytes);
+        let prepared = delimiter.prepare(bytes);
+        let mut rows = dataset::table_reader(&*prepared, delimiter)
+            .into_records()
+            .filter_map(Result::ok)
+            .filter(|row| row.iter().any(|f| !f.trim().is_empty()))
+            .peekable();
+        let mut times = None;
+        if let Some(first) = rows.peek() {
+            let label = first.get(0).unwrap_or_default().trim().to_ascii_lowercase();
+            if ["", "name", "neuron", "time", "frame"].contains(&label.as_str()) {
+                times = Some(first.iter().skip(1).map(parse).collect::<Vec<_>>());
+                rows.next();
+            }
+        }
+        let mut traces = BTreeMap::new();
+        for row in rows {
+            let Some(neuron) = row.get(0).map(str::trim).filter(|n| !n.is_empty()) else {
+                continue;
+            };
+            traces.insert(neuron.to_owned(), row.iter().skip(1).map(parse).collect());
+        }
+        Self::new(name, times, traces)
+    }
+
+    /// Activity of the neurons `traces`, padded with NaN to the longest. `times` are used
+    /// where there is a finite time for every frame.
+    pub fn new(
+        name: &str,
+        times: Option<Vec<f32>>,
+        mut traces: BTreeMap<String, Vec<f32>>,
+    ) -> Result<Self, String> {
+        let frames = traces.values().map(Vec::len).max().unwrap_or(0);
+        if frames == 0 {
+            return Err(tr("no rows with a neuron name and its activity").to_owned());
+        }
+        for trace in traces.values_mut() {
+            trace.resize(frames, f32::NAN);
+        }
+        let times = match times {
+            Some(times)
+                if times.len() >= frames && times[..frames].iter().all(|t| t.is_finite()) =>
+            {
+                times[..frames].to_vec()
+            }
+            _ => (0..frames).map(|i| i as f32).collect(),
+        };
+        let range = (traces.values().flatten())
+            .filter(|v| v.is_finite())
+            .fold((f32::MAX, f32::MIN), |(lo, hi), &v| (lo.min(v), hi.max(v)));
+        if range.0 > range.1 {
+            return Err(tr("no numeric activity values").to_owned());
+        }
+        Ok(Self {
+            name: name.to_owned(),
+            times,
+            traces,
+            range,
+            correlations: OnceCell::new(),
+        })
+    }
+
+    pub fn frames(&self) -> usize {
+        self.times.len()
+    }
+
+    /// Activity of the neuron `name` at `frame`, if it was measured.
+    pub fn value(&self, name: &str, frame: usize) -> Option<f32> {
+        let value = *self.traces.get(name)?.get(frame)?;
+        value.is_finite().then_some(value)
+    }
+
+    pub fn colorbar(&self) -> Colorbar {
+        Colorbar {
+            label: tr("Activity").to_owned(),
+            min: self.range.0,
+            max: self.range.1,
+            colormap: Colormap::Viridis,
+        }
+    }
+
+    /// Pairs of neurons whose traces correlate at least `threshold` in magnitude, with their
+    /// Pearson correlation. Frames where a neuron was not measured count as its mean.
+    pub fn correlated(&self, threshold: f32) -> Vec<(&str, &str, f32)> {
+        let correlations = self.correlations.get_or_init(|| {
+            let standardized: Vec<Vec<f32>> =
+                self.traces.values().map(|t| standardize(t)).collect();
+            let n = standardized.len();
+            let mut correlations = Vec::with_capacity(n * n.saturating_sub(1) / 2);
+            for i in 0..n {
+                for j in i + 1..n {
+                    let dot: f32 = (standardized[i].iter().zip(&standardized[j]))
+                        .map(|(a, b)| a * b)
+                        .sum();
+                    correlations.push(dot / self.frames() as f32);
+                }
+            }
+            correlations
+        });
+        let names: Vec<&str> = self.traces.keys().map(String::as_str).collect();
+        let mut pairs = Vec::new();
+        let mut k = 0;
+        for i in 0..names.len() {
+            for j in i + 1..names.len() {
+                let r = correlations[k];
+                if r.abs() >= threshold {
+                    pairs.push((names[i], names[j], r));
+                }
+                k += 1;
+            }
+        }
+        pairs
+    }
+
+    /// Timeline scrubber setting `frame`, with the time it stands for.
+    pub fn timeline_ui(&self, ui: &mut egui::Ui, frame: &mut usize) -> egui::Response {
+        let last = self.frames() - 1;
+        *frame = (*frame).min(last);
+        ui.spacing_mut().slider_width = (ui.available_width() - 160.0).max(100.0);
+        let times = &self.times;
+        ui.add(
+            egui::Slider::new(frame, 0..=last)
+                .custom_formatter(|v, _| format!("{}", times[v as usize]))
+                .custom_parser(|s| Some(nearest_frame(times, s.trim().parse().ok()?) as f64))
+                .prefix("t = "),
+        )
+    }
+
+    /// Plot of the traces of the neurons `names` against time, with `frame` marked; a click
+    /// in the plot moves `frame` to the time clicked.
+    pub fn trace_plot(&self, ui: &mut egui::Ui, names: &[&str], frame: &mut usize) {
+        let response = egui_plot::Plot::new("activity_traces")
+            .height(180.0)
+            .legend(egui_plot::Legend::default())
+            .x_axis_label(tr("Time"))
+            .allow_scroll(false)
+            .show(ui, |plot_ui| {
+                for &name in names {
+                    let Some(trace) = self.traces.get(name) else {
+                        continue;
+                    };
+                    // Gaps where the neuron was not measured split the line.
+                    let points: Vec<[f64; 2]> = (self.times.iter().zip(trace))
+                        .map(|(&t, &v)| [t as f64, if v.is_finite() { v as f64 } else { f64::NAN }])
+                        .collect();
+                    plot_ui.line(egui_plot::Line::new(points).name(name));
+                }
+                if let Some(&t) = self.times.get(*frame) {
+                    let stroke =
+                        egui::Stroke::new(1.5, plot_ui.ctx().style().visuals.warn_fg_color);
+                    plot_ui.vline(egui_plot::VLine::new(t).stroke(stroke));
+                }
+                plot_ui
+                    .response()
+                    .clicked()
+                    .then(|| plot_ui.pointer_coordinate())
+                    .flatten()
+            });
+        if let Some(pointer) = response.inner {
+            *frame = nearest_frame(&self.times, pointer.x as f32);
+        }
+    }
+}
+
+/// Index of the time in the sorted `times` closest to `t`.
+fn nearest_frame(times: &[f32], t: f32) -> usize {
+    let i = times.partition_point(|&time| time < t);
+    if i > 0 && (i == times.len() || t - times[i - 1] < times[i] - t) {
+        i - 1
+    } else {
+        i
+    }
+}
+
+/// `trace` less its mean, over its standard deviation, with unmeasured frames at zero.
+fn standardize(trace: &[f32]) -> Vec<f32> {
+    let measured = || trace.iter().filter(|v| v.is_finite());
+    let n = measured().count().max(1) as f32;
+    let mean = measured().sum::<f32>() / n;
+    let sd = (measured().map(|v| (v - mean).powi(2)).sum::<f32>() / n).sqrt();
+    (trace.iter())
+        .map(|&v| match v.is_finite() && sd > 0.0 {
+            true => (v - mean) / sd,
+            false => 0.0,
+        })
+        .collect()
+}
+
+fn parse(field: &str) -> f32 {
+    mapping::decimal_point(field.trim())
+        .parse()
+        .unwrap_or(f32::NAN)
+}
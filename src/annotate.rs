@@ -0,0 +1,333 @@
+use egui::{Align2, Color32, Pos2, RichText};
+use egui_plot::{Line, PlotPoint, PlotPoints, PlotUi, Text};
+
+use crate::i18n::tr;
+use crate::style::Orientation;
+use crate::view::View;
+
+/// Distance in points within which a right click removes a callout.
+const HIT_DISTANCE: f32 = 8.0;
+
+/// Shape of a callout, in the plane of its view.
+#[derive(Clone, PartialEq, serde::Deserialize, serde::Serialize)]
+pub enum Mark {
+    Arrow {
+        from: [f64; 2],
+        to: [f64; 2],
+    },
+    /// A text box with its top left corner at `at`.
+    Text {
+        at: [f64; 2],
+        text: String,
+    },
+    Freehand(Vec<[f64; 2]>),
+}
+
+/// A callout drawn on one of the views. Positions are atlas coordinates of the view's
+/// plane, so that it stays with the neurons when the orientation of the views changes.
+#[derive(Clone, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct Annotation {
+    pub view: View,
+    pub mark: Mark,
+    pub color: Color32,
+    /// Width of lines, in points; text is four times as tall.
+    pub width: f32,
+}
+
+/// Kind of callout the annotation tool draws next.
+#[derive(Clone, Copy, Default, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub enum MarkKind {
+    #[default]
+    Arrow,
+    Text,
+    Freehand,
+}
+
+impl std::fmt::Display for MarkKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Arrow => write!(f, "\u{2197} {}", tr("Arrow")),
+            Self::Text => write!(f, "\u{1F5DB} {}", tr("Text")),
+            Self::Freehand => write!(f, "\u{270D} {}", tr("Freehand")),
+        }
+    }
+}
+
+/// The callouts of a figure, and how the annotation tool draws the next one.
+#[derive(Clone, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct Annotations {
+    pub items: Vec<Annotation>,
+    pub kind: MarkKind,
+    pub color: Color32,
+    pub width: f32,
+    /// Text of the next text box.
+    pub text: String,
+}
+
+impl Default for Annotations {
+    fn default() -> Self {
+        Self {
+            items: Vec::new(),
+            kind: MarkKind::Arrow,
+            color: Color32::from_rgb(255, 87, 34),
+            width: 2.0,
+            text: String::new(),
+        }
+    }
+}
+
+/// Change to the callouts made on a view during a frame.
+pub enum AnnotationEdit {
+    Add(Annotation),
+    /// Remove the callout at this index of [`Annotations::items`].
+    Remove(usize),
+}
+
+impl Annotation {
+    /// The mark in displayed plot coordinates of its view.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn displayed(&self, o: Orientation) -> Mark {
+        flip_mark(&self.mark, self.view, o)
+    }
+}
+
+impl Annotations {
+    pub fn apply(&mut self, edit: AnnotationEdit) {
+        match edit {
+            AnnotationEdit::Add(annotation) => self.items.push(annotation),
+            AnnotationEdit::Remove(index) if index < self.items.len() => {
+                self.items.remove(index);
+            }
+            AnnotationEdit::Remove(_) => {}
+        }
+    }
+
+    /// Options of the annotation tool, shown in the toolbar while it is active.
+    pub fn toolbar_ui(&mut self, ui: &mut egui::Ui) {
+        for kind in [MarkKind::Arrow, MarkKind::Text, MarkKind::Freehand] {
+            ui.selectable_value(&mut self.kind, kind, kind.to_string());
+        }
+        if self.kind == MarkKind::Text {
+            ui.add(
+                egui::TextEdit::singleline(&mut self.text)
+                    .hint_text(tr("Text, then click a plot"))
+                    .desired_width(120.0),
+            );
+        }
+        ui.color_edit_button_srgba(&mut self.color);
+        ui.add(
+            egui::DragValue::new(&mut self.width)
+                .range(0.5..=10.0)
+                .speed(0.1)
+                .max_decimals(1),
+        )
+        .on_hover_text(tr("Line width; text is four times as tall"));
+        if ui
+            .add_enabled(!self.items.is_empty(), egui::Button::new(tr("Undo")))
+            .clicked()
+        {
+            self.items.pop();
+        }
+        if ui
+            .add_enabled(!self.items.is_empty(), egui::Button::new(tr("Clear")))
+            .on_hover_text(tr("Remove the callouts of every view"))
+            .clicked()
+        {
+            self.items.clear();
+        }
+    }
+
+    /// Draw the callouts of `view`.
+    pub fn draw(&self, plot_ui: &mut PlotUi, view: View, o: Orientation) {
+        let background = plot_ui.ctx().style().visuals.extreme_bg_color;
+        for annotation in self.items.iter().filter(|a| a.view == view) {
+            let mark = flip_mark(&annotation.mark, view, o);
+            draw_mark(
+                plot_ui,
+                &mark,
+                annotation.color,
+                annotation.width,
+                background,
+            );
+        }
+    }
+
+    /// Index of the callout of `view` drawn at `pointer`, in screen coordinates.
+    fn hit(&self, plot_ui: &PlotUi, view: View, o: Orientation, pointer: Pos2) -> Option<usize> {
+        let screen = |p: [f64; 2]| plot_ui.screen_from_plot(PlotPoint::from(flip(p, view, o)));
+        let near_line = |points: &[[f64; 2]]| {
+            let points: Vec<Pos2> = points.iter().map(|p| screen(*p)).collect();
+            (points.windows(2)).any(|w| segment_distance(pointer, w[0], w[1]) < HIT_DISTANCE)
+        };
+        (self.items.iter().enumerate().rev())
+            .filter(|(_, a)| a.view == view)
+            .find(|(_, a)| match &a.mark {
+                Mark::Arrow { from, to } => near_line(&[*from, *to]),
+                Mark::Freehand(points) => near_line(points),
+                Mark::Text { at, text } => {
+                    // Roughly the box of the text, below and right of its corner.
+                    let corner = screen(*at);
+                    let height = 4.0 * a.width;
+                    let width = 0.6 * height * text.chars().count() as f32;
+                    egui::Rect::from_min_size(corner, egui::vec2(width, height))
+                        .expand(HIT_DISTANCE / 2.0)
+                        .contains(pointer)
+                }
+            })
+            .map(|(index, _)| index)
+    }
+}
+
+/// Draw callouts with the annotation tool: drag for an arrow or a freehand line, click to
+/// place a text box. A right click removes the callout under the pointer.
+pub fn track(
+    plot_ui: &mut PlotUi,
+    view: View,
+    o: Orientation,
+    annotations: &Annotations,
+) -> Option<AnnotationEdit> {
+    let response = plot_ui.response().clone();
+    let id = response.id.with("annotation");
+    let mut stroke: Vec<[f64; 2]> = plot_ui.ctx().data(|d| d.get_temp(id)).unwrap_or_default();
+    let new = |mark| {
+        AnnotationEdit::Add(Annotation {
+            view,
+            mark: flip_mark(&mark, view, o),
+            color: annotations.color,
+            width: annotations.width,
+        })
+    };
+
+    let mut edit = None;
+    if response.secondary_clicked() {
+        let pointer = response.interact_pointer_pos();
+        edit = (pointer.and_then(|p| annotations.hit(plot_ui, view, o, p)))
+            .map(AnnotationEdit::Remove);
+    }
+    if let Some(p) = plot_ui.pointer_coordinate() {
+        let p = [p.x, p.y];
+        match annotations.kind {
+            MarkKind::Text if response.clicked() && !annotations.text.trim().is_empty() => {
+                let text = annotations.text.trim().to_owned();
+                edit = Some(new(Mark::Text { at: p, text }));
+            }
+            MarkKind::Arrow | MarkKind::Freehand if response.drag_started() => stroke = vec![p],
+            MarkKind::Arrow if response.dragged() => stroke.truncate(1),
+            _ => {}
+        }
+        if response.dragged() && !stroke.is_empty() && stroke.last() != Some(&p) {
+            stroke.push(p);
+        }
+    }
+    if response.drag_stopped() && stroke.len() > 1 {
+        let mark = match annotations.kind {
+            MarkKind::Arrow => Mark::Arrow {
+                from: stroke[0],
+                to: stroke[stroke.len() - 1],
+            },
+            _ => Mark::Freehand(std::mem::take(&mut stroke)),
+        };
+        edit = Some(new(mark));
+    }
+    if !response.dragged() {
+        stroke.clear();
+    }
+    // The callout being drawn, as it will look.
+    let preview = match annotations.kind {
+        MarkKind::Arrow if stroke.len() > 1 => Some(Mark::Arrow {
+            from: stroke[0],
+            to: stroke[stroke.len() - 1],
+        }),
+        MarkKind::Freehand if stroke.len() > 1 => Some(Mark::Freehand(stroke.clone())),
+        _ => None,
+    };
+    if let Some(mark) = preview {
+        let background = plot_ui.ctx().style().visuals.extreme_bg_color;
+        draw_mark(
+            plot_ui,
+            &mark,
+            annotations.color,
+            annotations.width,
+            background,
+        );
+    }
+    plot_ui.ctx().data_mut(|d| d.insert_temp(id, stroke));
+    edit
+}
+
+/// Convert between displayed plot coordinates of `view` and atlas coordinates of its
+/// plane; the orientation only flips axes, so the conversion is its own inverse.
+fn flip(p: [f64; 2], view: View, o: Orientation) -> [f64; 2] {
+    let mut atlas = [0.0; 3];
+    for (axis, value) in view.unproject(p) {
+        atlas[axis] = value;
+    }
+    view.project(o.apply(atlas))
+}
+
+fn flip_mark(mark: &Mark, view: View, o: Orientation) -> Mark {
+    match mark {
+        Mark::Arrow { from, to } => Mark::Arrow {
+            from: flip(*from, view, o),
+            to: flip(*to, view, o),
+        },
+        Mark::Text { at, text } => Mark::Text {
+            at: flip(*at, view, o),
+            text: text.clone(),
+        },
+        Mark::Freehand(points) => {
+            Mark::Freehand(points.iter().map(|p| flip(*p, view, o)).collect())
+        }
+    }
+}
+
+/// Draw `mark`, given in displayed plot coordinates.
+fn draw_mark(plot_ui: &mut PlotUi, mark: &Mark, color: Color32, width: f32, background: Color32) {
+    match mark {
+        Mark::Arrow { from, to } => {
+            // The head keeps its size on screen whatever the zoom.
+            let (tail, tip) = (
+                plot_ui.screen_from_plot(PlotPoint::from(*from)),
+                plot_ui.screen_from_plot(PlotPoint::from(*to)),
+            );
+            let back = (tail - tip).normalized() * (4.0 + 3.0 * width);
+            let barbs = [
+                egui::emath::Rot2::from_angle(0.45),
+                egui::emath::Rot2::from_angle(-0.45),
+            ]
+            .map(|rot| {
+                let p = plot_ui.plot_from_screen(tip + rot * back);
+                [p.x, p.y]
+            });
+            let head = vec![barbs[0], *to, barbs[1]];
+            plot_ui.line(
+                Line::new(PlotPoints::new(vec![*from, *to]))
+                    .color(color)
+                    .width(width),
+            );
+            plot_ui.line(Line::new(PlotPoints::new(head)).color(color).width(width));
+        }
+        Mark::Text { at, text } => {
+            let text = RichText::new(text)
+                .size(4.0 * width)
+                .color(color)
+                .background_color(background.gamma_multiply(0.8));
+            plot_ui.text(Text::new(PlotPoint::from(*at), text).anchor(Align2::LEFT_TOP));
+        }
+        Mark::Freehand(points) => {
+            plot_ui.line(
+                Line::new(PlotPoints::new(points.clone()))
+                    .color(color)
+                    .width(width),
+            );
+        }
+    }
+}
+
+/// Distance from `p` to the segment from `a` to `b`.
+fn segment_distance(p: Pos2, a: Pos2, b: Pos2) -> f32 {
+    let ab = b - a;
+    let t = ((p - a).dot(ab) / ab.length_sq().max(f32::EPSILON)).clamp(0.0, 1.0);
+    p.distance(a + t * ab)
+}
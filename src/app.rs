@@ -1,48 +1,102 @@
-use csv::{self, StringRecord};
 use egui::{
-    pos2, Align2, Button, Color32, FontId, NumExt as _, Rect, RichText, ScrollArea, Sense, Theme,
+    pos2, Align2, Button, Color32, Key, KeyboardShortcut, Modifiers, NumExt as _, Rect, RichText,
+    ScrollArea, Sense,
+};
+use egui_plot::{
+    Arrows, HLine, Line, PlotBounds, PlotPoint, PlotPoints, PlotUi, Points, Polygon, Text, VLine,
 };
-use egui_plot::{HLine, PlotPoints, Points, Text, VLine};
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::sync::mpsc;
 
-static NEUROPAL_ORG: &[u8] = include_bytes!("neuropal.csv");
-static NEUROPAL_HEADER: [&str; 7] = ["name", "x", "y", "z", "r", "g", "b"];
-#[derive(serde::Deserialize)]
-struct Neuron {
-    name: String,
-    x: f32,
-    y: f32,
-    z: f32,
-    r: f32,
-    g: f32,
-    b: f32,
-}
+use crate::action::Action;
+use crate::activity::Activity;
+use crate::annotate::{self, AnnotationEdit, Annotations};
+use crate::atlas::{Atlas, AtlasQuery, WormSide};
+use crate::batch::{self, GeometryCache};
+use crate::builder::AppBuilder;
+use crate::bundle;
+use crate::cluster::{self, ClusterSettings, Clustering};
+use crate::colormap::{Colorbar, Colormap};
+use crate::completion;
+use crate::dataset::{self, Dataset, DisplacementStats, Neuron};
+use crate::density::{DensityContours, DensitySettings, DENSITY_COLOR};
+use crate::edit::{self, AtlasEditor, ChangeKind, EDIT_COLOR};
+use crate::events::{AppEvent, EventHooks, FilterState};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::figure::{self, FigureContent, FigureFormat, FigureSettings, Legend};
+use crate::frame::{CoordinateFrame, FrameTransform};
+use crate::functional::{self, FunctionalType};
+use crate::ganglion;
+use crate::hdf5;
+use crate::heatmap::Heatmap;
+use crate::hull;
+use crate::i18n::{self, tr};
+use crate::index::NameIndex;
+use crate::loader::{self, Loading, PendingFile};
+use crate::mapping::{self, ColumnMapping, Delimiter, PendingMapping, TableFormat};
+use crate::metadata::{self, ColumnColoring, Metadata};
+use crate::midline::{self, Midline};
+use crate::outlier::{self, Outlier};
+use crate::pca::{self, PrincipalAxes};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::pdf;
+use crate::playback::{Playback, Seek, SWEEP_STEPS};
+use crate::prefs::Preferences;
+use crate::profile;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::raster;
+use crate::region::{self, BodyRegion, Region, RegionFilter};
+use crate::roi::{Roi, ROI_COLOR};
+use crate::selection::{self, ListComparison, Membership, NameListMatch};
+use crate::style::{self, Orientation, PlotStyle};
+use crate::symmetry::{self, SymmetryColumn, ASYMMETRY_COLOR};
+use crate::toast;
+use crate::tools::{self, CanvasTool};
+use crate::touch;
+use crate::tour::{self, Tour};
+use crate::variability::{self, NeuronStats};
+use crate::video::{Recording, VideoScope, VideoSettings};
+use crate::view::{self, Panel, View, ViewLayout, WindowGeometry};
+#[cfg(target_arch = "wasm32")]
+use crate::web;
 
-impl Neuron {
-    pub fn rgb(&self) -> [u8; 3] {
-        [
-            (self.r * 255.).clamp(0., 255.) as u8,
-            (self.g * 255.).clamp(0., 255.) as u8,
-            (self.b * 255.).clamp(0., 255.) as u8,
-        ]
-    }
-    pub fn luminance(&self) -> f32 {
-        0.2126 * self.r + 0.7152 * self.g + 0.0722 * self.b
-    }
-}
+const ZOOM_IN_SHORTCUTS: [KeyboardShortcut; 2] = [
+    egui::gui_zoom::kb_shortcuts::ZOOM_IN,
+    egui::gui_zoom::kb_shortcuts::ZOOM_IN_SECONDARY,
+];
+const ZOOM_OUT_SHORTCUT: KeyboardShortcut = egui::gui_zoom::kb_shortcuts::ZOOM_OUT;
+const RESET_ALL_SHORTCUT: KeyboardShortcut = KeyboardShortcut::new(Modifiers::COMMAND, Key::Num0);
+const PHARYNX_SHORTCUT: KeyboardShortcut =
+    KeyboardShortcut::new(Modifiers::COMMAND.plus(Modifiers::SHIFT), Key::P);
+const UNDO_SHORTCUT: KeyboardShortcut = KeyboardShortcut::new(Modifiers::COMMAND, Key::Z);
+const REDO_SHORTCUTS: [KeyboardShortcut; 2] = [
+    KeyboardShortcut::new(Modifiers::COMMAND.plus(Modifiers::SHIFT), Key::Z),
+    KeyboardShortcut::new(Modifiers::COMMAND, Key::Y),
+];
+/// Reset a single view, in the order of [`View::ALL`].
+const RESET_VIEW_SHORTCUTS: [KeyboardShortcut; 3] = [
+    KeyboardShortcut::new(Modifiers::COMMAND, Key::Num1),
+    KeyboardShortcut::new(Modifiers::COMMAND, Key::Num2),
+    KeyboardShortcut::new(Modifiers::COMMAND, Key::Num3),
+];
+/// Maximize a single view, in the order of [`View::ALL`].
+const MAXIMIZE_VIEW_SHORTCUTS: [KeyboardShortcut; 3] = [
+    KeyboardShortcut::new(Modifiers::ALT, Key::Num1),
+    KeyboardShortcut::new(Modifiers::ALT, Key::Num2),
+    KeyboardShortcut::new(Modifiers::ALT, Key::Num3),
+];
+/// How long a list row flashes after its neuron is clicked in a plot.
+const FLASH_SECONDS: f64 = 1.2;
+/// Pause in typing after which the neurons a search would show are outlined.
+const SEARCH_DEBOUNCE: f64 = 0.25;
+/// Characters of a metadata value shown in the neuron list.
+const LIST_COLUMN_CHARS: usize = 12;
 
 #[inline]
 fn l2_dist(x1: f64, x2: f64, y1: f64, y2: f64) -> f64 {
     ((x1 - x2).powi(2) + (y1 - y2).powi(2)).sqrt()
 }
-#[derive(serde::Deserialize, serde::Serialize)]
-enum WormSide {
-    Left,
-    Right,
-    Both,
-}
-
 impl WormSide {
     fn next(&self) -> Self {
         match self {
@@ -60,16 +114,91 @@ impl WormSide {
     }
 }
 
-impl std::fmt::Display for WormSide {
+/// How the markers in the plots are colored.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, serde::Deserialize, serde::Serialize)]
+enum ColorMode {
+    NeuroPAL,
+    ListMembership,
+    /// Left–right position mapped onto a colormap.
+    Depth,
+    /// Labels of the last clustering.
+    Cluster,
+    /// A numeric metadata column mapped onto a colormap.
+    Column,
+    /// Activity at the current frame of the playback.
+    Activity,
+    /// Sensory, inter-, motor or polymodal neuron.
+    Function,
+}
+
+impl ColorMode {
+    const ALL: [Self; 7] = [
+        Self::NeuroPAL,
+        Self::ListMembership,
+        Self::Depth,
+        Self::Cluster,
+        Self::Column,
+        Self::Activity,
+        Self::Function,
+    ];
+}
+
+impl std::fmt::Display for ColorMode {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Self::Left => write!(f, "Left"),
-            Self::Right => write!(f, "Right"),
-            Self::Both => write!(f, "Both"),
+            Self::NeuroPAL => write!(f, "NeuroPAL"),
+            Self::ListMembership => f.write_str(tr("List A/B")),
+            Self::Depth => f.write_str(tr("Depth (z)")),
+            Self::Cluster => f.write_str(tr("Clusters")),
+            Self::Column => f.write_str(tr("Metadata column")),
+            Self::Activity => f.write_str(tr("Activity")),
+            Self::Function => f.write_str(tr("Functional type")),
+        }
+    }
+}
+
+/// A named combination of the display settings, saved from the current ones and switched
+/// from the toolbar.
+#[derive(Clone, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+struct StylePreset {
+    name: String,
+    color_mode: ColorMode,
+    column_coloring: ColumnColoring,
+    /// See [`Preferences::depth_cue`].
+    depth_cue: f32,
+    point_size: f32,
+    outline_width: f32,
+    plot_styles: [PlotStyle; 3],
+    orientation: Orientation,
+    show_ganglia: bool,
+    show_principal_axes: bool,
+}
+
+impl Default for StylePreset {
+    fn default() -> Self {
+        let prefs = Preferences::default();
+        Self {
+            name: String::new(),
+            color_mode: ColorMode::NeuroPAL,
+            column_coloring: ColumnColoring::default(),
+            depth_cue: prefs.depth_cue,
+            point_size: prefs.point_size,
+            outline_width: prefs.outline_width,
+            plot_styles: Default::default(),
+            orientation: Orientation::default(),
+            show_ganglia: false,
+            show_principal_axes: false,
         }
     }
 }
 
+/// The encoded color as is; dim neurons stay visible through the marker outline.
+fn neuropal_color(neuron: &Neuron) -> Color32 {
+    let [r, g, b] = neuron.rgb();
+    egui::Color32::from_rgb(r, g, b)
+}
+
 /// We derive Deserialize/Serialize so we can persist app state on shutdown.
 #[derive(serde::Deserialize, serde::Serialize)]
 #[serde(default)] // if we add new fields, give them default values when deserializing old state
@@ -78,107 +207,955 @@ pub struct MyApp {
 
     #[serde(skip)] // This how you opt-out of serialization of a field
     data: HashMap<String, Neuron>,
+    /// Sorted names of `data`, for the search box.
+    #[serde(skip)]
+    index: NameIndex,
 
     show_side_panel: bool,
     view_side: WormSide,
+
+    /// Neurons highlighted in the plots and the list.
+    selection: BTreeSet<String>,
+    /// Restrict the plots and the list to the current selection.
+    selection_only: bool,
+    show_selection_window: bool,
+    /// Text buffer of the "Selection" window, filled by pasting or dropping a file.
+    name_list: String,
+    #[serde(skip)]
+    name_list_match: Option<NameListMatch>,
+
+    color_mode: ColorMode,
+    /// Metadata column shown after the positions in the neuron list.
+    list_column: Option<String>,
+    column_coloring: ColumnColoring,
+    /// Quick filters on the body region and the pharyngeal nervous system.
+    region_filter: RegionFilter,
+    show_compare_window: bool,
+    comparison: ListComparison,
+    show_cluster_window: bool,
+    cluster_settings: ClusterSettings,
+    /// Cluster labels of the last run, kept across sessions like the selection.
+    clustering: Clustering,
+
+    /// Annotated animals loaded by the user; index 0 of a source selector is the atlas itself.
+    #[serde(skip)]
+    datasets: Vec<Dataset>,
+    #[serde(skip)]
+    load_error: Option<String>,
+    /// Files being parsed in the background.
+    #[serde(skip)]
+    loading: Vec<Loading>,
+    /// Files still on their way, such as one picked in the browser's file dialog.
+    #[serde(skip)]
+    pending_files: Vec<PendingFile>,
+    /// A file whose columns are being assigned in the mapping dialog.
+    #[serde(skip)]
+    pending_mapping: Option<PendingMapping>,
+    /// Activity matrix being played back, and the frame shown.
+    #[serde(skip)]
+    activity: Option<Activity>,
+    #[serde(skip)]
+    activity_frame: usize,
+    /// Neuron whose trace is shown: the one last hovered or focused.
+    #[serde(skip)]
+    trace_neuron: Option<String>,
+    /// Neurons whose traces stay in the plot.
+    #[serde(skip)]
+    trace_pins: BTreeSet<String>,
+    show_trace_window: bool,
+    playback: Playback,
+    /// Step of the slice sweep, while the slices are swept through the shown neurons.
+    #[serde(skip)]
+    sweep: Option<usize>,
+    #[serde(skip)]
+    heatmap: Heatmap,
+    show_heatmap_window: bool,
+    /// Draw edges between neurons whose activity correlates at least `correlation_threshold`
+    /// in magnitude.
+    show_correlations: bool,
+    correlation_threshold: f32,
+    /// How the activity playback is exported as a video.
+    video: VideoSettings,
+    show_video_window: bool,
+    /// Layout and size of the composed figure of all views.
+    #[cfg(not(target_arch = "wasm32"))]
+    figure: FigureSettings,
+    #[cfg(not(target_arch = "wasm32"))]
+    show_figure_window: bool,
+    /// Video being exported, a frame of the activity at a time.
+    #[serde(skip)]
+    recording: Option<Recording>,
+    /// Part of the window the video records, where it was last drawn.
+    #[serde(skip)]
+    video_rect: Option<egui::Rect>,
+    /// Formats of tables whose columns were mapped, to reuse by [`mapping::file_pattern`].
+    table_formats: BTreeMap<String, TableFormat>,
+    /// Delimiter of the tables opened, detected in each file for `None`.
+    import_delimiter: Option<Delimiter>,
+    /// Time of the last check of watched dataset files.
+    #[serde(skip)]
+    last_watch_check: f64,
+    /// Hash of the datasets last written to IndexedDB, to skip unchanged writes.
+    #[cfg(target_arch = "wasm32")]
+    #[serde(skip)]
+    stored_session: Option<u64>,
+    /// The previous session, on its way from IndexedDB.
+    #[cfg(target_arch = "wasm32")]
+    #[serde(skip)]
+    restored_session: Option<mpsc::Receiver<String>>,
+    show_dataset_window: bool,
+    show_displacement: bool,
+    displacement_from: usize,
+    displacement_to: usize,
+
+    /// Statistical atlas built from all loaded datasets.
+    #[serde(skip)]
+    variability: Vec<NeuronStats>,
+    show_spread: bool,
+    /// Outline the ganglia in the lateral and anterior views.
+    show_ganglia: bool,
+    /// Outline the selected neurons in every view and report the areas enclosed.
+    show_selection_hull: bool,
+    /// Draw the principal axes of the neurons shown.
+    show_principal_axes: bool,
+    density: DensitySettings,
+    #[serde(skip)]
+    density_contours: DensityContours,
+
+    /// Problems found in the loaded dataset files.
+    show_warnings_window: bool,
+
+    show_outlier_window: bool,
+    /// Source indices (see [`MyApp::source`]) of the reference and the reviewed dataset.
+    outlier_reference: usize,
+    outlier_dataset: usize,
+    /// Z-score above which a neuron is flagged.
+    outlier_threshold: f32,
+    #[serde(skip)]
+    outliers: Vec<Outlier>,
+
+    show_symmetry_window: bool,
+    /// Atlas or dataset whose left/right pairs are compared, as in [`MyApp::source`].
+    symmetry_source: usize,
+    /// Column the pairs are sorted by and whether in ascending order.
+    symmetry_sort: (SymmetryColumn, bool),
+    /// Number of most asymmetric pairs marked on the canvas while the report is open.
+    symmetry_highlight: usize,
+
+    /// Styling of the xy, zy and xz plots.
+    plot_styles: [PlotStyle; 3],
+    show_style_window: bool,
+    #[serde(skip)]
+    style_view: usize,
+    orientation: Orientation,
+    style_presets: Vec<StylePreset>,
+    /// Index into `style_presets` of the preset applied last.
+    style_preset: Option<usize>,
+
+    /// Dataset whose midline is being edited on the xy canvas.
+    #[serde(skip)]
+    midline_edit: Option<usize>,
+    tool: CanvasTool,
+    #[serde(skip)]
+    editor: AtlasEditor,
+    show_diff_window: bool,
+    /// Draw arrows from the original to the edited positions while the changes are reviewed.
+    show_edit_arrows: bool,
+    roi: Roi,
+    /// Hide everything outside the ROI and fit the views to it.
+    roi_crop: bool,
+    #[serde(skip)]
+    roi_crop_shown: bool,
+    /// Arrows, text boxes and freehand lines drawn on the views as figure callouts.
+    annotations: Annotations,
+    /// Views to restore to their default bounds on the next frame.
+    #[serde(skip)]
+    reset_views: [bool; 3],
+    /// Placement of the floating view windows, indexed by [`View::index`].
+    window_geometry: [Option<WindowGeometry>; 3],
+    layout: ViewLayout,
+    /// A single view temporarily filling the central area.
+    #[serde(skip)]
+    maximized: Option<View>,
+    /// Show the orthogonal views in their own OS window.
+    pop_out_views: bool,
+    /// Show the neuron list in its own OS window instead of the side panel.
+    pop_out_table: bool,
+
+    #[serde(skip)]
+    geometry: GeometryCache,
+    /// Pointer position over a plot in the last frame, read out in the status bar.
+    #[serde(skip)]
+    cursor: Option<(View, [f64; 2])>,
+    /// Neuron of the list focused with the arrow keys.
+    #[serde(skip)]
+    focused: Option<String>,
+    /// Shift of the slices through the focused neuron along the anterior–posterior axis.
+    #[serde(skip)]
+    slice_offset: f64,
+    /// Neuron under the pointer in the last frame, highlighted in every view and the list,
+    /// and the view it was hovered in (`None` for a row of the list).
+    #[serde(skip)]
+    hovered: Option<(String, Option<View>)>,
+    /// How many times the hover label of the orthogonal views was cycled past the nearest
+    /// neuron.
+    #[serde(skip)]
+    hover_cycle: usize,
+    /// Row of the list flashed after its neuron was clicked in a plot, with the time the
+    /// flash started, and whether the list still has to scroll to it.
+    #[serde(skip)]
+    flash: Option<(String, f64, bool)>,
+    /// Search typed but not applied yet, and the time of the last keystroke.
+    #[serde(skip)]
+    search_draft: Option<(String, f64)>,
+    show_profiler: bool,
+    tour: Tour,
+    prefs: Preferences,
+    show_prefs_window: bool,
+
+    /// Whether the state is restored at startup and saved; see [`AppBuilder::persistence`].
+    #[serde(skip)]
+    persistence: bool,
+    /// Datasets cannot be opened or removed; see [`AppBuilder::read_only`].
+    #[serde(skip)]
+    read_only: bool,
+    /// Japanese fonts may be added to the host's when embedded; see
+    /// [`crate::NeuropalLensWidget::load_fonts`].
+    #[serde(skip)]
+    host_fonts: bool,
+    #[serde(skip)]
+    hooks: EventHooks,
+    /// Actions of this frame, applied at its end.
+    #[serde(skip)]
+    actions: Vec<Action>,
+    /// Context the app is drawn in, known from the first frame, to wake it for actions
+    /// dispatched by the host.
+    #[serde(skip)]
+    ctx: Option<egui::Context>,
+
+    /// Workspace tabs; the state of the active one lives in the fields above.
+    workspaces: Vec<Workspace>,
+    active_workspace: usize,
 }
 
 impl Default for MyApp {
     fn default() -> Self {
-        let header = StringRecord::from(NEUROPAL_HEADER.to_vec());
-
-        let data = csv::ReaderBuilder::new()
-            .delimiter(b',')
-            .from_reader(NEUROPAL_ORG)
-            .records()
-            .filter_map(|x| x.ok())
-            .filter_map(|r| r.deserialize::<Neuron>(Some(&header)).ok())
-            .map(|x| (x.name.to_owned(), x))
-            .collect();
+        let data = Atlas::neuropal().into_neurons();
+        let index = NameIndex::new(data.keys());
 
         Self {
             // Example stuff:
             label: "*".to_owned(),
             data,
+            index,
             show_side_panel: true,
             view_side: WormSide::Both,
+            selection: BTreeSet::new(),
+            selection_only: false,
+            show_selection_window: false,
+            name_list: String::new(),
+            name_list_match: None,
+            color_mode: ColorMode::NeuroPAL,
+            list_column: None,
+            column_coloring: ColumnColoring::default(),
+            region_filter: RegionFilter::default(),
+            show_compare_window: false,
+            comparison: ListComparison::default(),
+            show_cluster_window: false,
+            cluster_settings: ClusterSettings::default(),
+            clustering: Clustering::default(),
+            datasets: Vec::new(),
+            load_error: None,
+            loading: Vec::new(),
+            pending_files: Vec::new(),
+            pending_mapping: None,
+            activity: None,
+            activity_frame: 0,
+            trace_neuron: None,
+            trace_pins: BTreeSet::new(),
+            show_trace_window: false,
+            playback: Playback::default(),
+            sweep: None,
+            heatmap: Heatmap::default(),
+            show_heatmap_window: false,
+            show_correlations: false,
+            correlation_threshold: 0.7,
+            video: VideoSettings::default(),
+            show_video_window: false,
+            #[cfg(not(target_arch = "wasm32"))]
+            figure: FigureSettings::default(),
+            #[cfg(not(target_arch = "wasm32"))]
+            show_figure_window: false,
+            recording: None,
+            video_rect: None,
+            table_formats: BTreeMap::new(),
+            import_delimiter: None,
+            last_watch_check: 0.0,
+            #[cfg(target_arch = "wasm32")]
+            stored_session: None,
+            #[cfg(target_arch = "wasm32")]
+            restored_session: None,
+            show_dataset_window: false,
+            show_displacement: false,
+            displacement_from: 0,
+            displacement_to: 1,
+            variability: Vec::new(),
+            show_spread: true,
+            show_ganglia: false,
+            show_selection_hull: false,
+            show_principal_axes: false,
+            density: DensitySettings::default(),
+            density_contours: DensityContours::default(),
+            show_warnings_window: false,
+            show_outlier_window: false,
+            outlier_reference: 0,
+            outlier_dataset: 1,
+            outlier_threshold: 3.0,
+            outliers: Vec::new(),
+            show_symmetry_window: false,
+            symmetry_source: 0,
+            symmetry_sort: (SymmetryColumn::Position, false),
+            symmetry_highlight: 10,
+            plot_styles: Default::default(),
+            show_style_window: false,
+            style_view: 0,
+            orientation: Orientation::default(),
+            style_presets: Vec::new(),
+            style_preset: None,
+            midline_edit: None,
+            tool: CanvasTool::Navigate,
+            editor: AtlasEditor::default(),
+            show_diff_window: false,
+            show_edit_arrows: true,
+            roi: Roi::default(),
+            roi_crop: false,
+            roi_crop_shown: false,
+            annotations: Annotations::default(),
+            reset_views: [false; 3],
+            window_geometry: [None; 3],
+            layout: ViewLayout::default(),
+            maximized: None,
+            pop_out_views: false,
+            pop_out_table: false,
+            geometry: GeometryCache::default(),
+            cursor: None,
+            focused: None,
+            slice_offset: 0.0,
+            hovered: None,
+            hover_cycle: 0,
+            flash: None,
+            search_draft: None,
+            show_profiler: false,
+            tour: Tour::default(),
+            prefs: Preferences::default(),
+            show_prefs_window: false,
+            persistence: true,
+            read_only: false,
+            host_fonts: false,
+            hooks: EventHooks::default(),
+            actions: Vec::new(),
+            ctx: None,
+            workspaces: vec![Workspace::new("Workspace 1".to_owned())],
+            active_workspace: 0,
         }
     }
 }
 
 impl MyApp {
-    /// Called once before the first frame.
+    /// Called once before the first frame. See [`AppBuilder`] to configure the app.
     pub fn new(cc: &eframe::CreationContext<'_>) -> Self {
+        AppBuilder::new().build(cc)
+    }
+
+    pub(crate) fn from_builder(cc: &eframe::CreationContext<'_>, builder: AppBuilder) -> Self {
         // This is also where you can customize the look and feel of egui using
         // `cc.egui_ctx.set_visuals` and `cc.egui_ctx.set_fonts`.
 
+        // The interface scale is a preference with its own shortcuts, and Ctrl+0 resets the
+        // views rather than the zoom.
+        cc.egui_ctx.options_mut(|o| o.zoom_with_keyboard = false);
+        if let Some(theme) = builder.theme {
+            cc.egui_ctx.set_theme(theme);
+        }
+
         // Load previous app state (if any).
         // Note that you must enable the `persistence` feature for this to work.
-        if let Some(storage) = cc.storage {
-            return eframe::get_value(storage, eframe::APP_KEY).unwrap_or_default();
+        let storage = cc.storage.filter(|_| builder.persistence);
+        let mut app: Self = match storage {
+            Some(storage) => {
+                let app: Self = eframe::get_value(storage, eframe::APP_KEY).unwrap_or_default();
+                app.prefs.apply_scale(&cc.egui_ctx);
+                app
+            }
+            None => Default::default(),
+        };
+        app.persistence = builder.persistence;
+        app.read_only = builder.read_only;
+        app.ctx = Some(cc.egui_ctx.clone());
+        if let Some(atlas) = builder.atlas {
+            app.data = atlas.into_neurons();
+            app.index = NameIndex::new(app.data.keys());
+        }
+        if let Some(search) = builder.search {
+            app.label = search;
+        }
+        if let Some(side) = builder.side {
+            app.view_side = side;
+        }
+        for callback in builder.callbacks {
+            app.hooks.register(callback);
+        }
+        app.comparison.update(&app.data);
+
+        #[cfg(target_arch = "wasm32")]
+        if app.persistence {
+            app.restored_session = Some(web::load_session(&cc.egui_ctx));
+        }
+
+        // Links like `index.html?data=https://example.org/animal.csv` open remote datasets.
+        #[cfg(target_arch = "wasm32")]
+        if let Some(urls) = cc.integration_info.web_info.location.query_map.get("data") {
+            for url in urls {
+                app.pending_files
+                    .push(web::fetch(&cc.egui_ctx, url.clone()));
+            }
+        }
+
+        app
+    }
+
+    /// Call `callback` whenever the hovered neuron, the selection or the filters change, for
+    /// apps embedding the viewer. Events are collected at the end of each frame.
+    pub fn on_event(&mut self, callback: impl FnMut(&AppEvent) + 'static) {
+        self.hooks.register(Box::new(callback));
+    }
+
+    /// Queue `action` to be applied at the end of the next frame, as if the user had done it,
+    /// and ask for that frame to be drawn.
+    pub fn dispatch(&mut self, action: Action) {
+        self.actions.push(action);
+        if let Some(ctx) = &self.ctx {
+            ctx.request_repaint();
+        }
+    }
+
+    pub(crate) fn set_host_fonts(&mut self, load: bool) {
+        self.host_fonts = load;
+    }
+
+    fn apply(&mut self, ctx: &egui::Context, action: Action) {
+        if !matches!(action, Action::SetFocus(_) | Action::MoveSlices(_)) {
+            self.geometry.invalidate();
+        }
+        match action {
+            Action::SetFilter(search) => self.label = search,
+            Action::SetSide(side) => self.view_side = side,
+            Action::ToggleSide => self.view_side = self.view_side.next(),
+            Action::SelectNeuron(name) => {
+                self.selection.insert(name);
+            }
+            Action::DeselectNeuron(name) => {
+                self.selection.remove(&name);
+            }
+            Action::SetSelection(names) => self.selection = names.into_iter().collect(),
+            Action::ClearSelection => self.selection.clear(),
+            Action::SetRegionFilter(filter) => self.region_filter = filter,
+            Action::CyclePharyngeal => self.region_filter.cycle_pharyngeal(),
+            Action::SetSelectionOnly(on) => self.selection_only = on,
+            Action::SetRoiCrop(on) => self.roi_crop = on,
+            Action::SetRoi(roi) => {
+                self.roi = roi;
+                if self.roi.is_empty() {
+                    self.roi_crop = false;
+                } else {
+                    self.selection = (self.data.values())
+                        .filter(|n| self.roi.contains(n.position()))
+                        .map(|n| n.name.clone())
+                        .collect();
+                }
+            }
+            Action::SetFocus(name) => {
+                self.focused = name;
+                self.slice_offset = 0.0;
+            }
+            Action::MoveSlices(steps) => self.slice_offset += steps * self.prefs.slab_thickness,
+            Action::LoadDataset { name, bytes, path } => self.open_file(ctx, name, bytes, path),
+            Action::LoadActivity { name, bytes } => self.load_activity(&name, &bytes),
         }
+    }
 
-        Default::default()
+    fn fire_events(&mut self, hovered: Option<String>) {
+        if self.hooks.is_empty() {
+            return;
+        }
+        let filter = FilterState {
+            search: self.label.clone(),
+            side: self.view_side,
+            selection_only: self.selection_only,
+            roi_crop: self.roi_crop,
+            region: self.region_filter,
+        };
+        self.hooks
+            .update(hovered.as_deref(), &self.selection, filter);
     }
 }
 
 impl eframe::App for MyApp {
     /// Called by the frame work to save state before shutdown.
     fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        if !self.persistence {
+            return;
+        }
         eframe::set_value(storage, eframe::APP_KEY, self);
+        #[cfg(target_arch = "wasm32")]
+        self.store_session();
+    }
+
+    /// Saved more often on the web, where closing the tab may cut the last save short.
+    #[cfg(target_arch = "wasm32")]
+    fn auto_save_interval(&self) -> std::time::Duration {
+        std::time::Duration::from_secs(10)
     }
 
     /// Called each time the UI needs repainting, which may be many times per second.
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        self.show(ctx, None);
+    }
+}
+
+impl MyApp {
+    /// Draw the whole app, in the window or inside `host` when embedded in another app
+    /// (see [`crate::NeuropalLensWidget`]).
+    pub(crate) fn show(&mut self, ctx: &egui::Context, mut host: Option<&mut egui::Ui>) {
         // Put your widgets into a `SidePanel`, `TopBottomPanel`, `CentralPanel`, `Window` or `Area`.
         // For inspiration and more examples, go to https://emilk.github.io/egui
 
-        egui::TopBottomPanel::top("top_panel").show(ctx, |ui| {
-            // The top panel is often a good place for a menu bar:
+        let frame_scope = profile::scope("frame");
+        let embedded = host.is_some();
+        self.ctx.get_or_insert_with(|| ctx.clone());
+        self.global_shortcuts(ctx);
+        if !embedded {
+            // The interface scale and visuals belong to the host app when embedded.
+            self.prefs.apply_scale(ctx);
+            self.prefs.apply_visuals(ctx);
+        }
+        i18n::set_language(self.prefs.language);
+        if !embedded || self.host_fonts {
+            i18n::load_fonts(ctx, self.prefs.language);
+        }
+        if !self.tour.seen && !embedded {
+            self.start_tour();
+        }
+        Panel::TopBottom(egui::TopBottomPanel::top("top_panel")).show(
+            ctx,
+            host.as_deref_mut(),
+            |ui| {
+                // The top panel is often a good place for a menu bar:
 
-            egui::menu::bar(ui, |ui| {
-                // NOTE: no File->Quit on web pages!
-                let is_web = cfg!(target_arch = "wasm32");
-                if !is_web {
-                    ui.menu_button("File", |ui| {
-                        if ui.button("Quit").clicked() {
+                egui::menu::bar(ui, |ui| {
+                    // NOTE: no File->Quit on web pages!
+                    let is_web = cfg!(target_arch = "wasm32");
+                    ui.menu_button(tr("File"), |ui| {
+                        if !self.read_only && ui.button(tr("Open dataset…")).clicked() {
+                            ui.close_menu();
+                            #[cfg(not(target_arch = "wasm32"))]
+                            if let Some(path) = rfd::FileDialog::new()
+                                .add_filter("CSV", &["csv", "txt"])
+                                .add_filter("JSON", &["json"])
+                                .add_filter(tr("Project bundle"), &[bundle::EXTENSION])
+                                .pick_file()
+                            {
+                                match std::fs::read(&path) {
+                                    Ok(bytes) => self.actions.push(Action::LoadDataset {
+                                        name: path
+                                            .file_name()
+                                            .map(|x| x.to_string_lossy().into_owned())
+                                            .unwrap_or_default(),
+                                        bytes,
+                                        path: Some(path),
+                                    }),
+                                    Err(err) => self.load_error = Some(err.to_string()),
+                                }
+                            }
+                            #[cfg(target_arch = "wasm32")]
+                            self.pending_files.push(web::pick_file(ctx));
+                        }
+                        #[cfg(not(target_arch = "wasm32"))]
+                        if ui
+                            .button(tr("Open activity…"))
+                            .on_hover_text(tr(
+                                "A table with a row per neuron: its name, then its activity at \
+                                 each frame; or the fluorescence traces of an NWB file",
+                            ))
+                            .clicked()
+                        {
+                            ui.close_menu();
+                            if let Some(path) = rfd::FileDialog::new()
+                                .add_filter("CSV", &["csv", "tsv", "txt"])
+                                .add_filter("NWB", &["nwb"])
+                                .pick_file()
+                            {
+                                match std::fs::read(&path) {
+                                    Ok(bytes) => self.actions.push(Action::LoadActivity {
+                                        name: path
+                                            .file_name()
+                                            .map(|x| x.to_string_lossy().into_owned())
+                                            .unwrap_or_default(),
+                                        bytes,
+                                    }),
+                                    Err(err) => self.load_error = Some(err.to_string()),
+                                }
+                            }
+                        }
+                        #[cfg(not(target_arch = "wasm32"))]
+                        if ui.button(tr("Save atlas as…")).clicked() {
+                            ui.close_menu();
+                            if let Err(err) =
+                                dataset::save_atlas(&self.data, &self.prefs.export_dir)
+                            {
+                                self.load_error = Some(err);
+                            }
+                        }
+                        #[cfg(not(target_arch = "wasm32"))]
+                        if ui
+                            .button(tr("Export project bundle…"))
+                            .on_hover_text(tr(
+                                "One file with the datasets, their transforms and midlines, the \
+                                 edited atlas, the selections, the workspaces and the settings, \
+                                 to hand the project to a collaborator",
+                            ))
+                            .clicked()
+                        {
+                            ui.close_menu();
+                            if let Err(err) = self.export_bundle() {
+                                self.load_error = Some(err);
+                            }
+                        }
+                        #[cfg(not(target_arch = "wasm32"))]
+                        if ui
+                            .button(tr("Export figure…"))
+                            .on_hover_text(tr(
+                                "The views laid out in one image with a shared legend and \
+                                 scale bar",
+                            ))
+                            .clicked()
+                        {
+                            ui.close_menu();
+                            self.show_figure_window = true;
+                        }
+                        if !is_web && !embedded && ui.button(tr("Quit")).clicked() {
                             ctx.send_viewport_cmd(egui::ViewportCommand::Close);
                         }
                     });
                     ui.separator();
+                    ui.menu_button(tr("Debug"), |ui| {
+                        ui.checkbox(&mut self.show_profiler, tr("Timing overlay"));
+                    });
+                    ui.menu_button(tr("Help"), |ui| {
+                        if ui.button(tr("Guided tour")).clicked() {
+                            ui.close_menu();
+                            self.start_tour();
+                        }
+                    });
+                    egui::widgets::global_theme_preference_switch(ui);
+                    let mut btn = Button::new(RichText::new("\u{2699}").monospace());
+                    if self.show_prefs_window {
+                        btn = btn.fill(Color32::from_rgba_unmultiplied(22, 131, 240, 120));
+                    };
+                    if ui.add(btn).on_hover_text(tr("Preferences")).clicked() {
+                        self.show_prefs_window = !self.show_prefs_window;
+                    };
+                    ui.separator();
+                    let mut btn = Button::new(RichText::new(tr("Filter Panel")).monospace());
+                    if self.show_side_panel {
+                        btn = btn.fill(Color32::from_rgba_unmultiplied(22, 131, 240, 120));
+                    };
+                    if ui.add(btn).clicked() {
+                        self.show_side_panel = !self.show_side_panel;
+                    };
+                    let mut btn = Button::new(RichText::new(tr("Selection")).monospace());
+                    if self.show_selection_window {
+                        btn = btn.fill(Color32::from_rgba_unmultiplied(22, 131, 240, 120));
+                    };
+                    if ui.add(btn).clicked() {
+                        self.show_selection_window = !self.show_selection_window;
+                    };
+                    let mut btn = Button::new(RichText::new(tr("Datasets")).monospace());
+                    if self.show_dataset_window {
+                        btn = btn.fill(Color32::from_rgba_unmultiplied(22, 131, 240, 120));
+                    };
+                    if ui.add(btn).clicked() {
+                        self.show_dataset_window = !self.show_dataset_window;
+                    };
+                    let mut btn = Button::new(RichText::new(tr("Plot Style")).monospace());
+                    if self.show_style_window {
+                        btn = btn.fill(Color32::from_rgba_unmultiplied(22, 131, 240, 120));
+                    };
+                    if ui.add(btn).clicked() {
+                        self.show_style_window = !self.show_style_window;
+                    };
+                    self.style_preset_combo(ui);
+                    let mut btn = Button::new(RichText::new(tr("Compare Lists")).monospace());
+                    if self.show_compare_window {
+                        btn = btn.fill(Color32::from_rgba_unmultiplied(22, 131, 240, 120));
+                    };
+                    if ui.add(btn).clicked() {
+                        self.show_compare_window = !self.show_compare_window;
+                    };
+                    let mut btn = Button::new(RichText::new(tr("Clusters")).monospace());
+                    if self.show_cluster_window {
+                        btn = btn.fill(Color32::from_rgba_unmultiplied(22, 131, 240, 120));
+                    };
+                    if ui.add(btn).clicked() {
+                        self.show_cluster_window = !self.show_cluster_window;
+                    };
+                    let mut btn = Button::new(RichText::new(tr("Symmetry")).monospace());
+                    if self.show_symmetry_window {
+                        btn = btn.fill(Color32::from_rgba_unmultiplied(22, 131, 240, 120));
+                    };
+                    if ui.add(btn).clicked() {
+                        self.show_symmetry_window = !self.show_symmetry_window;
+                    };
+                    ui.separator();
+                    let text = match self.region_filter.pharyngeal {
+                        None => tr("Pharynx"),
+                        Some(true) => tr("Pharyngeal"),
+                        Some(false) => tr("Extrapharyngeal"),
+                    };
+                    let mut btn = Button::new(RichText::new(text).monospace());
+                    if self.region_filter.pharyngeal.is_some() {
+                        btn = btn.fill(Color32::from_rgba_unmultiplied(22, 131, 240, 120));
+                    };
+                    if ui
+                        .add(btn)
+                        .on_hover_text(format!(
+                            "{} ({})",
+                            tr(
+                                "Show the pharyngeal nervous system (I1–I6, M, MC, MI, NSM) \
+                                alone, then the other neurons, then all"
+                            ),
+                            ctx.format_shortcut(&PHARYNX_SHORTCUT)
+                        ))
+                        .clicked()
+                    {
+                        self.actions.push(Action::CyclePharyngeal);
+                    }
+                    ui.separator();
+                    for tool in CanvasTool::ALL {
+                        if tool == CanvasTool::Edit && self.read_only {
+                            continue;
+                        }
+                        ui.selectable_value(&mut self.tool, tool, tool.to_string());
+                    }
+                    if self.tool == CanvasTool::Annotate {
+                        self.annotations.toolbar_ui(ui);
+                    }
+                    if ui
+                        .button(tr("\u{27F2} Reset views"))
+                        .on_hover_text(format!(
+                            "{} ({})",
+                            tr("Restore the default bounds of all plots"),
+                            ctx.format_shortcut(&RESET_ALL_SHORTCUT)
+                        ))
+                        .clicked()
+                    {
+                        self.reset_views = [true; 3];
+                    }
+                    ui.separator();
+                    ui.label(tr("Hover"));
+                    ui.add(
+                        egui::DragValue::new(&mut self.prefs.hover_tolerance)
+                            .range(0.05..=3.0)
+                            .speed(0.01)
+                            .max_decimals(2),
+                    )
+                    .on_hover_text(tr("Hover radius in atlas units"));
+                    let mut sweeping = self.sweep.is_some();
+                    if ui
+                        .checkbox(&mut sweeping, tr("Sweep"))
+                        .on_hover_text(tr(
+                            "Move the slices from one end of the shown neurons to the other \
+                             with the playback controls",
+                        ))
+                        .changed()
+                    {
+                        self.sweep = sweeping.then_some(0);
+                    }
+                    ui.label(tr("Slab"));
+                    ui.add(
+                        egui::DragValue::new(&mut self.prefs.slab_thickness)
+                            .range(0.1..=10.0)
+                            .speed(0.05)
+                            .prefix("±")
+                            .max_decimals(2),
+                    )
+                    .on_hover_text(tr(
+                        "Half thickness of the anterior and dorsal slices in atlas units",
+                    ));
+                    if !self.roi.is_empty() {
+                        ui.label(RichText::new(self.roi.describe()).color(ROI_COLOR).small())
+                            .on_hover_text(if self.roi.is_complete() {
+                                tr("Neurons inside the box are selected")
+                            } else {
+                                tr("Draw a rectangle in another view to bound the remaining axis")
+                            });
+                        let mut crop = self.roi_crop;
+                        if ui.checkbox(&mut crop, tr("Crop")).changed() {
+                            self.actions.push(Action::SetRoiCrop(crop));
+                        }
+                        if ui.small_button(tr("Clear ROI")).clicked() {
+                            self.actions.push(Action::SetRoi(Roi::default()));
+                        }
+                    }
+                    egui::warn_if_debug_build(ui);
+                });
+            },
+        );
+
+        Panel::TopBottom(egui::TopBottomPanel::top("workspace_tabs")).show(
+            ctx,
+            host.as_deref_mut(),
+            |ui| {
+                self.workspace_tabs(ui);
+            },
+        );
+
+        if !self.read_only {
+            for file in ctx.input(|i| i.raw.dropped_files.clone()) {
+                if let Some((name, bytes)) = dataset::dropped_file_bytes(&file) {
+                    self.actions.push(Action::LoadDataset {
+                        name,
+                        bytes,
+                        path: file.path.clone(),
+                    });
                 }
-                egui::widgets::global_theme_preference_switch(ui);
-                ui.separator();
-                let mut btn = Button::new(RichText::new("Filter Panel").monospace());
-                if self.show_side_panel {
-                    btn = btn.fill(Color32::from_rgba_unmultiplied(22, 131, 240, 120));
-                };
-                if ui.add(btn).clicked() {
-                    self.show_side_panel = !self.show_side_panel;
-                };
-                egui::warn_if_debug_build(ui);
-            });
-        });
+            }
+        }
+        #[cfg(target_arch = "wasm32")]
+        self.receive_session();
+        self.receive_pending_files(ctx);
+        self.loading_window(ctx);
+        self.mapping_window(ctx);
+        #[cfg(not(target_arch = "wasm32"))]
+        self.reload_watched(ctx);
+        self.selection_window(ctx);
+        self.compare_window(ctx);
+        self.cluster_window(ctx);
+        self.symmetry_window(ctx);
+        self.atlas_edit_window(ctx);
+        self.diff_window(ctx);
+        self.dataset_window(ctx);
+        self.outlier_window(ctx);
+        self.warnings_window(ctx);
+        self.trace_window(ctx);
+        self.record_video(ctx);
+        self.video_window(ctx);
+        #[cfg(not(target_arch = "wasm32"))]
+        self.figure_window(ctx);
+        self.style_window(ctx);
+        self.prefs_window(ctx);
 
-        let mut data: Vec<_> = self
-            .data
-            .values()
-            .filter(|x| {
-                self.label
-                    .split(&[' ', ';', ',', '\t'])
-                    .filter(|x| !x.is_empty())
-                    .any(|pat| pat == "*" || x.name.starts_with(pat))
-            })
-            .filter(|x| match self.view_side {
-                WormSide::Left => x.z >= 0.,
-                WormSide::Right => x.z < 0.,
-                WormSide::Both => true,
-            })
-            .collect();
+        let filter_scope = profile::scope("filter");
+        let extent = region::extent(self.data.values());
+        let meta = Metadata::new(&self.data, &self.datasets);
+        let query = filter_query(
+            (&self.data, &self.index, &self.datasets),
+            &self.label,
+            extent,
+            self.view_side,
+            self.region_filter,
+            (
+                self.selection_only.then_some(&self.selection),
+                self.roi_crop.then_some(&self.roi),
+            ),
+        );
+        let mut data: Vec<_> = query.iter().collect();
+        drop(query);
+        filter_scope.end(ctx);
+        let sort_scope = profile::scope("sort");
         data.sort_unstable_by_key(|x| &x.name);
+        sort_scope.end(ctx);
+
+        let nav = navigation_keys(ctx);
+        let mut focus =
+            (self.focused.as_ref()).and_then(|name| data.iter().position(|n| &n.name == name));
+        if let Some(next) = step_focus(focus, nav.step, data.len()) {
+            focus = Some(next);
+            self.actions
+                .push(Action::SetFocus(Some(data[next].name.clone())));
+        }
+        let center_focus = nav.enter && focus.is_some();
+        if let (true, Some(i)) = (nav.enter, focus) {
+            let name = data[i].name.clone();
+            self.actions.push(if self.selection.contains(&name) {
+                Action::DeselectNeuron(name)
+            } else {
+                Action::SelectNeuron(name)
+            });
+        }
+        if nav.nudge != 0.0 {
+            self.actions.push(Action::MoveSlices(nav.nudge));
+        }
+        if (self.activity.is_some() || self.sweep.is_some()) && self.recording.is_none() {
+            // The arrow keys move the slices through a focused neuron, else through time;
+            // a video being exported steps through the frames itself.
+            let keys = self.playback.keys(ctx, focus.is_none());
+            for seek in [keys, self.playback.tick(ctx)].into_iter().flatten() {
+                seek_layers(
+                    &mut self.playback,
+                    seek,
+                    (self.activity.as_ref(), &mut self.activity_frame),
+                    &mut self.sweep,
+                );
+            }
+            if self.playback.playing {
+                self.prefs.request_repaint(ctx);
+            }
+        } else {
+            self.playback.playing = false;
+        }
+        self.hover_cycle += nav.cycle;
+
+        let now = ctx.input(|i| i.time);
+        let flash = self.flash.as_ref().and_then(|(name, start, _)| {
+            let alpha = 1.0 - (now - start) / FLASH_SECONDS;
+            let row = data.iter().position(|n| &n.name == name)?;
+            (alpha > 0.0).then_some((row, alpha as f32))
+        });
+        if flash.is_some() {
+            self.prefs.request_repaint(ctx);
+        } else {
+            self.flash = None;
+        }
+        let scroll_to = if nav.step != 0 {
+            focus
+        } else {
+            flash
+                .filter(|_| self.flash.as_ref().is_some_and(|f| f.2))
+                .map(|(row, _)| row)
+        };
+        if let Some(flash) = &mut self.flash {
+            flash.2 = false;
+        }
 
+        // What the search being typed would show, so a typo matching nothing is seen at once.
+        let draft_counts = self.search_draft.as_ref().map(|(draft, _)| {
+            let query = filter_query(
+                (&self.data, &self.index, &self.datasets),
+                draft,
+                extent,
+                self.view_side,
+                self.region_filter,
+                (
+                    self.selection_only.then_some(&self.selection),
+                    self.roi_crop.then_some(&self.roi),
+                ),
+            );
+            side_counts(query.iter(), &self.data)
+        });
+        let mut row_hovered = None;
         if self.show_side_panel {
-            egui::SidePanel::left("SideTool").show(ctx, |ui| {
+            let side_panel_ui = |ui: &mut egui::Ui| {
                 // The central panel the region left after adding TopPanel's and SidePanel's
                 ui.horizontal(|ui| {
                     ui.heading(RichText::new("NeuroPAL Lens").strong());
@@ -192,240 +1169,4314 @@ impl eframe::App for MyApp {
                 });
 
                 ui.horizontal(|ui| {
-                    ui.label(RichText::new("Body Side:").heading());
+                    ui.label(RichText::new(tr("Body Side:")).heading());
                     let btn = egui::Button::new(
                         RichText::new(self.view_side.to_string()).heading().strong(),
                     )
                     .min_size([180., 20.].into());
                     let btn = btn.fill(self.view_side.color());
-                    if ui.add(btn).clicked() {
-                        self.view_side = self.view_side.next();
+                    let response = ui.add(btn);
+                    tour::mark(ui.ctx(), tour::Target::Side, response.rect);
+                    if response.clicked() {
+                        self.actions.push(Action::ToggleSide);
                     }
                 });
+                let mut region_filter = self.region_filter;
+                region_filter.ui(ui);
+                if region_filter != self.region_filter {
+                    self.actions.push(Action::SetRegionFilter(region_filter));
+                }
 
-                ui.separator();
                 ui.horizontal(|ui| {
-                    ui.label("Search: ");
-                    ui.text_edit_singleline(&mut self.label);
+                    ui.label(tr("Color:"));
+                    egui::ComboBox::from_id_salt("color_mode")
+                        .selected_text(self.color_mode.to_string())
+                        .show_ui(ui, |ui| {
+                            for mode in ColorMode::ALL {
+                                ui.selectable_value(&mut self.color_mode, mode, mode.to_string());
+                            }
+                        });
                 });
-                ui.label(
-                    RichText::new(" Name  (    x,     y,     z)").font(FontId::monospace(16.0)),
-                );
-
-                huge_content_painter(ui, &data);
-            });
-        }
-        egui::CentralPanel::default().show(ctx, |ui| {
-            worm_canvas(ctx, ui, &data);
-        });
-    }
-}
-
-fn huge_content_painter(ui: &mut egui::Ui, data: &[&Neuron]) {
-    ui.add_space(4.0);
-    let font_id = FontId::monospace(16.0);
-    let row_height = ui.fonts(|f| f.row_height(&font_id)) + ui.spacing().item_spacing.y;
-    let row_width = ui.fonts(|f| f.glyph_width(&font_id, 'X')) * 28. + ui.spacing().item_spacing.x;
-    let num_rows = data.len();
-    ScrollArea::vertical()
-        .auto_shrink(false)
-        .show_viewport(ui, |ui, viewport| {
-            ui.set_height(row_height * num_rows as f32);
-
-            let first_item = (viewport.min.y / row_height).floor().at_least(0.0) as usize;
-            let last_item = (viewport.max.y / row_height).ceil() as usize + 1;
-            let last_item = last_item.at_most(num_rows);
-
-            let mut used_rect = Rect::NOTHING;
-
-            for i in first_item..last_item {
-                let x = ui.min_rect().left() + ui.spacing().item_spacing.x;
-                let y = ui.min_rect().top() + i as f32 * row_height;
-                if let Some(neuron) = data.get(i) {
-                    let text = neuron.name.as_str();
-                    let (r, g, b) = (neuron.r * 255., neuron.g * 255., neuron.b * 255.);
-
-                    let lut = neuron.luminance();
-                    let (mut r, mut g, mut b) = (r as u8, g as u8, b as u8);
+                if self.color_mode == ColorMode::Column {
+                    self.column_coloring.ui(ui, meta, self.data.keys());
+                }
+                if self.color_mode == ColorMode::Function {
+                    functional_legend(ui, &data);
+                }
 
-                    if r == 0 && g == 0 && b == 0 {
-                        r = 255;
-                        g = 255;
-                        b = 255;
-                    }
-                    let text_color = if lut == 0.0 || lut > 0.5 {
-                        egui::Color32::BLACK
-                    } else {
-                        egui::Color32::WHITE
+                ui.separator();
+                ui.horizontal(|ui| {
+                    ui.label(tr("Search: "));
+                    let mut search = match &self.search_draft {
+                        Some((draft, _)) => draft.clone(),
+                        None => self.label.clone(),
                     };
-                    ui.painter().rect(
-                        Rect::from_min_max(pos2(x, y), pos2(x + row_width, y + row_height)),
-                        0.0f32,
-                        egui::Color32::from_rgb(r, g, b),
-                        (0.0, egui::Color32::from_rgb(r, g, b)),
-                    );
-                    let text_rect = ui.painter().text(
-                        pos2(x, y),
-                        Align2::LEFT_TOP,
-                        format!(
-                            "{:<5} ({:>5.1}, {:>5.1}, {:>5.1})",
-                            text, neuron.x, neuron.y, neuron.z
-                        ),
-                        font_id.clone(),
-                        text_color,
-                    );
-                    used_rect = used_rect.union(text_rect);
+                    let response = completion::search_field(ui, &mut search, &self.index)
+                        .on_hover_text(format!(
+                            "{}\n{}: head, tail, ventral-cord, pharynx, preanal, dorsorectal, \
+                             lumbar\n{}: column=value",
+                            tr("Press Enter to apply, Esc to discard"),
+                            tr("Region keywords"),
+                            tr("Metadata")
+                        ));
+                    if response.changed() {
+                        self.search_draft = Some((search, ui.input(|i| i.time)));
+                    }
+                    // Only Enter applies the draft; Esc or clicking elsewhere discards it.
+                    if response.lost_focus() {
+                        if let Some((draft, _)) = self.search_draft.take() {
+                            if ui.input(|i| i.key_pressed(Key::Enter)) {
+                                self.actions.push(Action::SetFilter(draft));
+                            }
+                        }
+                    }
+                    tour::mark(ui.ctx(), tour::Target::Search, response.rect);
+                });
+                for term in search_terms(&self.label) {
+                    if Region::from_keyword(term).is_some()
+                        || metadata::search_term(term).is_some()
+                        || !self.index.with_prefix(term).is_empty()
+                    {
+                        continue;
+                    }
+                    let suggestions = self.index.suggestions(term, 3);
+                    ui.horizontal_wrapped(|ui| {
+                        if suggestions.is_empty() {
+                            ui.label(format!("\u{26A0} {term}: {}", tr("no matching neuron")));
+                            return;
+                        }
+                        ui.label(format!("{term} \u{2192} {}", tr("did you mean")));
+                        for name in suggestions {
+                            if ui.small_button(name).clicked() {
+                                let search = (self.label.split(&[' ', ';', ',', '\t']))
+                                    .filter(|t| !t.is_empty())
+                                    .map(|t| if t == term { name } else { t })
+                                    .collect::<Vec<_>>()
+                                    .join(" ");
+                                self.actions.push(Action::SetFilter(search));
+                            }
+                        }
+                    });
                 }
-            }
-
-            ui.allocate_rect(used_rect, Sense::hover()); // make sure it is visible!
-        });
-}
-
-fn worm_canvas(ctx: &egui::Context, ui: &mut egui::Ui, data: &[&Neuron]) {
-    let is_dark = ui.ctx().theme() == Theme::Dark;
-    let response = egui_plot::Plot::new("xy")
-        .height(500.)
-        .data_aspect(1.0)
-        .allow_zoom(true)
-        .allow_drag(true)
-        .allow_scroll(true)
-        .allow_double_click_reset(true)
-        .allow_boxed_zoom(true)
-        .include_x(0.0)
-        .include_y(0.0)
-        // .legend(Legend::default())
-        .x_axis_label(RichText::new("Anterior - Posterior").strong())
-        .y_axis_label(RichText::new("Ventral - Dorsal").strong())
-        .show(ui, |plot_ui| {
-            let boundary = plot_ui.plot_bounds();
-            let scale = boundary.max()[0] - boundary.min()[0];
-            let radius = (scale * -0.01 + 6.).clamp(1.0, 6.);
-
-            for neuron in data {
-                let pts = vec![[neuron.x as f64, neuron.y as f64]];
-                let points = PlotPoints::new(pts);
-                let [r, g, b] = neuron.rgb();
-
-                let mut color = if r == 0 && g == 0 && b == 0 && is_dark {
-                    egui::Color32::WHITE
+                let [left, right, midline] =
+                    draft_counts.unwrap_or_else(|| side_counts(data.iter().copied(), &self.data));
+                let shown = left + right + midline;
+                let mut text = RichText::new(format!(
+                    "{shown} {} {} {} (L: {left}, R: {right}, {}: {midline})",
+                    tr("of"),
+                    self.data.len(),
+                    tr("neurons shown"),
+                    tr("midline")
+                ));
+                if shown == 0 {
+                    text = text.color(ui.visuals().warn_fg_color);
+                }
+                if draft_counts.is_some() {
+                    ui.label(text.italics())
+                        .on_hover_text(tr("What the search being typed would show"));
                 } else {
-                    let [r, g, b] = neuron.rgb();
-                    egui::Color32::from_rgb(r, g, b)
-                };
-
-                if neuron.z < 0.0 {
-                    color = color.gamma_multiply(0.8);
+                    ui.label(text);
                 }
+                ui.horizontal(|ui| {
+                    ui.label(
+                        RichText::new(" Name  (    x,     y,     z)").font(self.prefs.list_font()),
+                    )
+                    .on_hover_text(tr(
+                        "\u{2191}/\u{2193} move through the list, Enter selects and centers, \
+                         \u{2190}/\u{2192} move the slices (without a focus, step through time), \
+                         Esc clears the focus, Space plays",
+                    ));
+                    if ui
+                        .small_button("\u{1F4CB}")
+                        .on_hover_text(tr("Copy the listed neurons with their positions"))
+                        .clicked()
+                    {
+                        toast::copy(
+                            ui.ctx(),
+                            neuron_table(&data),
+                            format!("{} {}", data.len(), tr("neurons copied")),
+                        );
+                    }
+                    let keys = meta.keys();
+                    if !keys.is_empty() {
+                        egui::ComboBox::from_id_salt("list_column")
+                            .width(90.0)
+                            .selected_text(self.list_column.as_deref().unwrap_or(tr("Column")))
+                            .show_ui(ui, |ui| {
+                                ui.selectable_value(&mut self.list_column, None, tr("None"));
+                                for key in keys {
+                                    ui.selectable_value(
+                                        &mut self.list_column,
+                                        Some(key.clone()),
+                                        key,
+                                    );
+                                }
+                            })
+                            .response
+                            .on_hover_text(tr("Metadata column shown in the list"));
+                    }
+                });
 
-                plot_ui.points(
-                    Points::new(points)
-                        .name(&neuron.name)
-                        .allow_hover(true)
-                        .color(color)
-                        .highlight(true)
-                        .radius(radius as f32),
+                row_hovered = huge_content_painter(
+                    ui,
+                    &data,
+                    &self.selection,
+                    RowMarks {
+                        focus,
+                        scroll_to,
+                        flash,
+                        hovered: self.hovered.as_ref().map(|(name, _)| name.as_str()),
+                    },
+                    &self.prefs,
+                    (meta, self.list_column.as_deref()),
                 );
+            };
+            if !self.pop_out_table {
+                Panel::Side(egui::SidePanel::left("SideTool")).show(
+                    ctx,
+                    host.as_deref_mut(),
+                    side_panel_ui,
+                );
+            } else if !view::show_in_viewport(
+                ctx,
+                "neuron_table",
+                tr("Neurons"),
+                [340.0, 720.0],
+                side_panel_ui,
+            ) {
+                self.pop_out_table = false;
             }
-        });
-
-    let pos = response
-        .response
-        .hover_pos()
-        .map(|pos| response.transform.value_from_position(pos));
-
-    let thickness = 1.5;
-    let bound = response.transform.bounds();
-    let x_bound = (bound.min()[0], bound.max()[0]);
-    let yz_window = egui::Window::new("Anterior View (z-y)")
-        .id(egui::Id::new("yz")) // required since we change the title
-        .resizable(true)
-        .constrain(true)
-        .collapsible(true)
-        .title_bar(true)
-        .scroll(true)
-        .enabled(true);
-
-    yz_window.show(ctx, |ui| {
-        egui_plot::Plot::new("yz")
-            .data_aspect(1.0)
-            .allow_zoom(true)
-            .allow_drag(true)
-            .allow_scroll(true)
-            .allow_double_click_reset(true)
-            .allow_boxed_zoom(true)
-            .include_x(-15.0)
-            .include_x(15.0)
-            .include_y(20.0)
-            .include_y(-25.0)
-            .x_axis_label(RichText::new("Right - Left").strong())
-            .y_axis_label(RichText::new("Ventral - Dorsal").strong())
-            // .legend(Legend::default())
-            .show(ui, |plot_ui| {
-                let boundary = plot_ui.plot_bounds();
-                let scale = boundary.max()[0] - boundary.min()[0];
-                let radius = (scale * -0.01 + 6.).clamp(1.0, 6.);
-                let mut low = f64::MIN;
-                let mut high = f64::MAX;
-                if let Some(pos) = pos {
-                    plot_ui.hline(HLine::new(pos.y).color(Color32::LIGHT_RED));
-                    low = pos.x - thickness;
-                    high = pos.x + thickness;
-                }
-
-                for neuron in data {
-                    let x_pos = neuron.x as f64;
-                    if x_pos < low || x_pos > high {
-                        continue;
+        }
+        let colorbar = self.colorbar();
+        let meta = Metadata::new(&self.data, &self.datasets);
+        let color_of = |neuron: &Neuron| match self.color_mode {
+            ColorMode::NeuroPAL => neuropal_color(neuron),
+            ColorMode::ListMembership => self.comparison.membership(&neuron.name).color(),
+            ColorMode::Depth => colorbar
+                .as_ref()
+                .map_or(Color32::GRAY, |c| c.color(neuron.z)),
+            ColorMode::Cluster => self.clustering.color_of(&neuron.name),
+            ColorMode::Column => {
+                let value = self.column_coloring.value(&meta, &neuron.name);
+                (colorbar.as_ref().zip(value)).map_or(Color32::GRAY, |(c, v)| c.color(v))
+            }
+            ColorMode::Activity => {
+                let activity = self.activity.as_ref();
+                let value = activity.and_then(|a| a.value(&neuron.name, self.activity_frame));
+                (colorbar.as_ref().zip(value)).map_or(Color32::GRAY, |(c, v)| c.color(v))
+            }
+            ColorMode::Function => functional::of(neuron).map_or(Color32::GRAY, |f| f.color()),
+        };
+        let mut overlay = CanvasOverlay::default();
+        if self.show_displacement {
+            if let (Some(from), Some(to)) = (
+                self.source(self.displacement_from),
+                self.source(self.displacement_to),
+            ) {
+                let o = self.orientation;
+                overlay.displacement = dataset::displacements(from, to)
+                    .iter()
+                    .map(|d| (o.apply(d.from), o.apply(d.to)))
+                    .collect();
+            }
+        }
+        if let (true, Some(activity)) = (self.show_correlations, &self.activity) {
+            let o = self.orientation;
+            let shown: HashMap<&str, [f32; 3]> = (data.iter())
+                .map(|n| (n.name.as_str(), o.apply(n.position())))
+                .collect();
+            overlay.correlations = (activity.correlated(self.correlation_threshold).into_iter())
+                .filter_map(|(a, b, r)| Some((*shown.get(a)?, *shown.get(b)?, r)))
+                .collect();
+        }
+        if self.show_spread {
+            let o = self.orientation;
+            overlay.spread = self
+                .variability
+                .iter()
+                .map(|s| (o.apply(s.mean), o.apply_cov(s.cov)))
+                .collect();
+        }
+        overlay.points = self
+            .datasets
+            .iter()
+            .enumerate()
+            .filter(|(i, d)| d.visible || self.midline_edit == Some(*i))
+            .map(|(i, d)| {
+                let points = if self.midline_edit == Some(i) {
+                    // Show the unstraightened positions the midline is drawn over.
+                    d.raw
+                        .values()
+                        .map(|n| self.orientation.apply(d.transform.apply(n.position())))
+                        .collect()
+                } else {
+                    d.neurons
+                        .values()
+                        .map(|n| self.orientation.apply(n.position()))
+                        .collect()
+                };
+                (points, d.color)
+            })
+            .collect();
+        if self.show_ganglia {
+            let mut ganglia: BTreeMap<&'static str, Vec<[f32; 3]>> = BTreeMap::new();
+            for neuron in self.data.values() {
+                if let Some(name) = ganglion::of(neuron) {
+                    let position = self.orientation.apply(neuron.position());
+                    ganglia.entry(name).or_default().push(position);
+                }
+            }
+            overlay.ganglia = ganglia.into_iter().collect();
+        }
+        if self.density.enabled {
+            let class = self.density.class.trim();
+            let positions: Vec<[f32; 3]> = if class.is_empty() {
+                (self.selection.iter())
+                    .filter_map(|name| self.data.get(name))
+                    .map(|n| self.orientation.apply(n.position()))
+                    .collect()
+            } else {
+                (self.index.with_prefix(class).iter())
+                    .filter_map(|name| self.data.get(name))
+                    .map(|n| self.orientation.apply(n.position()))
+                    .collect()
+            };
+            self.density_contours
+                .update(&positions, self.density.bandwidth);
+            overlay.density = View::ALL.map(|view| self.density_contours.view(view).to_vec());
+        }
+        if self.show_selection_hull {
+            overlay.selection_hull = (self.selection.iter())
+                .filter_map(|name| self.data.get(name))
+                .map(|n| self.orientation.apply(n.position()))
+                .collect();
+        }
+        if self.show_symmetry_window && self.symmetry_highlight > 0 {
+            if let Some(source) = self.source(self.symmetry_source) {
+                let mut pairs = symmetry::pairs(source);
+                SymmetryColumn::Position.sort(&mut pairs, false);
+                let o = self.orientation;
+                overlay.asymmetric = pairs
+                    .into_iter()
+                    .take(self.symmetry_highlight)
+                    .map(|p| (p.class, o.apply(p.left), o.apply(p.right)))
+                    .collect();
+            }
+        }
+        if let (true, true, Some(original)) = (
+            self.show_diff_window,
+            self.show_edit_arrows,
+            &self.editor.original,
+        ) {
+            let o = self.orientation;
+            overlay.edits = edit::diff(original, &self.data)
+                .iter()
+                .filter(|c| c.moved().is_some())
+                .filter_map(|c| Some((o.apply(c.from?), o.apply(c.to?))))
+                .collect();
+        }
+        if self.show_principal_axes {
+            let positions: Vec<[f32; 3]> = data
+                .iter()
+                .map(|n| self.orientation.apply(n.position()))
+                .collect();
+            overlay.principal_axes = PrincipalAxes::of(&positions);
+        }
+        for loading in &self.loading {
+            let points = loading
+                .partial
+                .iter()
+                .map(|p| self.orientation.apply(*p))
+                .collect();
+            overlay.points.push((points, Color32::GRAY));
+        }
+        if self.roi_crop {
+            let o = self.orientation;
+            overlay.retain(|p| self.roi.contains(o.apply(p)));
+        }
+        let midline = self.midline_edit.and_then(|i| {
+            let d = self.datasets.get(i)?;
+            Some((d.midline.clone().unwrap_or_default(), d.transform))
+        });
+        let geometry_scope = profile::scope("geometry");
+        // Edits of the atlas and the datasets, and selection changes, invalidate the
+        // geometry; the filters and the colors are part of its key.
+        let key = batch::fingerprint((
+            (
+                &self.label,
+                self.view_side,
+                self.region_filter,
+                self.selection_only,
+                self.roi_crop
+                    .then(|| self.roi.ranges.map(|r| r.map(|r| r.map(f32::to_bits)))),
+            ),
+            self.orientation,
+            (
+                self.color_mode,
+                &self.comparison.text_a,
+                &self.comparison.text_b,
+                &self.clustering,
+                self.prefs.depth_cue.to_bits(),
+                (&self.column_coloring, self.datasets.len()),
+                (self.activity.as_ref().map(|a| &a.name), self.activity_frame),
+            ),
+        ));
+        self.geometry.update(key, &data, self.orientation, |n| {
+            marker_color(&color_of, n, &self.prefs)
+        });
+        // The flipped copies are made along with the geometry, not on every frame.
+        let oriented = self.geometry.oriented();
+        let canvas_data: Vec<&Neuron> = if self.orientation.is_identity() {
+            data.clone()
+        } else {
+            oriented.iter().collect()
+        };
+        geometry_scope.end(ctx);
+        Panel::TopBottom(egui::TopBottomPanel::bottom("status_bar")).show(
+            ctx,
+            host.as_deref_mut(),
+            |ui| {
+                self.status_bar(ui, &canvas_data);
+            },
+        );
+        if self.activity.is_some() || self.sweep.is_some() {
+            let (mut close, mut stop_sweep) = (false, false);
+            let mut seek = None;
+            Panel::TopBottom(egui::TopBottomPanel::bottom("timeline")).show(
+                ctx,
+                host.as_deref_mut(),
+                |ui| {
+                    ui.horizontal(|ui| {
+                        seek = self.playback.controls_ui(ui);
+                        ui.separator();
+                        match &self.activity {
+                            Some(activity) => {
+                                close = timeline(
+                                    ui,
+                                    activity,
+                                    &mut self.activity_frame,
+                                    [&mut self.show_trace_window, &mut self.show_heatmap_window],
+                                );
+                            }
+                            None => {
+                                ui.label(tr("Slice sweep"));
+                            }
+                        }
+                    });
+                    if let Some(step) = &mut self.sweep {
+                        ui.horizontal(|ui| {
+                            if self.activity.is_some() {
+                                ui.label(tr("Slice sweep"));
+                            }
+                            ui.spacing_mut().slider_width = ui.available_width() - 60.0;
+                            ui.add(egui::Slider::new(step, 0..=SWEEP_STEPS - 1).show_value(false));
+                            if ui
+                                .small_button("\u{2716}")
+                                .on_hover_text(tr("Stop sweeping"))
+                                .clicked()
+                            {
+                                stop_sweep = true;
+                            }
+                        });
+                    }
+                    if self.activity.is_none() {
+                        return;
+                    }
+                    ui.horizontal(|ui| {
+                        ui.checkbox(&mut self.show_correlations, tr("Correlation network"))
+                            .on_hover_text(tr(
+                                "Join the shown neurons whose activity correlates, positive in \
+                                 red and negative in blue",
+                            ));
+                        if self.show_correlations {
+                            ui.label("|r| \u{2265}");
+                            ui.add(
+                                egui::DragValue::new(&mut self.correlation_threshold)
+                                    .range(0.3..=1.0)
+                                    .speed(0.01)
+                                    .max_decimals(2),
+                            );
+                        }
+                        if cfg!(target_arch = "wasm32") {
+                            return;
+                        }
+                        ui.separator();
+                        match &self.recording {
+                            Some(recording) => {
+                                ui.add(
+                                    egui::ProgressBar::new(recording.progress())
+                                        .desired_width(160.0)
+                                        .text(format!(
+                                            "{} {}/{}",
+                                            tr("Recording"),
+                                            recording.frame,
+                                            recording.frames
+                                        )),
+                                );
+                                if ui.small_button(tr("Cancel")).clicked() {
+                                    if let Some(recording) = self.recording.take() {
+                                        recording.cancel();
+                                    }
+                                }
+                            }
+                            None => {
+                                ui.toggle_value(
+                                    &mut self.show_video_window,
+                                    format!("\u{1F3AC} {}", tr("Export video…")),
+                                );
+                            }
+                        }
+                    });
+                },
+            );
+            if let Some(seek) = seek {
+                seek_layers(
+                    &mut self.playback,
+                    seek,
+                    (self.activity.as_ref(), &mut self.activity_frame),
+                    &mut self.sweep,
+                );
+            }
+            if stop_sweep {
+                self.sweep = None;
+            }
+            if close {
+                self.activity = None;
+                if self.color_mode == ColorMode::Activity {
+                    self.color_mode = ColorMode::NeuroPAL;
+                }
+            }
+        }
+        if let Some(activity) = &self.activity {
+            let mut clicked = None;
+            egui::Window::new(tr("Activity Heatmap"))
+                .open(&mut self.show_heatmap_window)
+                .resizable(true)
+                .default_width(480.)
+                .show(ctx, |ui| {
+                    ui.label(tr(
+                        "Neurons shown, by time; click a row to find the neuron, a column to \
+                         go to its time",
+                    ));
+                    clicked = self.heatmap.ui(
+                        ui,
+                        activity,
+                        data.iter().map(|n| n.name.as_str()),
+                        &mut self.activity_frame,
+                        self.focused.as_deref(),
+                    );
+                });
+            if let Some(name) = clicked {
+                self.actions.push(Action::SetFocus(Some(name.clone())));
+                self.flash = Some((name, now, true));
+            }
+        }
+        let preview_query = self.search_draft.as_ref().and_then(|(draft, edited)| {
+            let wait = SEARCH_DEBOUNCE - (now - edited);
+            if wait > 0.0 {
+                ctx.request_repaint_after(std::time::Duration::from_secs_f64(wait));
+                return None;
+            }
+            // The draft replaces the applied search, so it is matched against the whole atlas.
+            let meta = Metadata::new(&self.data, &self.datasets);
+            Some(search_query(
+                AtlasQuery::new(&self.data)
+                    .index(&self.index)
+                    .side(self.view_side),
+                draft,
+                extent,
+                meta,
+            ))
+        });
+        let preview: Vec<Neuron> = match &preview_query {
+            Some(query) => (query.iter())
+                .map(|n| self.orientation.apply_neuron(n))
+                .collect(),
+            None => Vec::new(),
+        };
+        drop(preview_query);
+        let preview: Vec<&Neuron> = preview.iter().collect();
+        let chips = self.filter_chips();
+        let mut removed_filter = None;
+        let mut output = CanvasOutput::default();
+        let mut reset = std::mem::take(&mut self.reset_views);
+        if self.roi_crop != self.roi_crop_shown {
+            reset = [true; 3];
+        }
+        let mut video_rect = None;
+        Panel::Central(egui::CentralPanel::default()).show(ctx, host, |ui| {
+            removed_filter = filter_chip_bar(ui, chips, data.len(), self.data.len());
+            let canvas_rect = ui.available_rect_before_wrap();
+            let settings = CanvasSettings {
+                selection: &self.selection,
+                geometry: &self.geometry,
+                overlay: &overlay,
+                plot_styles: &self.plot_styles,
+                orientation: self.orientation,
+                midline: midline.as_ref(),
+                tool: self.tool,
+                roi: &self.roi,
+                annotations: &self.annotations,
+                crop: self.roi_crop,
+                reset,
+                window_geometry: &self.window_geometry,
+                layout: self.layout,
+                maximized: self.maximized,
+                pop_out: self.pop_out_views,
+                prefs: &self.prefs,
+                focus: focus.map(|i| canvas_data[i]),
+                preview: &preview,
+                center_focus,
+                slice: focus
+                    .map(|i| {
+                        let [x, y] = View::XY.project(canvas_data[i].position());
+                        [x + self.slice_offset, y]
+                    })
+                    .or_else(|| sweep_slice(&canvas_data, self.sweep?)),
+                colorbar: colorbar.clone(),
+                hovered: self.hovered.as_ref().and_then(|(name, view)| {
+                    canvas_data
+                        .iter()
+                        .find(|n| &n.name == name)
+                        .map(|n| (*n, *view))
+                }),
+                hover_cycle: self.hover_cycle,
+            };
+            output = worm_canvas(ctx, ui, &canvas_data, &settings);
+            video_rect = match self.video.scope {
+                VideoScope::XyView => output.xy_rect,
+                VideoScope::Canvas => Some(canvas_rect),
+            };
+        });
+        self.roi_crop_shown = self.roi_crop;
+        self.video_rect = video_rect;
+        // The status bar was drawn before the plots. The cursor only moves on input, after
+        // which egui draws one more frame, and that shows the new position.
+        self.cursor = output.cursor;
+        if let Some((view, p)) = output.clicked {
+            let clicked = nearest(&canvas_data, view, p)
+                .filter(|(_, distance)| *distance < self.prefs.pick_tolerance());
+            if let Some((neuron, _)) = clicked {
+                let name = neuron.name.clone();
+                self.actions.push(if self.selection.contains(&name) {
+                    Action::DeselectNeuron(name.clone())
+                } else {
+                    Action::SelectNeuron(name.clone())
+                });
+                self.flash = Some((name, now, true));
+            }
+        }
+        let grabbed = match output.drag {
+            Some((view, p, true)) => nearest(&canvas_data, view, p)
+                .filter(|(_, distance)| *distance < self.prefs.pick_tolerance())
+                .map(|(neuron, _)| (neuron.name.clone(), view)),
+            _ => None,
+        };
+        let hovered = row_hovered.map(|name| (name, None)).or_else(|| {
+            self.cursor
+                .and_then(|(view, p)| nearest(&canvas_data, view, p))
+                .filter(|(_, distance)| *distance < self.prefs.pick_tolerance())
+                .zip(self.cursor)
+                .map(|((neuron, _), (view, _))| (neuron.name.clone(), Some(view)))
+        });
+        if self.hovered != hovered {
+            // The other views and the list were drawn with the previous hover.
+            ctx.request_repaint();
+            if self.hovered.as_ref().map(|h| &h.0) != hovered.as_ref().map(|h| &h.0) {
+                self.hover_cycle = 0;
+            }
+            self.hovered = hovered.clone();
+        }
+        if let Some((name, _)) = &hovered {
+            if self.trace_neuron.as_ref() != Some(name) {
+                self.trace_neuron = Some(name.clone());
+            }
+        }
+        if let Some(view) = output.toggle_maximized {
+            self.toggle_maximized(view);
+        }
+        if self.tool == CanvasTool::Edit && !self.read_only {
+            if grabbed.is_some() {
+                self.editor.dragging = grabbed;
+            }
+            if let (Some((name, view)), Some((_, p, _))) =
+                (self.editor.dragging.clone(), output.drag)
+            {
+                if let Some(mut neuron) = self.data.get(&name).cloned() {
+                    edit::move_in_view(&mut neuron, view, p, self.orientation);
+                    self.editor.apply(&mut self.data, &name, Some(neuron));
+                    self.geometry.invalidate();
+                }
+            }
+            if output.drag.is_none() {
+                self.editor.dragging = None;
+            }
+        }
+        if !ctx.input(|i| i.pointer.any_down()) {
+            self.editor.close();
+        }
+        if output.pop_out_closed {
+            self.pop_out_views = false;
+        }
+        for (stored, shown) in self.window_geometry.iter_mut().zip(output.window_geometry) {
+            if shown.is_some() {
+                *stored = shown;
+            }
+        }
+        if let (Some(midline), Some(i)) = (output.midline, self.midline_edit) {
+            if let Some(dataset) = self.datasets.get_mut(i) {
+                dataset.midline = Some(midline);
+                self.geometry.invalidate();
+            }
+        }
+        if let Some((view, a, b)) = output.roi_rect {
+            let mut roi = self.roi.clone();
+            roi.set_from_view(view, a, b, self.orientation);
+            self.actions.push(Action::SetRoi(roi));
+        }
+        if let Some(edit) = output.annotation {
+            self.annotations.apply(edit);
+        }
+        self.actions.extend(removed_filter);
+        if !self.actions.is_empty() {
+            for action in std::mem::take(&mut self.actions) {
+                self.apply(ctx, action);
+            }
+        }
+        self.fire_events(hovered.map(|(name, _)| name));
+        self.tour.show(ctx);
+        toast::draw(ctx);
+        frame_scope.end(ctx);
+        if self.show_profiler {
+            profile::overlay(ctx);
+        }
+    }
+}
+
+/// Name prefixes of a search; `*` matches every name, as does an empty prefix.
+fn search_terms(search: &str) -> impl Iterator<Item = &str> {
+    search
+        .split(&[' ', ';', ',', '\t'])
+        .filter(|x| !x.is_empty())
+        .map(|x| if x == "*" { "" } else { x })
+}
+
+/// Neurons passing `search`, the `side` and `region_filter`, and when given, being in
+/// `selection` and inside `roi`; for an atlas spanning `extent`.
+fn filter_query<'a>(
+    (neurons, index, datasets): (&'a HashMap<String, Neuron>, &'a NameIndex, &'a [Dataset]),
+    search: &str,
+    extent: (f32, f32),
+    side: WormSide,
+    region_filter: RegionFilter,
+    (selection, roi): (Option<&'a BTreeSet<String>>, Option<&'a Roi>),
+) -> AtlasQuery<'a> {
+    let mut query = AtlasQuery::new(neurons).index(index).side(side);
+    let meta = Metadata::new(neurons, datasets);
+    query = search_query(query, search, extent, meta);
+    if !region_filter.is_empty() {
+        query = query.matching(move |n| region_filter.contains(n, extent, neurons));
+    }
+    if let Some(selection) = selection {
+        query = query.names(selection.iter().cloned());
+    }
+    if let Some(roi) = roi {
+        query = query.matching(move |n| roi.contains(n.position()));
+    }
+    query
+}
+
+/// Narrow `query` to the neurons `search` finds: names starting with one of its terms, in any
+/// of the regions its keywords name. Keywords alone find every neuron of their regions.
+fn search_query<'a>(
+    query: AtlasQuery<'a>,
+    search: &str,
+    extent: (f32, f32),
+    meta: Metadata<'a>,
+) -> AtlasQuery<'a> {
+    let mut names = Vec::new();
+    let mut regions = Vec::new();
+    let mut columns = Vec::new();
+    for term in search_terms(search) {
+        if let Some((key, value)) = metadata::search_term(term) {
+            columns.push((key.to_owned(), value.to_owned()));
+            continue;
+        }
+        match Region::from_keyword(term) {
+            Some(region) => regions.push(region),
+            None => names.push(term.to_owned()),
+        }
+    }
+    let mut query = query;
+    if !columns.is_empty() {
+        // Every column term must hold, as with the other filters.
+        query = query.matching(move |n| {
+            (columns.iter()).all(|(key, value)| meta.matches(&n.name, key, value))
+        });
+        if names.is_empty() && regions.is_empty() {
+            return query;
+        }
+    }
+    if regions.is_empty() {
+        return query.name_prefixes(names);
+    }
+    let query = if names.is_empty() {
+        query
+    } else {
+        query.name_prefixes(names)
+    };
+    query.matching(move |n| regions.iter().any(|r| r.contains(n, extent)))
+}
+
+/// The neuron of `shown` closest to `point` in `view`, and its distance.
+fn nearest<'a>(shown: &[&'a Neuron], view: View, [x, y]: [f64; 2]) -> Option<(&'a Neuron, f64)> {
+    shown
+        .iter()
+        .map(|n| {
+            let [px, py] = view.project(n.position());
+            (*n, l2_dist(px, x, py, y))
+        })
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+}
+
+/// A tab with its own loaded animals, filters and selection.
+///
+/// Only inactive workspaces keep their state here; the active one is swapped into
+/// [`MyApp`] so the rest of the app needn't know about tabs.
+#[derive(serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+struct Workspace {
+    name: String,
+    label: String,
+    view_side: WormSide,
+    selection: BTreeSet<String>,
+    selection_only: bool,
+    name_list: String,
+    #[serde(skip)]
+    name_list_match: Option<NameListMatch>,
+    #[serde(skip)]
+    datasets: Vec<Dataset>,
+    show_displacement: bool,
+    displacement_from: usize,
+    displacement_to: usize,
+    #[serde(skip)]
+    variability: Vec<NeuronStats>,
+    outlier_reference: usize,
+    outlier_dataset: usize,
+    #[serde(skip)]
+    outliers: Vec<Outlier>,
+    symmetry_source: usize,
+    #[serde(skip)]
+    midline_edit: Option<usize>,
+    roi: Roi,
+    roi_crop: bool,
+    annotations: Annotations,
+}
+
+impl Default for Workspace {
+    fn default() -> Self {
+        Self::new(String::new())
+    }
+}
+
+impl Workspace {
+    fn new(name: String) -> Self {
+        Self {
+            name,
+            label: "*".to_owned(),
+            view_side: WormSide::Both,
+            selection: BTreeSet::new(),
+            selection_only: false,
+            name_list: String::new(),
+            name_list_match: None,
+            datasets: Vec::new(),
+            show_displacement: false,
+            displacement_from: 0,
+            displacement_to: 1,
+            variability: Vec::new(),
+            outlier_reference: 0,
+            outlier_dataset: 1,
+            outliers: Vec::new(),
+            symmetry_source: 0,
+            midline_edit: None,
+            roi: Roi::default(),
+            roi_crop: false,
+            annotations: Annotations::default(),
+        }
+    }
+}
+
+impl MyApp {
+    /// Exchange the per-workspace state of the app with `workspaces[index]`.
+    fn swap_workspace(&mut self, index: usize) {
+        use std::mem::swap;
+        let ws = &mut self.workspaces[index];
+        swap(&mut self.label, &mut ws.label);
+        swap(&mut self.view_side, &mut ws.view_side);
+        swap(&mut self.selection, &mut ws.selection);
+        swap(&mut self.selection_only, &mut ws.selection_only);
+        swap(&mut self.name_list, &mut ws.name_list);
+        swap(&mut self.name_list_match, &mut ws.name_list_match);
+        swap(&mut self.datasets, &mut ws.datasets);
+        self.geometry.invalidate();
+        swap(&mut self.show_displacement, &mut ws.show_displacement);
+        swap(&mut self.displacement_from, &mut ws.displacement_from);
+        swap(&mut self.displacement_to, &mut ws.displacement_to);
+        swap(&mut self.variability, &mut ws.variability);
+        swap(&mut self.outlier_reference, &mut ws.outlier_reference);
+        swap(&mut self.outlier_dataset, &mut ws.outlier_dataset);
+        swap(&mut self.outliers, &mut ws.outliers);
+        swap(&mut self.symmetry_source, &mut ws.symmetry_source);
+        swap(&mut self.midline_edit, &mut ws.midline_edit);
+        swap(&mut self.roi, &mut ws.roi);
+        swap(&mut self.roi_crop, &mut ws.roi_crop);
+        swap(&mut self.annotations, &mut ws.annotations);
+    }
+
+    fn switch_workspace(&mut self, index: usize) {
+        if index == self.active_workspace || index >= self.workspaces.len() {
+            return;
+        }
+        self.swap_workspace(self.active_workspace);
+        self.swap_workspace(index);
+        self.active_workspace = index;
+        self.reset_views = [true; 3];
+    }
+
+    fn close_workspace(&mut self, index: usize) {
+        if self.workspaces.len() < 2 || index >= self.workspaces.len() {
+            return;
+        }
+        if index == self.active_workspace {
+            let next = if index == 0 { 1 } else { index - 1 };
+            self.switch_workspace(next);
+        }
+        self.workspaces.remove(index);
+        if self.active_workspace > index {
+            self.active_workspace -= 1;
+        }
+    }
+
+    /// Tab bar to switch, add, rename (right click) and close workspaces.
+    fn workspace_tabs(&mut self, ui: &mut egui::Ui) {
+        if self.workspaces.is_empty() {
+            self.workspaces
+                .push(Workspace::new("Workspace 1".to_owned()));
+        }
+        self.active_workspace = self.active_workspace.min(self.workspaces.len() - 1);
+        let mut switch_to = None;
+        let mut close = None;
+        ui.horizontal(|ui| {
+            let closable = self.workspaces.len() > 1;
+            for (i, ws) in self.workspaces.iter_mut().enumerate() {
+                let tab = ui.selectable_label(i == self.active_workspace, &ws.name);
+                if tab.clicked() {
+                    switch_to = Some(i);
+                }
+                if tab.middle_clicked() && closable {
+                    close = Some(i);
+                }
+                tab.context_menu(|ui| {
+                    ui.label(tr("Name:"));
+                    ui.text_edit_singleline(&mut ws.name);
+                    if ui.add_enabled(closable, Button::new(tr("Close"))).clicked() {
+                        close = Some(i);
+                        ui.close_menu();
+                    }
+                });
+            }
+            if ui
+                .small_button("+")
+                .on_hover_text(tr("New workspace"))
+                .clicked()
+            {
+                let name = format!("Workspace {}", self.workspaces.len() + 1);
+                self.workspaces.push(Workspace::new(name));
+                switch_to = Some(self.workspaces.len() - 1);
+            }
+        });
+        if let Some(i) = switch_to {
+            self.switch_workspace(i);
+        }
+        if let Some(i) = close {
+            self.close_workspace(i);
+        }
+    }
+}
+
+/// Everything of a project, written to and read from a bundle file (see [`bundle`]): the app
+/// state, the datasets of each workspace, which the app state leaves out, and the atlas
+/// if it was edited.
+#[derive(serde::Deserialize, serde::Serialize)]
+struct ProjectBundle<App, Datasets, Atlas> {
+    app: App,
+    datasets: Vec<Datasets>,
+    atlas: Option<Atlas>,
+}
+
+/// What the web app keeps of a session in IndexedDB: the datasets and the annotations of
+/// each workspace, and the atlas if it was edited.
+#[cfg(target_arch = "wasm32")]
+#[derive(serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+struct WebSession<Datasets, Annotations, Atlas> {
+    datasets: Vec<Datasets>,
+    annotations: Vec<Annotations>,
+    atlas: Option<Atlas>,
+}
+
+#[cfg(target_arch = "wasm32")]
+impl<D, A, T> Default for WebSession<D, A, T> {
+    fn default() -> Self {
+        Self {
+            datasets: Vec::new(),
+            annotations: Vec::new(),
+            atlas: None,
+        }
+    }
+}
+
+impl MyApp {
+    /// Labels of all active filters, with the action removing each.
+    fn filter_chips(&self) -> Vec<(String, Action)> {
+        let mut chips = Vec::new();
+        let terms = || self.label.split(&[' ', ';', ',', '\t']);
+        for term in terms() {
+            if term.is_empty() || term == "*" {
+                continue;
+            }
+            let rest: Vec<_> = terms().filter(|x| !x.is_empty() && *x != term).collect();
+            let search = if rest.is_empty() {
+                "*".to_owned()
+            } else {
+                rest.join(" ")
+            };
+            let text = match Region::from_keyword(term) {
+                Some(region) => format!("{} {}", tr("Region:"), region.keyword()),
+                None => format!("{} {term}", tr("Name:")),
+            };
+            chips.push((text, Action::SetFilter(search)));
+        }
+        if !matches!(self.view_side, WormSide::Both) {
+            let text = format!("{} {}", tr("Side:"), self.view_side);
+            chips.push((text, Action::SetSide(WormSide::Both)));
+        }
+        if self.selection_only {
+            chips.push((
+                tr("Selected only").to_owned(),
+                Action::SetSelectionOnly(false),
+            ));
+        }
+        if self.roi_crop {
+            chips.push((tr("Inside ROI").to_owned(), Action::SetRoiCrop(false)));
+        }
+        let without = |change: fn(&mut RegionFilter)| {
+            let mut filter = self.region_filter;
+            change(&mut filter);
+            Action::SetRegionFilter(filter)
+        };
+        if let Some(body) = self.region_filter.body {
+            let text = format!("{} {body}", tr("Region:"));
+            chips.push((text, without(|f| f.body = None)));
+        }
+        if let Some(pharyngeal) = self.region_filter.pharyngeal {
+            let text = tr(if pharyngeal {
+                "Pharyngeal"
+            } else {
+                "Extrapharyngeal"
+            });
+            chips.push((text.to_owned(), without(|f| f.pharyngeal = None)));
+        }
+        if let Some(paired) = self.region_filter.paired {
+            let text = tr(if paired { "Paired" } else { "Unpaired" });
+            chips.push((text.to_owned(), without(|f| f.paired = None)));
+        }
+        if let Some(function) = self.region_filter.function {
+            let text = format!("{} {function}", tr("Type:"));
+            chips.push((text, without(|f| f.function = None)));
+        }
+        chips
+    }
+
+    /// Cursor position, the neuron nearest to it in the hovered view and the number of
+    /// neurons passing the filters.
+    fn status_bar(&self, ui: &mut egui::Ui, shown: &[&Neuron]) {
+        ui.horizontal(|ui| {
+            match self.cursor {
+                Some((view, [x, y])) => {
+                    let position = ui
+                        .label(format!("{}: {x:.2}, {y:.2}", view.title()))
+                        .on_hover_text(tr("Right-click to copy"));
+                    if position.secondary_clicked() {
+                        toast::copy(ui.ctx(), format!("{x:.3}\t{y:.3}"), tr("Position copied"));
+                    }
+                    if let Some((neuron, distance)) = nearest(shown, view, [x, y]) {
+                        ui.separator();
+                        let label = ui.label(format!(
+                            "{} {}  ({})",
+                            tr("Nearest:"),
+                            neuron.name,
+                            self.prefs.format_distance(distance)
+                        ));
+                        if label
+                            .on_hover_text(tr("Right-click to copy"))
+                            .secondary_clicked()
+                        {
+                            let [x, y, z] = neuron.position();
+                            toast::copy(
+                                ui.ctx(),
+                                format!("{}\t{x:.3}\t{y:.3}\t{z:.3}", neuron.name),
+                                format!("{} {}", neuron.name, tr("copied")),
+                            );
+                        }
+                    }
+                }
+                None => {
+                    ui.weak(tr("Hover a plot to read out coordinates"));
+                }
+            }
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                ui.label(format!(
+                    "{} / {} {}",
+                    shown.len(),
+                    self.data.len(),
+                    tr("neurons shown")
+                ));
+            });
+        });
+    }
+}
+
+/// The `chips` of the active filters above the plots, with how many of the `total` neurons
+/// are `shown`; returns the action removing the chip clicked.
+fn filter_chip_bar(
+    ui: &mut egui::Ui,
+    chips: Vec<(String, Action)>,
+    shown: usize,
+    total: usize,
+) -> Option<Action> {
+    if chips.is_empty() {
+        return None;
+    }
+    let mut removed = None;
+    ui.horizontal_wrapped(|ui| {
+        ui.label(format!("{shown} / {total} {}", tr("shown, filtered by:")));
+        for (text, remove) in chips {
+            let btn = Button::new(RichText::new(format!("{text}  \u{2715}")).small())
+                .fill(Color32::from_rgba_unmultiplied(22, 131, 240, 60))
+                .rounding(8.0);
+            if ui.add(btn).on_hover_text(tr("Remove filter")).clicked() {
+                removed = Some(remove);
+            }
+        }
+    });
+    removed
+}
+
+impl MyApp {
+    /// Load a file as a dataset if it is a neuron table, otherwise as a list of names. A
+    /// table whose header does not name its columns is read in the format remembered for
+    /// files named alike, or else its columns are asked for in the mapping dialog. NWB files
+    /// are read as activity.
+    fn open_file(
+        &mut self,
+        ctx: &egui::Context,
+        name: String,
+        bytes: Vec<u8>,
+        path: Option<std::path::PathBuf>,
+    ) {
+        if bundle::is_bundle(&bytes) {
+            self.import_bundle(&bytes);
+            return;
+        }
+        if hdf5::is_hdf5(&bytes) {
+            self.load_activity(&name, &bytes);
+            return;
+        }
+        let format = TableFormat {
+            delimiter: self.import_delimiter,
+            columns: None,
+        };
+        if !mapping::needs_mapping(&bytes, &format) {
+            self.load_table(ctx, name, bytes, path, format);
+            return;
+        }
+        match self.table_formats.get(&mapping::file_pattern(&name)) {
+            Some(format) => {
+                let format = format.clone();
+                self.load_table(ctx, name, bytes, path, format);
+            }
+            None => {
+                let pending = PendingMapping::new(name, bytes, path, format.delimiter);
+                self.pending_mapping = Some(pending);
+            }
+        }
+    }
+
+    /// Replace the activity played back by that of a table or an NWB file.
+    fn load_activity(&mut self, name: &str, bytes: &[u8]) {
+        match Activity::read(name, bytes) {
+            Ok(activity) => {
+                self.activity = Some(activity);
+                self.activity_frame = 0;
+                self.color_mode = ColorMode::Activity;
+                self.show_trace_window = true;
+            }
+            Err(err) => self.load_error = Some(format!("{name}: {err}")),
+        }
+    }
+
+    fn load_table(
+        &mut self,
+        ctx: &egui::Context,
+        name: String,
+        bytes: Vec<u8>,
+        path: Option<std::path::PathBuf>,
+        format: TableFormat,
+    ) {
+        if bytes.len() >= loader::BACKGROUND_THRESHOLD {
+            let mut loading = Loading::start(ctx, name, bytes, format);
+            loading.path = path;
+            self.loading.push(loading);
+            return;
+        }
+        let mapped = format.columns.is_some();
+        match Dataset::from_bytes(&name, &bytes, format) {
+            Ok(dataset) => self.add_dataset(dataset, path),
+            Err(err) if mapped => self.load_error = Some(format!("{name}: {err}")),
+            Err(_) => {
+                self.name_list = String::from_utf8_lossy(&bytes).into_owned();
+                self.show_selection_window = true;
+            }
+        }
+    }
+
+    /// Dialog assigning the columns of a table whose header does not name them.
+    fn mapping_window(&mut self, ctx: &egui::Context) {
+        let Some(pending) = &mut self.pending_mapping else {
+            return;
+        };
+        let mut open = true;
+        let (mut load, mut cancel) = (false, false);
+        egui::Window::new(tr("Map columns"))
+            .open(&mut open)
+            .resizable(true)
+            .default_width(420.)
+            .show(ctx, |ui| {
+                ui.label(format!(
+                    "{} {}",
+                    pending.name,
+                    tr("does not name its columns. Choose the column of each field:")
+                ));
+                ui.horizontal(|ui| {
+                    ui.label(tr("Delimiter:"));
+                    let detected = Delimiter::detect(&pending.bytes);
+                    let delimiter = &mut pending.format.delimiter;
+                    if mapping::delimiter_combo(ui, "mapping_delimiter", delimiter, Some(detected))
+                    {
+                        pending.split();
+                    }
+                });
+                if let Some(columns) = &mut pending.format.columns {
+                    columns.ui(ui, &pending.rows);
+                    ui.separator();
+                    columns.preview_ui(ui, &pending.rows);
+                }
+                ui.separator();
+                ui.checkbox(
+                    &mut pending.remember,
+                    format!(
+                        "{} {}",
+                        tr("Use for files named like"),
+                        mapping::file_pattern(&pending.name)
+                    ),
+                );
+                ui.horizontal(|ui| {
+                    load = ui
+                        .add_enabled(
+                            (pending.format.columns.as_ref())
+                                .is_some_and(ColumnMapping::is_complete),
+                            egui::Button::new(tr("Load")),
+                        )
+                        .on_disabled_hover_text(tr(
+                            "Name, x, y and z each need a column of their own",
+                        ))
+                        .clicked();
+                    cancel = ui.button(tr("Cancel")).clicked();
+                });
+            });
+        if load {
+            let Some(pending) = self.pending_mapping.take() else {
+                return;
+            };
+            if pending.remember {
+                self.table_formats
+                    .insert(mapping::file_pattern(&pending.name), pending.format.clone());
+            }
+            self.load_table(
+                ctx,
+                pending.name,
+                pending.bytes,
+                pending.path,
+                pending.format,
+            );
+        } else if !open || cancel {
+            self.pending_mapping = None;
+        }
+    }
+
+    #[cfg_attr(target_arch = "wasm32", allow(unused_mut, unused_variables))]
+    fn add_dataset(&mut self, mut dataset: Dataset, path: Option<std::path::PathBuf>) {
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            dataset.source = path.map(dataset::SourceFile::new);
+        }
+        if !dataset.warnings.is_empty() {
+            self.show_warnings_window = true;
+        }
+        self.datasets.push(dataset);
+        self.geometry.invalidate();
+        self.load_error = None;
+        self.show_dataset_window = true;
+        self.outlier_dataset = self.datasets.len();
+        self.update_outliers();
+        if !self.outliers.is_empty() {
+            self.show_outlier_window = true;
+        }
+    }
+
+    /// Reload watched datasets whose files changed, checking about once a second.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn reload_watched(&mut self, ctx: &egui::Context) {
+        const INTERVAL: f64 = 1.0;
+        let watching = self
+            .datasets
+            .iter()
+            .any(|d| d.source.as_ref().is_some_and(|s| s.watch));
+        if !watching {
+            return;
+        }
+        ctx.request_repaint_after(std::time::Duration::from_secs_f64(INTERVAL));
+        let now = ctx.input(|i| i.time);
+        if now - self.last_watch_check < INTERVAL {
+            return;
+        }
+        self.last_watch_check = now;
+        let mut reloaded = false;
+        for dataset in &mut self.datasets {
+            match dataset.reload_if_changed() {
+                Some(Ok(())) => reloaded = true,
+                Some(Err(err)) => self.load_error = Some(err),
+                None => {}
+            }
+        }
+        if reloaded {
+            self.geometry.invalidate();
+            self.update_outliers();
+        }
+    }
+
+    /// Write the loaded datasets and the annotations of every workspace, and the atlas if it
+    /// was edited, to IndexedDB. They are too large for the local storage eframe keeps the
+    /// rest of the state in.
+    #[cfg(target_arch = "wasm32")]
+    fn store_session(&mut self) {
+        use std::hash::{Hash, Hasher};
+
+        let active = |i| i == self.active_workspace;
+        let session = WebSession {
+            datasets: (self.workspaces.iter().enumerate())
+                .map(|(i, ws)| {
+                    if active(i) {
+                        &self.datasets[..]
+                    } else {
+                        &ws.datasets[..]
+                    }
+                })
+                .collect(),
+            annotations: (self.workspaces.iter().enumerate())
+                .map(|(i, ws)| {
+                    if active(i) {
+                        &self.annotations
+                    } else {
+                        &ws.annotations
+                    }
+                })
+                .collect(),
+            atlas: self.editor.is_edited().then_some(&self.data),
+        };
+        let Ok(text) = ron::to_string(&session) else {
+            return;
+        };
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        text.hash(&mut hasher);
+        let hash = hasher.finish();
+        if self.stored_session != Some(hash) {
+            self.stored_session = Some(hash);
+            web::store_session(text);
+        }
+    }
+
+    /// Put back the datasets, annotations and atlas of the previous session once IndexedDB
+    /// delivers them.
+    #[cfg(target_arch = "wasm32")]
+    fn receive_session(&mut self) {
+        let Some(receiver) = &self.restored_session else {
+            return;
+        };
+        let text = match receiver.try_recv() {
+            Ok(text) => text,
+            Err(mpsc::TryRecvError::Empty) => return,
+            Err(mpsc::TryRecvError::Disconnected) => {
+                self.restored_session = None;
+                return;
+            }
+        };
+        self.restored_session = None;
+        // Sessions stored before the annotations and the atlas were hold the datasets alone.
+        let session = ron::from_str(&text).or_else(|err| match ron::from_str(&text) {
+            Ok(datasets) => Ok(WebSession {
+                datasets,
+                ..WebSession::default()
+            }),
+            Err(_) => Err(err),
+        });
+        let session: WebSession<Vec<Dataset>, Annotations, HashMap<String, Neuron>> = match session
+        {
+            Ok(session) => session,
+            Err(err) => {
+                self.load_error = Some(format!("previous session: {err}"));
+                return;
+            }
+        };
+        if let Some(atlas) = session.atlas.filter(|_| !self.editor.is_edited()) {
+            self.editor.original = Some(std::mem::replace(&mut self.data, atlas));
+            self.index = NameIndex::new(self.data.keys());
+            self.comparison.update(&self.data);
+        }
+        for (i, mut annotations) in session.annotations.into_iter().enumerate() {
+            let target = if i == self.active_workspace {
+                &mut self.annotations
+            } else if let Some(ws) = self.workspaces.get_mut(i) {
+                &mut ws.annotations
+            } else {
+                continue;
+            };
+            // Callouts drawn while the session was loading come after the restored ones.
+            annotations.items.append(&mut target.items);
+            *target = annotations;
+        }
+        for (i, mut datasets) in session.datasets.into_iter().enumerate() {
+            datasets.iter_mut().for_each(Dataset::reconvert);
+            let target = if i == self.active_workspace {
+                &mut self.datasets
+            } else if let Some(ws) = self.workspaces.get_mut(i) {
+                &mut ws.datasets
+            } else {
+                continue;
+            };
+            // Files opened while the session was loading come after the restored ones.
+            let opened = std::mem::replace(target, datasets);
+            target.extend(opened);
+        }
+        self.geometry.invalidate();
+        self.update_outliers();
+    }
+
+    /// Ask for a destination and write the whole project there as a bundle.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn export_bundle(&self) -> Result<(), String> {
+        let datasets = (self.workspaces.iter().enumerate())
+            .map(|(i, ws)| match i == self.active_workspace {
+                true => &self.datasets[..],
+                false => &ws.datasets[..],
+            })
+            .collect();
+        let atlas = self.editor.is_edited().then_some(&self.data);
+        let bytes = bundle::encode(&ProjectBundle {
+            app: self,
+            datasets,
+            atlas,
+        })?;
+        let name = format!("project.{}", bundle::EXTENSION);
+        dataset::save_file(&name, &self.prefs.export_dir, &bytes)
+    }
+
+    /// Replace the project by the one of a bundle, with its settings. Files still loading and
+    /// the activity recording belong to this session and stay.
+    fn import_bundle(&mut self, bytes: &[u8]) {
+        let project: ProjectBundle<Box<MyApp>, Vec<Dataset>, HashMap<String, Neuron>> =
+            match bundle::decode(bytes) {
+                Ok(project) => project,
+                Err(err) => {
+                    self.load_error = Some(format!("bundle: {err}"));
+                    return;
+                }
+            };
+        use std::mem::take;
+        let mut app = *project.app;
+        app.hooks = take(&mut self.hooks);
+        app.loading = take(&mut self.loading);
+        app.pending_files = take(&mut self.pending_files);
+        app.pending_mapping = self.pending_mapping.take();
+        app.activity = self.activity.take();
+        app.activity_frame = self.activity_frame;
+        app.trace_neuron = self.trace_neuron.take();
+        app.trace_pins = take(&mut self.trace_pins);
+        app.heatmap = take(&mut self.heatmap);
+        #[cfg(target_arch = "wasm32")]
+        {
+            app.stored_session = self.stored_session;
+            app.restored_session = self.restored_session.take();
+        }
+        app.persistence = self.persistence;
+        app.read_only = self.read_only;
+        if let Some(atlas) = project.atlas {
+            app.editor.original = Some(std::mem::replace(&mut app.data, atlas));
+            app.index = NameIndex::new(app.data.keys());
+        }
+        app.comparison.update(&app.data);
+        if app.workspaces.is_empty() {
+            app.workspaces
+                .push(Workspace::new("Workspace 1".to_owned()));
+        }
+        app.active_workspace = app.active_workspace.min(app.workspaces.len() - 1);
+        for (i, mut datasets) in project.datasets.into_iter().enumerate() {
+            datasets.iter_mut().for_each(Dataset::reconvert);
+            if i == app.active_workspace {
+                app.datasets = datasets;
+            } else if let Some(ws) = app.workspaces.get_mut(i) {
+                ws.datasets = datasets;
+            }
+        }
+        app.update_outliers();
+        app.reset_views = [true; 3];
+        *self = app;
+    }
+
+    /// Open the files that arrived since the last frame.
+    fn receive_pending_files(&mut self, ctx: &egui::Context) {
+        let mut arrived = Vec::new();
+        self.pending_files
+            .retain(|pending| match pending.try_recv() {
+                Ok(file) => {
+                    arrived.push(file);
+                    false
+                }
+                Err(mpsc::TryRecvError::Empty) => true,
+                Err(mpsc::TryRecvError::Disconnected) => false,
+            });
+        for file in arrived {
+            match file {
+                Ok((name, bytes)) => self.open_file(ctx, name, bytes, None),
+                Err(err) => self.load_error = Some(err),
+            }
+        }
+    }
+
+    /// Progress of files parsed in the background; finished ones are added as datasets.
+    fn loading_window(&mut self, ctx: &egui::Context) {
+        let mut finished = Vec::new();
+        self.loading.retain_mut(|loading| match loading.poll() {
+            Some(result) => {
+                finished.push((loading.name.clone(), loading.path.take(), result));
+                false
+            }
+            None => true,
+        });
+        for (name, path, result) in finished {
+            match result {
+                Ok(dataset) => self.add_dataset(dataset, path),
+                Err(err) => self.load_error = Some(format!("{name}: {err}")),
+            }
+        }
+        if self.loading.is_empty() {
+            return;
+        }
+        egui::Window::new(tr("Loading"))
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                for loading in &self.loading {
+                    ui.horizontal(|ui| {
+                        ui.label(&loading.name);
+                        ui.add(
+                            egui::ProgressBar::new(loading.progress())
+                                .desired_width(200.0)
+                                .show_percentage(),
+                        );
+                        if ui.button(tr("Cancel")).clicked() {
+                            loading.cancel();
+                        }
+                        ui.label(format!("{} rows", loading.partial.len()));
+                    });
+                }
+            });
+    }
+
+    /// Neuron table for a source index: 0 is the bundled atlas, `i` is `datasets[i - 1]`.
+    fn source(&self, index: usize) -> Option<&HashMap<String, Neuron>> {
+        match index {
+            0 => Some(&self.data),
+            i => self.datasets.get(i - 1).map(|d| &d.neurons),
+        }
+    }
+
+    fn source_name(&self, index: usize) -> &str {
+        match index {
+            0 => tr("NeuroPAL atlas"),
+            i => self
+                .datasets
+                .get(i - 1)
+                .map(|d| d.name.as_str())
+                .unwrap_or("-"),
+        }
+    }
+
+    fn source_combo(&self, ui: &mut egui::Ui, id: &str, index: &mut usize) {
+        egui::ComboBox::from_id_salt(id)
+            .selected_text(self.source_name(*index))
+            .show_ui(ui, |ui| {
+                for i in 0..=self.datasets.len() {
+                    ui.selectable_value(index, i, self.source_name(i));
+                }
+            });
+    }
+
+    /// Window listing the loaded datasets and comparing positions between two of them.
+    fn dataset_window(&mut self, ctx: &egui::Context) {
+        let mut open = self.show_dataset_window;
+        egui::Window::new(tr("Datasets"))
+            .open(&mut open)
+            .resizable(true)
+            .default_width(300.)
+            .show(ctx, |ui| {
+                if !self.read_only {
+                    ui.label(tr("Drop a CSV file with name, x, y, z and optional r, g, b columns to load a dataset."));
+                    ui.horizontal(|ui| {
+                        ui.label(tr("Delimiter:"));
+                        let delimiter = &mut self.import_delimiter;
+                        mapping::delimiter_combo(ui, "import_delimiter", delimiter, None);
+                    });
+                }
+                if let Some(err) = &self.load_error {
+                    ui.colored_label(ui.visuals().error_fg_color, err);
+                }
+                let mut remove = None;
+                let mut changed = false;
+                for (i, dataset) in self.datasets.iter_mut().enumerate() {
+                    ui.horizontal(|ui| {
+                        if !self.read_only && ui.small_button("\u{1F5D9}").clicked() {
+                            remove = Some(i);
+                        }
+                        ui.checkbox(&mut dataset.visible, "")
+                            .on_hover_text(tr("Overlay on the atlas"));
+                        ui.color_edit_button_srgba(&mut dataset.color);
+                        ui.label(format!(
+                            "{} ({} neurons)",
+                            dataset.name,
+                            dataset.neurons.len()
+                        ));
+                        if !dataset.warnings.is_empty() {
+                            let text = RichText::new(format!("\u{26A0} {}", dataset.warnings.len()))
+                                .color(ui.visuals().warn_fg_color);
+                            if ui
+                                .small_button(text)
+                                .on_hover_text(tr("Problems found in the file"))
+                                .clicked()
+                            {
+                                self.show_warnings_window = true;
+                            }
+                        }
+                        if let Some(source) = &mut dataset.source {
+                            ui.checkbox(&mut source.watch, tr("Watch"))
+                                .on_hover_text(format!(
+                                    "{}\n{}",
+                                    tr("Reload when the file changes on disk"),
+                                    source.path.display()
+                                ));
+                        }
+                    });
+                    ui.indent(i, |ui| {
+                        let before = (dataset.frame, dataset.transform);
+                        egui::ComboBox::from_id_salt(("frame", i))
+                            .selected_text(dataset.frame.to_string())
+                            .show_ui(ui, |ui| {
+                                for frame in CoordinateFrame::ALL {
+                                    ui.selectable_value(
+                                        &mut dataset.frame,
+                                        frame,
+                                        frame.to_string(),
+                                    );
+                                }
+                            });
+                        if dataset.frame != CoordinateFrame::Atlas {
+                            dataset.transform.ui(ui);
+                        }
+                        let mut principal = dataset.principal.is_some();
+                        if ui
+                            .checkbox(&mut principal, tr("Rotate into principal frame"))
+                            .on_hover_text(tr(
+                                "Turn the positions about their mean so the axes of largest spread become x, y and z",
+                            ))
+                            .changed()
+                        {
+                            dataset.set_principal_frame(principal);
+                            changed = true;
+                        }
+                        if dataset.frame == CoordinateFrame::LabMicrons {
+                            let mut editing = self.midline_edit == Some(i);
+                            if midline_ui(ui, i, dataset, &mut editing) {
+                                changed = true;
+                            }
+                            if editing {
+                                self.midline_edit = Some(i);
+                            } else if self.midline_edit == Some(i) {
+                                self.midline_edit = None;
+                            }
+                        }
+                        if before != (dataset.frame, dataset.transform) {
+                            dataset.reconvert();
+                            changed = true;
+                        }
+                    });
+                }
+                if let Some(i) = remove {
+                    self.datasets.remove(i);
+                    for source in [
+                        &mut self.displacement_from,
+                        &mut self.displacement_to,
+                        &mut self.outlier_reference,
+                        &mut self.outlier_dataset,
+                        &mut self.symmetry_source,
+                    ] {
+                        *source = source_after_removal(*source, i);
+                    }
+                    self.midline_edit = None;
+                    changed = true;
+                }
+                if changed {
+                    self.geometry.invalidate();
+                    self.update_outliers();
+                }
+                if ui.button(tr("Review outliers…")).clicked() {
+                    self.show_outlier_window = true;
+                }
+
+                ui.separator();
+                ui.label(RichText::new(tr("Displacement")).strong());
+                let (mut from, mut to) = (self.displacement_from, self.displacement_to);
+                ui.horizontal(|ui| {
+                    ui.label(tr("From:"));
+                    self.source_combo(ui, "displacement_from", &mut from);
+                });
+                ui.horizontal(|ui| {
+                    ui.label(tr("To:"));
+                    self.source_combo(ui, "displacement_to", &mut to);
+                });
+                (self.displacement_from, self.displacement_to) = (from, to);
+                ui.checkbox(&mut self.show_displacement, tr("Show displacement arrows"));
+
+                if let (Some(a), Some(b)) = (self.source(from), self.source(to)) {
+                    let stats = DisplacementStats::new(&dataset::displacements(a, b));
+                    displacement_grid(ui, &stats);
+                }
+
+                ui.separator();
+                ui.label(RichText::new(tr("Variability atlas")).strong());
+                ui.horizontal(|ui| {
+                    let enough = self.datasets.len() >= 2;
+                    if ui
+                        .add_enabled(enough, Button::new(tr("Build from datasets")))
+                        .on_disabled_hover_text(tr("Load at least two datasets"))
+                        .clicked()
+                    {
+                        let animals: Vec<_> = self.datasets.iter().map(|d| &d.neurons).collect();
+                        self.variability = variability::variability(&animals);
+                    }
+                    if !self.variability.is_empty() && ui.button(tr("Clear")).clicked() {
+                        self.variability.clear();
+                    }
+                });
+                if self.variability.is_empty() {
+                    return;
+                }
+                ui.label(format!(
+                    "{} neurons from {} animals",
+                    self.variability.len(),
+                    self.datasets.len()
+                ));
+                ui.checkbox(&mut self.show_spread, tr("Show positional spread (1 SD)"));
+                ui.horizontal(|ui| {
+                    if ui.button(tr("Add as dataset")).clicked() {
+                        self.datasets.push(Dataset::new(
+                            format!("Variability atlas (n={})", self.datasets.len()),
+                            self.variability
+                                .iter()
+                                .map(|s| (s.name.clone(), s.to_neuron()))
+                                .collect(),
+                        ));
+                        self.geometry.invalidate();
+                    }
+                    #[cfg(not(target_arch = "wasm32"))]
+                    if ui.button(tr("Export CSV…")).clicked() {
+                        let csv = variability::to_csv(&self.variability);
+                        if let Err(err) = dataset::save_file(
+                            "variability_atlas.csv",
+                            &self.prefs.export_dir,
+                            csv.as_bytes(),
+                        ) {
+                            self.load_error = Some(err);
+                        }
+                    }
+                });
+                let mut widest: Vec<_> = self.variability.iter().filter(|s| s.n > 1).collect();
+                widest.sort_unstable_by(|a, b| b.rms_spread().total_cmp(&a.rms_spread()));
+                ui.label(tr("Most variable neurons:"));
+                egui::Grid::new("variability_top")
+                    .striped(true)
+                    .show(ui, |ui| {
+                        for s in widest.iter().take(5) {
+                            ui.label(&s.name);
+                            ui.label(format!("n={}", s.n));
+                            ui.label(format!("{:.2}", s.rms_spread()));
+                            ui.end_row();
+                        }
+                    });
+            });
+        self.show_dataset_window = open;
+    }
+
+    /// Run the guided tour with the controls it points at visible.
+    fn start_tour(&mut self) {
+        self.tour.start();
+        self.show_side_panel = true;
+        self.maximized = None;
+    }
+
+    fn global_shortcuts(&mut self, ctx: &egui::Context) {
+        if ctx.input_mut(|i| ZOOM_IN_SHORTCUTS.iter().any(|s| i.consume_shortcut(s))) {
+            self.prefs.step_scale(0.1);
+        }
+        if ctx.input_mut(|i| i.consume_shortcut(&ZOOM_OUT_SHORTCUT)) {
+            self.prefs.step_scale(-0.1);
+        }
+        if ctx.input_mut(|i| i.consume_shortcut(&RESET_ALL_SHORTCUT)) {
+            self.reset_views = [true; 3];
+        }
+        if ctx.input_mut(|i| i.consume_shortcut(&PHARYNX_SHORTCUT)) {
+            self.actions.push(Action::CyclePharyngeal);
+        }
+        for (view, shortcut) in View::ALL.into_iter().zip(RESET_VIEW_SHORTCUTS) {
+            if ctx.input_mut(|i| i.consume_shortcut(&shortcut)) {
+                self.reset_views[view.index()] = true;
+            }
+        }
+        for (view, shortcut) in View::ALL.into_iter().zip(MAXIMIZE_VIEW_SHORTCUTS) {
+            if ctx.input_mut(|i| i.consume_shortcut(&shortcut)) {
+                self.toggle_maximized(view);
+            }
+        }
+        self.escape(ctx);
+        // Text fields keep their own undo.
+        if self.tool == CanvasTool::Annotate
+            && !ctx.wants_keyboard_input()
+            && ctx.input_mut(|i| i.consume_shortcut(&UNDO_SHORTCUT))
+        {
+            self.annotations.items.pop();
+        }
+        if self.tool == CanvasTool::Edit && !ctx.wants_keyboard_input() {
+            // Redo first: the undo shortcut matches with Shift held too.
+            if ctx.input_mut(|i| REDO_SHORTCUTS.iter().any(|s| i.consume_shortcut(s))) {
+                self.redo_edit();
+            }
+            if ctx.input_mut(|i| i.consume_shortcut(&UNDO_SHORTCUT)) {
+                self.undo_edit();
+            }
+        }
+    }
+
+    fn undo_edit(&mut self) {
+        if self.editor.undo(&mut self.data) {
+            self.atlas_changed();
+        }
+    }
+
+    fn redo_edit(&mut self) {
+        if self.editor.redo(&mut self.data) {
+            self.atlas_changed();
+        }
+    }
+
+    /// Refresh what is derived from the atlas after it was edited.
+    fn atlas_changed(&mut self) {
+        self.index = NameIndex::new(self.data.keys());
+        self.geometry.invalidate();
+    }
+
+    /// Handle Esc once, for the first of: a text field or a popup, which close themselves;
+    /// the tour, which ends; a maximized view, which is restored; the keyboard focus, which
+    /// leaves the list. The key is consumed so nothing later in the frame acts on it again.
+    fn escape(&mut self, ctx: &egui::Context) {
+        if ctx.wants_keyboard_input() || ctx.memory(|m| m.any_popup_open()) {
+            return;
+        }
+        let escape = || ctx.input_mut(|i| i.consume_key(Modifiers::NONE, Key::Escape));
+        if self.tour.is_running() {
+            if escape() {
+                self.tour.stop();
+            }
+        } else if let Some(view) = self.maximized {
+            if escape() {
+                self.toggle_maximized(view);
+            }
+        } else if self.focused.is_some() && escape() {
+            self.actions.push(Action::SetFocus(None));
+        }
+    }
+
+    /// Let `view` fill the central area, or restore the layout if it already does.
+    fn toggle_maximized(&mut self, view: View) {
+        self.maximized = (self.maximized != Some(view)).then_some(view);
+        self.reset_views[view.index()] = true;
+    }
+
+    fn update_outliers(&mut self) {
+        self.outliers = match (
+            self.source(self.outlier_reference),
+            self.source(self.outlier_dataset),
+        ) {
+            (Some(reference), Some(dataset)) if self.outlier_reference != self.outlier_dataset => {
+                outlier::find_outliers(reference, dataset, self.outlier_threshold)
+            }
+            _ => Vec::new(),
+        };
+    }
+
+    /// Problems found in the dataset files when they were read, each with a button focusing
+    /// the neuron it concerns in the list and the views.
+    fn warnings_window(&mut self, ctx: &egui::Context) {
+        let mut open = self.show_warnings_window;
+        let mut jump = None;
+        egui::Window::new(tr("Warnings"))
+            .open(&mut open)
+            .resizable(true)
+            .default_width(420.)
+            .show(ctx, |ui| {
+                let with_warnings: Vec<_> = self
+                    .datasets
+                    .iter()
+                    .filter(|d| !d.warnings.is_empty())
+                    .collect();
+                if with_warnings.is_empty() {
+                    ui.weak(tr("No problems found in the loaded datasets"));
+                }
+                ScrollArea::vertical().max_height(360.).show(ui, |ui| {
+                    for dataset in with_warnings {
+                        egui::CollapsingHeader::new(format!(
+                            "{} ({})",
+                            dataset.name,
+                            dataset.warnings.len()
+                        ))
+                        .default_open(true)
+                        .show(ui, |ui| {
+                            egui::Grid::new(("warnings", &dataset.name))
+                                .striped(true)
+                                .show(ui, |ui| {
+                                    for warning in &dataset.warnings {
+                                        ui.label(warning.line.map_or(String::new(), |l| {
+                                            format!("{} {l}", tr("line"))
+                                        }));
+                                        match &warning.name {
+                                            Some(name) => {
+                                                if ui
+                                                    .link(name)
+                                                    .on_hover_text(tr("Focus this neuron"))
+                                                    .clicked()
+                                                {
+                                                    jump = Some(name.clone());
+                                                }
+                                            }
+                                            None => {
+                                                ui.label("");
+                                            }
+                                        }
+                                        ui.label(warning.problem.to_string());
+                                        ui.end_row();
+                                    }
+                                });
+                        });
+                    }
+                });
+            });
+        self.show_warnings_window = open;
+        if let Some(name) = jump {
+            let now = ctx.input(|i| i.time);
+            self.actions.push(Action::SetFocus(Some(name.clone())));
+            self.flash = Some((name, now, true));
+        }
+    }
+
+    /// Review panel of likely mislabels in an imported dataset.
+    fn outlier_window(&mut self, ctx: &egui::Context) {
+        let mut open = self.show_outlier_window;
+        egui::Window::new(tr("Outliers"))
+            .open(&mut open)
+            .resizable(true)
+            .default_width(300.)
+            .show(ctx, |ui| {
+                let (mut reference, mut dataset) = (self.outlier_reference, self.outlier_dataset);
+                let mut threshold = self.outlier_threshold;
+                ui.horizontal(|ui| {
+                    ui.label(tr("Reference:"));
+                    self.source_combo(ui, "outlier_reference", &mut reference);
+                });
+                ui.horizontal(|ui| {
+                    ui.label(tr("Dataset:"));
+                    self.source_combo(ui, "outlier_dataset", &mut dataset);
+                });
+                ui.add(egui::Slider::new(&mut threshold, 1.0..=6.0).text("z-score threshold"));
+                if (reference, dataset, threshold)
+                    != (
+                        self.outlier_reference,
+                        self.outlier_dataset,
+                        self.outlier_threshold,
+                    )
+                {
+                    self.outlier_reference = reference;
+                    self.outlier_dataset = dataset;
+                    self.outlier_threshold = threshold;
+                    self.update_outliers();
+                }
+
+                ui.separator();
+                ui.label(format!("{} neurons flagged", self.outliers.len()));
+                if !self.outliers.is_empty() && ui.button(tr("Select all flagged")).clicked() {
+                    let flagged = self.outliers.iter().map(|o| o.name.clone()).collect();
+                    self.actions.push(Action::SetSelection(flagged));
+                }
+                ScrollArea::vertical().max_height(300.).show(ui, |ui| {
+                    egui::Grid::new("outliers").striped(true).show(ui, |ui| {
+                        ui.label(RichText::new(tr("Name")).strong());
+                        ui.label(RichText::new(tr("Distance")).strong());
+                        ui.label(RichText::new("z (pos)").strong());
+                        ui.label(RichText::new("z (color)").strong());
+                        ui.end_row();
+                        for o in &self.outliers {
+                            let selected = self.selection.contains(&o.name);
+                            if ui.selectable_label(selected, &o.name).clicked() {
+                                self.actions.push(if selected {
+                                    Action::DeselectNeuron(o.name.clone())
+                                } else {
+                                    Action::SelectNeuron(o.name.clone())
+                                });
+                            }
+                            ui.label(format!("{:.2}", o.distance));
+                            ui.label(format!("{:.1}", o.position_z));
+                            ui.label(format!("{:.1}", o.color_z));
+                            ui.end_row();
+                        }
+                    });
+                });
+            });
+        self.show_outlier_window = open;
+    }
+
+    /// Activity traces of the neuron last hovered or focused and of the pinned neurons.
+    fn trace_window(&mut self, ctx: &egui::Context) {
+        let Some(activity) = &self.activity else {
+            return;
+        };
+        if let Some(name) = &self.focused {
+            if self.trace_neuron.as_ref() != Some(name) && self.hovered.is_none() {
+                self.trace_neuron = Some(name.clone());
+            }
+        }
+        let mut open = self.show_trace_window;
+        egui::Window::new(tr("Activity Traces"))
+            .open(&mut open)
+            .resizable(true)
+            .default_width(420.)
+            .show(ctx, |ui| {
+                ui.horizontal_wrapped(|ui| {
+                    match &self.trace_neuron {
+                        Some(name) if !self.trace_pins.contains(name) => {
+                            ui.label(RichText::new(name).strong());
+                            if ui
+                                .small_button(format!("\u{1F4CC} {}", tr("Pin")))
+                                .on_hover_text(tr("Keep this trace in the plot"))
+                                .clicked()
+                            {
+                                self.trace_pins.insert(name.clone());
+                            }
+                        }
+                        Some(_) => {}
+                        None => {
+                            ui.weak(tr("Hover a neuron to show its trace"));
+                        }
+                    }
+                    if !self.selection.is_empty()
+                        && ui
+                            .small_button(tr("Pin selection"))
+                            .on_hover_text(tr("Keep the traces of the selected neurons"))
+                            .clicked()
+                    {
+                        self.trace_pins.extend(self.selection.iter().cloned());
+                    }
+                });
+                if !self.trace_pins.is_empty() {
+                    ui.horizontal_wrapped(|ui| {
+                        ui.label(tr("Pinned:"));
+                        let mut unpin = None;
+                        for name in &self.trace_pins {
+                            let missing = !activity.traces.contains_key(name);
+                            let text = format!("{name} \u{2716}");
+                            let button = ui.small_button(if missing {
+                                RichText::new(text).weak()
+                            } else {
+                                RichText::new(text)
+                            });
+                            let button = if missing {
+                                button.on_hover_text(tr("Not in the activity data"))
+                            } else {
+                                button.on_hover_text(tr("Unpin"))
+                            };
+                            if button.clicked() {
+                                unpin = Some(name.clone());
+                            }
+                        }
+                        if let Some(name) = unpin {
+                            self.trace_pins.remove(&name);
+                        }
+                    });
+                }
+                let names: Vec<&str> = (self.trace_pins.iter())
+                    .chain((self.trace_neuron.iter()).filter(|n| !self.trace_pins.contains(*n)))
+                    .map(String::as_str)
+                    .collect();
+                activity.trace_plot(ui, &names, &mut self.activity_frame);
+            });
+        self.show_trace_window = open;
+    }
+
+    /// Legend of a continuous color encoding. It spans the whole atlas, so colors don't
+    /// shift with the filters; there is none for depth while no neuron has a finite depth.
+    fn colorbar(&self) -> Option<Colorbar> {
+        match self.color_mode {
+            ColorMode::Depth => {
+                let (min, max) = (self.data.values())
+                    .map(|n| n.z)
+                    .filter(|z| z.is_finite())
+                    .fold((f32::MAX, f32::MIN), |(lo, hi), z| (lo.min(z), hi.max(z)));
+                if min > max {
+                    return None;
+                }
+                Some(Colorbar {
+                    label: "z".to_owned(),
+                    min,
+                    max,
+                    colormap: Colormap::Viridis,
+                })
+            }
+            ColorMode::Column => self.column_coloring.colorbar(),
+            ColorMode::Activity => self.activity.as_ref().map(Activity::colorbar),
+            _ => None,
+        }
+    }
+
+    /// Key to the marker colors in an exported figure; NeuroPAL colors need none.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn figure_legend(&self) -> Option<Legend> {
+        let title = self.color_mode.to_string();
+        let swatches = match self.color_mode {
+            ColorMode::NeuroPAL => return None,
+            ColorMode::Depth | ColorMode::Column | ColorMode::Activity => {
+                return self.colorbar().map(Legend::Colorbar);
+            }
+            ColorMode::ListMembership => (Membership::ALL.into_iter())
+                .map(|m| (m.color(), m.to_string()))
+                .collect(),
+            ColorMode::Cluster => {
+                let noise = self.clustering.members(None).next().is_some();
+                (0..self.clustering.count)
+                    .map(|i| {
+                        (
+                            cluster::color(Some(i)),
+                            format!("{} {}", tr("Cluster"), i + 1),
+                        )
+                    })
+                    .chain(noise.then(|| (cluster::color(None), tr("Noise").to_owned())))
+                    .collect()
+            }
+            ColorMode::Function => (FunctionalType::ALL.into_iter())
+                .map(|kind| (kind.color(), kind.to_string()))
+                .collect(),
+        };
+        Some(Legend::Swatches(title, swatches))
+    }
+
+    /// Layout of the composed figure, and where to write it.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn figure_window(&mut self, ctx: &egui::Context) {
+        let mut open = self.show_figure_window;
+        let mut export = false;
+        egui::Window::new(tr("Export Figure"))
+            .open(&mut open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                self.figure.ui(ui);
+                ui.weak(tr(
+                    "The neurons shown, with the selection and the callouts, in the current \
+                     colors and orientation.",
+                ));
+                let any = self.figure.views.iter().any(|&shown| shown);
+                export = ui.add_enabled(any, Button::new(tr("Export…"))).clicked();
+            });
+        self.show_figure_window = open;
+        if export {
+            if let Err(err) = self.export_figure(ctx) {
+                self.load_error = Some(err);
+            }
+        }
+    }
+
+    /// Compose the figure and ask where to write it, as a PNG image or a PDF document.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn export_figure(&self, ctx: &egui::Context) -> Result<(), String> {
+        let content = FigureContent {
+            markers: self.geometry.markers(),
+            selection: &self.selection,
+            annotations: &self.annotations,
+            orientation: self.orientation,
+            plot_styles: &self.plot_styles,
+            legend: self.figure_legend(),
+            prefs: &self.prefs,
+        };
+        let figure = figure::compose(&self.figure, &content);
+        let fonts = ctx.fonts(|f| f.lock().fonts.definitions().clone());
+        let bytes = match self.figure.format {
+            FigureFormat::Png => {
+                let scale = self.figure.width as f32 / figure.size.x;
+                raster::encode_png(&raster::render(&figure, scale, fonts))
+            }
+            FigureFormat::Pdf => pdf::encode(&figure, &fonts),
+        };
+        let name = format!("figure.{}", self.figure.format.extension());
+        dataset::save_file(&name, &self.prefs.export_dir, &bytes)
+    }
+
+    /// Settings of the video export, and where to write it. Hidden while recording, so it
+    /// stays out of the video.
+    fn video_window(&mut self, ctx: &egui::Context) {
+        let Some(activity) = &self.activity else {
+            return;
+        };
+        if self.recording.is_some() {
+            return;
+        }
+        let mut open = self.show_video_window;
+        let start = egui::Window::new(tr("Export Video"))
+            .open(&mut open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                self.video.ui(ui);
+                ui.weak(format!(
+                    "{} {}, {:.1} s",
+                    activity.frames(),
+                    tr("frames"),
+                    activity.frames() as f32 / self.video.fps
+                ));
+                ui.weak(tr("Frames are encoded by ffmpeg, which must be installed."));
+                #[cfg(not(target_arch = "wasm32"))]
+                if ui.button(tr("Export…")).clicked() {
+                    let extension = self.video.format.extension();
+                    let mut dialog = rfd::FileDialog::new()
+                        .set_file_name(format!("activity.{extension}"))
+                        .add_filter(self.video.format.to_string(), &[extension]);
+                    if !self.prefs.export_dir.is_empty() {
+                        dialog = dialog.set_directory(&self.prefs.export_dir);
+                    }
+                    return dialog.save_file();
+                }
+                None
+            });
+        self.show_video_window = open;
+        if let Some(path) = start.and_then(|window| window.inner).flatten() {
+            let frames = activity.frames();
+            self.recording = Some(Recording::new(self.video.clone(), path, frames));
+        }
+    }
+
+    /// Encode the frame last drawn into the video being exported once its screenshot
+    /// comes, and show the next frame.
+    fn record_video(&mut self, ctx: &egui::Context) {
+        let Some(mut recording) = self.recording.take() else {
+            return;
+        };
+        if self.activity.is_none() {
+            recording.cancel();
+            return;
+        }
+        let screenshot = ctx.input(|i| {
+            i.raw.events.iter().find_map(|event| match event {
+                egui::Event::Screenshot { image, .. } => Some(image.clone()),
+                _ => None,
+            })
+        });
+        if let Some(image) = screenshot.filter(|_| recording.waiting) {
+            recording.waiting = false;
+            let bounds = egui::Rect::from_min_size(
+                egui::Pos2::ZERO,
+                egui::vec2(image.width() as f32, image.height() as f32) / ctx.pixels_per_point(),
+            );
+            let region = *recording
+                .region
+                .get_or_insert(self.video_rect.unwrap_or(bounds).intersect(bounds));
+            let frame = image.region(&region, Some(ctx.pixels_per_point()));
+            if let Err(err) = recording.push(&frame) {
+                self.load_error = Some(err);
+                recording.cancel();
+                return;
+            }
+            if recording.frame >= recording.frames {
+                match recording.finish() {
+                    Ok(path) => {
+                        toast::show(ctx, format!("{} {}", tr("Saved"), path.to_string_lossy()))
+                    }
+                    Err(err) => self.load_error = Some(err),
+                }
+                return;
+            }
+        }
+        self.activity_frame = recording.frame;
+        if !recording.waiting {
+            // The screenshot is of the next frame drawn, which shows this activity frame.
+            recording.waiting = true;
+            ctx.send_viewport_cmd(egui::ViewportCommand::Screenshot(Default::default()));
+        }
+        ctx.request_repaint();
+        self.recording = Some(recording);
+    }
+
+    /// Report of how far each left/right pair is from mirror symmetry, in position and color.
+    fn symmetry_window(&mut self, ctx: &egui::Context) {
+        let mut open = self.show_symmetry_window;
+        egui::Window::new(tr("Bilateral Symmetry"))
+            .open(&mut open)
+            .resizable(true)
+            .default_width(380.)
+            .show(ctx, |ui| {
+                let mut source = self.symmetry_source;
+                ui.horizontal(|ui| {
+                    ui.label(tr("Dataset:"));
+                    self.source_combo(ui, "symmetry_source", &mut source);
+                });
+                self.symmetry_source = source;
+                ui.add(
+                    egui::Slider::new(&mut self.symmetry_highlight, 0..=50)
+                        .text(tr("Mark most asymmetric")),
+                )
+                .on_hover_text(tr("Pairs joined by a dashed line on the canvas"));
+                let Some(data) = self.source(self.symmetry_source) else {
+                    return;
+                };
+                let mut pairs = symmetry::pairs(data);
+                let (column, ascending) = self.symmetry_sort;
+                column.sort(&mut pairs, ascending);
+                ui.label(format!(
+                    "{} {}",
+                    pairs.len(),
+                    tr("left/right pairs, the right one mirrored")
+                ));
+                ui.separator();
+                ScrollArea::vertical().max_height(360.).show(ui, |ui| {
+                    egui::Grid::new("symmetry").striped(true).show(ui, |ui| {
+                        for header in SymmetryColumn::ALL {
+                            let arrow = match (header == column, ascending) {
+                                (false, _) => "",
+                                (true, true) => " \u{2B06}",
+                                (true, false) => " \u{2B07}",
+                            };
+                            let text = RichText::new(format!("{header}{arrow}")).strong();
+                            if ui.selectable_label(header == column, text).clicked() {
+                                self.symmetry_sort = if header == column {
+                                    (column, !ascending)
+                                } else {
+                                    (header, header == SymmetryColumn::Class)
+                                };
+                            }
+                            if header == SymmetryColumn::Position {
+                                ui.label(RichText::new("\u{0394}x, \u{0394}y, \u{0394}z").strong());
+                            }
+                        }
+                        ui.end_row();
+                        for pair in &pairs {
+                            let names = [format!("{}L", pair.class), format!("{}R", pair.class)];
+                            let selected = names.iter().all(|n| self.selection.contains(n));
+                            if ui.selectable_label(selected, &pair.class).clicked() {
+                                self.actions.push(Action::SetSelection(names.to_vec()));
+                            }
+                            ui.label(format!("{:.2}", pair.distance));
+                            let [dx, dy, dz] = pair.offset;
+                            ui.label(format!("{dx:+.1}, {dy:+.1}, {dz:+.1}"));
+                            ui.label(format!("{:.2}", pair.color));
+                            ui.end_row();
+                        }
+                    });
+                });
+            });
+        self.show_symmetry_window = open;
+    }
+
+    /// Position and color editors for the selected neurons of the atlas, shown with the edit
+    /// tool.
+    fn atlas_edit_window(&mut self, ctx: &egui::Context) {
+        if self.tool != CanvasTool::Edit || self.read_only {
+            return;
+        }
+        let mut open = true;
+        egui::Window::new(tr("Edit Atlas"))
+            .open(&mut open)
+            .resizable(true)
+            .default_width(360.)
+            .show(ctx, |ui| {
+                ui.label(tr(
+                    "Drag neurons on the canvas to move them, or select them to edit them here.",
+                ));
+                ui.separator();
+                let names: Vec<String> = (self.selection.iter())
+                    .filter(|name| self.data.contains_key(*name))
+                    .cloned()
+                    .collect();
+                if names.is_empty() {
+                    ui.weak(tr("No neurons selected"));
+                }
+                ScrollArea::vertical().max_height(320.).show(ui, |ui| {
+                    egui::Grid::new("atlas_edit").striped(true).show(ui, |ui| {
+                        for name in &names {
+                            let mut neuron = self.data[name].clone();
+                            if edit::neuron_ui(ui, &mut neuron) {
+                                self.editor.apply(&mut self.data, name, Some(neuron));
+                                self.geometry.invalidate();
+                            }
+                            if ui
+                                .small_button("\u{1F5D1}")
+                                .on_hover_text(tr("Remove from the atlas"))
+                                .clicked()
+                            {
+                                self.editor.apply(&mut self.data, name, None);
+                                self.actions.push(Action::DeselectNeuron(name.clone()));
+                                self.atlas_changed();
+                            }
+                            ui.end_row();
+                        }
+                    });
+                });
+                ui.separator();
+                let added = egui::CollapsingHeader::new(tr("Add neuron"))
+                    .show(ui, |ui| self.editor.form.ui(ui, &self.data))
+                    .body_returned
+                    .flatten();
+                if let Some(neuron) = added {
+                    let name = neuron.name.clone();
+                    self.actions.push(Action::SetSelection(vec![name.clone()]));
+                    self.editor.apply(&mut self.data, &name, Some(neuron));
+                    self.atlas_changed();
+                }
+                if self.editor.is_edited() {
+                    ui.separator();
+                    ui.horizontal(|ui| {
+                        let undo = ui
+                            .add_enabled(!self.editor.undo.is_empty(), Button::new(tr("Undo")))
+                            .on_hover_text(ctx.format_shortcut(&UNDO_SHORTCUT));
+                        if undo.clicked() {
+                            self.undo_edit();
+                        }
+                        let redo = ui
+                            .add_enabled(!self.editor.redo.is_empty(), Button::new(tr("Redo")))
+                            .on_hover_text(ctx.format_shortcut(&REDO_SHORTCUTS[0]));
+                        if redo.clicked() {
+                            self.redo_edit();
+                        }
+                        if ui.button(tr("Review changes…")).clicked() {
+                            self.show_diff_window = true;
+                        }
+                        if ui.button(tr("Revert all edits")).clicked() {
+                            self.editor.revert(&mut self.data);
+                            self.atlas_changed();
+                        }
+                    });
+                    let count = self.editor.undo.len() + self.editor.redo.len();
+                    egui::CollapsingHeader::new(format!("{} ({count})", tr("History"))).show(
+                        ui,
+                        |ui| {
+                            let mut step_to = None;
+                            ScrollArea::vertical().max_height(160.).show(ui, |ui| {
+                                let done = self.editor.undo.iter().map(|e| (e, true));
+                                let undone = self.editor.redo.iter().rev().map(|e| (e, false));
+                                for (i, (edit, applied)) in done.chain(undone).enumerate() {
+                                    let text = format!("{}. {}", i + 1, edit.describe());
+                                    let text = if applied {
+                                        RichText::new(text)
+                                    } else {
+                                        RichText::new(text).weak()
+                                    };
+                                    let current = i + 1 == self.editor.undo.len();
+                                    if ui
+                                        .selectable_label(current, text)
+                                        .on_hover_text(tr("Undo or redo up to this edit"))
+                                        .clicked()
+                                    {
+                                        step_to = Some(i + 1);
+                                    }
+                                }
+                            });
+                            if let Some(count) = step_to {
+                                self.editor.step_to(&mut self.data, count);
+                                self.atlas_changed();
+                            }
+                        },
+                    );
+                }
+            });
+        if !open {
+            self.tool = CanvasTool::Navigate;
+        }
+    }
+
+    /// Neurons added, removed or changed since the atlas was first edited, for reviewing the
+    /// edits before the atlas is exported.
+    fn diff_window(&mut self, ctx: &egui::Context) {
+        let mut open = self.show_diff_window;
+        egui::Window::new(tr("Atlas Changes"))
+            .open(&mut open)
+            .resizable(true)
+            .default_width(340.)
+            .show(ctx, |ui| {
+                let Some(original) = &self.editor.original else {
+                    ui.weak(tr("The atlas has not been edited"));
+                    return;
+                };
+                let changes = edit::diff(original, &self.data);
+                ui.horizontal(|ui| {
+                    for kind in [ChangeKind::Added, ChangeKind::Removed, ChangeKind::Changed] {
+                        let count = changes.iter().filter(|c| c.kind == kind).count();
+                        ui.label(format!("{kind}: {count}"));
+                    }
+                });
+                ui.checkbox(
+                    &mut self.show_edit_arrows,
+                    tr("Arrows from original to edited positions"),
+                );
+                ui.separator();
+                ScrollArea::vertical().max_height(320.).show(ui, |ui| {
+                    egui::Grid::new("atlas_changes")
+                        .striped(true)
+                        .show(ui, |ui| {
+                            for change in &changes {
+                                let selected = self.selection.contains(&change.name);
+                                let exists = change.kind != ChangeKind::Removed;
+                                if ui
+                                    .add_enabled(
+                                        exists,
+                                        egui::SelectableLabel::new(selected, &change.name),
+                                    )
+                                    .clicked()
+                                {
+                                    self.actions.push(if selected {
+                                        Action::DeselectNeuron(change.name.clone())
+                                    } else {
+                                        Action::SelectNeuron(change.name.clone())
+                                    });
+                                }
+                                ui.label(change.kind.to_string());
+                                let mut details = Vec::new();
+                                if let Some(d) = change.moved() {
+                                    details.push(format!(
+                                        "{} {}",
+                                        tr("moved"),
+                                        self.prefs.format_distance(d as f64)
+                                    ));
+                                }
+                                if change.recolored {
+                                    details.push(tr("recolored").to_owned());
+                                }
+                                if change.annotated {
+                                    details.push(tr("region changed").to_owned());
+                                }
+                                ui.label(details.join(", "));
+                                ui.end_row();
+                            }
+                        });
+                });
+            });
+        self.show_diff_window = open;
+    }
+
+    fn prefs_window(&mut self, ctx: &egui::Context) {
+        let mut open = self.show_prefs_window;
+        egui::Window::new(tr("Preferences"))
+            .open(&mut open)
+            .resizable(false)
+            .show(ctx, |ui| self.prefs.ui(ui));
+        self.show_prefs_window = open;
+    }
+
+    /// The current display settings as a preset called `name`.
+    fn current_style(&self, name: String) -> StylePreset {
+        StylePreset {
+            name,
+            color_mode: self.color_mode,
+            column_coloring: self.column_coloring.clone(),
+            depth_cue: self.prefs.depth_cue,
+            point_size: self.prefs.point_size,
+            outline_width: self.prefs.outline_width,
+            plot_styles: self.plot_styles.clone(),
+            orientation: self.orientation,
+            show_ganglia: self.show_ganglia,
+            show_principal_axes: self.show_principal_axes,
+        }
+    }
+
+    fn apply_style(&mut self, index: usize) {
+        let Some(preset) = self.style_presets.get(index).cloned() else {
+            return;
+        };
+        self.color_mode = preset.color_mode;
+        self.column_coloring = preset.column_coloring;
+        self.prefs.depth_cue = preset.depth_cue;
+        self.prefs.point_size = preset.point_size;
+        self.prefs.outline_width = preset.outline_width;
+        self.plot_styles = preset.plot_styles;
+        self.orientation = preset.orientation;
+        self.show_ganglia = preset.show_ganglia;
+        self.show_principal_axes = preset.show_principal_axes;
+        self.style_preset = Some(index);
+    }
+
+    /// Whether the settings were changed since the active preset was applied.
+    fn style_modified(&self) -> bool {
+        self.style_preset
+            .and_then(|index| self.style_presets.get(index))
+            .is_some_and(|preset| self.current_style(preset.name.clone()) != *preset)
+    }
+
+    /// Toolbar dropdown switching between the style presets, or saving the current style as one.
+    fn style_preset_combo(&mut self, ui: &mut egui::Ui) {
+        let active = self
+            .style_preset
+            .and_then(|index| self.style_presets.get(index));
+        let text = match active {
+            Some(preset) if self.style_modified() => format!("{}*", preset.name),
+            Some(preset) => preset.name.clone(),
+            None => tr("Style preset").to_owned(),
+        };
+        let mut apply = None;
+        egui::ComboBox::from_id_salt("style_preset")
+            .selected_text(RichText::new(text).monospace())
+            .show_ui(ui, |ui| {
+                for (index, preset) in self.style_presets.iter().enumerate() {
+                    if ui
+                        .selectable_label(self.style_preset == Some(index), &preset.name)
+                        .clicked()
+                    {
+                        apply = Some(index);
+                    }
+                }
+                if !self.style_presets.is_empty() {
+                    ui.separator();
+                }
+                if ui.button(tr("Save current style…")).clicked() {
+                    let name = format!("{} {}", tr("Preset"), self.style_presets.len() + 1);
+                    self.style_presets.push(self.current_style(name));
+                    self.style_preset = Some(self.style_presets.len() - 1);
+                    self.show_style_window = true;
+                }
+            })
+            .response
+            .on_hover_text(tr(
+                "Coloring, depth cue, marker size and plot styles saved under a name; an asterisk marks changes since the preset was applied",
+            ));
+        if let Some(index) = apply {
+            self.apply_style(index);
+        }
+    }
+
+    /// Name, update and delete the active style preset.
+    fn style_preset_ui(&mut self, ui: &mut egui::Ui) {
+        ui.label(RichText::new(tr("Preset")).strong());
+        let Some(index) = self.style_preset.filter(|&i| i < self.style_presets.len()) else {
+            ui.label(tr(
+                "Save the current style from the toolbar to make a preset.",
+            ));
+            return;
+        };
+        let modified = self.style_modified();
+        ui.horizontal(|ui| {
+            ui.add(
+                egui::TextEdit::singleline(&mut self.style_presets[index].name)
+                    .desired_width(140.0),
+            );
+            if ui
+                .add_enabled(modified, egui::Button::new(tr("Update")))
+                .on_hover_text(tr("Save the current settings into this preset"))
+                .clicked()
+            {
+                let name = self.style_presets[index].name.clone();
+                self.style_presets[index] = self.current_style(name);
+            }
+            if ui
+                .add_enabled(modified, egui::Button::new(tr("Revert")))
+                .clicked()
+            {
+                self.apply_style(index);
+            }
+            if ui.button(tr("Delete")).clicked() {
+                self.style_presets.remove(index);
+                self.style_preset = None;
+            }
+        });
+    }
+
+    /// Figure styling of the plots, independent of the egui theme.
+    fn style_window(&mut self, ctx: &egui::Context) {
+        let mut open = self.show_style_window;
+        egui::Window::new(tr("Plot Style"))
+            .open(&mut open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    for view in View::ALL {
+                        ui.selectable_value(&mut self.style_view, view.index(), view.title());
+                    }
+                });
+                ui.separator();
+                self.plot_styles[self.style_view].ui(ui);
+                ui.separator();
+                ui.label(RichText::new(tr("Orientation")).strong());
+                self.orientation.ui(ui);
+                ui.separator();
+                ui.label(RichText::new(tr("Layers")).strong());
+                ui.checkbox(&mut self.show_ganglia, tr("Ganglion outlines"))
+                    .on_hover_text(tr("Convex hulls of the neurons of each ganglion"));
+                ui.checkbox(&mut self.show_principal_axes, tr("Principal axes"))
+                    .on_hover_text(tr(
+                        "Axes of largest to smallest spread of the neurons shown, two standard deviations long",
+                    ));
+                self.density.ui(ui);
+                ui.separator();
+                ui.label(RichText::new(tr("Layout")).strong());
+                for layout in ViewLayout::ALL {
+                    ui.radio_value(&mut self.layout, layout, layout.to_string());
+                }
+                if !cfg!(target_arch = "wasm32") {
+                    ui.checkbox(
+                        &mut self.pop_out_views,
+                        tr("Orthogonal views in own window"),
+                    )
+                    .on_hover_text(tr("Move them to a second monitor; not used when docked"));
+                    ui.checkbox(&mut self.pop_out_table, tr("Neuron list in own window"));
+                }
+                ui.separator();
+                ui.horizontal(|ui| {
+                    if ui.button(tr("Apply to all plots")).clicked() {
+                        let style = self.plot_styles[self.style_view].clone();
+                        self.plot_styles = [style.clone(), style.clone(), style];
+                    }
+                    if ui.button(tr("Reset")).clicked() {
+                        self.plot_styles[self.style_view] = PlotStyle::default();
+                    }
+                });
+                ui.separator();
+                self.style_preset_ui(ui);
+            });
+        self.show_style_window = open;
+    }
+
+    /// Window to paste (or drop a file with) a list of neuron names and turn it into a selection.
+    fn selection_window(&mut self, ctx: &egui::Context) {
+        let mut open = self.show_selection_window;
+        egui::Window::new(tr("Selection"))
+            .open(&mut open)
+            .resizable(true)
+            .default_width(260.)
+            .show(ctx, |ui| {
+                ui.label(tr("Paste neuron names or drop a text file:"));
+                ScrollArea::vertical().max_height(160.).show(ui, |ui| {
+                    ui.add(
+                        egui::TextEdit::multiline(&mut self.name_list)
+                            .desired_width(f32::INFINITY)
+                            .hint_text("AVAL AVAR\nRIAL, RIAR"),
+                    );
+                });
+                ui.horizontal(|ui| {
+                    let names = selection::parse_name_list(&self.name_list);
+                    if ui.button(tr("Select")).clicked() {
+                        let result = selection::match_names(&names, &self.data);
+                        let matched = result.matched.iter().cloned().collect();
+                        self.actions.push(Action::SetSelection(matched));
+                        self.name_list_match = Some(result);
+                    }
+                    if ui.button(tr("Add")).clicked() {
+                        let result = selection::match_names(&names, &self.data);
+                        self.actions
+                            .extend(result.matched.iter().cloned().map(Action::SelectNeuron));
+                        self.name_list_match = Some(result);
+                    }
+                    if ui.button(tr("Clear")).clicked() {
+                        self.actions.push(Action::ClearSelection);
+                        self.name_list_match = None;
+                    }
+                    if ui
+                        .add_enabled(!self.selection.is_empty(), Button::new(tr("Copy")))
+                        .on_hover_text(tr("Copy the selected names"))
+                        .clicked()
+                    {
+                        let names: Vec<_> = self.selection.iter().map(String::as_str).collect();
+                        toast::copy(
+                            ctx,
+                            names.join("\n"),
+                            format!("{} {}", names.len(), tr("names copied")),
+                        );
+                    }
+                });
+                let mut selection_only = self.selection_only;
+                if ui
+                    .checkbox(&mut selection_only, tr("Show selected only"))
+                    .changed()
+                {
+                    self.actions.push(Action::SetSelectionOnly(selection_only));
+                }
+                ui.checkbox(&mut self.show_selection_hull, tr("Outline the selection"))
+                    .on_hover_text(tr(
+                        "Draw the convex hull of the selected neurons in each view",
+                    ));
+                if self.show_selection_hull {
+                    let positions: Vec<[f32; 3]> = (self.selection.iter())
+                        .filter_map(|name| self.data.get(name))
+                        .map(|n| self.orientation.apply(n.position()))
+                        .collect();
+                    for view in View::ALL {
+                        let points: Vec<_> = positions.iter().map(|p| view.project(*p)).collect();
+                        let area = hull::area(&hull::convex_hull(&points));
+                        ui.label(format!(
+                            "{}: {} {}",
+                            view.title(),
+                            tr("area"),
+                            self.prefs.format_area(area)
+                        ));
+                    }
+                }
+                ui.separator();
+                ui.label(format!("{} neurons selected", self.selection.len()));
+                if let Some(result) = &self.name_list_match {
+                    ui.label(format!("{} names matched", result.matched.len()));
+                    if !result.unmatched.is_empty() {
+                        ui.colored_label(
+                            ui.visuals().warn_fg_color,
+                            format!(
+                                "{} not found: {}",
+                                result.unmatched.len(),
+                                result.unmatched.join(", ")
+                            ),
+                        );
+                    }
+                }
+            });
+        self.show_selection_window = open;
+    }
+
+    /// Window comparing two name lists and coloring neurons by their membership.
+    fn compare_window(&mut self, ctx: &egui::Context) {
+        let mut open = self.show_compare_window;
+        egui::Window::new(tr("Compare Lists"))
+            .open(&mut open)
+            .resizable(true)
+            .default_width(360.)
+            .show(ctx, |ui| {
+                ui.columns(2, |cols| {
+                    for (ui, (title, text)) in cols.iter_mut().zip([
+                        (tr("List A"), &mut self.comparison.text_a),
+                        (tr("List B"), &mut self.comparison.text_b),
+                    ]) {
+                        ui.label(RichText::new(title).strong());
+                        ScrollArea::vertical()
+                            .id_salt(title)
+                            .max_height(160.)
+                            .show(ui, |ui| {
+                                ui.add(
+                                    egui::TextEdit::multiline(text).desired_width(f32::INFINITY),
+                                );
+                            });
+                    }
+                });
+                if ui.button(tr("Compare")).clicked() {
+                    self.comparison.update(&self.data);
+                    self.color_mode = ColorMode::ListMembership;
+                }
+                ui.separator();
+                egui::Grid::new("membership_legend").show(ui, |ui| {
+                    for membership in Membership::ALL {
+                        let count = self
+                            .data
+                            .keys()
+                            .filter(|name| self.comparison.membership(name) == membership)
+                            .count();
+                        ui.colored_label(membership.color(), "\u{2B24}");
+                        ui.label(membership.to_string());
+                        ui.label(count.to_string());
+                        ui.end_row();
+                    }
+                });
+                for (title, result) in [("A", &self.comparison.a), ("B", &self.comparison.b)] {
+                    if !result.unmatched.is_empty() {
+                        ui.colored_label(
+                            ui.visuals().warn_fg_color,
+                            format!("List {title} not found: {}", result.unmatched.join(", ")),
+                        );
+                    }
+                }
+            });
+        self.show_compare_window = open;
+    }
+
+    /// Window clustering the atlas by position, and optionally color, and selecting or
+    /// coloring neurons by cluster.
+    fn cluster_window(&mut self, ctx: &egui::Context) {
+        let mut open = self.show_cluster_window;
+        egui::Window::new(tr("Clusters"))
+            .open(&mut open)
+            .resizable(true)
+            .default_width(280.)
+            .show(ctx, |ui| {
+                self.cluster_settings.ui(ui);
+                ui.horizontal(|ui| {
+                    if ui.button(tr("Run")).clicked() {
+                        let mut neurons: Vec<&Neuron> = self.data.values().collect();
+                        neurons.sort_unstable_by(|a, b| a.name.cmp(&b.name));
+                        self.clustering = self.cluster_settings.run(&neurons);
+                        self.color_mode = ColorMode::Cluster;
+                    }
+                    if ui
+                        .add_enabled(!self.clustering.is_empty(), Button::new(tr("Clear")))
+                        .clicked()
+                    {
+                        self.clustering = Clustering::default();
+                    }
+                });
+                if self.clustering.is_empty() {
+                    return;
+                }
+                ui.separator();
+                let clusters = (0..self.clustering.count).map(Some).chain([None]);
+                ScrollArea::vertical().max_height(240.).show(ui, |ui| {
+                    egui::Grid::new("cluster_legend").show(ui, |ui| {
+                        for cluster in clusters {
+                            let members: Vec<String> =
+                                self.clustering.members(cluster).cloned().collect();
+                            if cluster.is_none() && members.is_empty() {
+                                continue;
+                            }
+                            ui.colored_label(cluster::color(cluster), "\u{2B24}");
+                            ui.label(match cluster {
+                                Some(i) => format!("{} {}", tr("Cluster"), i + 1),
+                                None => tr("Noise").to_owned(),
+                            });
+                            ui.label(members.len().to_string());
+                            if ui.small_button(tr("Select")).clicked() {
+                                self.actions.push(Action::SetSelection(members));
+                            }
+                            ui.end_row();
+                        }
+                    });
+                });
+            });
+        self.show_cluster_window = open;
+    }
+}
+
+/// Move the activity, one of its frames, and the slice sweep as `seek` asks.
+fn seek_layers(
+    playback: &mut Playback,
+    seek: Seek,
+    (activity, frame): (Option<&Activity>, &mut usize),
+    sweep: &mut Option<usize>,
+) {
+    if let Some(activity) = activity {
+        playback.seek(frame, activity.frames(), seek);
+    }
+    if let Some(step) = sweep {
+        playback.seek(step, SWEEP_STEPS, seek);
+    }
+}
+
+/// Point of the lateral view the slices go through at `step` of the sweep: at that fraction
+/// of the way along the shown neurons, halfway up.
+fn sweep_slice(shown: &[&Neuron], step: usize) -> Option<[f64; 2]> {
+    let (min, max) = (shown.iter()).map(|n| View::XY.project(n.position())).fold(
+        ([f64::MAX; 2], [f64::MIN; 2]),
+        |(min, max), [x, y]| {
+            (
+                [min[0].min(x), min[1].min(y)],
+                [max[0].max(x), max[1].max(y)],
+            )
+        },
+    );
+    let t = step as f64 / (SWEEP_STEPS - 1) as f64;
+    (min[0] <= max[0]).then(|| [min[0] + t * (max[0] - min[0]), (min[1] + max[1]) / 2.0])
+}
+
+/// Color of each functional type and how many of the `shown` neurons are of it.
+fn functional_legend(ui: &mut egui::Ui, shown: &[&Neuron]) {
+    let mut counts = [0; FunctionalType::ALL.len() + 1];
+    for neuron in shown {
+        let kind = functional::of(neuron);
+        counts[kind.map_or(FunctionalType::ALL.len(), |k| k as usize)] += 1;
+    }
+    egui::Grid::new("functional_legend").show(ui, |ui| {
+        for (kind, count) in FunctionalType::ALL.into_iter().zip(counts) {
+            ui.colored_label(kind.color(), "\u{2B24}");
+            ui.label(kind.to_string());
+            ui.label(count.to_string());
+            ui.end_row();
+        }
+        if let Some(&unknown) = counts.last().filter(|&&c| c > 0) {
+            ui.colored_label(Color32::GRAY, "\u{2B24}");
+            ui.label(tr("Unknown"));
+            ui.label(unknown.to_string());
+            ui.end_row();
+        }
+    });
+}
+
+/// How many of `shown` are the left and the right neuron of a pair in `neurons`, and how
+/// many lie on the midline without a partner.
+fn side_counts<'a>(
+    shown: impl Iterator<Item = &'a Neuron>,
+    neurons: &HashMap<String, Neuron>,
+) -> [usize; 3] {
+    let mut counts = [0; 3];
+    for neuron in shown {
+        let side = match symmetry::is_paired(&neuron.name, neurons) {
+            true if neuron.name.ends_with('L') => 0,
+            true => 1,
+            false => 2,
+        };
+        counts[side] += 1;
+    }
+    counts
+}
+
+/// Scrubber through the frames of `activity`, with toggles of the trace and heatmap windows;
+/// returns whether the activity was closed.
+fn timeline(
+    ui: &mut egui::Ui,
+    activity: &Activity,
+    frame: &mut usize,
+    [show_traces, show_heatmap]: [&mut bool; 2],
+) -> bool {
+    ui.horizontal(|ui| {
+        ui.label(&activity.name).on_hover_text(format!(
+            "{} {}, {} {}",
+            activity.traces.len(),
+            tr("neurons"),
+            activity.frames(),
+            tr("frames")
+        ));
+        let close = ui
+            .small_button("\u{2716}")
+            .on_hover_text(tr("Close the activity"))
+            .clicked();
+        ui.toggle_value(show_traces, tr("Traces"));
+        ui.toggle_value(show_heatmap, tr("Heatmap"));
+        activity.timeline_ui(ui, frame);
+        close
+    })
+    .inner
+}
+
+/// Midline editor of a lab-frame dataset; returns `true` when the positions were re-straightened.
+///
+/// `editing` toggles editing the control points on the xy canvas.
+fn midline_ui(ui: &mut egui::Ui, id: usize, dataset: &mut Dataset, editing: &mut bool) -> bool {
+    let mut changed = false;
+    egui::CollapsingHeader::new(tr("Midline (head to tail, x,y per line)"))
+        .id_salt(("midline", id))
+        .default_open(dataset.midline.is_none())
+        .show(ui, |ui| {
+            ui.add(
+                egui::TextEdit::multiline(&mut dataset.midline_text)
+                    .desired_rows(4)
+                    .hint_text("12.0,40.5\n30.2,44.1\n..."),
+            );
+            let mut midline = dataset.midline.clone().unwrap_or_default();
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut midline.reversed, tr("Tail first"));
+                ui.checkbox(&mut midline.flip_dv, tr("Dorsal on the right"));
+            });
+            let parsed = Midline::parse(&dataset.midline_text);
+            if let (Err(err), false) = (&parsed, dataset.midline_text.trim().is_empty()) {
+                ui.colored_label(ui.visuals().error_fg_color, err);
+            }
+            ui.horizontal(|ui| {
+                if ui
+                    .add_enabled(parsed.is_ok(), Button::new(tr("Straighten")))
+                    .clicked()
+                {
+                    midline.points = parsed.clone().unwrap_or_default();
+                    dataset.midline = Some(midline.clone());
+                    changed = true;
+                }
+                #[cfg(not(target_arch = "wasm32"))]
+                if ui.button(tr("Load file…")).clicked() {
+                    if let Some(text) = rfd::FileDialog::new()
+                        .pick_file()
+                        .and_then(|p| std::fs::read_to_string(p).ok())
+                    {
+                        dataset.midline_text = text;
                     }
-                    let pts = [neuron.z as f64, neuron.y as f64];
+                }
+                if dataset.midline.is_some() && ui.button(tr("Remove")).clicked() {
+                    dataset.midline = None;
+                    *editing = false;
+                    changed = true;
+                }
+            });
+            let label = if *editing {
+                tr("Done editing")
+            } else {
+                tr("Edit on canvas")
+            };
+            if ui
+                .toggle_value(editing, label)
+                .on_hover_text(tr(
+                    "Drag points to move, Shift+click to append, right-click to delete",
+                ))
+                .clicked()
+            {
+                if *editing {
+                    dataset.midline.get_or_insert(midline.clone());
+                } else if let Some(current) = &dataset.midline {
+                    dataset.midline_text = current.to_text();
+                    changed = true;
+                }
+            }
+            if let Some(current) = &mut dataset.midline {
+                if (current.reversed, current.flip_dv) != (midline.reversed, midline.flip_dv) {
+                    current.reversed = midline.reversed;
+                    current.flip_dv = midline.flip_dv;
+                    changed = true;
+                }
+            } else {
+                ui.colored_label(
+                    ui.visuals().warn_fg_color,
+                    tr("Lab-frame positions are not straightened."),
+                );
+            }
+        });
+    if changed {
+        dataset.reconvert();
+    }
+    changed
+}
+
+fn displacement_grid(ui: &mut egui::Ui, stats: &DisplacementStats) {
+    egui::Grid::new("displacement_stats").show(ui, |ui| {
+        ui.label(tr("Matched neurons"));
+        ui.label(stats.count.to_string());
+        ui.end_row();
+        ui.label(tr("Mean vector (x, y, z)"));
+        let [dx, dy, dz] = stats.mean_vector;
+        ui.label(format!("({dx:.2}, {dy:.2}, {dz:.2})"));
+        ui.end_row();
+        ui.label(tr("Mean distance"));
+        ui.label(format!("{:.2}", stats.mean_length));
+        ui.end_row();
+        ui.label(tr("Median distance"));
+        ui.label(format!("{:.2}", stats.median_length));
+        ui.end_row();
+        if let Some((name, length)) = &stats.max {
+            ui.label(tr("Largest shift"));
+            ui.label(format!("{name} ({length:.2})"));
+            ui.end_row();
+        }
+    });
+}
+
+/// Tab separated name and position of each neuron, ready to paste into a spreadsheet.
+fn neuron_table(data: &[&Neuron]) -> String {
+    let mut table = String::from("name\tx\ty\tz\n");
+    for n in data {
+        table.push_str(&format!("{}\t{:.3}\t{:.3}\t{:.3}\n", n.name, n.x, n.y, n.z));
+    }
+    table
+}
+
+/// Rows of the neuron list drawn with an emphasis.
+struct RowMarks<'a> {
+    /// Row focused with the arrow keys.
+    focus: Option<usize>,
+    /// Row to scroll into view this frame.
+    scroll_to: Option<usize>,
+    /// Row flashing after its neuron was clicked in a plot, and the strength of the flash.
+    flash: Option<(usize, f32)>,
+    /// Name of the neuron hovered in a plot or in the list.
+    hovered: Option<&'a str>,
+}
+
+/// Paint the rows of the neuron list that are scrolled into view, with the value of a
+/// metadata `column` if one is chosen, and all metadata in the tooltip of a row. Returns the
+/// name of the neuron whose row is hovered.
+fn huge_content_painter(
+    ui: &mut egui::Ui,
+    data: &[&Neuron],
+    selection: &BTreeSet<String>,
+    marks: RowMarks<'_>,
+    prefs: &Preferences,
+    (meta, column): (Metadata<'_>, Option<&str>),
+) -> Option<String> {
+    let RowMarks {
+        focus,
+        scroll_to,
+        flash,
+        hovered,
+    } = marks;
+    let font_id = prefs.list_font();
+    ui.add_space(4.0);
+    let row_height = ui.fonts(|f| f.row_height(&font_id)) + ui.spacing().item_spacing.y;
+    let chars = if column.is_some() {
+        28 + LIST_COLUMN_CHARS + 1
+    } else {
+        28
+    };
+    let row_width =
+        ui.fonts(|f| f.glyph_width(&font_id, 'X')) * chars as f32 + ui.spacing().item_spacing.x;
+    let num_rows = data.len();
+    ScrollArea::vertical()
+        .auto_shrink(false)
+        .show_viewport(ui, |ui, viewport| {
+            ui.set_height(row_height * num_rows as f32);
+            let mut row_hovered = None;
+
+            let first_item = (viewport.min.y / row_height).floor().at_least(0.0) as usize;
+            let last_item = (viewport.max.y / row_height).ceil() as usize + 1;
+            let last_item = last_item.at_most(num_rows);
+
+            let mut used_rect = Rect::NOTHING;
+            let row_rect = |i: usize| {
+                let x = ui.min_rect().left() + ui.spacing().item_spacing.x;
+                let y = ui.min_rect().top() + i as f32 * row_height;
+                Rect::from_min_max(pos2(x, y), pos2(x + row_width, y + row_height))
+            };
+            if let Some(i) = scroll_to {
+                ui.scroll_to_rect(row_rect(i), Some(egui::Align::Center));
+            }
 
-                    let points = PlotPoints::new(vec![pts]);
-                    let [r, g, b] = neuron.rgb();
+            for i in first_item..last_item {
+                if let Some(neuron) = data.get(i) {
+                    let text = neuron.name.as_str();
+                    let (r, g, b) = (neuron.r * 255., neuron.g * 255., neuron.b * 255.);
 
-                    let mut color = if r == 0 && g == 0 && b == 0 && is_dark {
+                    let lut = neuron.luminance();
+                    let (mut r, mut g, mut b) = (r as u8, g as u8, b as u8);
+
+                    if r == 0 && g == 0 && b == 0 {
+                        r = 255;
+                        g = 255;
+                        b = 255;
+                    }
+                    let text_color = if lut == 0.0 || lut > 0.5 {
+                        egui::Color32::BLACK
+                    } else {
                         egui::Color32::WHITE
+                    };
+                    let selected = selection.contains(&neuron.name);
+                    let stroke = if focus == Some(i) {
+                        (3.0, prefs.focus_color())
+                    } else if hovered == Some(text) {
+                        (2.0, prefs.hover_stroke().color)
+                    } else if selected {
+                        (2.0, prefs.selection_color())
                     } else {
-                        let [r, g, b] = neuron.rgb();
-                        egui::Color32::from_rgb(r, g, b)
+                        (0.0, egui::Color32::from_rgb(r, g, b))
                     };
-
-                    if neuron.z < 0.0 {
-                        color = color.gamma_multiply(0.8);
+                    let row_rect = row_rect(i);
+                    ui.painter()
+                        .rect(row_rect, 0.0f32, egui::Color32::from_rgb(r, g, b), stroke);
+                    let text_rect = ui.painter().text(
+                        row_rect.min,
+                        Align2::LEFT_TOP,
+                        format!(
+                            "{:<5} ({:>5.1}, {:>5.1}, {:>5.1}){}",
+                            text,
+                            neuron.x,
+                            neuron.y,
+                            neuron.z,
+                            column.map_or(String::new(), |key| {
+                                let value = meta.get(&neuron.name, key).unwrap_or("");
+                                let value: String = value.chars().take(LIST_COLUMN_CHARS).collect();
+                                format!(" {value}")
+                            })
+                        ),
+                        font_id.clone(),
+                        text_color,
+                    );
+                    used_rect = used_rect.union(text_rect);
+                    if let Some((_, alpha)) = flash.filter(|(row, _)| *row == i) {
+                        let color = prefs.focus_color().gamma_multiply(alpha);
+                        ui.painter()
+                            .rect_stroke(row_rect, 0.0, egui::Stroke::new(4.0, color));
+                    }
+                    // The rows are only painted; give screen readers a node for each.
+                    let mut response =
+                        ui.interact(row_rect, ui.id().with(("row", text)), Sense::hover());
+                    let entries = meta.of(&neuron.name);
+                    if !entries.is_empty() {
+                        response = response.on_hover_ui(|ui| {
+                            egui::Grid::new("row_meta").show(ui, |ui| {
+                                for (key, value, source) in entries {
+                                    ui.label(RichText::new(key).strong());
+                                    ui.label(value);
+                                    ui.weak(source.unwrap_or(""));
+                                    ui.end_row();
+                                }
+                            });
+                        });
                     }
+                    if response.hovered() {
+                        row_hovered = Some(neuron.name.clone());
+                    }
+                    response.widget_info(|| {
+                        let mut label = format!(
+                            "{text}, x {:.1}, y {:.1}, z {:.1}",
+                            neuron.x, neuron.y, neuron.z
+                        );
+                        if selected {
+                            label = format!("{label}, {}", tr("selected"));
+                        }
+                        egui::WidgetInfo::labeled(egui::WidgetType::Label, true, label)
+                    });
+                }
+            }
 
-                    plot_ui.points(
-                        Points::new(points)
-                            .name(&neuron.name)
-                            .allow_hover(true)
-                            .color(color)
-                            .highlight(true)
-                            .radius(radius as f32),
-                    );
+            ui.allocate_rect(used_rect, Sense::hover()); // make sure it is visible!
+            row_hovered
+        })
+        .inner
+}
 
-                    if pos.is_some_and(|pos| {
-                        l2_dist(neuron.x as f64, pos.x, neuron.y as f64, pos.y) < 0.35
-                    }) {
-                        plot_ui.vline(VLine::new(neuron.z).color(Color32::LIGHT_RED));
-                        let points = PlotPoints::new(vec![pts]);
-                        plot_ui.points(
-                            Points::new(points)
-                                .color(egui::Color32::LIGHT_RED)
-                                .filled(false)
-                                .radius(radius as f32 + 2.0),
-                        );
-                        let text_pos = [
-                            neuron.z as f64 + radius / 1.5,
-                            neuron.y as f64 + radius / 1.5,
-                        ]
-                        .into();
+/// Extra layers drawn underneath the neurons in every view.
+#[derive(Default)]
+struct CanvasOverlay {
+    /// Arrows from a neuron's position in one dataset to its position in another.
+    displacement: Vec<([f32; 3], [f32; 3])>,
+    /// Mean position and covariance of each neuron in a variability atlas.
+    spread: Vec<([f32; 3], [[f32; 3]; 3])>,
+    /// Positions of loaded datasets drawn on top of the atlas, one color per dataset.
+    points: Vec<(Vec<[f32; 3]>, Color32)>,
+    /// Positions of the atlas neurons of each ganglion, outlined by their convex hull.
+    ganglia: Vec<(&'static str, Vec<[f32; 3]>)>,
+    /// Positions of the selected neurons, outlined by their convex hull with its area.
+    selection_hull: Vec<[f32; 3]>,
+    /// Density contour lines with their levels in each view, indexed by [`View::index`].
+    density: [Vec<(f64, Vec<[f64; 2]>)>; 3],
+    principal_axes: Option<PrincipalAxes>,
+    /// Class and positions of the left and right neuron of the most asymmetric pairs.
+    asymmetric: Vec<(String, [f32; 3], [f32; 3])>,
+    /// Arrows from the original to the edited position of each moved atlas neuron.
+    edits: Vec<([f32; 3], [f32; 3])>,
+    /// Pairs of neurons with correlated activity, and their correlation.
+    correlations: Vec<([f32; 3], [f32; 3], f32)>,
+}
 
-                        plot_ui.text(Text::new(text_pos, &neuron.name).highlight(true));
-                    }
+impl CanvasOverlay {
+    /// Keep only the elements anchored at a position for which `keep` holds.
+    fn retain(&mut self, keep: impl Fn([f32; 3]) -> bool) {
+        self.displacement.retain(|(from, _)| keep(*from));
+        self.edits.retain(|(_, to)| keep(*to));
+        self.spread.retain(|(mean, _)| keep(*mean));
+        for (points, _) in &mut self.points {
+            points.retain(|p| keep(*p));
+        }
+        for (_, points) in &mut self.ganglia {
+            points.retain(|p| keep(*p));
+        }
+        self.selection_hull.retain(|p| keep(*p));
+        self.principal_axes = self.principal_axes.filter(|axes| keep(axes.mean));
+        self.asymmetric
+            .retain(|(_, left, right)| keep(*left) || keep(*right));
+        self.correlations.retain(|(a, b, _)| keep(*a) && keep(*b));
+    }
+
+    fn draw(&self, plot_ui: &mut PlotUi, view: View, prefs: &Preferences) {
+        for (level, line) in &self.density[view.index()] {
+            plot_ui.line(
+                Line::new(PlotPoints::new(line.clone()))
+                    .color(DENSITY_COLOR)
+                    .width(0.5 + 2.0 * *level as f32),
+            );
+        }
+        let points: Vec<_> = self
+            .selection_hull
+            .iter()
+            .map(|p| view.project(*p))
+            .collect();
+        let outline = hull::convex_hull(&points);
+        if outline.len() >= 3 {
+            let color = prefs.selection_color();
+            let text = format!("{} {}", tr("area"), prefs.format_area(hull::area(&outline)));
+            let top = outline.iter().copied().max_by(|a, b| a[1].total_cmp(&b[1]));
+            if let Some(top) = top {
+                plot_ui.text(
+                    Text::new(top.into(), text)
+                        .color(color)
+                        .anchor(Align2::CENTER_BOTTOM),
+                );
+            }
+            plot_ui.polygon(
+                Polygon::new(PlotPoints::new(outline))
+                    .fill_color(color.gamma_multiply(0.1))
+                    .stroke(egui::Stroke::new(1.5, color)),
+            );
+        }
+        if view != View::XZ {
+            for (name, points) in &self.ganglia {
+                let points: Vec<_> = points.iter().map(|p| view.project(*p)).collect();
+                let outline = hull::convex_hull(&points);
+                if outline.len() < 3 {
+                    continue;
                 }
+                let color = ganglion::color(name);
+                plot_ui.text(
+                    Text::new(hull::center(&outline).into(), tr(name))
+                        .color(color)
+                        .anchor(Align2::CENTER_CENTER),
+                );
+                plot_ui.polygon(
+                    Polygon::new(PlotPoints::new(outline))
+                        .fill_color(color.gamma_multiply(0.12))
+                        .stroke(egui::Stroke::new(1.5, color)),
+                );
+            }
+        }
+        for (points, color) in &self.points {
+            let points = points.iter().map(|p| view.project(*p)).collect();
+            let points = batch::level_of_detail(plot_ui, points);
+            plot_ui.points(
+                Points::new(PlotPoints::new(points))
+                    .color(*color)
+                    .filled(false)
+                    .radius(3.0),
+            );
+        }
+        for (mean, cov) in &self.spread {
+            let outline = variability::ellipse(view.project(*mean), view.project_cov(cov), 32);
+            plot_ui.line(
+                Line::new(PlotPoints::new(outline))
+                    .color(Color32::from_rgba_unmultiplied(150, 150, 150, 160))
+                    .width(1.0),
+            );
+        }
+        if !self.displacement.is_empty() {
+            let (origins, tips): (Vec<_>, Vec<_>) = self
+                .displacement
+                .iter()
+                .map(|(from, to)| (view.project(*from), view.project(*to)))
+                .unzip();
+            plot_ui.arrows(
+                Arrows::new(PlotPoints::new(origins), PlotPoints::new(tips))
+                    .color(Color32::from_rgb(0, 172, 193))
+                    .tip_length(6.0),
+            );
+        }
+        if !self.edits.is_empty() {
+            let (origins, tips): (Vec<_>, Vec<_>) = self
+                .edits
+                .iter()
+                .map(|(from, to)| (view.project(*from), view.project(*to)))
+                .unzip();
+            plot_ui.arrows(
+                Arrows::new(PlotPoints::new(origins), PlotPoints::new(tips))
+                    .color(EDIT_COLOR)
+                    .tip_length(6.0),
+            );
+        }
+        for (a, b, r) in &self.correlations {
+            // Positive correlations in red and negative ones in blue, stronger ones wider.
+            let color = Colormap::Coolwarm.color((r + 1.0) / 2.0);
+            plot_ui.line(
+                Line::new(PlotPoints::new(vec![view.project(*a), view.project(*b)]))
+                    .color(color.gamma_multiply(0.8))
+                    .width(0.5 + 2.5 * r.abs()),
+            );
+        }
+        for (class, left, right) in &self.asymmetric {
+            let (left, right) = (view.project(*left), view.project(*right));
+            let middle = [(left[0] + right[0]) / 2.0, (left[1] + right[1]) / 2.0];
+            plot_ui.line(
+                Line::new(PlotPoints::new(vec![left, right]))
+                    .color(ASYMMETRY_COLOR)
+                    .style(egui_plot::LineStyle::dashed_loose()),
+            );
+            plot_ui.points(
+                Points::new(PlotPoints::new(vec![left, right]))
+                    .color(ASYMMETRY_COLOR)
+                    .filled(false)
+                    .radius(7.0),
+            );
+            plot_ui.text(Text::new(middle.into(), class).color(ASYMMETRY_COLOR));
+        }
+        if let Some(axes) = &self.principal_axes {
+            for (i, (from, to)) in axes.segments().into_iter().enumerate() {
+                let (from, to) = (view.project(from), view.project(to));
+                let color = pca::AXIS_COLORS[i];
+                plot_ui.line(
+                    Line::new(PlotPoints::new(vec![from, to]))
+                        .color(color)
+                        .width(2.0),
+                );
+                plot_ui.text(Text::new(to.into(), format!("PC{}", i + 1)).color(color));
+            }
+        }
+    }
+}
+
+/// Everything [`worm_canvas`] needs besides the neurons themselves.
+struct CanvasSettings<'a> {
+    selection: &'a BTreeSet<String>,
+    geometry: &'a GeometryCache,
+    overlay: &'a CanvasOverlay,
+    /// Styling of the xy, zy and xz plots, indexed by [`View::index`].
+    plot_styles: &'a [PlotStyle; 3],
+    orientation: Orientation,
+    /// Midline being edited in the xy view, with the transform of its dataset.
+    midline: Option<&'a (Midline, FrameTransform)>,
+    tool: CanvasTool,
+    roi: &'a Roi,
+    annotations: &'a Annotations,
+    /// Only the ROI is shown and the views are fitted to it.
+    crop: bool,
+    /// Restore the default bounds of each view (indexed by [`View::index`]) this frame.
+    reset: [bool; 3],
+    window_geometry: &'a [Option<WindowGeometry>; 3],
+    layout: ViewLayout,
+    /// A single view filling the whole central area.
+    maximized: Option<View>,
+    /// Show the floating orthogonal views in a separate OS window.
+    pop_out: bool,
+    prefs: &'a Preferences,
+    /// Neuron focused with the arrow keys, outlined in every view.
+    focus: Option<&'a Neuron>,
+    /// Neurons the search being typed would keep, outlined in every view.
+    preview: &'a [&'a Neuron],
+    /// Pan the views to center the focused neuron this frame.
+    center_focus: bool,
+    /// Lateral-view position the orthogonal views slice through while no plot is hovered.
+    slice: Option<[f64; 2]>,
+    /// Legend of the color encoding, when it is continuous.
+    colorbar: Option<Colorbar>,
+    /// Neuron under the pointer in the last frame and the view it was hovered in, `None`
+    /// for a row of the list.
+    hovered: Option<(&'a Neuron, Option<View>)>,
+    /// Candidates to skip when labelling the hovered neuron in the orthogonal views.
+    hover_cycle: usize,
+}
+
+/// Edits made directly on the canvas during a frame.
+#[derive(Default)]
+struct CanvasOutput {
+    midline: Option<Midline>,
+    /// Rectangle finished with the ROI tool, in the displayed coordinates of a view.
+    roi_rect: Option<(View, [f64; 2], [f64; 2])>,
+    /// Callout drawn or removed with the annotation tool.
+    annotation: Option<AnnotationEdit>,
+    /// Where the floating views ended up, indexed by [`View::index`].
+    window_geometry: [Option<WindowGeometry>; 3],
+    /// A view whose maximize button was clicked.
+    toggle_maximized: Option<View>,
+    /// The separate window of the orthogonal views was closed.
+    pop_out_closed: bool,
+    /// Plot coordinates under the pointer and the view they belong to.
+    cursor: Option<(View, [f64; 2])>,
+    /// Plot coordinates of a click made while navigating, and the view clicked.
+    clicked: Option<(View, [f64; 2])>,
+    /// Plot coordinates of the pointer during a drag with the edit tool, the view dragged
+    /// in, and whether the drag started this frame.
+    drag: Option<(View, [f64; 2], bool)>,
+    /// Where the xy plot was drawn, across both halves when the sides are split.
+    xy_rect: Option<egui::Rect>,
+}
+
+/// Neurons drawn in a lateral view, one of two when the layout splits it.
+#[derive(Clone, Copy, PartialEq)]
+enum XyPart {
+    Side(WormSide),
+    /// Neurons of a body region, which lie between these positions along the body.
+    Span(BodyRegion, [f32; 2]),
+}
+
+impl XyPart {
+    fn contains(&self, [x, _, z]: [f32; 3]) -> bool {
+        match self {
+            Self::Side(side) => side.contains(z),
+            Self::Span(_, [min, max]) => (*min..=*max).contains(&x),
+        }
+    }
+}
+
+/// Parts of the lateral view holding the head and the tail neurons of `data`.
+fn body_ends(data: &[&Neuron]) -> [(String, XyPart); 2] {
+    let extent = region::extent(data.iter().copied());
+    [BodyRegion::Head, BodyRegion::Tail].map(|end| {
+        let span = (data.iter())
+            .filter(|n| region::body_region(n, extent) == end)
+            .fold([f32::MAX, f32::MIN], |[min, max], n| {
+                [min.min(n.x), max.max(n.x)]
+            });
+        (end.to_string(), XyPart::Span(end, span))
+    })
+}
+
+/// Record where the pointer is over a plot, and where it clicked while `navigating`.
+fn track_pointer(plot_ui: &PlotUi, view: View, navigating: bool, output: &mut CanvasOutput) {
+    let Some(p) = plot_ui.pointer_coordinate() else {
+        return;
+    };
+    let response = plot_ui.response();
+    if response.hovered() {
+        output.cursor = Some((view, [p.x, p.y]));
+    }
+    if navigating && response.clicked() {
+        output.clicked = Some((view, [p.x, p.y]));
+    }
+}
+
+/// Record a primary drag over a plot, which moves a neuron with the edit tool.
+fn track_drag(plot_ui: &PlotUi, view: View, output: &mut CanvasOutput) {
+    let (Some(p), response) = (plot_ui.pointer_coordinate(), plot_ui.response()) else {
+        return;
+    };
+    if response.dragged() {
+        output.drag = Some((view, [p.x, p.y], response.drag_started()));
+    }
+}
+
+/// Buttons above a plot to restore its default bounds and to maximize it. Returns whether
+/// the bounds should be reset this frame.
+fn view_header(
+    ui: &mut egui::Ui,
+    view: View,
+    settings: &CanvasSettings<'_>,
+    output: &mut CanvasOutput,
+) -> bool {
+    let maximized = settings.maximized == Some(view);
+    let clicked = ui
+        .horizontal(|ui| {
+            let reset = ui
+                .small_button(tr("\u{27F2} Reset view"))
+                .on_hover_text(tr("Restore the default bounds (or double-click the plot)"))
+                .clicked();
+            let (label, hint) = if maximized {
+                (tr("Restore layout"), tr("Show all views again (Esc)"))
+            } else {
+                (tr("Maximize"), tr("Let this view fill the window"))
+            };
+            if ui.small_button(label).on_hover_text(hint).clicked() {
+                output.toggle_maximized = Some(view);
+            }
+            reset
+        })
+        .inner;
+    clicked || settings.reset[view.index()]
+}
+
+/// The source index (see [`MyApp::source`]) that still names the same source once
+/// `datasets[removed]` is gone; the removed dataset itself falls back to the atlas.
+fn source_after_removal(source: usize, removed: usize) -> usize {
+    match source.cmp(&(removed + 1)) {
+        std::cmp::Ordering::Less => source,
+        std::cmp::Ordering::Equal => 0,
+        std::cmp::Ordering::Greater => source - 1,
+    }
+}
+
+/// Marker color of a neuron; those on the right side (negative z) are drawn dimmer
+/// according to the depth cue preference.
+fn marker_color(
+    color_of: &dyn Fn(&Neuron) -> Color32,
+    neuron: &Neuron,
+    prefs: &Preferences,
+) -> Color32 {
+    let color = color_of(neuron);
+    if neuron.z < 0.0 {
+        color.gamma_multiply(prefs.depth_cue)
+    } else {
+        color
+    }
+}
+
+/// Lateral (x-y) view of the neurons on `side`; returns the plot response and any midline
+/// edit made in it. The single-side plots of the split layout pan and zoom together.
+fn xy_view(
+    ui: &mut egui::Ui,
+    data: &[&Neuron],
+    settings: &CanvasSettings<'_>,
+    part: XyPart,
+    height: Option<f32>,
+    output: &mut CanvasOutput,
+) -> egui_plot::PlotResponse<Option<Midline>> {
+    let CanvasSettings {
+        selection,
+        geometry,
+        overlay,
+        plot_styles,
+        orientation,
+        midline,
+        tool,
+        roi,
+        annotations,
+        crop,
+        prefs,
+        ..
+    } = settings;
+    let allow_drag = tool.allows_pan() && midline.is_none();
+    let clicks = tool.selects_on_click() && midline.is_none();
+    let sides = (egui::Id::new("xy_sides"), egui::Vec2b::TRUE);
+    // The ends lie far apart along the body, so only their dorsoventral axes are linked.
+    let ends = (egui::Id::new("xy_ends"), egui::Vec2b::new(false, true));
+    let (plot_id, link) = match part {
+        XyPart::Side(WormSide::Both) => ("xy", None),
+        XyPart::Side(WormSide::Left) => ("xy_left", Some(sides)),
+        XyPart::Side(WormSide::Right) => ("xy_right", Some(sides)),
+        XyPart::Span(BodyRegion::Tail, _) => ("xy_tail", Some(ends)),
+        XyPart::Span(_, _) => ("xy_head", Some(ends)),
+    };
+    let batch = if part == XyPart::Side(WormSide::Both) {
+        geometry.view(View::XY)
+    } else {
+        geometry.filtered(View::XY, plot_id, |p| part.contains(p))
+    };
+    let selected: Vec<[f64; 2]> = data
+        .iter()
+        .filter(|n| part.contains(n.position()) && selection.contains(&n.name))
+        .map(|n| [n.x as f64, n.y as f64])
+        .collect();
+    let ticks = prefs.tick_format();
+    let plot = egui_plot::Plot::new(plot_id)
+        .label_formatter(|name, value| batch.label(name, value))
+        .data_aspect(1.0)
+        .allow_zoom(true)
+        .allow_drag(allow_drag)
+        .allow_scroll(true)
+        .allow_double_click_reset(true)
+        .allow_boxed_zoom(allow_drag)
+        // .legend(Legend::default())
+        .x_axis_label(RichText::new(prefs.axis_label(orientation.ap_label())).strong())
+        .y_axis_label(RichText::new(prefs.axis_label(orientation.dv_label())).strong())
+        .x_axis_formatter(style::tick_formatter(orientation.flip_ap, ticks))
+        .y_axis_formatter(style::tick_formatter(orientation.flip_dv, ticks))
+        .x_grid_spacer(ticks.grid_spacer())
+        .y_grid_spacer(ticks.grid_spacer());
+    let plot = if *crop || matches!(part, XyPart::Span(..)) {
+        plot
+    } else {
+        plot.include_x(0.0).include_y(0.0)
+    };
+    let plot = match height {
+        Some(height) => plot.height(height),
+        None => plot,
+    };
+    let plot = match link {
+        Some((link, axes)) => plot.link_axis(link, axes).link_cursor(link, axes),
+        None => plot,
+    };
+    // Overlays span the whole body, so each end is framed on its own neurons.
+    let span_bounds = match part {
+        XyPart::Span(_, [min, max]) => {
+            let (low, high) = (data.iter()).fold((f32::MAX, f32::MIN), |(low, high), n| {
+                (low.min(n.y), high.max(n.y))
             });
+            let pad = 0.05 * (max - min).max(high - low).max(1.0);
+            (low <= high && min <= max).then(|| {
+                egui_plot::PlotBounds::from_min_max(
+                    [(min - pad) as f64, (low - pad) as f64],
+                    [(max + pad) as f64, (high + pad) as f64],
+                )
+            })
+        }
+        XyPart::Side(_) => None,
+    };
+    let reset_bounds = view_header(ui, View::XY, settings, output);
+    let plot = if reset_bounds { plot.reset() } else { plot };
+    let scope = profile::scope("xy plot");
+    let response = plot_styles[View::XY.index()].show(ui, plot, |plot_ui| {
+        if let Some(bounds) = span_bounds.filter(|_| plot_ui.auto_bounds().any()) {
+            plot_ui.set_plot_bounds(bounds);
+        }
+        track_pointer(plot_ui, View::XY, clicks, output);
+        let boundary = plot_ui.plot_bounds();
+        let scale = boundary.max()[0] - boundary.min()[0];
+        let radius = prefs.marker_radius(scale);
+        overlay.draw(plot_ui, View::XY, prefs);
+        roi.draw(plot_ui, View::XY, *orientation);
+        if *tool == CanvasTool::Roi {
+            if let Some((a, b)) = tools::drag_rect(plot_ui, ROI_COLOR) {
+                output.roi_rect = Some((View::XY, a, b));
+            }
+        }
+        if *tool == CanvasTool::Ruler {
+            tools::ruler(plot_ui, prefs);
+        }
+        if *tool == CanvasTool::Edit {
+            track_drag(plot_ui, View::XY, output);
+        }
+
+        let outline = prefs.marker_outline(plot_ui.ctx().style().visuals.strong_text_color());
+        batch.draw(plot_ui, radius as f32, outline);
+        draw_focus(plot_ui, View::XY, settings, radius);
+        draw_preview(plot_ui, View::XY, settings, radius);
+        draw_hovered(plot_ui, View::XY, settings, radius);
+        touch::gestures(plot_ui, View::XY, data, prefs);
+        annotations.draw(plot_ui, View::XY, *orientation);
+        if *tool == CanvasTool::Annotate {
+            if let Some(edit) = annotate::track(plot_ui, View::XY, *orientation, annotations) {
+                output.annotation = Some(edit);
+            }
+        }
+        if !selected.is_empty() {
+            plot_ui.points(
+                Points::new(PlotPoints::new(selected))
+                    .color(prefs.selection_color())
+                    .filled(false)
+                    .radius(radius as f32 + 2.0),
+            );
+        }
+
+        midline
+            .map(|(midline, transform)| {
+                let mut midline = midline.clone();
+                let to_plot = |[x, y]: [f32; 2]| {
+                    let [x, y, _] = orientation.apply(transform.apply([x, y, 0.0]));
+                    [x as f64, y as f64]
+                };
+                let from_plot = |[x, y]: [f64; 2]| {
+                    let [x, y, _] = transform.invert(orientation.apply([x as f32, y as f32, 0.0]));
+                    [x, y]
+                };
+                midline::edit_in_plot(plot_ui, &mut midline, to_plot, from_plot).then_some(midline)
+            })
+            .flatten()
+    });
+    scope.end(ui.ctx());
+    if let Some(colorbar) = &settings.colorbar {
+        let rect = response.response.rect;
+        colorbar.paint(&ui.painter_at(rect), rect, ui.visuals());
+    }
+    tour::mark(ui.ctx(), tour::Target::Plots, response.response.rect);
+    let rect = response.response.rect;
+    output.xy_rect = Some(output.xy_rect.map_or(rect, |r| r.union(rect)));
+    response
+}
+
+/// Anterior (z-y) view of the slab around the position hovered in the lateral view.
+fn yz_view(
+    ui: &mut egui::Ui,
+    data: &[&Neuron],
+    settings: &CanvasSettings<'_>,
+    pos: Option<egui_plot::PlotPoint>,
+    output: &mut CanvasOutput,
+) {
+    let CanvasSettings {
+        geometry,
+        overlay,
+        plot_styles,
+        orientation,
+        midline,
+        tool,
+        roi,
+        annotations,
+        crop,
+        prefs,
+        ..
+    } = settings;
+    let allow_drag = tool.allows_pan() && midline.is_none();
+    let clicks = tool.selects_on_click() && midline.is_none();
+    let thickness = prefs.slab_thickness;
+    let (low, high) = match pos {
+        Some(pos) => (pos.x - thickness, pos.x + thickness),
+        None => (f64::MIN, f64::MAX),
+    };
+    let in_slab = |x: f32| (low..=high).contains(&(x as f64));
+    let batch = if pos.is_some() {
+        geometry.filtered(View::ZY, (low.to_bits(), high.to_bits()), |p| in_slab(p[0]))
+    } else {
+        geometry.view(View::ZY)
+    };
+    let ticks = prefs.tick_format();
+    let plot = egui_plot::Plot::new("yz")
+        .label_formatter(|name, value| batch.label(name, value))
+        .data_aspect(1.0)
+        .allow_zoom(true)
+        .allow_drag(allow_drag)
+        .allow_scroll(true)
+        .allow_double_click_reset(true)
+        .allow_boxed_zoom(allow_drag)
+        .x_axis_label(RichText::new(prefs.axis_label(tr("Right–Left"))).strong())
+        .y_axis_label(RichText::new(prefs.axis_label(orientation.dv_label())).strong())
+        .x_axis_formatter(style::tick_formatter(false, ticks))
+        .y_axis_formatter(style::tick_formatter(orientation.flip_dv, ticks))
+        .x_grid_spacer(ticks.grid_spacer())
+        .y_grid_spacer(ticks.grid_spacer());
+    // .legend(Legend::default())
+    let plot = if *crop {
+        plot
+    } else {
+        plot.include_x(-15.0)
+            .include_x(15.0)
+            .include_y(20.0)
+            .include_y(-25.0)
+    };
+    let reset_bounds = view_header(ui, View::ZY, settings, output);
+    let plot = if reset_bounds { plot.reset() } else { plot };
+    let scope = profile::scope("yz plot");
+    let response = plot_styles[View::ZY.index()].show(ui, plot, |plot_ui| {
+        track_pointer(plot_ui, View::ZY, clicks, output);
+        let boundary = plot_ui.plot_bounds();
+        let scale = boundary.max()[0] - boundary.min()[0];
+        let radius = prefs.marker_radius(scale);
+        overlay.draw(plot_ui, View::ZY, prefs);
+        roi.draw(plot_ui, View::ZY, *orientation);
+        if *tool == CanvasTool::Roi {
+            if let Some((a, b)) = tools::drag_rect(plot_ui, ROI_COLOR) {
+                output.roi_rect = Some((View::ZY, a, b));
+            }
+        }
+        if *tool == CanvasTool::Ruler {
+            tools::ruler(plot_ui, prefs);
+        }
+        if *tool == CanvasTool::Edit {
+            track_drag(plot_ui, View::ZY, output);
+        }
+        if let Some(pos) = pos {
+            plot_ui.hline(HLine::new(pos.y).stroke(prefs.guide_stroke()));
+        }
+        let outline = prefs.marker_outline(plot_ui.ctx().style().visuals.strong_text_color());
+        batch.draw(plot_ui, radius as f32, outline);
+        draw_focus(plot_ui, View::ZY, settings, radius);
+        draw_preview(plot_ui, View::ZY, settings, radius);
+        draw_hovered(plot_ui, View::ZY, settings, radius);
+        touch::gestures(plot_ui, View::ZY, data, prefs);
+        annotations.draw(plot_ui, View::ZY, *orientation);
+        if *tool == CanvasTool::Annotate {
+            if let Some(edit) = annotate::track(plot_ui, View::ZY, *orientation, annotations) {
+                output.annotation = Some(edit);
+            }
+        }
+        let in_slice = data.iter().filter(|n| in_slab(n.x));
+        if let Some((neuron, label)) = hover_pick(in_slice, pos, settings) {
+            plot_ui.vline(VLine::new(neuron.z).stroke(prefs.guide_stroke()));
+            let center = [neuron.z as f64, neuron.y as f64];
+            style::ring(plot_ui, center, radius as f32 + 2.0, prefs.hover_stroke());
+            let text_pos = [
+                neuron.z as f64 + radius / 1.5,
+                neuron.y as f64 + radius / 1.5,
+            ]
+            .into();
+
+            plot_ui.text(Text::new(text_pos, label).highlight(true));
+        }
+    });
+    scope.end(ui.ctx());
+    tour::mark(ui.ctx(), tour::Target::Slices, response.response.rect);
+}
+
+/// Dorsal (x-z) view of the slab around the position hovered in the lateral view, limited
+/// to the anterior-posterior range `x_bound` shown there.
+fn xz_view(
+    ui: &mut egui::Ui,
+    data: &[&Neuron],
+    settings: &CanvasSettings<'_>,
+    pos: Option<egui_plot::PlotPoint>,
+    x_bound: (f64, f64),
+    output: &mut CanvasOutput,
+) {
+    let CanvasSettings {
+        geometry,
+        overlay,
+        plot_styles,
+        orientation,
+        midline,
+        tool,
+        roi,
+        annotations,
+        crop,
+        prefs,
+        ..
+    } = settings;
+    let allow_drag = tool.allows_pan() && midline.is_none();
+    let clicks = tool.selects_on_click() && midline.is_none();
+    let thickness = prefs.slab_thickness;
+    let (y_min, y_max) = match pos {
+        Some(pos) => (pos.y - thickness, pos.y + thickness),
+        None => (f64::MIN, f64::MAX),
+    };
+    let (x_min, x_max) = x_bound;
+    let in_slab = |p: [f32; 3]| {
+        (y_min..=y_max).contains(&(p[1] as f64)) && (x_min..=x_max).contains(&(p[0] as f64))
+    };
+    let bounds = [y_min, y_max, x_min, x_max].map(f64::to_bits);
+    let batch = geometry.filtered(View::XZ, bounds, in_slab);
+    let ticks = prefs.tick_format();
+    let plot = egui_plot::Plot::new("xz")
+        .label_formatter(|name, value| batch.label(name, value))
+        .data_aspect(1.0)
+        .allow_zoom(true)
+        .allow_drag(allow_drag)
+        .allow_scroll(true)
+        .allow_double_click_reset(true)
+        .allow_boxed_zoom(allow_drag)
+        .x_axis_label(RichText::new(prefs.axis_label(orientation.ap_label())).strong())
+        .y_axis_label(RichText::new(prefs.axis_label(tr("Left–Right"))).strong())
+        .x_axis_formatter(style::tick_formatter(orientation.flip_ap, ticks))
+        .y_axis_formatter(style::tick_formatter(false, ticks))
+        .x_grid_spacer(ticks.grid_spacer())
+        .y_grid_spacer(ticks.grid_spacer());
+    let plot = if *crop {
+        plot
+    } else {
+        plot.include_x(x_bound.0)
+            .include_x(x_bound.1)
+            .include_y(15.0)
+            .include_y(-15.0)
+    };
+    let reset_bounds = view_header(ui, View::XZ, settings, output);
+    let plot = if reset_bounds { plot.reset() } else { plot };
+    let scope = profile::scope("xz plot");
+    plot_styles[View::XZ.index()].show(ui, plot, |plot_ui| {
+        track_pointer(plot_ui, View::XZ, clicks, output);
+        let boundary = plot_ui.plot_bounds();
+        let scale = boundary.max()[0] - boundary.min()[0];
+        let radius = prefs.marker_radius(scale);
+        overlay.draw(plot_ui, View::XZ, prefs);
+        roi.draw(plot_ui, View::XZ, *orientation);
+        if *tool == CanvasTool::Roi {
+            if let Some((a, b)) = tools::drag_rect(plot_ui, ROI_COLOR) {
+                output.roi_rect = Some((View::XZ, a, b));
+            }
+        }
+        if *tool == CanvasTool::Ruler {
+            tools::ruler(plot_ui, prefs);
+        }
+        if *tool == CanvasTool::Edit {
+            track_drag(plot_ui, View::XZ, output);
+        }
+
+        if let Some(pos) = pos {
+            plot_ui.vline(VLine::new(pos.x).stroke(prefs.guide_stroke()));
+        }
+        let outline = prefs.marker_outline(plot_ui.ctx().style().visuals.strong_text_color());
+        batch.draw(plot_ui, radius as f32, outline);
+        draw_focus(plot_ui, View::XZ, settings, radius);
+        draw_preview(plot_ui, View::XZ, settings, radius);
+        draw_hovered(plot_ui, View::XZ, settings, radius);
+        touch::gestures(plot_ui, View::XZ, data, prefs);
+        annotations.draw(plot_ui, View::XZ, *orientation);
+        if *tool == CanvasTool::Annotate {
+            if let Some(edit) = annotate::track(plot_ui, View::XZ, *orientation, annotations) {
+                output.annotation = Some(edit);
+            }
+        }
+        let in_slice = data.iter().filter(|n| in_slab(n.position()));
+        if let Some((neuron, label)) = hover_pick(in_slice, pos, settings) {
+            plot_ui.hline(HLine::new(-neuron.z).stroke(prefs.guide_stroke()));
+            let center = [neuron.x as f64, -neuron.z as f64];
+            style::ring(plot_ui, center, radius as f32 + 2.0, prefs.hover_stroke());
+            let text_pos = [
+                neuron.x as f64 + radius / 1.5,
+                -neuron.z as f64 + radius / 1.5,
+            ]
+            .into();
+
+            plot_ui.text(Text::new(text_pos, label).highlight(true));
+        }
     });
-    let xz_window = egui::Window::new("Dorsal View (x-z)")
+    scope.end(ui.ctx());
+}
+
+/// The neuron to label in an orthogonal view: the one nearest to the lateral-view position
+/// `pos` within the pick tolerance, or a farther one after cycling with the N key. The label
+/// counts the candidates when there is more than one.
+fn hover_pick<'a>(
+    slice: impl Iterator<Item = &'a &'a Neuron>,
+    pos: Option<PlotPoint>,
+    settings: &CanvasSettings<'_>,
+) -> Option<(&'a Neuron, String)> {
+    let pos = pos?;
+    let tolerance = settings.prefs.pick_tolerance();
+    let mut candidates: Vec<(&Neuron, f64)> = slice
+        .map(|n| (*n, l2_dist(n.x as f64, pos.x, n.y as f64, pos.y)))
+        .filter(|(_, distance)| *distance < tolerance)
+        .collect();
+    candidates.sort_by(|a, b| a.1.total_cmp(&b.1).then_with(|| a.0.name.cmp(&b.0.name)));
+    let count = candidates.len();
+    let (neuron, _) = *candidates.get(settings.hover_cycle % count.max(1))?;
+    let label = if count > 1 {
+        let index = settings.hover_cycle % count + 1;
+        format!("{} ({index}/{count}, N)", neuron.name)
+    } else {
+        neuron.name.clone()
+    };
+    Some((neuron, label))
+}
+
+/// Outline the neuron focused from the keyboard, centering the view on it when asked to.
+fn draw_focus(plot_ui: &mut PlotUi, view: View, settings: &CanvasSettings<'_>, radius: f64) {
+    let Some(neuron) = settings.focus else {
+        return;
+    };
+    let [x, y] = view.project(neuron.position());
+    if settings.center_focus {
+        let bounds = plot_ui.plot_bounds();
+        let (w, h) = (bounds.width() / 2.0, bounds.height() / 2.0);
+        plot_ui.set_plot_bounds(PlotBounds::from_min_max([x - w, y - h], [x + w, y + h]));
+    }
+    plot_ui.points(
+        Points::new(PlotPoints::new(vec![[x, y]]))
+            .color(settings.prefs.focus_color())
+            .filled(false)
+            .radius(radius as f32 + 4.0),
+    );
+}
+
+/// Outline the neurons the search being typed would keep.
+fn draw_preview(plot_ui: &mut PlotUi, view: View, settings: &CanvasSettings<'_>, radius: f64) {
+    if settings.preview.is_empty() {
+        return;
+    }
+    let points: Vec<[f64; 2]> = (settings.preview.iter())
+        .map(|n| view.project(n.position()))
+        .collect();
+    plot_ui.points(
+        Points::new(PlotPoints::new(points))
+            .color(settings.prefs.hover_stroke().color)
+            .filled(false)
+            .radius(radius as f32 + 3.0),
+    );
+}
+
+/// Ring and label the neuron hovered in another view or in the list. The plot hovered labels
+/// it itself, and the slices follow lateral-view hovers through [`hover_pick`].
+fn draw_hovered(plot_ui: &mut PlotUi, view: View, settings: &CanvasSettings<'_>, radius: f64) {
+    let Some((neuron, source)) = settings.hovered else {
+        return;
+    };
+    if source == Some(view) || source == Some(View::XY) {
+        return;
+    }
+    let [x, y] = view.project(neuron.position());
+    let prefs = settings.prefs;
+    style::ring(plot_ui, [x, y], radius as f32 + 2.0, prefs.hover_stroke());
+    let text_pos = [x + radius / 1.5, y + radius / 1.5].into();
+    plot_ui.text(Text::new(text_pos, &neuron.name).highlight(true));
+}
+
+/// Keys moving through the neuron list and the slices, read while no text field has focus.
+#[derive(Default)]
+struct Navigation {
+    /// Rows to move the focus by.
+    step: isize,
+    /// Toggle the selection of the focused neuron and center the views on it.
+    enter: bool,
+    /// Slab thicknesses to move the slices by.
+    nudge: f64,
+    /// Steps to cycle the hover label through neurons equally close to the cursor.
+    cycle: usize,
+}
+
+/// Row `step` rows away from `focus` in a list of `len`, clamped to the list; stepping
+/// without a focus starts from the first row down or the last row up. `None` when nothing
+/// moves.
+fn step_focus(focus: Option<usize>, step: isize, len: usize) -> Option<usize> {
+    if step == 0 || len == 0 {
+        return None;
+    }
+    let last = len as isize - 1;
+    let next = match focus {
+        Some(i) => (i as isize + step).clamp(0, last),
+        None if step > 0 => 0,
+        None => last,
+    };
+    Some(next as usize)
+}
+
+fn navigation_keys(ctx: &egui::Context) -> Navigation {
+    if ctx.wants_keyboard_input() {
+        return Navigation::default();
+    }
+    ctx.input(|i| {
+        let count = |key| i.num_presses(key) as isize;
+        Navigation {
+            step: count(Key::ArrowDown) - count(Key::ArrowUp) + 10 * count(Key::PageDown)
+                - 10 * count(Key::PageUp),
+            enter: i.key_pressed(Key::Enter),
+            nudge: (count(Key::ArrowRight) - count(Key::ArrowLeft)) as f64,
+            cycle: i.num_presses(Key::N),
+        }
+    })
+}
+
+fn worm_canvas(
+    ctx: &egui::Context,
+    ui: &mut egui::Ui,
+    data: &[&Neuron],
+    settings: &CanvasSettings<'_>,
+) -> CanvasOutput {
+    let mut output = CanvasOutput::default();
+    let hover = |response: &egui_plot::PlotResponse<Option<Midline>>| {
+        let bound = response.transform.bounds();
+        let pos = response
+            .response
+            .hover_pos()
+            .map(|pos| response.transform.value_from_position(pos))
+            .or(settings.slice.map(|[x, y]| PlotPoint::new(x, y)));
+        (pos, (bound.min()[0], bound.max()[0]))
+    };
+
+    let top_down = egui::Layout::top_down(egui::Align::Min);
+    if let Some(view) = settings.maximized {
+        ui.allocate_ui_with_layout(ui.available_size(), top_down, |ui| match view {
+            View::XY => {
+                output.midline = xy_view(
+                    ui,
+                    data,
+                    settings,
+                    XyPart::Side(WormSide::Both),
+                    None,
+                    &mut output,
+                )
+                .inner
+            }
+            View::ZY => yz_view(ui, data, settings, None, &mut output),
+            View::XZ => xz_view(ui, data, settings, None, (f64::MIN, f64::MAX), &mut output),
+        });
+        return output;
+    }
+
+    if settings.layout == ViewLayout::Docked {
+        let full = ui.available_size();
+        let side = (full.x * 0.3).clamp(160.0, 480.0);
+        let top = (full.y * 0.6).max(200.0);
+        let (response, (pos, x_bound)) = ui
+            .horizontal_top(|ui| {
+                let response = ui
+                    .allocate_ui_with_layout(
+                        egui::vec2(full.x - side - ui.spacing().item_spacing.x, top),
+                        top_down,
+                        |ui| {
+                            xy_view(
+                                ui,
+                                data,
+                                settings,
+                                XyPart::Side(WormSide::Both),
+                                None,
+                                &mut output,
+                            )
+                        },
+                    )
+                    .inner;
+                let (pos, x_bound) = hover(&response);
+                ui.allocate_ui_with_layout(egui::vec2(side, top), top_down, |ui| {
+                    yz_view(ui, data, settings, pos, &mut output);
+                });
+                (response, (pos, x_bound))
+            })
+            .inner;
+        ui.separator();
+        ui.allocate_ui_with_layout(ui.available_size(), top_down, |ui| {
+            xz_view(ui, data, settings, pos, x_bound, &mut output);
+        });
+        output.midline = response.inner;
+        return output;
+    }
+
+    let split = match settings.layout {
+        ViewLayout::SplitSides => Some(
+            [WormSide::Left, WormSide::Right].map(|side| (side.to_string(), XyPart::Side(side))),
+        ),
+        ViewLayout::HeadTail => Some(body_ends(data)),
+        ViewLayout::Floating | ViewLayout::Docked => None,
+    };
+    let (pos, x_bound) = if let Some(parts) = split {
+        let width = (ui.available_width() - ui.spacing().item_spacing.x) / 2.0;
+        let [left, right] = ui
+            .horizontal_top(|ui| {
+                parts.map(|(title, part)| {
+                    ui.allocate_ui_with_layout(egui::vec2(width, 560.0), top_down, |ui| {
+                        ui.label(RichText::new(title).strong());
+                        xy_view(ui, data, settings, part, Some(500.), &mut output)
+                    })
+                    .inner
+                })
+            })
+            .inner;
+        output.midline = left.inner.clone().or(right.inner.clone());
+        if right.response.hovered() {
+            hover(&right)
+        } else {
+            hover(&left)
+        }
+    } else {
+        let response = xy_view(
+            ui,
+            data,
+            settings,
+            XyPart::Side(WormSide::Both),
+            Some(500.),
+            &mut output,
+        );
+        output.midline = response.inner.clone();
+        hover(&response)
+    };
+    if settings.pop_out {
+        let open = view::show_in_viewport(
+            ctx,
+            "orthogonal_views",
+            tr("Orthogonal views"),
+            [480.0, 800.0],
+            |ui| {
+                let size = egui::vec2(ui.available_width(), ui.available_height() / 2.0);
+                ui.allocate_ui_with_layout(size, top_down, |ui| {
+                    ui.label(RichText::new(tr("Anterior View (z-y)")).strong());
+                    yz_view(ui, data, settings, pos, &mut output);
+                });
+                ui.allocate_ui_with_layout(ui.available_size(), top_down, |ui| {
+                    ui.label(RichText::new(tr("Dorsal View (x-z)")).strong());
+                    xz_view(ui, data, settings, pos, x_bound, &mut output);
+                });
+            },
+        );
+        output.pop_out_closed = !open;
+        return output;
+    }
+    let window_geometry = settings.window_geometry;
+    let yz_window = egui::Window::new(tr("Anterior View (z-y)"))
+        .id(egui::Id::new("yz")) // required since we change the title
+        .resizable(true)
+        .constrain(true)
+        .collapsible(true)
+        .title_bar(true)
+        .scroll(true)
+        .enabled(true);
+
+    let screen = ctx.screen_rect();
+    let yz_geometry = WindowGeometry::show(
+        ctx,
+        yz_window,
+        window_geometry[View::ZY.index()],
+        screen.right_top() + egui::vec2(-400.0, 40.0),
+        |ui| yz_view(ui, data, settings, pos, &mut output),
+    );
+    let xz_window = egui::Window::new(tr("Dorsal View (x-z)"))
         .id(egui::Id::new("xz")) // required since we change the title
         .resizable(true)
         .constrain(true)
@@ -434,86 +5485,196 @@ fn worm_canvas(ctx: &egui::Context, ui: &mut egui::Ui, data: &[&Neuron]) {
         .scroll(true)
         .enabled(true);
 
-    xz_window.show(ctx, |ui| {
-        egui_plot::Plot::new("xz")
-            .data_aspect(1.0)
-            .allow_zoom(true)
-            .allow_drag(true)
-            .allow_scroll(true)
-            .allow_double_click_reset(true)
-            .allow_boxed_zoom(true)
-            .include_x(x_bound.0)
-            .include_x(x_bound.1)
-            .include_y(15.0)
-            .include_y(-15.0)
-            .x_axis_label(RichText::new("Anterior - Posterior").strong())
-            .y_axis_label(RichText::new("Left - Right").strong())
-            .show(ui, |plot_ui| {
-                let boundary = plot_ui.plot_bounds();
-                let scale = boundary.max()[0] - boundary.min()[0];
-                let radius = (scale * -0.01 + 6.).clamp(1.0, 6.);
-
-                let mut y_min = f64::MIN;
-                let mut y_max = f64::MAX;
-                let (x_min, x_max) = x_bound;
-
-                if let Some(pos) = pos {
-                    plot_ui.vline(VLine::new(pos.x).color(Color32::LIGHT_RED));
-                    y_min = pos.y - thickness;
-                    y_max = pos.y + thickness;
-                }
-                for neuron in data {
-                    let x_pos = neuron.x as f64;
-                    let y_pos = neuron.y as f64;
-                    if y_pos < y_min || y_pos > y_max || x_pos < x_min || x_pos > x_max {
-                        continue;
-                    }
+    let xz_geometry = WindowGeometry::show(
+        ctx,
+        xz_window,
+        window_geometry[View::XZ.index()],
+        screen.right_top() + egui::vec2(-400.0, 420.0),
+        |ui| xz_view(ui, data, settings, pos, x_bound, &mut output),
+    );
+    output.window_geometry = [None, yz_geometry, xz_geometry];
+    output
+}
 
-                    let pts = [neuron.x as f64, -neuron.z as f64];
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::rc::Rc;
 
-                    let points = PlotPoints::new(vec![pts]);
-                    let [r, g, b] = neuron.rgb();
+    /// The app after applying `actions` in order, as at the end of a frame.
+    fn applied(actions: impl IntoIterator<Item = Action>) -> MyApp {
+        let ctx = egui::Context::default();
+        let mut app = MyApp::default();
+        for action in actions {
+            app.apply(&ctx, action);
+        }
+        app
+    }
 
-                    let mut color = if r == 0 && g == 0 && b == 0 && is_dark {
-                        egui::Color32::WHITE
-                    } else {
-                        let [r, g, b] = neuron.rgb();
-                        egui::Color32::from_rgb(r, g, b)
-                    };
+    #[test]
+    fn filter_and_side() {
+        let app = applied([Action::SetFilter("AVA RIM".to_owned()), Action::ToggleSide]);
+        assert_eq!(app.label, "AVA RIM");
+        assert_eq!(app.view_side, WormSide::Left);
+        let app = applied([Action::SetSide(WormSide::Right), Action::ToggleSide]);
+        assert_eq!(app.view_side, WormSide::Both);
+    }
 
-                    if neuron.z < 0.0 {
-                        color = color.gamma_multiply(0.8);
-                    }
+    #[test]
+    fn selection() {
+        let app = applied([
+            Action::SetSelection(vec!["AVAL".to_owned(), "AVAR".to_owned()]),
+            Action::SelectNeuron("RIML".to_owned()),
+            Action::DeselectNeuron("AVAL".to_owned()),
+        ]);
+        assert_eq!(Vec::from_iter(&app.selection), ["AVAR", "RIML"]);
+        let app = applied([
+            Action::SelectNeuron("AVAL".to_owned()),
+            Action::ClearSelection,
+        ]);
+        assert!(app.selection.is_empty());
+    }
 
-                    plot_ui.points(
-                        Points::new(points)
-                            .name(&neuron.name)
-                            .allow_hover(true)
-                            .color(color)
-                            .highlight(true)
-                            .radius(radius as f32),
-                    );
+    #[test]
+    fn removing_every_chip_clears_the_filters() {
+        let mut app = applied([
+            Action::SetFilter("AVA, RIM".to_owned()),
+            Action::SetSide(WormSide::Left),
+            Action::SetSelectionOnly(true),
+            Action::SetRoiCrop(true),
+            Action::CyclePharyngeal,
+            Action::SetRegionFilter(RegionFilter {
+                paired: Some(false),
+                pharyngeal: Some(true),
+                ..RegionFilter::default()
+            }),
+        ]);
+        assert_eq!(app.filter_chips().len(), 7);
+        let ctx = egui::Context::default();
+        while let Some((_, remove)) = app.filter_chips().into_iter().next() {
+            app.apply(&ctx, remove);
+        }
+        assert_eq!(app.label, "*");
+        assert_eq!(app.view_side, WormSide::Both);
+        assert!(!app.selection_only && !app.roi_crop);
+        assert!(app.region_filter.is_empty());
+    }
 
-                    if pos.is_some_and(|pos| {
-                        l2_dist(neuron.x as f64, pos.x, neuron.y as f64, pos.y) < 0.35
-                    }) {
-                        plot_ui.hline(HLine::new(-neuron.z).color(Color32::LIGHT_RED));
-                        let points = PlotPoints::new(vec![pts]);
-                        plot_ui.points(
-                            Points::new(points)
-                                .color(egui::Color32::LIGHT_RED)
-                                .filled(false)
-                                .radius(radius as f32 + 2.0),
-                        );
-                        let text_pos = [
-                            neuron.x as f64 + radius / 1.5,
-                            -neuron.z as f64 + radius / 1.5,
-                        ]
-                        .into();
+    #[test]
+    fn removing_a_search_chip_keeps_the_other_terms() {
+        let app = applied([Action::SetFilter("AVA RIM".to_owned())]);
+        let chips = app.filter_chips();
+        assert_eq!(chips[0].1, Action::SetFilter("RIM".to_owned()));
+        assert_eq!(chips[1].1, Action::SetFilter("AVA".to_owned()));
+    }
 
-                        plot_ui.text(Text::new(text_pos, &neuron.name).highlight(true));
-                    }
-                }
-            });
-    });
+    #[test]
+    fn roi_selects_the_neurons_inside() {
+        let [x, y, z] = MyApp::default().data["AVAL"].position();
+        let roi = Roi {
+            ranges: [x, y, z].map(|v| Some([v - 1e-3, v + 1e-3])),
+        };
+        let app = applied([Action::SetRoiCrop(true), Action::SetRoi(roi.clone())]);
+        assert_eq!(app.roi, roi);
+        assert_eq!(Vec::from_iter(&app.selection), ["AVAL"]);
+        assert!(app.roi_crop);
+        let app = applied([
+            Action::SetRoi(roi),
+            Action::SetRoiCrop(true),
+            Action::SetRoi(Roi::default()),
+        ]);
+        assert!(app.roi.is_empty() && !app.roi_crop);
+        assert_eq!(Vec::from_iter(&app.selection), ["AVAL"]);
+    }
+
+    #[test]
+    fn focus_and_slices() {
+        let app = applied([
+            Action::SetFocus(Some("AVAL".to_owned())),
+            Action::MoveSlices(2.0),
+        ]);
+        assert_eq!(app.focused.as_deref(), Some("AVAL"));
+        assert_eq!(app.slice_offset, 2.0 * app.prefs.slab_thickness);
+        let app = applied([Action::MoveSlices(1.0), Action::SetFocus(None)]);
+        assert_eq!((app.focused, app.slice_offset), (None, 0.0));
+    }
+
+    #[test]
+    fn dispatch_queues_until_the_end_of_the_frame() {
+        let mut app = MyApp::default();
+        app.dispatch(Action::SetSide(WormSide::Right));
+        assert_eq!(app.view_side, WormSide::Both);
+        assert_eq!(app.actions, [Action::SetSide(WormSide::Right)]);
+    }
+
+    #[test]
+    fn dispatch_wakes_the_app() {
+        let ctx = egui::Context::default();
+        while ctx.has_requested_repaint() {
+            let _ = ctx.run(Default::default(), |_| {});
+        }
+        let mut app = MyApp {
+            ctx: Some(ctx.clone()),
+            ..Default::default()
+        };
+        app.dispatch(Action::ToggleSide);
+        assert!(ctx.has_requested_repaint());
+    }
+
+    #[test]
+    fn region_filter_changes_are_reported() {
+        let events = Rc::new(RefCell::new(Vec::new()));
+        let mut app = MyApp::default();
+        let sink = events.clone();
+        app.on_event(move |event| sink.borrow_mut().push(event.clone()));
+        app.fire_events(None);
+        app.apply(&egui::Context::default(), Action::CyclePharyngeal);
+        app.fire_events(None);
+        let events = events.borrow();
+        let [AppEvent::FilterChange(filter)] = &events[..] else {
+            panic!("expected one filter change, got {events:?}");
+        };
+        assert_eq!(filter.region.pharyngeal, Some(true));
+    }
+
+    #[test]
+    fn escape_undoes_one_thing_at_a_time() {
+        let ctx = egui::Context::default();
+        let press_escape = |app: &mut MyApp| {
+            let event = egui::Event::Key {
+                key: Key::Escape,
+                physical_key: None,
+                pressed: true,
+                repeat: false,
+                modifiers: Modifiers::NONE,
+            };
+            let input = egui::RawInput {
+                events: vec![event],
+                ..Default::default()
+            };
+            let _ = ctx.run(input, |ctx| app.escape(ctx));
+        };
+        let mut app = applied([Action::SetFocus(Some("AVAL".to_owned()))]);
+        app.tour.start();
+        app.maximized = Some(View::XY);
+        press_escape(&mut app);
+        assert!(!app.tour.is_running());
+        assert!(app.maximized == Some(View::XY));
+        press_escape(&mut app);
+        assert!(app.maximized.is_none());
+        assert!(app.actions.is_empty());
+        press_escape(&mut app);
+        assert_eq!(app.actions, [Action::SetFocus(None)]);
+    }
+
+    #[test]
+    fn keyboard_steps() {
+        assert_eq!(step_focus(None, 1, 5), Some(0));
+        assert_eq!(step_focus(None, -1, 5), Some(4));
+        assert_eq!(step_focus(Some(3), 10, 5), Some(4));
+        assert_eq!(step_focus(Some(3), -10, 5), Some(0));
+        assert_eq!(step_focus(Some(3), 0, 5), None);
+        assert_eq!(step_focus(None, 1, 0), None);
+    }
 }
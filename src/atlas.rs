@@ -0,0 +1,249 @@
+use std::collections::{BTreeSet, HashMap};
+use std::ops::{Bound, RangeBounds};
+
+use crate::dataset::{self, Neuron};
+use crate::i18n::tr;
+use crate::index::{self, NameIndex};
+
+/// Body side of the worm; the left side has positive z.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, serde::Deserialize, serde::Serialize)]
+pub enum WormSide {
+    Left,
+    Right,
+    Both,
+}
+
+impl WormSide {
+    /// Whether a neuron at depth `z` lies on this side.
+    pub fn contains(&self, z: f32) -> bool {
+        match self {
+            Self::Left => z >= 0.,
+            Self::Right => z < 0.,
+            Self::Both => true,
+        }
+    }
+}
+
+impl std::fmt::Display for WormSide {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Left => f.write_str(tr("Left")),
+            Self::Right => f.write_str(tr("Right")),
+            Self::Both => f.write_str(tr("Both")),
+        }
+    }
+}
+
+/// A set of uniquely named neurons, such as the bundled NeuroPAL atlas or an annotated
+/// animal, for use of the crate as a library.
+#[derive(Clone, Default)]
+pub struct Atlas {
+    neurons: HashMap<String, Neuron>,
+    index: NameIndex,
+}
+
+impl Atlas {
+    /// The NeuroPAL atlas shipped with the viewer, which spans the whole animal: the head
+    /// ganglia, the ventral cord and the preanal, dorsorectal and lumbar ganglia of the tail.
+    pub fn neuropal() -> Self {
+        Self::from_csv(dataset::NEUROPAL_ORG).unwrap_or_default()
+    }
+
+    /// Read a table of neurons with a header naming its `name`, `x`, `y` and `z` columns, and
+    /// optionally its color and region columns; other columns are kept as metadata.
+    pub fn from_csv(bytes: &[u8]) -> Result<Self, String> {
+        dataset::parse_neurons(bytes).map(Self::from)
+    }
+
+    pub fn len(&self) -> usize {
+        self.neurons.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.neurons.is_empty()
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Neuron> {
+        self.neurons.get(name)
+    }
+
+    /// All neurons, in no particular order.
+    pub fn iter(&self) -> impl Iterator<Item = &Neuron> {
+        self.neurons.values()
+    }
+
+    pub fn iter_side(&self, side: WormSide) -> impl Iterator<Item = &Neuron> {
+        self.iter().filter(move |n| side.contains(n.z))
+    }
+
+    /// Neurons within `radius` atlas units of `point`.
+    pub fn within_radius(&self, point: [f32; 3], radius: f32) -> impl Iterator<Item = &Neuron> {
+        self.iter().filter(move |n| {
+            let [dx, dy, dz] = [n.x - point[0], n.y - point[1], n.z - point[2]];
+            dx * dx + dy * dy + dz * dz <= radius * radius
+        })
+    }
+
+    /// Smallest axis-aligned box holding every neuron, as its minimum and maximum corners.
+    pub fn bounding_box(&self) -> Option<([f32; 3], [f32; 3])> {
+        let mut neurons = self.iter();
+        let first = neurons.next()?.position();
+        Some(neurons.fold((first, first), |(mut min, mut max), n| {
+            for (i, v) in n.position().into_iter().enumerate() {
+                min[i] = min[i].min(v);
+                max[i] = max[i].max(v);
+            }
+            (min, max)
+        }))
+    }
+
+    /// The neurons keyed by name.
+    pub fn neurons(&self) -> &HashMap<String, Neuron> {
+        &self.neurons
+    }
+
+    pub fn into_neurons(self) -> HashMap<String, Neuron> {
+        self.neurons
+    }
+
+    /// Start a query over this atlas; see [`AtlasQuery`].
+    pub fn query(&self) -> AtlasQuery<'_> {
+        AtlasQuery::new(&self.neurons).index(&self.index)
+    }
+}
+
+impl From<HashMap<String, Neuron>> for Atlas {
+    fn from(neurons: HashMap<String, Neuron>) -> Self {
+        let index = NameIndex::new(neurons.keys());
+        Self { neurons, index }
+    }
+}
+
+impl FromIterator<Neuron> for Atlas {
+    fn from_iter<I: IntoIterator<Item = Neuron>>(iter: I) -> Self {
+        let neurons: HashMap<_, _> = iter.into_iter().map(|n| (n.name.clone(), n)).collect();
+        Self::from(neurons)
+    }
+}
+
+type Predicate<'a> = Box<dyn Fn(&Neuron) -> bool + 'a>;
+
+/// Filter over a set of neurons, built up criterion by criterion; a neuron must meet all of
+/// them. The viewer's search, side and selection filters are expressed with it too.
+///
+/// `atlas.query().name_prefix("AVA").side(WormSide::Left).z_range(0.0..5.0).collect()`
+pub struct AtlasQuery<'a> {
+    neurons: &'a HashMap<String, Neuron>,
+    index: Option<&'a NameIndex>,
+    /// `None` accepts any name; an empty list accepts none.
+    prefixes: Option<Vec<String>>,
+    side: WormSide,
+    z_range: (Bound<f32>, Bound<f32>),
+    names: Option<BTreeSet<String>>,
+    predicates: Vec<Predicate<'a>>,
+}
+
+impl<'a> AtlasQuery<'a> {
+    /// Query over `neurons` that matches all of them until criteria are added.
+    pub fn new(neurons: &'a HashMap<String, Neuron>) -> Self {
+        Self {
+            neurons,
+            index: None,
+            prefixes: None,
+            side: WormSide::Both,
+            z_range: (Bound::Unbounded, Bound::Unbounded),
+            names: None,
+            predicates: Vec::new(),
+        }
+    }
+
+    /// Look name prefixes up in `index`, built over the same neurons, instead of testing
+    /// every name.
+    pub fn index(mut self, index: &'a NameIndex) -> Self {
+        self.index = Some(index);
+        self
+    }
+
+    /// Accept names starting with `prefix`, in addition to earlier prefixes.
+    pub fn name_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.prefixes
+            .get_or_insert_with(Vec::new)
+            .push(prefix.into());
+        self
+    }
+
+    /// Accept names starting with any of `prefixes`; an empty list matches no neuron.
+    pub fn name_prefixes<S: Into<String>>(mut self, prefixes: impl IntoIterator<Item = S>) -> Self {
+        self.prefixes
+            .get_or_insert_with(Vec::new)
+            .extend(prefixes.into_iter().map(Into::into));
+        self
+    }
+
+    pub fn side(mut self, side: WormSide) -> Self {
+        self.side = side;
+        self
+    }
+
+    pub fn z_range(mut self, range: impl RangeBounds<f32>) -> Self {
+        self.z_range = (range.start_bound().cloned(), range.end_bound().cloned());
+        self
+    }
+
+    /// Only accept neurons named in `names`.
+    pub fn names<S: Into<String>>(mut self, names: impl IntoIterator<Item = S>) -> Self {
+        self.names = Some(names.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Only accept neurons for which `predicate` holds.
+    pub fn matching(mut self, predicate: impl Fn(&Neuron) -> bool + 'a) -> Self {
+        self.predicates.push(Box::new(predicate));
+        self
+    }
+
+    /// Only accept neurons whose metadata holds `value` in the column `key`, both compared
+    /// ignoring case.
+    pub fn meta(self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        let (key, value) = (key.into(), value.into());
+        self.matching(move |n| {
+            (n.meta.iter())
+                .any(|(k, v)| k.eq_ignore_ascii_case(&key) && v.eq_ignore_ascii_case(&value))
+        })
+    }
+
+    pub fn matches(&self, neuron: &Neuron) -> bool {
+        let prefix = |p: &Vec<String>| p.iter().any(|p| neuron.name.starts_with(p.as_str()));
+        self.prefixes.as_ref().map_or(true, prefix)
+            && self.side.contains(neuron.z)
+            && self.z_range.contains(&neuron.z)
+            && self
+                .names
+                .as_ref()
+                .map_or(true, |n| n.contains(&neuron.name))
+            && self.predicates.iter().all(|p| p(neuron))
+    }
+
+    /// Matching neurons, in name order when searched by prefix through an index and in no
+    /// particular order otherwise.
+    pub fn iter(&self) -> impl Iterator<Item = &'a Neuron> + '_ {
+        let candidates: Box<dyn Iterator<Item = &'a Neuron> + '_> =
+            match (self.index, &self.prefixes) {
+                (Some(index), Some(prefixes)) => Box::new(
+                    index::disjoint_prefixes(prefixes.iter().map(String::as_str))
+                        .into_iter()
+                        .flat_map(move |prefix| index.with_prefix(prefix))
+                        .filter_map(|name| self.neurons.get(name)),
+                ),
+                _ => Box::new(self.neurons.values()),
+            };
+        candidates.filter(|n| self.matches(n))
+    }
+
+    /// Matching neurons sorted by name.
+    pub fn collect(&self) -> Vec<&'a Neuron> {
+        let mut neurons: Vec<_> = self.iter().collect();
+        neurons.sort_unstable_by_key(|n| &n.name);
+        neurons
+    }
+}
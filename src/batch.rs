@@ -0,0 +1,377 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::ops::RangeInclusive;
+use std::rc::Rc;
+
+use egui::{Color32, Id, Shape, Stroke, Ui};
+use egui_plot::{PlotBounds, PlotGeometry, PlotItem, PlotPoint, PlotTransform, PlotUi};
+
+use crate::dataset::Neuron;
+use crate::style::Orientation;
+use crate::view::View;
+
+/// Above this many points a series is drawn aggregated on a screen-space grid.
+const LOD_THRESHOLD: usize = 2000;
+/// Grid cell size of the aggregated rendering, in screen points.
+const LOD_CELL: f64 = 4.0;
+
+/// Level of detail for a large point series: with more than [`LOD_THRESHOLD`] points, all
+/// points falling into the same grid cell of a few screen points are merged into their
+/// centroid. The grid follows the zoom, so full detail returns as the user zooms in.
+pub fn level_of_detail(plot_ui: &PlotUi, points: Vec<[f64; 2]>) -> Vec<[f64; 2]> {
+    let [dx, dy] = plot_ui.transform().dvalue_dpos();
+    merge_cells(points, [dx.abs() * LOD_CELL, dy.abs() * LOD_CELL])
+}
+
+/// `points` merged into the centroid of each grid cell of size `cell` they fall into, when
+/// there are more than [`LOD_THRESHOLD`]; fewer are returned as they are.
+fn merge_cells(points: Vec<[f64; 2]>, cell: [f64; 2]) -> Vec<[f64; 2]> {
+    if points.len() <= LOD_THRESHOLD || cell[0] <= 0.0 || cell[1] <= 0.0 {
+        return points;
+    }
+    let mut cells: HashMap<(i64, i64), ([f64; 2], usize)> = HashMap::new();
+    for p in points {
+        let key = (
+            (p[0] / cell[0]).floor() as i64,
+            (p[1] / cell[1]).floor() as i64,
+        );
+        let (sum, n) = cells.entry(key).or_insert(([0.0; 2], 0));
+        sum[0] += p[0];
+        sum[1] += p[1];
+        *n += 1;
+    }
+    cells
+        .into_values()
+        .map(|(sum, n)| [sum[0] / n as f64, sum[1] / n as f64])
+        .collect()
+}
+
+/// Neurons of one view gathered into a few point series by color bucket, so a frame adds a
+/// handful of plot items instead of one per neuron. Hover labels still name single neurons
+/// through [`PointBatch::label`].
+#[derive(Default)]
+pub struct PointBatch {
+    series: Vec<Series>,
+    names: Vec<([f64; 2], Rc<str>)>,
+}
+
+/// Points of one color bucket, shared with the plot items drawing them on every frame.
+struct Series {
+    color: Color32,
+    points: Rc<[PlotPoint]>,
+    bounds: PlotBounds,
+}
+
+/// Snap a color to a coarse grid; neighbouring buckets are indistinguishable on screen.
+fn bucket(color: Color32) -> Color32 {
+    let q = |v: u8| (v / 8) * 8 + 4;
+    let [r, g, b, a] = color.to_array();
+    Color32::from_rgba_premultiplied(q(r), q(g), q(b), a)
+}
+
+impl PointBatch {
+    fn new<'a>(markers: impl Iterator<Item = ([f64; 2], Color32, &'a Rc<str>)>) -> Self {
+        let mut series: HashMap<Color32, Vec<PlotPoint>> = HashMap::new();
+        let mut names = Vec::new();
+        for (p, color, name) in markers {
+            series
+                .entry(bucket(color))
+                .or_default()
+                .push(PlotPoint::new(p[0], p[1]));
+            names.push((p, Rc::clone(name)));
+        }
+        let series = series
+            .into_iter()
+            .map(|(color, points)| {
+                let mut bounds = PlotBounds::NOTHING;
+                points.iter().for_each(|p| bounds.extend_with(p));
+                let points = points.into();
+                Series {
+                    color,
+                    points,
+                    bounds,
+                }
+            })
+            .collect();
+        Self { series, names }
+    }
+
+    /// Draw the markers, each with an outline when given.
+    pub fn draw(&self, plot_ui: &mut PlotUi, radius: f32, outline: Option<Stroke>) {
+        if let Some(outline) = outline {
+            for series in &self.series {
+                plot_ui.add(SharedPoints {
+                    radius: radius + outline.width,
+                    color: outline.color,
+                    highlight: false,
+                    allow_hover: false,
+                    ..SharedPoints::new(series)
+                });
+            }
+        }
+        for series in &self.series {
+            plot_ui.add(SharedPoints {
+                radius,
+                ..SharedPoints::new(series)
+            });
+        }
+    }
+
+    /// Name of the neuron drawn at `value`, if any.
+    pub fn name_at(&self, value: &PlotPoint) -> Option<&str> {
+        const EPS: f64 = 1e-6;
+        self.names
+            .iter()
+            .find(|(p, _)| (p[0] - value.x).abs() < EPS && (p[1] - value.y).abs() < EPS)
+            .map(|(_, name)| &**name)
+    }
+
+    /// Hover label for a plot: the neuron under the cursor, or the series name elsewhere.
+    pub fn label(&self, series: &str, value: &PlotPoint) -> String {
+        let name = self.name_at(value).unwrap_or(series);
+        if name.is_empty() {
+            format!("x = {:.2}\ny = {:.2}", value.x, value.y)
+        } else {
+            format!("{name}\nx = {:.2}\ny = {:.2}", value.x, value.y)
+        }
+    }
+}
+
+/// Filled circle markers drawn from a series without copying its points, unlike
+/// [`egui_plot::Points`], which owns them. Highlighted markers are drawn larger, as there.
+/// Large series are drawn at a [level of detail](level_of_detail), merged on the screen.
+struct SharedPoints {
+    points: Rc<[PlotPoint]>,
+    bounds: PlotBounds,
+    color: Color32,
+    radius: f32,
+    highlight: bool,
+    allow_hover: bool,
+}
+
+impl SharedPoints {
+    fn new(series: &Series) -> Self {
+        Self {
+            points: Rc::clone(&series.points),
+            bounds: series.bounds,
+            color: series.color,
+            radius: 1.0,
+            highlight: true,
+            allow_hover: true,
+        }
+    }
+}
+
+impl PlotItem for SharedPoints {
+    fn shapes(&self, _ui: &Ui, transform: &PlotTransform, shapes: &mut Vec<Shape>) {
+        let radius = if self.highlight {
+            self.radius * std::f32::consts::SQRT_2
+        } else {
+            self.radius
+        };
+        let frame = transform.frame().expand(radius);
+        let centers = (self.points.iter())
+            .map(|p| transform.position_from_point(p))
+            .filter(|center| frame.contains(*center))
+            .map(|center| [center.x as f64, center.y as f64])
+            .collect();
+        shapes.extend(
+            merge_cells(centers, [LOD_CELL; 2])
+                .into_iter()
+                .map(|[x, y]| {
+                    Shape::circle_filled(egui::pos2(x as f32, y as f32), radius, self.color)
+                }),
+        );
+    }
+
+    fn initialize(&mut self, _x_range: RangeInclusive<f64>) {}
+
+    fn name(&self) -> &str {
+        ""
+    }
+
+    fn color(&self) -> Color32 {
+        self.color
+    }
+
+    fn highlight(&mut self) {
+        self.highlight = true;
+    }
+
+    fn highlighted(&self) -> bool {
+        self.highlight
+    }
+
+    fn allow_hover(&self) -> bool {
+        self.allow_hover
+    }
+
+    fn geometry(&self) -> PlotGeometry<'_> {
+        PlotGeometry::Points(&self.points)
+    }
+
+    fn bounds(&self) -> PlotBounds {
+        self.bounds
+    }
+
+    fn id(&self) -> Option<Id> {
+        None
+    }
+}
+
+/// Displayed position, marker color and name of a neuron.
+pub type Marker = ([f32; 3], Color32, Rc<str>);
+
+/// Filtered subsets kept per view, enough for the parts of a split view and a few slabs.
+const FILTERED_CACHE: usize = 8;
+
+/// Marker geometry of the displayed neurons in every view. It is rebuilt only when the
+/// neurons, their colors or the orientation change, not on every frame, and so are the
+/// subsets drawn by views showing part of the neurons.
+#[derive(Default)]
+pub struct GeometryCache {
+    key: Option<(u64, u64)>,
+    /// Bumped by [`GeometryCache::invalidate`].
+    generation: u64,
+    markers: Vec<Marker>,
+    /// The neurons as displayed in a flipped orientation; empty in the default one, where
+    /// they are shown as they are.
+    oriented: Rc<[Neuron]>,
+    /// All markers of each view, indexed by [`View::index`].
+    views: [Rc<PointBatch>; 3],
+    /// Recently drawn subsets of each view with the hash of what selected them, the most
+    /// recent last.
+    filtered: RefCell<[Subsets; 3]>,
+}
+
+type Subsets = Vec<(u64, Rc<PointBatch>)>;
+
+impl GeometryCache {
+    /// Mark the geometry stale after the atlas, the datasets, the filters or the selection
+    /// changed, so the next [`GeometryCache::update`] rebuilds it.
+    pub fn invalidate(&mut self) {
+        self.generation += 1;
+    }
+
+    /// Rebuild the geometry of `neurons` shown in `orientation` if it was invalidated or `key`
+    /// (see [`fingerprint`]) differs from the cached one; `key` must cover the orientation.
+    pub fn update(
+        &mut self,
+        key: u64,
+        neurons: &[&Neuron],
+        orientation: Orientation,
+        color_of: impl Fn(&Neuron) -> Color32,
+    ) {
+        let key = Some((self.generation, key));
+        if self.key == key {
+            return;
+        }
+        self.key = key;
+        self.oriented = match orientation.is_identity() {
+            true => Rc::from([]),
+            false => neurons
+                .iter()
+                .map(|n| orientation.apply_neuron(n))
+                .collect(),
+        };
+        let marker = |n: &Neuron| (n.position(), color_of(n), Rc::from(n.name.as_str()));
+        self.markers = match orientation.is_identity() {
+            true => neurons.iter().map(|n| marker(n)).collect(),
+            false => self.oriented.iter().map(marker).collect(),
+        };
+        self.views = View::ALL.map(|view| Rc::new(self.project(view, |_| true)));
+        self.filtered.get_mut().iter_mut().for_each(Vec::clear);
+    }
+
+    /// The neurons last passed to [`GeometryCache::update`] as displayed in a flipped
+    /// orientation, or nothing in the default orientation.
+    pub fn oriented(&self) -> Rc<[Neuron]> {
+        Rc::clone(&self.oriented)
+    }
+
+    /// Displayed position, marker color and name of each neuron.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn markers(&self) -> &[Marker] {
+        &self.markers
+    }
+
+    pub fn view(&self, view: View) -> Rc<PointBatch> {
+        Rc::clone(&self.views[view.index()])
+    }
+
+    /// Markers of `view` whose displayed 3D position satisfies `keep`. The batch is kept
+    /// under `key`, which must tell apart every `keep` used with this view, until the
+    /// geometry is rebuilt.
+    pub fn filtered(
+        &self,
+        view: View,
+        key: impl Hash,
+        keep: impl Fn([f32; 3]) -> bool,
+    ) -> Rc<PointBatch> {
+        let key = fingerprint(key);
+        let mut cache = self.filtered.borrow_mut();
+        let cache = &mut cache[view.index()];
+        if let Some(i) = cache.iter().position(|(k, _)| *k == key) {
+            let hit = cache.remove(i);
+            let batch = Rc::clone(&hit.1);
+            cache.push(hit);
+            return batch;
+        }
+        let batch = if self.markers.iter().all(|(p, _, _)| keep(*p)) {
+            self.view(view)
+        } else {
+            Rc::new(self.project(view, keep))
+        };
+        if cache.len() == FILTERED_CACHE {
+            cache.remove(0);
+        }
+        cache.push((key, Rc::clone(&batch)));
+        batch
+    }
+
+    fn project(&self, view: View, keep: impl Fn([f32; 3]) -> bool) -> PointBatch {
+        PointBatch::new(
+            self.markers
+                .iter()
+                .filter(|(p, _, _)| keep(*p))
+                .map(|(p, color, name)| (view.project(*p), *color, name)),
+        )
+    }
+}
+
+/// Hash of `value`, for the cache keys of [`GeometryCache`].
+pub fn fingerprint(value: impl Hash) -> u64 {
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    value.hash(&mut hasher);
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn few_points_are_drawn_as_they_are() {
+        let points: Vec<[f64; 2]> = (0..LOD_THRESHOLD).map(|i| [0.1 * i as f64, 0.0]).collect();
+        assert_eq!(merge_cells(points.clone(), [LOD_CELL; 2]), points);
+    }
+
+    #[test]
+    fn many_points_merge_into_the_centroid_of_their_cell() {
+        // Pairs of points 1 apart in cells 4 apart, plus one point in a cell of its own.
+        let mut points: Vec<[f64; 2]> = (0..LOD_THRESHOLD / 2)
+            .flat_map(|i| {
+                let x = i as f64 * LOD_CELL;
+                [[x + 1.0, 1.0], [x + 2.0, 3.0]]
+            })
+            .collect();
+        points.push([-2.0, -2.0]);
+        let mut merged = merge_cells(points, [LOD_CELL; 2]);
+        merged.sort_by(|a, b| a[0].total_cmp(&b[0]));
+        assert_eq!(merged.len(), LOD_THRESHOLD / 2 + 1);
+        assert_eq!(merged[0], [-2.0, -2.0]);
+        assert_eq!(merged[1], [1.5, 2.0]);
+        assert_eq!(merged[2], [LOD_CELL + 1.5, 2.0]);
+    }
+}
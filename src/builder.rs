@@ -0,0 +1,83 @@
+use crate::app::MyApp;
+use crate::atlas::{Atlas, WormSide};
+use crate::events::{AppEvent, EventCallback};
+
+/// Startup configuration of [`MyApp`], for deployments that need something other than the
+/// NeuroPAL atlas and the state of the last session.
+///
+/// Settings given here take precedence over the persisted state.
+pub struct AppBuilder {
+    pub(crate) atlas: Option<Atlas>,
+    pub(crate) search: Option<String>,
+    pub(crate) side: Option<WormSide>,
+    pub(crate) persistence: bool,
+    pub(crate) read_only: bool,
+    pub(crate) theme: Option<egui::ThemePreference>,
+    pub(crate) callbacks: Vec<EventCallback>,
+}
+
+impl Default for AppBuilder {
+    fn default() -> Self {
+        Self {
+            atlas: None,
+            search: None,
+            side: None,
+            persistence: true,
+            read_only: false,
+            theme: None,
+            callbacks: Vec::new(),
+        }
+    }
+}
+
+impl AppBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reference atlas shown instead of the NeuroPAL atlas.
+    pub fn atlas(mut self, atlas: Atlas) -> Self {
+        self.atlas = Some(atlas);
+        self
+    }
+
+    /// Initial search text: name prefixes separated by spaces or commas, `*` for all.
+    pub fn search(mut self, search: impl Into<String>) -> Self {
+        self.search = Some(search.into());
+        self
+    }
+
+    /// Initial body side filter.
+    pub fn side(mut self, side: WormSide) -> Self {
+        self.side = Some(side);
+        self
+    }
+
+    /// Whether to restore the last session and save this one; on by default.
+    pub fn persistence(mut self, persistence: bool) -> Self {
+        self.persistence = persistence;
+        self
+    }
+
+    /// Forbid opening and removing datasets, for kiosks and published figures.
+    pub fn read_only(mut self, read_only: bool) -> Self {
+        self.read_only = read_only;
+        self
+    }
+
+    pub fn theme(mut self, theme: egui::ThemePreference) -> Self {
+        self.theme = Some(theme);
+        self
+    }
+
+    /// Call `callback` on each hover, selection and filter change; see [`MyApp::on_event`].
+    pub fn on_event(mut self, callback: impl FnMut(&AppEvent) + 'static) -> Self {
+        self.callbacks.push(Box::new(callback));
+        self
+    }
+
+    /// Create the app; call from the app creator passed to eframe.
+    pub fn build(self, cc: &eframe::CreationContext<'_>) -> MyApp {
+        MyApp::from_builder(cc, self)
+    }
+}
@@ -0,0 +1,35 @@
+use serde::de::DeserializeOwned;
+#[cfg(not(target_arch = "wasm32"))]
+use serde::Serialize;
+
+use crate::i18n::tr;
+
+/// Extension of a bundle file.
+pub const EXTENSION: &str = "npl";
+
+/// First bytes of a bundle, followed by its contents as zlib-compressed JSON. The digit is
+/// the version of the format.
+const MAGIC: &[u8] = b"NPLBUNDLE1\n";
+
+/// Whether `bytes` are a bundle, rather than a dataset or an activity file.
+pub fn is_bundle(bytes: &[u8]) -> bool {
+    bytes.starts_with(MAGIC)
+}
+
+/// Write `contents` as a bundle, to be read back by [`decode`].
+#[cfg(not(target_arch = "wasm32"))]
+pub fn encode(contents: &impl Serialize) -> Result<Vec<u8>, String> {
+    let json = serde_json::to_vec(contents).map_err(|e| e.to_string())?;
+    let mut bytes = MAGIC.to_vec();
+    bytes.extend(miniz_oxide::deflate::compress_to_vec_zlib(&json, 6));
+    Ok(bytes)
+}
+
+pub fn decode<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, String> {
+    let Some(body) = bytes.strip_prefix(MAGIC) else {
+        return Err(tr("not a bundle of this version").to_owned());
+    };
+    let json = miniz_oxide::inflate::decompress_to_vec_zlib(body)
+        .map_err(|err| format!("corrupt bundle: {err:?}"))?;
+    serde_json::from_slice(&json).map_err(|e| e.to_string())
+}
@@ -0,0 +1,254 @@
+use std::collections::BTreeMap;
+
+use egui::Color32;
+
+use crate::dataset::Neuron;
+use crate::i18n::tr;
+
+/// How neurons are grouped into clusters.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub enum ClusterMethod {
+    /// A fixed number of clusters around their means.
+    #[default]
+    KMeans,
+    /// Clusters of densely packed neurons; isolated ones are left as noise.
+    Dbscan,
+}
+
+impl std::fmt::Display for ClusterMethod {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::KMeans => write!(f, "k-means"),
+            Self::Dbscan => write!(f, "DBSCAN"),
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct ClusterSettings {
+    pub method: ClusterMethod,
+    /// Number of k-means clusters.
+    pub k: usize,
+    /// DBSCAN neighborhood radius, in atlas units.
+    pub eps: f32,
+    /// Neighbors within `eps` that make a neuron a DBSCAN core point, itself included.
+    pub min_points: usize,
+    /// Cluster on the NeuroPAL color as well as on the position.
+    pub use_color: bool,
+    /// Atlas units a full step of a color channel counts as.
+    pub color_weight: f32,
+}
+
+impl Default for ClusterSettings {
+    fn default() -> Self {
+        Self {
+            method: ClusterMethod::KMeans,
+            k: 6,
+            eps: 6.0,
+            min_points: 4,
+            use_color: false,
+            color_weight: 20.0,
+        }
+    }
+}
+
+impl ClusterSettings {
+    pub fn ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            for method in [ClusterMethod::KMeans, ClusterMethod::Dbscan] {
+                ui.radio_value(&mut self.method, method, method.to_string());
+            }
+        });
+        match self.method {
+            ClusterMethod::KMeans => {
+                ui.add(egui::Slider::new(&mut self.k, 2..=30).text(tr("Clusters")));
+            }
+            ClusterMethod::Dbscan => {
+                ui.add(egui::Slider::new(&mut self.eps, 0.5..=50.0).text(tr("Radius")));
+                ui.add(
+                    egui::Slider::new(&mut self.min_points, 2..=20).text(tr("Minimum neighbors")),
+                );
+            }
+        }
+        ui.checkbox(&mut self.use_color, tr("Include colors"));
+        if self.use_color {
+            ui.add(egui::Slider::new(&mut self.color_weight, 1.0..=100.0).text(tr("Color weight")))
+                .on_hover_text(tr(
+                    "Distance in atlas units that a full change of a color channel counts as",
+                ));
+        }
+    }
+
+    /// Cluster `neurons`; each gets a cluster index, or none when DBSCAN leaves it as noise.
+    pub fn run(&self, neurons: &[&Neuron]) -> Clustering {
+        let features: Vec<Vec<f32>> = neurons
+            .iter()
+            .map(|n| {
+                let mut f = vec![n.x, n.y, n.z];
+                if self.use_color {
+                    f.extend([n.r, n.g, n.b].map(|c| c * self.color_weight));
+                }
+                f
+            })
+            .collect();
+        let labels = match self.method {
+            ClusterMethod::KMeans => kmeans(&features, self.k, 100)
+                .into_iter()
+                .map(Some)
+                .collect(),
+            ClusterMethod::Dbscan => dbscan(&features, self.eps, self.min_points),
+        };
+        let count = labels.iter().flatten().map(|&l| l + 1).max().unwrap_or(0);
+        Clustering {
+            labels: neurons
+                .iter()
+                .zip(labels)
+                .map(|(n, l)| (n.name.clone(), l))
+                .collect(),
+            count,
+        }
+    }
+}
+
+/// Cluster labels of the neurons clustered last, kept with the app state.
+#[derive(Clone, Default, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct Clustering {
+    pub labels: BTreeMap<String, Option<usize>>,
+    pub count: usize,
+}
+
+impl std::hash::Hash for Clustering {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.labels.hash(state);
+    }
+}
+
+impl Clustering {
+    pub fn is_empty(&self) -> bool {
+        self.labels.is_empty()
+    }
+
+    /// Names of the neurons in `cluster`, or of the noise for `None`.
+    pub fn members(&self, cluster: Option<usize>) -> impl Iterator<Item = &String> {
+        self.labels
+            .iter()
+            .filter(move |(_, l)| **l == cluster)
+            .map(|(name, _)| name)
+    }
+
+    pub fn color_of(&self, name: &str) -> Color32 {
+        match self.labels.get(name) {
+            Some(label) => color(*label),
+            None => Color32::DARK_GRAY,
+        }
+    }
+}
+
+/// Distinct color of a cluster; noise is gray.
+pub fn color(cluster: Option<usize>) -> Color32 {
+    match cluster {
+        // Golden-ratio steps around the hue circle keep neighboring indices apart.
+        Some(i) => egui::ecolor::Hsva::new((i as f32 * 0.618_034).fract(), 0.75, 0.95, 1.0).into(),
+        None => Color32::GRAY,
+    }
+}
+
+fn distance2(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b).map(|(x, y)| (x - y) * (x - y)).sum()
+}
+
+/// Lloyd's k-means from deterministic farthest-point seeds, so the same data always gives
+/// the same clusters.
+fn kmeans(features: &[Vec<f32>], k: usize, iterations: usize) -> Vec<usize> {
+    let k = k.min(features.len());
+    if k == 0 {
+        return vec![0; features.len()];
+    }
+    let mut centers = vec![features[0].clone()];
+    while centers.len() < k {
+        let farthest = features
+            .iter()
+            .max_by(|a, b| {
+                let d = |p: &Vec<f32>| {
+                    centers
+                        .iter()
+                        .map(|c| distance2(p, c))
+                        .fold(f32::INFINITY, f32::min)
+                };
+                d(a).total_cmp(&d(b))
+            })
+            .cloned()
+            .unwrap_or_default();
+        centers.push(farthest);
+    }
+    let mut labels = vec![0; features.len()];
+    for _ in 0..iterations {
+        let mut changed = false;
+        for (label, f) in labels.iter_mut().zip(features) {
+            let nearest = (0..k)
+                .min_by(|&a, &b| distance2(f, &centers[a]).total_cmp(&distance2(f, &centers[b])))
+                .unwrap_or(0);
+            changed |= *label != nearest;
+            *label = nearest;
+        }
+        for (i, center) in centers.iter_mut().enumerate() {
+            let members: Vec<&Vec<f32>> = (features.iter().zip(&labels))
+                .filter(|(_, l)| **l == i)
+                .map(|(f, _)| f)
+                .collect();
+            if members.is_empty() {
+                continue;
+            }
+            for (d, c) in center.iter_mut().enumerate() {
+                *c = members.iter().map(|m| m[d]).sum::<f32>() / members.len() as f32;
+            }
+        }
+        if !changed {
+            break;
+        }
+    }
+    labels
+}
+
+/// Density-based clustering: neurons with at least `min_points` neighbors within `eps` seed
+/// clusters that grow through their neighbors; the rest is noise.
+fn dbscan(features: &[Vec<f32>], eps: f32, min_points: usize) -> Vec<Option<usize>> {
+    let eps2 = eps * eps;
+    let neighbors = |i: usize| -> Vec<usize> {
+        (0..features.len())
+            .filter(|&j| distance2(&features[i], &features[j]) <= eps2)
+            .collect()
+    };
+    let mut labels: Vec<Option<usize>> = vec![None; features.len()];
+    let mut visited = vec![false; features.len()];
+    let mut cluster = 0;
+    for i in 0..features.len() {
+        if visited[i] {
+            continue;
+        }
+        visited[i] = true;
+        let seeds = neighbors(i);
+        if seeds.len() < min_points {
+            continue;
+        }
+        labels[i] = Some(cluster);
+        let mut queue = seeds;
+        while let Some(j) = queue.pop() {
+            if labels[j].is_none() {
+                labels[j] = Some(cluster);
+            }
+            if visited[j] {
+                continue;
+            }
+            visited[j] = true;
+            let reach = neighbors(j);
+            if reach.len() >= min_points {
+                queue.extend(reach);
+            }
+        }
+        cluster += 1;
+    }
+    labels
+}
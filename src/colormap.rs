@@ -0,0 +1,151 @@
+use egui::{Align2, Color32, FontId, Mesh, Pos2, Rect, Stroke};
+
+/// Samples of the viridis colormap, evenly spaced from 0 to 1.
+const VIRIDIS: [[u8; 3]; 9] = [
+    [68, 1, 84],
+    [71, 44, 122],
+    [59, 81, 139],
+    [44, 113, 142],
+    [33, 144, 141],
+    [39, 173, 129],
+    [92, 200, 99],
+    [170, 220, 50],
+    [253, 231, 37],
+];
+
+/// Samples of Moreland's cool-warm diverging colormap, evenly spaced from 0 to 1.
+const COOLWARM: [[u8; 3]; 9] = [
+    [59, 76, 192],
+    [98, 130, 234],
+    [141, 176, 254],
+    [184, 208, 249],
+    [221, 221, 221],
+    [245, 196, 173],
+    [244, 154, 123],
+    [222, 96, 77],
+    [180, 4, 38],
+];
+
+/// Color for `t` in 0..=1 interpolated between evenly spaced `samples`; values outside are
+/// clamped.
+fn sample(samples: &[[u8; 3]], t: f32) -> Color32 {
+    let t = t.clamp(0.0, 1.0) * (samples.len() - 1) as f32;
+    let i = (t.floor() as usize).min(samples.len() - 2);
+    let f = t - i as f32;
+    let [a, b] = [samples[i], samples[i + 1]];
+    let mix = |k: usize| (a[k] as f32 + (b[k] as f32 - a[k] as f32) * f).round() as u8;
+    Color32::from_rgb(mix(0), mix(1), mix(2))
+}
+
+#[derive(
+    Clone, Copy, Debug, Default, PartialEq, Eq, Hash, serde::Deserialize, serde::Serialize,
+)]
+pub enum Colormap {
+    /// Perceptually uniform, from dark blue to yellow.
+    #[default]
+    Viridis,
+    /// Diverging from blue through gray to red, for values around a midpoint such as ΔF/F.
+    Coolwarm,
+}
+
+impl Colormap {
+    pub const ALL: [Self; 2] = [Self::Viridis, Self::Coolwarm];
+
+    pub fn color(self, t: f32) -> Color32 {
+        match self {
+            Self::Viridis => sample(&VIRIDIS, t),
+            Self::Coolwarm => sample(&COOLWARM, t),
+        }
+    }
+}
+
+impl std::fmt::Display for Colormap {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Viridis => write!(f, "viridis"),
+            Self::Coolwarm => write!(f, "coolwarm"),
+        }
+    }
+}
+
+/// Legend of a continuous color encoding, mapping `min..=max` onto `colormap`.
+#[derive(Clone, PartialEq)]
+pub struct Colorbar {
+    pub label: String,
+    pub min: f32,
+    pub max: f32,
+    pub colormap: Colormap,
+}
+
+impl Colorbar {
+    /// Position of `value` along the bar, from 0 at `min` to 1 at `max`.
+    pub fn normalize(&self, value: f32) -> f32 {
+        if self.max > self.min {
+            (value - self.min) / (self.max - self.min)
+        } else {
+            0.5
+        }
+    }
+
+    pub fn color(&self, value: f32) -> Color32 {
+        self.colormap.color(self.normalize(value))
+    }
+
+    /// Paint the bar with numeric ticks into the top right corner of `plot_rect`, so it is
+    /// part of the plot wherever the plot is shown or captured.
+    pub fn paint(&self, painter: &egui::Painter, plot_rect: Rect, visuals: &egui::Visuals) {
+        const TICKS: usize = 5;
+        let font = FontId::proportional(11.0);
+        let bar = Rect::from_min_size(
+            Pos2::new(plot_rect.right() - 56.0, plot_rect.top() + 28.0),
+            egui::vec2(12.0, 120.0),
+        );
+        let frame = Rect::from_min_max(
+            bar.min - egui::vec2(8.0, 22.0),
+            bar.max + egui::vec2(44.0, 8.0),
+        );
+        painter.rect_filled(frame, 4.0, visuals.extreme_bg_color.gamma_multiply(0.85));
+        painter.text(
+            Pos2::new(frame.center().x, frame.top() + 4.0),
+            Align2::CENTER_TOP,
+            &self.label,
+            font.clone(),
+            visuals.text_color(),
+        );
+
+        // Top of the bar is the maximum.
+        let mut mesh = Mesh::default();
+        const STEPS: usize = 32;
+        for i in 0..=STEPS {
+            let t = i as f32 / STEPS as f32;
+            let y = bar.bottom() - t * bar.height();
+            let color = self.colormap.color(t);
+            mesh.colored_vertex(Pos2::new(bar.left(), y), color);
+            mesh.colored_vertex(Pos2::new(bar.right(), y), color);
+            if i > 0 {
+                let k = 2 * i as u32;
+                mesh.add_triangle(k - 2, k - 1, k);
+                mesh.add_triangle(k - 1, k, k + 1);
+            }
+        }
+        painter.add(mesh);
+        painter.rect_stroke(bar, 0.0, Stroke::new(1.0, visuals.text_color()));
+
+        for i in 0..TICKS {
+            let t = i as f32 / (TICKS - 1) as f32;
+            let y = bar.bottom() - t * bar.height();
+            let value = self.min + t * (self.max - self.min);
+            painter.line_segment(
+                [Pos2::new(bar.right(), y), Pos2::new(bar.right() + 4.0, y)],
+                Stroke::new(1.0, visuals.text_color()),
+            );
+            painter.text(
+                Pos2::new(bar.right() + 6.0, y),
+                Align2::LEFT_CENTER,
+                format!("{value:.1}"),
+                font.clone(),
+                visuals.text_color(),
+            );
+        }
+    }
+}
@@ -0,0 +1,116 @@
+use egui::{Key, Modifiers, Order, Rect, RichText};
+
+use crate::i18n::tr;
+use crate::index::NameIndex;
+
+/// Completions listed under the search field at most.
+const LIMIT: usize = 8;
+
+/// Characters separating the terms of a search.
+const SEPARATORS: [char; 4] = [' ', ';', ',', '\t'];
+
+/// Single-line search field completing the term being typed with the neuron names and
+/// classes of `index`. ↑/↓ move through the completions and Tab or Enter inserts the
+/// highlighted one; the returned response is marked changed when a completion is inserted.
+pub fn search_field(ui: &mut egui::Ui, search: &mut String, index: &NameIndex) -> egui::Response {
+    let id = ui.id().with("search_field");
+    let highlight_id = id.with("highlight");
+    let popup_id = id.with("popup");
+    let mut highlight: Option<usize> = ui.data(|d| d.get_temp(highlight_id)).flatten();
+    // The field gives up focus when a completion is pressed; keep the list up for the click.
+    let popup_rect: Option<Rect> = ui.data(|d| d.get_temp(popup_id));
+    let over_popup = (ui.ctx().pointer_interact_pos())
+        .zip(popup_rect)
+        .is_some_and(|(pos, rect)| rect.contains(pos));
+
+    // Take the keys from the field before it moves the cursor or gives up focus on Enter.
+    let open = ui.memory(|m| m.has_focus(id)) && !completions(search, index).is_empty();
+    let (down, up, accept) = if open {
+        ui.input_mut(|i| {
+            let mut count = |key| {
+                let mut n = 0;
+                while i.consume_key(Modifiers::NONE, key) {
+                    n += 1;
+                }
+                n
+            };
+            let down = count(Key::ArrowDown);
+            let up = count(Key::ArrowUp);
+            let accept = highlight.is_some()
+                && (i.consume_key(Modifiers::NONE, Key::Tab)
+                    || i.consume_key(Modifiers::NONE, Key::Enter));
+            (down, up, accept)
+        })
+    } else {
+        (0, 0, false)
+    };
+
+    let mut response = ui.add(egui::TextEdit::singleline(search).id(id));
+    if response.changed() {
+        highlight = None;
+    }
+
+    let options = completions(search, index);
+    if options.is_empty() || !(response.has_focus() || over_popup) {
+        ui.data_mut(|d| {
+            d.remove::<Option<usize>>(highlight_id);
+            d.remove::<Rect>(popup_id);
+        });
+        return response;
+    }
+    let last = options.len() - 1;
+    highlight = match (highlight, down as isize - up as isize) {
+        (h, 0) => h,
+        (None, step) if step > 0 => Some(step as usize - 1),
+        (None, _) => Some(last),
+        (Some(h), step) => Some((h as isize + step).clamp(0, last as isize) as usize),
+    }
+    .map(|h| h.min(last));
+
+    let mut chosen = highlight.filter(|_| accept);
+    let popup = egui::Area::new(popup_id)
+        .order(Order::Foreground)
+        .fixed_pos(response.rect.left_bottom())
+        .show(ui.ctx(), |ui| {
+            egui::Frame::popup(ui.style()).show(ui, |ui| {
+                ui.set_min_width(response.rect.width());
+                for (i, (term, count)) in options.iter().enumerate() {
+                    let text = format!("{term}  ({count})");
+                    let label = ui.selectable_label(highlight == Some(i), RichText::new(text));
+                    if label.clicked() {
+                        chosen = Some(i);
+                    }
+                }
+                ui.label(RichText::new(tr("↑/↓ choose, Tab inserts")).weak().small());
+            });
+        });
+    ui.data_mut(|d| d.insert_temp(popup_id, popup.response.rect));
+
+    if let Some(i) = chosen {
+        let start = search.rfind(SEPARATORS).map_or(0, |i| i + 1);
+        search.replace_range(start.., &options[i].0);
+        search.push(' ');
+        highlight = None;
+        response.request_focus();
+        response.mark_changed();
+    }
+    ui.data_mut(|d| d.insert_temp(highlight_id, highlight));
+    response
+}
+
+/// Completions of the last term of `search`, unless it is complete already.
+fn completions(search: &str, index: &NameIndex) -> Vec<(String, usize)> {
+    let term = search.rsplit(SEPARATORS).next().unwrap_or_default();
+    if term.is_empty() {
+        return Vec::new();
+    }
+    let options = index.completions(term);
+    if options.len() == 1 && options[0].0 == term {
+        return Vec::new();
+    }
+    options
+        .into_iter()
+        .take(LIMIT)
+        .map(|(term, count)| (term.to_owned(), count))
+        .collect()
+}
@@ -0,0 +1,845 @@
+use csv::StringRecord;
+use egui::Color32;
+
+use std::borrow::Cow;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use crate::frame::{self, CoordinateFrame, FrameTransform};
+use crate::mapping::{self, ColumnMapping, Delimiter, TableFormat, FIELDS};
+use crate::midline::Midline;
+use crate::pca::PrincipalAxes;
+use crate::region::{self, BodyRegion};
+use crate::validate::{self, Validator, Warning};
+
+pub static NEUROPAL_ORG: &[u8] = include_bytes!("neuropal.csv");
+static NEUROPAL_HEADER: [&str; 9] = ["name", "x", "y", "z", "r", "g", "b", "region", "pharyngeal"];
+/// Columns a header line must name for the file to be read by its header.
+static REQUIRED_COLUMNS: [&str; 4] = ["name", "x", "y", "z"];
+/// Names of a column of `#RRGGBB` colors.
+static COLOR_COLUMNS: [&str; 3] = ["color", "colour", "hex"];
+/// Color channel of neurons whose file gives no color, drawing them gray.
+const DEFAULT_CHANNEL: f32 = 0.5;
+
+/// A named neuron with its position in atlas units and its NeuroPAL color, each channel in
+/// `0..=1`. Files may add the body region and whether the neuron is pharyngeal in two
+/// optional columns, which are inferred from its position and name when they are left out.
+/// Without a color, a neuron is gray. Any other column of a file is kept in `meta`.
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
+#[serde(from = "NeuronRecord")]
+pub struct Neuron {
+    pub name: String,
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+    pub r: f32,
+    pub g: f32,
+    pub b: f32,
+    pub region: Option<BodyRegion>,
+    pub pharyngeal: Option<bool>,
+    /// Values of the extra columns of the file, by column name; empty values are left out.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub meta: BTreeMap<String, String>,
+}
+
+/// A neuron as found in a file. Its color may be a `#RRGGBB` string, in the `r` column or
+/// in a `color` column of its own, or channels in `0..=1` or `0..=255`, which are told apart
+/// for the whole file by [`normalize_colors`]. Channels left out are NaN until then, so they
+/// take no part in telling the two apart.
+#[derive(serde::Deserialize)]
+struct NeuronRecord {
+    name: String,
+    x: f32,
+    y: f32,
+    z: f32,
+    #[serde(default)]
+    r: Option<Channel>,
+    #[serde(default)]
+    g: Option<Channel>,
+    #[serde(default)]
+    b: Option<Channel>,
+    #[serde(default, alias = "colour", alias = "hex")]
+    color: Option<Channel>,
+    #[serde(default, deserialize_with = "region::deserialize_body_region")]
+    region: Option<BodyRegion>,
+    #[serde(default, deserialize_with = "region::deserialize_flag")]
+    pharyngeal: Option<bool>,
+    /// Only found in JSON; the extra columns of a table are added once the row is read.
+    #[serde(default)]
+    meta: BTreeMap<String, String>,
+}
+
+impl From<NeuronRecord> for Neuron {
+    fn from(record: NeuronRecord) -> Self {
+        let channel = |c: Option<Channel>| match c {
+            Some(Channel::Value(v)) => v,
+            _ => f32::NAN,
+        };
+        let [r, g, b] = match (record.color, record.r) {
+            (Some(Channel::Hex(rgb)), _) | (_, Some(Channel::Hex(rgb))) => rgb,
+            (_, r) => [channel(r), channel(record.g), channel(record.b)],
+        };
+        Self {
+            name: record.name,
+            x: record.x,
+            y: record.y,
+            z: record.z,
+            r,
+            g,
+            b,
+            region: record.region,
+            pharyngeal: record.pharyngeal,
+            meta: record.meta,
+        }
+    }
+}
+
+/// A color channel as written in a file, or a whole color as a hex string.
+enum Channel {
+    Value(f32),
+    Hex([f32; 3]),
+}
+
+impl<'de> serde::Deserialize<'de> for Channel {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(ChannelVisitor)
+    }
+}
+
+struct ChannelVisitor;
+
+impl serde::de::Visitor<'_> for ChannelVisitor {
+    type Value = Channel;
+
+    fn expecting(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("a color channel or a #RRGGBB color")
+    }
+
+    fn visit_f64<E>(self, v: f64) -> Result<Channel, E> {
+        Ok(Channel::Value(v as f32))
+    }
+
+    fn visit_u64<E>(self, v: u64) -> Result<Channel, E> {
+        Ok(Channel::Value(v as f32))
+    }
+
+    fn visit_i64<E>(self, v: i64) -> Result<Channel, E> {
+        Ok(Channel::Value(v as f32))
+    }
+
+    fn visit_str<E: serde::de::Error>(self, v: &str) -> Result<Channel, E> {
+        let v = v.trim();
+        if let Ok(value) = v.parse() {
+            return Ok(Channel::Value(value));
+        }
+        let hex = v.strip_prefix('#').unwrap_or(v);
+        match u32::from_str_radix(hex, 16) {
+            Ok(rgb) if hex.len() == 6 => Ok(Channel::Hex(
+                [16, 8, 0].map(|shift| ((rgb >> shift) & 0xff) as f32 / 255.0),
+            )),
+            _ => Err(E::invalid_value(serde::de::Unexpected::Str(v), &self)),
+        }
+    }
+}
+
+/// Scale the colors of `neurons` into `0..=1` if they were given as `0..=255` channels: if
+/// any channel is above 1 and none above 255. Channels the file left out, which are NaN,
+/// become [`DEFAULT_CHANNEL`] either way.
+fn normalize_colors(neurons: &mut HashMap<String, Neuron>) {
+    let channels = || neurons.values().flat_map(|n| [n.r, n.g, n.b]);
+    let max = channels().filter(|c| c.is_finite()).fold(0.0f32, f32::max);
+    let scale = if max > 1.0 && max <= 255.0 {
+        255.0
+    } else {
+        1.0
+    };
+    for n in neurons.values_mut() {
+        [n.r, n.g, n.b] = [n.r, n.g, n.b].map(|c| {
+            if c.is_nan() {
+                DEFAULT_CHANNEL
+            } else {
+                c / scale
+            }
+        });
+    }
+}
+
+impl Neuron {
+    pub fn rgb(&self) -> [u8; 3] {
+        [
+            (self.r * 255.).clamp(0., 255.) as u8,
+            (self.g * 255.).clamp(0., 255.) as u8,
+            (self.b * 255.).clamp(0., 255.) as u8,
+        ]
+    }
+    pub fn luminance(&self) -> f32 {
+        0.2126 * self.r + 0.7152 * self.g + 0.0722 * self.b
+    }
+    pub fn position(&self) -> [f32; 3] {
+        [self.x, self.y, self.z]
+    }
+}
+
+/// The columns named by a header line, as fields of [`Neuron`], and the other columns with
+/// their names; `None` if `record` is not a header naming at least the name and coordinate
+/// columns.
+fn header_of(record: &StringRecord) -> Option<(StringRecord, Vec<(usize, String)>)> {
+    let columns: Vec<String> = record
+        .iter()
+        .map(|f| f.trim().to_ascii_lowercase())
+        .collect();
+    if !(REQUIRED_COLUMNS.iter()).all(|c| columns.iter().any(|f| f == c)) {
+        return None;
+    }
+    let known = |c: &str| NEUROPAL_HEADER.contains(&c) || COLOR_COLUMNS.contains(&c);
+    let extra = extra_columns(record, |i| known(&columns[i]));
+    let header = columns
+        .iter()
+        .map(|c| if known(c) { c.as_str() } else { "" })
+        .collect();
+    Some((header, extra))
+}
+
+/// `record` with the decimal commas of its coordinate and color columns, as `header` names
+/// them, made points; other columns, such as names and metadata, keep their commas.
+fn decimal_points<'a>(record: &'a StringRecord, header: &StringRecord) -> Cow<'a, StringRecord> {
+    if !record.iter().any(|f| f.contains(',')) {
+        return Cow::Borrowed(record);
+    }
+    let numeric = |i| header.get(i).is_some_and(|c| FIELDS[1..].contains(&c));
+    let fields = record.iter().enumerate().map(|(i, f)| match numeric(i) {
+        true => mapping::decimal_point(f),
+        false => Cow::Borrowed(f),
+    });
+    Cow::Owned(fields.collect())
+}
+
+/// Columns of a header line that are not `used`, with their names; unnamed ones are left out.
+fn extra_columns(record: &StringRecord, used: impl Fn(usize) -> bool) -> Vec<(usize, String)> {
+    (record.iter().enumerate())
+        .filter(|&(i, name)| !used(i) && !name.trim().is_empty())
+        .map(|(i, name)| (i, name.trim().to_owned()))
+        .collect()
+}
+
+/// Parse a table of neurons, or a JSON array of neurons as written by [`to_json`].
+///
+/// A table with a header line naming its columns may order them freely and add columns of
+/// its own, which are ignored; only `name`, `x`, `y` and `z` are required. Without a header,
+/// the columns are read as `name,x,y,z[,r,g,b[,region,pharyngeal]]`. Rows that cannot be
+/// read are skipped.
+pub fn parse_neurons(bytes: &[u8]) -> Result<HashMap<String, Neuron>, String> {
+    parse_neurons_with_progress(bytes, &TableFormat::default(), |_, _| true)
+        .map(|(neurons, _)| neurons)
+}
+
+/// Like [`parse_neurons`], calling `progress` after each row with the fraction of the input
+/// read so far and the neuron parsed from the row, if any. Parsing is abandoned as soon as
+/// `progress` returns `false`. A table is read in `format`.
+///
+/// Rows are checked along the way, and the likely mistakes found are returned with the
+/// neurons; for JSON, the entries of the array are numbered instead of the lines.
+pub fn parse_neurons_with_progress(
+    bytes: &[u8],
+    format: &TableFormat,
+    mut progress: impl FnMut(f32, Option<&Neuron>) -> bool,
+) -> Result<(HashMap<String, Neuron>, Vec<Warning>), String> {
+    let mut validator = Validator::default();
+    if is_json(bytes) {
+        let list: Vec<Neuron> = serde_json::from_slice(bytes).map_err(|e| e.to_string())?;
+        let mut neurons = HashMap::new();
+        for (i, neuron) in list.into_iter().enumerate() {
+            if !progress(1.0, Some(&neuron)) {
+                return Err("cancelled".to_owned());
+            }
+            validator.row(i as u64 + 1, Some(&neuron), None);
+            neurons.insert(neuron.name.clone(), neuron);
+        }
+        normalize_colors(&mut neurons);
+        let warnings = validator.finish(&neurons);
+        return Ok((neurons, warnings));
+    }
+    let mut parser = TableParser::new(bytes, format);
+    loop {
+        if let Some(parsed) = parser.step(usize::MAX, &mut progress) {
+            return parsed;
+        }
+    }
+}
+
+/// Neurons read from a file, with the likely mistakes found in it.
+pub type Parsed = (HashMap<String, Neuron>, Vec<Warning>);
+
+/// A table of neurons read a number of rows at a time, as
+/// [`parse_neurons_with_progress`] reads it, so the web app can parse a large file between
+/// frames.
+pub struct TableParser<'a> {
+    reader: csv::Reader<std::io::Cursor<Cow<'a, [u8]>>>,
+    mapping: Option<&'a ColumnMapping>,
+    header: StringRecord,
+    /// The first row is skipped when the mapping says it is a header, and otherwise read as
+    /// one if it names the columns.
+    first: bool,
+    /// Columns kept as metadata, named by the header.
+    extra: Vec<(usize, String)>,
+    record: StringRecord,
+    total: f32,
+    validator: Validator,
+    neurons: HashMap<String, Neuron>,
+}
+
+impl<'a> TableParser<'a> {
+    /// A parser of the table `bytes`, read in `format`.
+    pub fn new(bytes: &'a [u8], format: &'a TableFormat) -> Self {
+        let mapping = format.columns.as_ref();
+        let header = match mapping {
+            Some(mapping) => mapping.header_record(),
+            None => StringRecord::from(NEUROPAL_HEADER.to_vec()),
+        };
+        let delimiter = format.delimiter_of(bytes);
+        let bytes = delimiter.prepare(bytes);
+        Self {
+            total: bytes.len().max(1) as f32,
+            reader: table_reader(std::io::Cursor::new(bytes), delimiter),
+            mapping,
+            header,
+            first: true,
+            extra: Vec::new(),
+            record: StringRecord::new(),
+            validator: Validator::default(),
+            neurons: HashMap::new(),
+        }
+    }
+
+    /// Read up to `rows` more rows, calling `progress` after each; returns the neurons and
+    /// warnings once the table is read, or the error that stopped it.
+    pub fn step(
+        &mut self,
+        rows: usize,
+        mut progress: impl FnMut(f32, Option<&Neuron>) -> bool,
+    ) -> Option<Result<Parsed, String>> {
+        for _ in 0..rows {
+            let record = &mut self.record;
+            let neuron = match self.reader.read_record(record) {
+                Ok(false) => return Some(self.finish()),
+                Ok(true) => {
+                    if std::mem::take(&mut self.first) {
+                        if let Some(mapping) = self.mapping {
+                            if mapping.header {
+                                let used = |i| mapping.columns.contains(&Some(i));
+                                self.extra = extra_columns(record, used);
+                                continue;
+                            }
+                        } else if let Some((columns, columns_extra)) = header_of(record) {
+                            self.header = columns;
+                            self.extra = columns_extra;
+                            continue;
+                        }
+                    }
+                    decimal_points(record, &self.header)
+                        .deserialize::<Neuron>(Some(&self.header))
+                        .ok()
+                        .map(|mut neuron| {
+                            neuron.meta = (self.extra.iter())
+                                .filter_map(|(i, name)| {
+                                    let value = record.get(*i)?.trim();
+                                    (!value.is_empty()).then(|| (name.clone(), value.to_owned()))
+                                })
+                                .collect();
+                            neuron
+                        })
+                }
+                Err(_) => None,
+            };
+            let fraction = self.reader.position().byte() as f32 / self.total;
+            if !progress(fraction, neuron.as_ref()) {
+                return Some(Err("cancelled".to_owned()));
+            }
+            if let Some(position) = record.position() {
+                (self.validator).row(position.line(), neuron.as_ref(), record.get(0));
+            }
+            if let Some(neuron) = neuron {
+                self.neurons.insert(neuron.name.to_owned(), neuron);
+            }
+        }
+        None
+    }
+
+    fn finish(&mut self) -> Result<Parsed, String> {
+        if self.neurons.is_empty() {
+            return Err(format!(
+                "no rows with a {} and {} coordinates",
+                REQUIRED_COLUMNS[0],
+                REQUIRED_COLUMNS[1..].join(", ")
+            ));
+        }
+        let mut neurons = std::mem::take(&mut self.neurons);
+        normalize_colors(&mut neurons);
+        let warnings = std::mem::take(&mut self.validator).finish(&neurons);
+        Ok((neurons, warnings))
+    }
+}
+
+pub fn is_json(bytes: &[u8]) -> bool {
+    bytes.iter().find(|b| !b.is_ascii_whitespace()) == Some(&b'[')
+}
+
+/// A reader of a table [prepared](Delimiter::prepare) for `delimiter`.
+pub fn table_reader<R: std::io::Read>(bytes: R, delimiter: Delimiter) -> csv::Reader<R> {
+    csv::ReaderBuilder::new()
+        .delimiter(delimiter.byte())
+        .has_headers(false)
+        .flexible(true)
+        .comment(Some(b'#'))
+        .from_reader(bytes)
+}
+
+/// The first `count` rows of a table, as split into fields when it is parsed.
+pub fn preview_rows(bytes: &[u8], delimiter: Delimiter, count: usize) -> Vec<StringRecord> {
+    table_reader(&*delimiter.prepare(bytes), delimiter)
+        .records()
+        .filter_map(Result::ok)
+        .take(count)
+        .collect()
+}
+
+/// Names of the metadata columns of any of `neurons`.
+pub fn meta_keys(neurons: &HashMap<String, Neuron>) -> BTreeSet<String> {
+    (neurons.values())
+        .flat_map(|n| n.meta.keys().cloned())
+        .collect()
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn sorted(neurons: &HashMap<String, Neuron>) -> Vec<&Neuron> {
+    let mut sorted: Vec<_> = neurons.values().collect();
+    sorted.sort_unstable_by(|a, b| a.name.cmp(&b.name));
+    sorted
+}
+
+/// Write `neurons` sorted by name as a table with every column of [`Neuron`] and then their
+/// metadata, which [`parse_neurons`] reads back unchanged: numbers are written with as many
+/// digits as it takes to restore them exactly.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn to_csv(neurons: &HashMap<String, Neuron>) -> Result<String, String> {
+    let keys: BTreeSet<&String> = neurons.values().flat_map(|n| n.meta.keys()).collect();
+    let mut writer = csv::WriterBuilder::new()
+        .has_headers(false)
+        .from_writer(Vec::new());
+    let header = NEUROPAL_HEADER
+        .iter()
+        .copied()
+        .chain(keys.iter().map(|k| k.as_str()));
+    writer.write_record(header).map_err(|e| e.to_string())?;
+    for neuron in sorted(neurons) {
+        let meta: Vec<&str> = (keys.iter())
+            .map(|k| neuron.meta.get(*k).map_or("", String::as_str))
+            .collect();
+        // Tables take the metadata as columns of their own, after the fixed ones.
+        let fixed = Neuron {
+            meta: BTreeMap::new(),
+            ..neuron.clone()
+        };
+        writer.serialize((fixed, meta)).map_err(|e| e.to_string())?;
+    }
+    let bytes = writer.into_inner().map_err(|e| e.to_string())?;
+    String::from_utf8(bytes).map_err(|e| e.to_string())
+}
+
+/// Like [`to_csv`], as a JSON array of objects.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn to_json(neurons: &HashMap<String, Neuron>) -> Result<String, String> {
+    serde_json::to_string_pretty(&sorted(neurons)).map_err(|e| e.to_string())
+}
+
+/// File a dataset was read from, so it can follow a pipeline that keeps rewriting it.
+pub struct SourceFile {
+    pub path: std::path::PathBuf,
+    /// Reload the dataset whenever the file changes on disk.
+    pub watch: bool,
+    #[cfg(not(target_arch = "wasm32"))]
+    modified: Option<std::time::SystemTime>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl SourceFile {
+    pub fn new(path: std::path::PathBuf) -> Self {
+        let modified = std::fs::metadata(&path).and_then(|m| m.modified()).ok();
+        Self {
+            path,
+            watch: false,
+            modified,
+        }
+    }
+
+    /// Whether the file was modified since it was last read; remembers the new time.
+    fn changed(&mut self) -> bool {
+        let modified = std::fs::metadata(&self.path)
+            .and_then(|m| m.modified())
+            .ok();
+        if modified.is_some() && modified != self.modified {
+            self.modified = modified;
+            return true;
+        }
+        false
+    }
+}
+
+/// An annotated animal (or alternative atlas) loaded from a file.
+///
+/// Serialized without the converted positions, so call [`Dataset::reconvert`] after
+/// deserializing.
+#[derive(serde::Deserialize, serde::Serialize)]
+pub struct Dataset {
+    pub name: String,
+    /// Positions as found in the file, in `frame`.
+    pub raw: HashMap<String, Neuron>,
+    pub frame: CoordinateFrame,
+    pub transform: FrameTransform,
+    /// Positions converted into the atlas frame; this is what every view and analysis uses.
+    #[serde(skip)]
+    pub neurons: HashMap<String, Neuron>,
+    /// Draw the dataset's neurons on top of the atlas.
+    pub visible: bool,
+    pub color: Color32,
+    /// Midline used to straighten lab-frame positions.
+    pub midline: Option<Midline>,
+    /// Editable text form of the midline control points.
+    pub midline_text: String,
+    /// Principal axes the converted positions are rotated onto, see [`Dataset::set_principal_frame`].
+    pub principal: Option<PrincipalAxes>,
+    #[serde(skip)]
+    pub source: Option<SourceFile>,
+    /// Likely mistakes found in the file when it was read.
+    #[serde(skip)]
+    pub warnings: Vec<Warning>,
+    /// How the file was read, to read it again the same way.
+    #[serde(skip)]
+    pub format: TableFormat,
+    /// Names of the metadata columns of the neurons, see [`Neuron::meta`].
+    #[serde(skip)]
+    pub columns: BTreeSet<String>,
+}
+
+impl Dataset {
+    /// A dataset already expressed in atlas coordinates.
+    pub fn new(name: impl Into<String>, neurons: HashMap<String, Neuron>) -> Self {
+        let columns = meta_keys(&neurons);
+        Self {
+            name: name.into(),
+            raw: neurons.clone(),
+            frame: CoordinateFrame::Atlas,
+            transform: FrameTransform::default(),
+            neurons,
+            visible: false,
+            color: Color32::from_rgb(0, 172, 193),
+            midline: None,
+            midline_text: String::new(),
+            principal: None,
+            source: None,
+            warnings: Vec::new(),
+            format: TableFormat::default(),
+            columns,
+        }
+    }
+
+    /// Read a dataset from a file; a table is read in `format`.
+    pub fn from_bytes(
+        name: impl Into<String>,
+        bytes: &[u8],
+        format: TableFormat,
+    ) -> Result<Self, String> {
+        Self::from_bytes_with_progress(name, bytes, format, |_, _| true)
+    }
+
+    /// Like [`Dataset::from_bytes`], see [`parse_neurons_with_progress`].
+    pub fn from_bytes_with_progress(
+        name: impl Into<String>,
+        bytes: &[u8],
+        format: TableFormat,
+        progress: impl FnMut(f32, Option<&Neuron>) -> bool,
+    ) -> Result<Self, String> {
+        let parsed = parse_neurons_with_progress(bytes, &format, progress)?;
+        Ok(Self::from_parsed(name, bytes, format, parsed))
+    }
+
+    /// The dataset of the neurons `parsed` from the file `bytes`, read in `format`.
+    pub fn from_parsed(
+        name: impl Into<String>,
+        bytes: &[u8],
+        format: TableFormat,
+        (neurons, warnings): Parsed,
+    ) -> Self {
+        let mut dataset = Self::new(name, neurons);
+        dataset.warnings = warnings;
+        dataset.format = format;
+        if let Some(frame) = CoordinateFrame::declared_in(&String::from_utf8_lossy(bytes)) {
+            dataset.frame = frame;
+            dataset.reconvert();
+        }
+        dataset
+            .warnings
+            .extend(validate::units(&dataset.raw, dataset.frame));
+        dataset
+    }
+
+    /// Re-read the source file if it is watched and changed on disk, keeping the frame,
+    /// transform, midline and display settings. Returns `None` if nothing was reloaded.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn reload_if_changed(&mut self) -> Option<Result<(), String>> {
+        let source = self.source.as_mut().filter(|s| s.watch)?;
+        if !source.changed() {
+            return None;
+        }
+        let result = std::fs::read(&source.path)
+            .map_err(|err| err.to_string())
+            .and_then(|bytes| parse_neurons_with_progress(&bytes, &self.format, |_, _| true))
+            .map(|(raw, warnings)| {
+                self.raw = raw;
+                self.warnings = warnings;
+                self.warnings.extend(validate::units(&self.raw, self.frame));
+                self.reconvert();
+            });
+        Some(result.map_err(|err| format!("{}: {err}", self.name)))
+    }
+
+    /// Recompute the atlas-frame positions after the frame or its transform changed.
+    pub fn reconvert(&mut self) {
+        self.columns = meta_keys(&self.raw);
+        self.neurons = frame::convert(
+            &self.raw,
+            self.frame,
+            &self.transform,
+            self.midline.as_ref(),
+        );
+        if let Some(axes) = &self.principal {
+            for neuron in self.neurons.values_mut() {
+                [neuron.x, neuron.y, neuron.z] = axes.rotate(neuron.position());
+            }
+        }
+    }
+
+    /// Rotate the converted positions about their mean so that their principal axes become
+    /// the x, y and z axes, roughly orienting an unregistered dataset; `false` undoes it.
+    pub fn set_principal_frame(&mut self, enabled: bool) {
+        self.principal = None;
+        self.reconvert();
+        if enabled {
+            let positions: Vec<[f32; 3]> = self.neurons.values().map(Neuron::position).collect();
+            self.principal = PrincipalAxes::of(&positions);
+            self.reconvert();
+        }
+    }
+}
+
+/// Read the content of a file dropped onto the window, together with its display name.
+pub fn dropped_file_bytes(file: &egui::DroppedFile) -> Option<(String, Vec<u8>)> {
+    let name = file
+        .path
+        .as_ref()
+        .and_then(|p| p.file_name())
+        .map(|p| p.to_string_lossy().into_owned())
+        .unwrap_or_else(|| file.name.clone());
+    if let Some(bytes) = &file.bytes {
+        return Some((name, bytes.to_vec()));
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    if let Some(path) = &file.path {
+        return std::fs::read(path).ok().map(|bytes| (name, bytes));
+    }
+    None
+}
+
+/// Position of one neuron in two datasets with matching names.
+pub struct Displacement<'a> {
+    pub name: &'a str,
+    pub from: [f32; 3],
+    pub to: [f32; 3],
+}
+
+impl Displacement<'_> {
+    pub fn vector(&self) -> [f32; 3] {
+        [
+            self.to[0] - self.from[0],
+            self.to[1] - self.from[1],
+            self.to[2] - self.from[2],
+        ]
+    }
+    pub fn length(&self) -> f32 {
+        let [dx, dy, dz] = self.vector();
+        (dx * dx + dy * dy + dz * dz).sqrt()
+    }
+}
+
+/// Pair the neurons present in both `from` and `to`, sorted by name.
+pub fn displacements<'a>(
+    from: &'a HashMap<String, Neuron>,
+    to: &'a HashMap<String, Neuron>,
+) -> Vec<Displacement<'a>> {
+    let mut out: Vec<_> = from
+        .values()
+        .filter_map(|a| {
+            to.get(&a.name).map(|b| Displacement {
+                name: &a.name,
+                from: a.position(),
+                to: b.position(),
+            })
+        })
+        .collect();
+    out.sort_unstable_by_key(|d| d.name);
+    out
+}
+
+/// Summary statistics over a set of displacements.
+pub struct DisplacementStats {
+    pub count: usize,
+    pub mean_vector: [f32; 3],
+    pub mean_length: f32,
+    pub median_length: f32,
+    pub max: Option<(String, f32)>,
+}
+
+impl DisplacementStats {
+    pub fn new(items: &[Displacement<'_>]) -> Self {
+        let count = items.len();
+        let n = count.max(1) as f32;
+        let mut mean_vector = [0.0; 3];
+        for d in items {
+            for (m, v) in mean_vector.iter_mut().zip(d.vector()) {
+                *m += v / n;
+            }
+        }
+        let mut lengths: Vec<f32> = items.iter().map(|d| d.length()).collect();
+        lengths.sort_unstable_by(f32::total_cmp);
+        let median_length = match count {
+            0 => 0.0,
+            c if c % 2 == 0 => (lengths[c / 2 - 1] + lengths[c / 2]) / 2.0,
+            c => lengths[c / 2],
+        };
+        let max = items
+            .iter()
+            .max_by(|a, b| a.length().total_cmp(&b.length()))
+            .map(|d| (d.name.to_owned(), d.length()));
+        Self {
+            count,
+            mean_vector,
+            mean_length: lengths.iter().sum::<f32>() / n,
+            median_length,
+            max,
+        }
+    }
+}
+
+/// Ask the user for a destination and write `content` there. Cancelling is not an error.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn save_file(default_name: &str, directory: &str, content: &[u8]) -> Result<(), String> {
+    let mut dialog = rfd::FileDialog::new().set_file_name(default_name);
+    if !directory.is_empty() {
+        dialog = dialog.set_directory(directory);
+    }
+    let Some(path) = dialog.save_file() else {
+        return Ok(());
+    };
+    std::fs::write(path, content).map_err(|e| e.to_string())
+}
+
+/// Ask the user for a destination and write `neurons` there as CSV, or as JSON if the file
+/// name ends in `.json`. Cancelling is not an error.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn save_atlas(neurons: &HashMap<String, Neuron>, directory: &str) -> Result<(), String> {
+    let mut dialog = rfd::FileDialog::new()
+        .set_file_name("atlas.csv")
+        .add_filter("CSV", &["csv"])
+        .add_filter("JSON", &["json"]);
+    if !directory.is_empty() {
+        dialog = dialog.set_directory(directory);
+    }
+    let Some(path) = dialog.save_file() else {
+        return Ok(());
+    };
+    let json = path
+        .extension()
+        .is_some_and(|e| e.eq_ignore_ascii_case("json"));
+    let content = if json {
+        to_json(neurons)?
+    } else {
+        to_csv(neurons)?
+    };
+    std::fs::write(path, content).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn table_parser_reads_a_few_rows_at_a_time() {
+        let format = TableFormat::default();
+        let mut parser = TableParser::new(NEUROPAL_ORG, &format);
+        let mut steps = 0;
+        let (neurons, _) = loop {
+            steps += 1;
+            if let Some(parsed) = parser.step(50, |_, _| true) {
+                break parsed.unwrap();
+            }
+        };
+        let whole = parse_neurons(NEUROPAL_ORG).unwrap();
+        assert!(steps > 2);
+        assert_eq!(neurons.len(), whole.len());
+        assert_eq!(neurons["AVAL"].position(), whole["AVAL"].position());
+    }
+
+    #[test]
+    fn table_parser_stops_when_cancelled() {
+        let format = TableFormat::default();
+        let mut parser = TableParser::new(NEUROPAL_ORG, &format);
+        let result = parser.step(usize::MAX, |fraction, _| fraction < 0.5);
+        assert!(matches!(result, Some(Err(err)) if err == "cancelled"));
+    }
+
+    #[test]
+    fn uncolored_neurons_stay_gray_among_0_255_colors() {
+        let table = b"name,x,y,z,r,g,b\nAVAL,1,2,3,255,0,128\nAVAR,1,2,-3,,,\nRIAL,4,5,6\n";
+        let neurons = parse_neurons(table).unwrap();
+        let rgb = |name: &str| [neurons[name].r, neurons[name].g, neurons[name].b];
+        assert_eq!(rgb("AVAL"), [1.0, 0.0, 128.0 / 255.0]);
+        assert_eq!(rgb("AVAR"), [DEFAULT_CHANNEL; 3]);
+        assert_eq!(rgb("RIAL"), [DEFAULT_CHANNEL; 3]);
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[test]
+    fn written_tables_read_back_unchanged() {
+        let mut neurons = parse_neurons(NEUROPAL_ORG).unwrap();
+        let regions = [
+            None,
+            Some(BodyRegion::Head),
+            Some(BodyRegion::Midbody),
+            Some(BodyRegion::Tail),
+        ];
+        for (i, neuron) in neurons.values_mut().enumerate() {
+            neuron.region = regions[i % 4];
+            neuron.pharyngeal = [None, Some(true), Some(false)][i % 3];
+            neuron
+                .meta
+                .insert("strain".to_owned(), format!("OH{}", 15_000 + i));
+            if i % 2 == 0 {
+                neuron
+                    .meta
+                    .insert("note".to_owned(), "left, \"faint\"".to_owned());
+            }
+        }
+        for written in [to_csv(&neurons).unwrap(), to_json(&neurons).unwrap()] {
+            let read = parse_neurons(written.as_bytes()).unwrap();
+            assert_eq!(read.len(), neurons.len());
+            for (name, neuron) in &neurons {
+                let other = &read[name];
+                assert_eq!(other.name, neuron.name);
+                assert_eq!(other.position(), neuron.position());
+                assert_eq!([other.r, other.g, other.b], [neuron.r, neuron.g, neuron.b]);
+                assert_eq!(other.region, neuron.region);
+                assert_eq!(other.pharyngeal, neuron.pharyngeal);
+                assert_eq!(other.meta, neuron.meta);
+            }
+        }
+    }
+}
@@ -0,0 +1,207 @@
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+
+use egui::Color32;
+
+use crate::i18n::tr;
+use crate::view::View;
+
+pub const DENSITY_COLOR: Color32 = Color32::from_rgb(255, 112, 67);
+
+/// Fractions of the peak density at which contour lines are drawn.
+pub const LEVELS: [f64; 4] = [0.2, 0.4, 0.6, 0.8];
+
+/// Grid nodes along each axis the density is estimated at.
+const GRID: usize = 64;
+
+/// Which neurons the density contours are drawn for, and how smooth they are.
+#[derive(Clone, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct DensitySettings {
+    pub enabled: bool,
+    /// Name prefix of the class to draw; the selection when empty.
+    pub class: String,
+    /// Standard deviation of the Gaussian kernel, in atlas units.
+    pub bandwidth: f32,
+}
+
+impl Default for DensitySettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            class: String::new(),
+            bandwidth: 4.0,
+        }
+    }
+}
+
+impl DensitySettings {
+    pub fn ui(&mut self, ui: &mut egui::Ui) {
+        ui.checkbox(&mut self.enabled, tr("Density contours"))
+            .on_hover_text(tr("Kernel density estimate of the selection or of a class"));
+        if !self.enabled {
+            return;
+        }
+        ui.horizontal(|ui| {
+            ui.label(tr("Of:"));
+            ui.add(
+                egui::TextEdit::singleline(&mut self.class)
+                    .hint_text(tr("selection, or a class like AVA"))
+                    .desired_width(140.0),
+            );
+        });
+        ui.add(egui::Slider::new(&mut self.bandwidth, 0.5..=20.0).text(tr("Bandwidth")));
+    }
+}
+
+/// Density contour lines of a set of positions in every view. They are recomputed only when
+/// the positions or the bandwidth change, not on every frame.
+#[derive(Default)]
+pub struct DensityContours {
+    key: Option<u64>,
+    /// Contour lines of each view, indexed by [`View::index`], with the level of each.
+    views: [Vec<(f64, Vec<[f64; 2]>)>; 3],
+}
+
+impl DensityContours {
+    pub fn update(&mut self, positions: &[[f32; 3]], bandwidth: f32) {
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        bandwidth.to_bits().hash(&mut hasher);
+        for p in positions {
+            p.map(f32::to_bits).hash(&mut hasher);
+        }
+        let key = hasher.finish();
+        if self.key == Some(key) {
+            return;
+        }
+        self.key = Some(key);
+        self.views = View::ALL.map(|view| {
+            let points: Vec<_> = positions.iter().map(|p| view.project(*p)).collect();
+            contours(&points, bandwidth as f64)
+        });
+    }
+
+    pub fn view(&self, view: View) -> &[(f64, Vec<[f64; 2]>)] {
+        &self.views[view.index()]
+    }
+}
+
+/// Contour lines of the Gaussian kernel density estimate of `points` at each of [`LEVELS`],
+/// found by marching squares on a grid covering the points.
+pub fn contours(points: &[[f64; 2]], bandwidth: f64) -> Vec<(f64, Vec<[f64; 2]>)> {
+    if points.len() < 2 || bandwidth <= 0.0 {
+        return Vec::new();
+    }
+    let (mut min, mut max) = ([f64::INFINITY; 2], [f64::NEG_INFINITY; 2]);
+    for p in points {
+        for axis in 0..2 {
+            min[axis] = min[axis].min(p[axis] - 3.0 * bandwidth);
+            max[axis] = max[axis].max(p[axis] + 3.0 * bandwidth);
+        }
+    }
+    let step = [0, 1].map(|axis| (max[axis] - min[axis]) / (GRID - 1) as f64);
+    let node = |i: usize, j: usize| [min[0] + i as f64 * step[0], min[1] + j as f64 * step[1]];
+    let mut density = vec![0.0; GRID * GRID];
+    for p in points {
+        // Beyond three bandwidths the kernel adds nothing visible.
+        let lo = [0, 1].map(|a| {
+            ((p[a] - 3.0 * bandwidth - min[a]) / step[a])
+                .floor()
+                .max(0.0) as usize
+        });
+        let hi = [0, 1]
+            .map(|a| (((p[a] + 3.0 * bandwidth - min[a]) / step[a]).ceil() as usize).min(GRID - 1));
+        for i in lo[0]..=hi[0] {
+            for j in lo[1]..=hi[1] {
+                let [x, y] = node(i, j);
+                let d2 = (x - p[0]).powi(2) + (y - p[1]).powi(2);
+                density[i * GRID + j] += (-d2 / (2.0 * bandwidth * bandwidth)).exp();
+            }
+        }
+    }
+    let peak = density.iter().copied().fold(0.0, f64::max);
+    if peak <= 0.0 {
+        return Vec::new();
+    }
+    let value = |i: usize, j: usize| density[i * GRID + j];
+
+    let mut lines = Vec::new();
+    for level in LEVELS {
+        let threshold = level * peak;
+        // Crossing of the contour with the grid edge from node `a` to node `b`.
+        let crossing = |a: (usize, usize), b: (usize, usize)| {
+            let (va, vb) = (value(a.0, a.1), value(b.0, b.1));
+            let t = ((threshold - va) / (vb - va)).clamp(0.0, 1.0);
+            let (pa, pb) = (node(a.0, a.1), node(b.0, b.1));
+            [pa[0] + t * (pb[0] - pa[0]), pa[1] + t * (pb[1] - pa[1])]
+        };
+        // Edges are keyed by their lower-left node and direction (0 along x, 1 along y).
+        let mut segments: Vec<[(usize, usize, u8); 2]> = Vec::new();
+        let mut points: HashMap<(usize, usize, u8), [f64; 2]> = HashMap::new();
+        for i in 0..GRID - 1 {
+            for j in 0..GRID - 1 {
+                let corners = [(i, j), (i + 1, j), (i + 1, j + 1), (i, j + 1)];
+                let case = corners.iter().enumerate().fold(0, |case, (k, &(a, b))| {
+                    case | (usize::from(value(a, b) > threshold) << k)
+                });
+                // Bottom, right, top and left edge of the cell.
+                let edges = [(i, j, 0), (i + 1, j, 1), (i, j + 1, 0), (i, j, 1)];
+                let pairs: &[(usize, usize)] = match case {
+                    1 | 14 => &[(3, 0)],
+                    2 | 13 => &[(0, 1)],
+                    3 | 12 => &[(3, 1)],
+                    4 | 11 => &[(1, 2)],
+                    5 => &[(3, 2), (0, 1)],
+                    6 | 9 => &[(0, 2)],
+                    7 | 8 => &[(3, 2)],
+                    10 => &[(3, 0), (1, 2)],
+                    _ => &[],
+                };
+                for &(a, b) in pairs {
+                    for e in [edges[a], edges[b]] {
+                        points.entry(e).or_insert_with(|| {
+                            let (x, y, dir) = e;
+                            let end = if dir == 0 { (x + 1, y) } else { (x, y + 1) };
+                            crossing((x, y), end)
+                        });
+                    }
+                    segments.push([edges[a], edges[b]]);
+                }
+            }
+        }
+        for chain in chain_segments(&segments) {
+            lines.push((level, chain.iter().map(|e| points[e]).collect()));
+        }
+    }
+    lines
+}
+
+/// Join segments sharing an end into polylines.
+fn chain_segments<K: Copy + Eq + Hash>(segments: &[[K; 2]]) -> Vec<Vec<K>> {
+    let mut at: HashMap<K, Vec<usize>> = HashMap::new();
+    for (i, s) in segments.iter().enumerate() {
+        for end in s {
+            at.entry(*end).or_default().push(i);
+        }
+    }
+    let mut used = vec![false; segments.len()];
+    let mut chains = Vec::new();
+    for start in 0..segments.len() {
+        if used[start] {
+            continue;
+        }
+        used[start] = true;
+        let mut chain = vec![segments[start][0], segments[start][1]];
+        // Grow the chain at its end, then turn it around and grow the other end.
+        for _ in 0..2 {
+            while let Some(&next) = at[chain.last().unwrap()].iter().find(|&&i| !used[i]) {
+                used[next] = true;
+                let [a, b] = segments[next];
+                chain.push(if a == *chain.last().unwrap() { b } else { a });
+            }
+            chain.reverse();
+        }
+        chains.push(chain);
+    }
+    chains
+}
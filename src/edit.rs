@@ -0,0 +1,363 @@
+use std::collections::HashMap;
+
+use egui::Color32;
+
+use crate::dataset::Neuron;
+use crate::i18n::tr;
+use crate::region::BodyRegion;
+use crate::style::Orientation;
+use crate::view::View;
+
+/// Color of the arrows from original to edited positions.
+pub const EDIT_COLOR: Color32 = Color32::from_rgb(236, 64, 122);
+
+/// One change to a neuron of the atlas, kept so it can be undone.
+pub struct AtlasEdit {
+    pub name: String,
+    /// The neuron before and after the change; `None` where it did not exist, so an addition
+    /// has no `before` and a removal no `after`.
+    pub before: Option<Neuron>,
+    pub after: Option<Neuron>,
+}
+
+impl AtlasEdit {
+    pub fn describe(&self) -> String {
+        let what = match (&self.before, &self.after) {
+            (None, _) => tr("Added"),
+            (_, None) => tr("Removed"),
+            (Some(a), Some(b)) => {
+                let moved = a.position() != b.position();
+                let recolored = [a.r, a.g, a.b] != [b.r, b.g, b.b];
+                match (moved, recolored) {
+                    (true, false) => tr("Moved"),
+                    (false, true) => tr("Recolored"),
+                    _ => tr("Edited"),
+                }
+            }
+        };
+        format!("{what} {}", self.name)
+    }
+}
+
+/// State of the atlas editor, in which neurons of the atlas are dragged on the canvas,
+/// recolored, added and removed to build a customized reference.
+#[derive(Default)]
+pub struct AtlasEditor {
+    /// The atlas as it was before the first edit; `None` while it is unedited.
+    pub original: Option<HashMap<String, Neuron>>,
+    /// Edits in the order they were made, and those undone, the last undone last.
+    pub undo: Vec<AtlasEdit>,
+    pub redo: Vec<AtlasEdit>,
+    /// The last edit is still being made, by a drag or in the color picker, and further
+    /// changes to the same neuron are merged into it.
+    open: bool,
+    /// Neuron being dragged and the view it was grabbed in.
+    pub dragging: Option<(String, View)>,
+    pub form: NeuronForm,
+}
+
+impl AtlasEditor {
+    pub fn is_edited(&self) -> bool {
+        self.original.is_some()
+    }
+
+    /// Replace the neuron `name` of `data` by `after`, or remove it for `None`, and record the
+    /// change in the history.
+    pub fn apply(&mut self, data: &mut HashMap<String, Neuron>, name: &str, after: Option<Neuron>) {
+        if self.original.is_none() {
+            self.original = Some(data.clone());
+        }
+        let before = set(data, name, after.clone());
+        self.redo.clear();
+        match self.undo.last_mut() {
+            Some(last)
+                if self.open && last.name == name && last.after.is_some() && after.is_some() =>
+            {
+                last.after = after;
+            }
+            _ => self.undo.push(AtlasEdit {
+                name: name.to_owned(),
+                before,
+                after,
+            }),
+        }
+        self.open = true;
+    }
+
+    /// End the edit being made, so the next change gets its own history entry. Called once
+    /// the pointer is released.
+    pub fn close(&mut self) {
+        self.open = false;
+    }
+
+    /// Undo the last edit; returns whether there was one.
+    pub fn undo(&mut self, data: &mut HashMap<String, Neuron>) -> bool {
+        let Some(edit) = self.undo.pop() else {
+            return false;
+        };
+        set(data, &edit.name, edit.before.clone());
+        self.redo.push(edit);
+        self.open = false;
+        true
+    }
+
+    /// Redo the last undone edit; returns whether there was one.
+    pub fn redo(&mut self, data: &mut HashMap<String, Neuron>) -> bool {
+        let Some(edit) = self.redo.pop() else {
+            return false;
+        };
+        set(data, &edit.name, edit.after.clone());
+        self.undo.push(edit);
+        self.open = false;
+        true
+    }
+
+    /// Undo or redo edits until the first `count` are applied.
+    pub fn step_to(&mut self, data: &mut HashMap<String, Neuron>, count: usize) {
+        while self.undo.len() > count && self.undo(data) {}
+        while self.undo.len() < count && self.redo(data) {}
+    }
+
+    /// Restore the atlas as it was before the first edit and forget the history.
+    pub fn revert(&mut self, data: &mut HashMap<String, Neuron>) {
+        if let Some(original) = self.original.take() {
+            *data = original;
+        }
+        self.undo.clear();
+        self.redo.clear();
+        self.open = false;
+    }
+}
+
+/// Put `neuron` in `data` under `name`, or remove the entry for `None`, returning the
+/// neuron it replaces.
+fn set(data: &mut HashMap<String, Neuron>, name: &str, neuron: Option<Neuron>) -> Option<Neuron> {
+    match neuron {
+        Some(neuron) => data.insert(name.to_owned(), neuron),
+        None => data.remove(name),
+    }
+}
+
+/// How a neuron of the edited atlas differs from the original.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ChangeKind {
+    Added,
+    Removed,
+    Changed,
+}
+
+impl std::fmt::Display for ChangeKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Added => f.write_str(tr("Added")),
+            Self::Removed => f.write_str(tr("Removed")),
+            Self::Changed => f.write_str(tr("Changed")),
+        }
+    }
+}
+
+/// A neuron added, removed or changed by editing the atlas.
+pub struct NeuronChange {
+    pub name: String,
+    pub kind: ChangeKind,
+    /// Original and edited position, where the neuron exists.
+    pub from: Option<[f32; 3]>,
+    pub to: Option<[f32; 3]>,
+    pub recolored: bool,
+    /// The body region or pharyngeal flag changed.
+    pub annotated: bool,
+}
+
+impl NeuronChange {
+    /// Distance the neuron was moved, in atlas units.
+    pub fn moved(&self) -> Option<f32> {
+        let (from, to) = (self.from?, self.to?);
+        let d = (0..3)
+            .map(|i| (to[i] - from[i]).powi(2))
+            .sum::<f32>()
+            .sqrt();
+        (d > 0.0).then_some(d)
+    }
+}
+
+/// Neurons differing between `original` and `edited`, sorted by kind and then by name.
+pub fn diff(
+    original: &HashMap<String, Neuron>,
+    edited: &HashMap<String, Neuron>,
+) -> Vec<NeuronChange> {
+    let mut changes: Vec<NeuronChange> = edited
+        .values()
+        .filter_map(|n| {
+            let Some(o) = original.get(&n.name) else {
+                return Some(NeuronChange {
+                    name: n.name.clone(),
+                    kind: ChangeKind::Added,
+                    from: None,
+                    to: Some(n.position()),
+                    recolored: false,
+                    annotated: false,
+                });
+            };
+            let change = NeuronChange {
+                name: n.name.clone(),
+                kind: ChangeKind::Changed,
+                from: Some(o.position()),
+                to: Some(n.position()),
+                recolored: [o.r, o.g, o.b] != [n.r, n.g, n.b],
+                annotated: (o.region, o.pharyngeal) != (n.region, n.pharyngeal),
+            };
+            (change.moved().is_some() || change.recolored || change.annotated).then_some(change)
+        })
+        .chain(
+            (original.values())
+                .filter(|o| !edited.contains_key(&o.name))
+                .map(|o| NeuronChange {
+                    name: o.name.clone(),
+                    kind: ChangeKind::Removed,
+                    from: Some(o.position()),
+                    to: None,
+                    recolored: false,
+                    annotated: false,
+                }),
+        )
+        .collect();
+    changes.sort_unstable_by(|a, b| a.kind.cmp(&b.kind).then_with(|| a.name.cmp(&b.name)));
+    changes
+}
+
+/// Fields of the form adding a neuron missing from the atlas.
+pub struct NeuronForm {
+    pub name: String,
+    pub position: [f32; 3],
+    pub rgb: [f32; 3],
+    /// Body region and pharyngeal flag; left out, they are inferred as for files without them.
+    pub region: Option<BodyRegion>,
+    pub pharyngeal: Option<bool>,
+}
+
+impl Default for NeuronForm {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            position: [0.0; 3],
+            rgb: [0.5; 3],
+            region: None,
+            pharyngeal: None,
+        }
+    }
+}
+
+impl NeuronForm {
+    /// The neuron the form describes, or why it cannot be added to `data`.
+    pub fn neuron(&self, data: &HashMap<String, Neuron>) -> Result<Neuron, String> {
+        let name = self.name.trim();
+        if name.is_empty() {
+            return Err(tr("Enter a name").to_owned());
+        }
+        if name.contains(|c: char| c.is_whitespace() || c == ',' || c == ';') {
+            return Err(tr("Names cannot contain spaces, commas or semicolons").to_owned());
+        }
+        // Searches ignore case, so names differing only in case could not be told apart.
+        if let Some(existing) = data.keys().find(|n| n.eq_ignore_ascii_case(name)) {
+            return Err(format!("{} {existing}", tr("The atlas already has")));
+        }
+        if !self.position.iter().all(|v| v.is_finite()) {
+            return Err(tr("Coordinates must be numbers").to_owned());
+        }
+        let [x, y, z] = self.position;
+        let [r, g, b] = self.rgb;
+        Ok(Neuron {
+            name: name.to_owned(),
+            x,
+            y,
+            z,
+            r,
+            g,
+            b,
+            region: self.region,
+            pharyngeal: self.pharyngeal,
+            meta: Default::default(),
+        })
+    }
+
+    /// Show the form; returns the neuron to add once it is valid and "Add" is clicked.
+    pub fn ui(&mut self, ui: &mut egui::Ui, data: &HashMap<String, Neuron>) -> Option<Neuron> {
+        egui::Grid::new("neuron_form")
+            .num_columns(2)
+            .show(ui, |ui| {
+                ui.label(tr("Name"));
+                ui.add(egui::TextEdit::singleline(&mut self.name).desired_width(120.0));
+                ui.end_row();
+                ui.label("x, y, z");
+                ui.horizontal(|ui| {
+                    for v in &mut self.position {
+                        ui.add(egui::DragValue::new(v).speed(0.05));
+                    }
+                });
+                ui.end_row();
+                ui.label(tr("Color"));
+                ui.color_edit_button_rgb(&mut self.rgb);
+                ui.end_row();
+                ui.label(tr("Region:"));
+                egui::ComboBox::from_id_salt("neuron_form_region")
+                    .selected_text(
+                        self.region
+                            .map_or(tr("From position").to_owned(), |r| r.to_string()),
+                    )
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(&mut self.region, None, tr("From position"));
+                        for region in BodyRegion::ALL {
+                            ui.selectable_value(&mut self.region, Some(region), region.to_string());
+                        }
+                    });
+                ui.end_row();
+                ui.label(tr("Pharyngeal"));
+                ui.horizontal(|ui| {
+                    ui.radio_value(&mut self.pharyngeal, None, tr("From name"));
+                    ui.radio_value(&mut self.pharyngeal, Some(true), tr("Yes"));
+                    ui.radio_value(&mut self.pharyngeal, Some(false), tr("No"));
+                });
+                ui.end_row();
+            });
+        let neuron = self.neuron(data);
+        let add = ui.add_enabled(neuron.is_ok(), egui::Button::new(tr("Add neuron")));
+        match neuron {
+            Ok(neuron) if add.clicked() => {
+                self.name.clear();
+                return Some(neuron);
+            }
+            Ok(_) => {}
+            Err(err) if !self.name.is_empty() => {
+                ui.colored_label(ui.visuals().error_fg_color, err);
+            }
+            Err(_) => {}
+        }
+        None
+    }
+}
+
+/// Move `neuron` to the displayed plot position `p` of `view`, leaving the coordinate the
+/// view does not show unchanged.
+pub fn move_in_view(neuron: &mut Neuron, view: View, p: [f64; 2], orientation: Orientation) {
+    let mut shown = orientation.apply(neuron.position());
+    for (axis, value) in view.unproject(p) {
+        shown[axis] = value;
+    }
+    // Flipping an axis is its own inverse.
+    [neuron.x, neuron.y, neuron.z] = orientation.apply(shown);
+}
+
+/// Editors for the position and color of `neuron`; returns whether it changed.
+pub fn neuron_ui(ui: &mut egui::Ui, neuron: &mut Neuron) -> bool {
+    let mut changed = false;
+    ui.label(&neuron.name);
+    for v in [&mut neuron.x, &mut neuron.y, &mut neuron.z] {
+        changed |= ui.add(egui::DragValue::new(v).speed(0.05)).changed();
+    }
+    let mut rgb = [neuron.r, neuron.g, neuron.b];
+    if ui.color_edit_button_rgb(&mut rgb).changed() {
+        [neuron.r, neuron.g, neuron.b] = rgb;
+        changed = true;
+    }
+    changed
+}
@@ -0,0 +1,83 @@
+use std::collections::BTreeSet;
+
+use crate::atlas::WormSide;
+use crate::region::RegionFilter;
+
+/// User interaction reported to the callbacks registered with [`crate::MyApp::on_event`].
+#[derive(Clone, Debug, PartialEq)]
+pub enum AppEvent {
+    /// The pointer moved onto a neuron in one of the plots, or off it (`None`).
+    Hover(Option<String>),
+    /// The selection changed; holds the selected names in order.
+    Select(Vec<String>),
+    /// One of the filters of the side panel changed.
+    FilterChange(FilterState),
+}
+
+/// The filters deciding which neurons are listed and plotted.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FilterState {
+    /// Search text: name prefixes separated by spaces or commas, `*` for all.
+    pub search: String,
+    pub side: WormSide,
+    /// Only the selected neurons are shown.
+    pub selection_only: bool,
+    /// Only the neurons inside the ROI box are shown.
+    pub roi_crop: bool,
+    /// Quick filters on body region, pharynx, pairing and functional type.
+    pub region: RegionFilter,
+}
+
+pub type EventCallback = Box<dyn FnMut(&AppEvent)>;
+
+/// Registered callbacks, and what they were last told, to report changes only.
+#[derive(Default)]
+pub struct EventHooks {
+    callbacks: Vec<EventCallback>,
+    hovered: Option<String>,
+    selection: BTreeSet<String>,
+    filter: Option<FilterState>,
+}
+
+impl EventHooks {
+    pub fn register(&mut self, callback: EventCallback) {
+        self.callbacks.push(callback);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.callbacks.is_empty()
+    }
+
+    /// Compare the state of this frame with the last one and fire an event for each change.
+    /// The first call only records the state.
+    pub fn update(
+        &mut self,
+        hovered: Option<&str>,
+        selection: &BTreeSet<String>,
+        filter: FilterState,
+    ) {
+        let first = self.filter.is_none();
+        let mut events = Vec::new();
+        if self.hovered.as_deref() != hovered {
+            self.hovered = hovered.map(str::to_owned);
+            events.push(AppEvent::Hover(self.hovered.clone()));
+        }
+        if self.selection != *selection {
+            self.selection = selection.clone();
+            if !first {
+                events.push(AppEvent::Select(selection.iter().cloned().collect()));
+            }
+        }
+        if self.filter.as_ref() != Some(&filter) {
+            if !first {
+                events.push(AppEvent::FilterChange(filter.clone()));
+            }
+            self.filter = Some(filter);
+        }
+        for event in &events {
+            for callback in &mut self.callbacks {
+                callback(event);
+            }
+        }
+    }
+}
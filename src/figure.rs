@@ -0,0 +1,652 @@
+use std::collections::BTreeSet;
+
+use egui::{Align2, Color32, Pos2, Rect, Stroke, Vec2};
+
+use crate::annotate::{Annotations, Mark};
+use crate::batch::Marker;
+use crate::colormap::Colorbar;
+use crate::i18n::tr;
+use crate::prefs::{DistanceUnit, Preferences};
+use crate::style::{Orientation, PlotStyle};
+use crate::view::View;
+
+/// Width of the panels of a figure, in points; the legend comes on their right.
+const PANELS_WIDTH: f32 = 600.0;
+/// Room around the plot of a panel for its title, ticks and axis labels, in points.
+const MARGIN_LEFT: f32 = 48.0;
+const MARGIN_RIGHT: f32 = 12.0;
+const MARGIN_TOP: f32 = 20.0;
+const MARGIN_BOTTOM: f32 = 36.0;
+const LEGEND_WIDTH: f32 = 120.0;
+const FONT_SIZE: f32 = 9.0;
+/// Preferred distance between ticks, in points.
+const TICK_SPACING: f32 = 50.0;
+
+/// How the panels of a figure are laid out.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub enum Arrangement {
+    /// The lateral view with the anterior view on its right and the dorsal view below it,
+    /// sharing their axes as in a technical drawing.
+    #[default]
+    Orthographic,
+    Row,
+    Column,
+}
+
+impl Arrangement {
+    pub const ALL: [Self; 3] = [Self::Orthographic, Self::Row, Self::Column];
+
+    /// Row and column of `view`, the `order`-th of the views shown.
+    fn cell(self, view: View, order: usize) -> (usize, usize) {
+        match self {
+            Self::Orthographic => match view {
+                View::XY => (0, 0),
+                View::ZY => (0, 1),
+                View::XZ => (1, 0),
+            },
+            Self::Row => (0, order),
+            Self::Column => (order, 0),
+        }
+    }
+}
+
+impl std::fmt::Display for Arrangement {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Orthographic => f.write_str(tr("Orthographic")),
+            Self::Row => f.write_str(tr("Side by side")),
+            Self::Column => f.write_str(tr("Stacked")),
+        }
+    }
+}
+
+/// File format of an exported figure.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub enum FigureFormat {
+    #[default]
+    Png,
+    /// Vector shapes and text, as many journals ask for.
+    Pdf,
+}
+
+impl FigureFormat {
+    pub const ALL: [Self; 2] = [Self::Png, Self::Pdf];
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Png => "png",
+            Self::Pdf => "pdf",
+        }
+    }
+}
+
+impl std::fmt::Display for FigureFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Png => "PNG",
+            Self::Pdf => "PDF",
+        })
+    }
+}
+
+/// What a composed figure shows and how large it is exported.
+#[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct FigureSettings {
+    pub arrangement: Arrangement,
+    /// Views included, indexed by [`View::index`].
+    pub views: [bool; 3],
+    pub format: FigureFormat,
+    /// Width of the exported image in pixels, its height following the layout. A PDF is as
+    /// large as the figure is in points.
+    pub width: u32,
+    /// Radius of the markers, in points of the figure.
+    pub marker_radius: f32,
+    pub legend: bool,
+    pub scale_bar: bool,
+}
+
+impl Default for FigureSettings {
+    fn default() -> Self {
+        Self {
+            arrangement: Arrangement::Orthographic,
+            views: [true; 3],
+            format: FigureFormat::Png,
+            width: 2400,
+            marker_radius: 2.5,
+            legend: true,
+            scale_bar: true,
+        }
+    }
+}
+
+impl FigureSettings {
+    pub fn ui(&mut self, ui: &mut egui::Ui) {
+        egui::Grid::new("figure_settings")
+            .num_columns(2)
+            .show(ui, |ui| {
+                ui.label(tr("Layout"));
+                egui::ComboBox::from_id_salt("figure_arrangement")
+                    .selected_text(self.arrangement.to_string())
+                    .show_ui(ui, |ui| {
+                        for arrangement in Arrangement::ALL {
+                            let text = arrangement.to_string();
+                            ui.selectable_value(&mut self.arrangement, arrangement, text);
+                        }
+                    });
+                ui.end_row();
+                ui.label(tr("Views"));
+                ui.horizontal(|ui| {
+                    for view in View::ALL {
+                        ui.checkbox(&mut self.views[view.index()], view.title());
+                    }
+                });
+                ui.end_row();
+                ui.label(tr("Format"));
+                ui.horizontal(|ui| {
+                    for format in FigureFormat::ALL {
+                        ui.selectable_value(&mut self.format, format, format.to_string());
+                    }
+                });
+                ui.end_row();
+                ui.label(tr("Width"));
+                ui.add_enabled_ui(self.format == FigureFormat::Png, |ui| {
+                    for width in [1200, 2400, 4800] {
+                        ui.selectable_value(&mut self.width, width, width.to_string());
+                    }
+                    ui.add(
+                        egui::DragValue::new(&mut self.width)
+                            .range(200..=8000)
+                            .suffix(" px"),
+                    );
+                });
+                ui.end_row();
+                ui.label(tr("Marker radius"));
+                ui.add(
+                    egui::DragValue::new(&mut self.marker_radius)
+                        .range(0.5..=10.0)
+                        .speed(0.05)
+                        .max_decimals(2)
+                        .suffix(" pt"),
+                );
+                ui.end_row();
+                ui.label("");
+                ui.horizontal(|ui| {
+                    ui.checkbox(&mut self.legend, tr("Legend"));
+                    ui.checkbox(&mut self.scale_bar, tr("Scale bar"));
+                });
+                ui.end_row();
+            });
+    }
+}
+
+/// Key to the marker colors, shared by the panels.
+pub enum Legend {
+    Colorbar(Colorbar),
+    /// A title, then a swatch and a label per category.
+    Swatches(String, Vec<(Color32, String)>),
+}
+
+/// Everything a figure is drawn from.
+pub struct FigureContent<'a> {
+    /// Displayed position, color and name of each marker, see
+    /// [`GeometryCache::markers`](crate::batch::GeometryCache::markers).
+    pub markers: &'a [Marker],
+    pub selection: &'a BTreeSet<String>,
+    pub annotations: &'a Annotations,
+    pub orientation: Orientation,
+    pub plot_styles: &'a [PlotStyle; 3],
+    pub legend: Option<Legend>,
+    pub prefs: &'a Preferences,
+}
+
+/// A drawing element of a figure, in points from its top left corner.
+pub enum Primitive {
+    Circle {
+        center: Pos2,
+        radius: f32,
+        fill: Color32,
+        stroke: Stroke,
+    },
+    Line {
+        points: Vec<Pos2>,
+        stroke: Stroke,
+    },
+    Rect {
+        rect: Rect,
+        fill: Color32,
+        stroke: Stroke,
+    },
+    /// Text placed by `anchor` at `pos`; vertical text reads upwards and is centered at `pos`.
+    Text {
+        pos: Pos2,
+        anchor: Align2,
+        text: String,
+        size: f32,
+        color: Color32,
+        vertical: bool,
+    },
+    /// Colors spread evenly from the bottom to the top of `rect`.
+    Gradient {
+        rect: Rect,
+        colors: Vec<Color32>,
+    },
+}
+
+/// Elements drawn only inside `clip`.
+pub struct Group {
+    pub clip: Rect,
+    pub items: Vec<Primitive>,
+}
+
+/// A composed figure, independent of how it is written out.
+pub struct Figure {
+    /// Size in points.
+    pub size: Vec2,
+    pub background: Color32,
+    pub groups: Vec<Group>,
+}
+
+/// Lay out the views chosen in `settings` into one figure, with one scale for all panels,
+/// so a single scale bar holds for each of them.
+pub fn compose(settings: &FigureSettings, content: &FigureContent<'_>) -> Figure {
+    let views: Vec<View> = (View::ALL.into_iter())
+        .filter(|view| settings.views[view.index()])
+        .collect();
+    let bounds = shared_bounds(content.markers);
+    let cells: Vec<(View, (usize, usize))> = (views.iter().enumerate())
+        .map(|(order, view)| (*view, settings.arrangement.cell(*view, order)))
+        .collect();
+    let rows = cells.iter().map(|(_, (row, _))| row + 1).max().unwrap_or(0);
+    let columns = cells
+        .iter()
+        .map(|(_, (_, column))| column + 1)
+        .max()
+        .unwrap_or(0);
+    let (mut widths, mut heights) = (vec![0.0f32; columns], vec![0.0f32; rows]);
+    for (view, (row, column)) in &cells {
+        let [u, v] = bounds[view.index()];
+        widths[*column] = widths[*column].max((u[1] - u[0]) as f32);
+        heights[*row] = heights[*row].max((v[1] - v[0]) as f32);
+    }
+    // Points per atlas unit.
+    let margins = columns as f32 * (MARGIN_LEFT + MARGIN_RIGHT);
+    let k = (PANELS_WIDTH - margins).max(1.0) / widths.iter().sum::<f32>().max(f32::EPSILON);
+    let offsets = |spans: &[f32], margin: f32| -> Vec<f32> {
+        (spans.iter())
+            .scan(0.0, |offset, span| {
+                let start = *offset;
+                *offset += span * k + margin;
+                Some(start)
+            })
+            .collect()
+    };
+    let xs = offsets(&widths, MARGIN_LEFT + MARGIN_RIGHT);
+    let ys = offsets(&heights, MARGIN_TOP + MARGIN_BOTTOM);
+    let panels_height: f32 = (heights.iter())
+        .map(|h| h * k + MARGIN_TOP + MARGIN_BOTTOM)
+        .sum();
+
+    let mut figure = Figure {
+        size: Vec2::new(PANELS_WIDTH, panels_height),
+        background: Color32::WHITE,
+        groups: Vec::new(),
+    };
+    for (i, (view, (row, column))) in cells.iter().enumerate() {
+        let [u, v] = bounds[view.index()];
+        let rect = Rect::from_min_size(
+            Pos2::new(xs[*column] + MARGIN_LEFT, ys[*row] + MARGIN_TOP),
+            Vec2::new((u[1] - u[0]) as f32 * k, (v[1] - v[0]) as f32 * k),
+        );
+        let scale_bar = settings.scale_bar && i == 0;
+        panel(
+            &mut figure,
+            (*view, rect, bounds[view.index()], k),
+            settings,
+            content,
+            scale_bar,
+        );
+    }
+    if let Some(legend) = content.legend.as_ref().filter(|_| settings.legend) {
+        let origin = Pos2::new(PANELS_WIDTH + 8.0, MARGIN_TOP);
+        let (items, bottom) = legend_items(legend, origin);
+        figure.size.x += LEGEND_WIDTH;
+        figure.size.y = figure.size.y.max(bottom + 8.0);
+        figure.groups.push(Group {
+            clip: Rect::EVERYTHING,
+            items,
+        });
+    }
+    figure
+}
+
+/// Range of each view along its horizontal and vertical axis, padded. Views sharing an
+/// axis share its range so their panels line up.
+fn shared_bounds(markers: &[Marker]) -> [[[f64; 2]; 2]; 3] {
+    let mut bounds = View::ALL.map(|view| {
+        let mut range = [[f64::MAX, f64::MIN]; 2];
+        for (p, _, _) in markers {
+            for (axis, value) in view.project(*p).into_iter().enumerate() {
+                range[axis] = [range[axis][0].min(value), range[axis][1].max(value)];
+            }
+        }
+        range.map(|[lo, hi]| if lo <= hi { [lo, hi] } else { [-1.0, 1.0] })
+    });
+    // Anterior–posterior in the lateral and dorsal views, dorsoventral in the lateral and
+    // anterior views.
+    let (xy, zy, xz) = (View::XY.index(), View::ZY.index(), View::XZ.index());
+    for (a, b, axis) in [(xy, xz, 0), (xy, zy, 1)] {
+        let [lo, hi] = [
+            bounds[a][axis][0].min(bounds[b][axis][0]),
+            bounds[a][axis][1].max(bounds[b][axis][1]),
+        ];
+        bounds[a][axis] = [lo, hi];
+        bounds[b][axis] = [lo, hi];
+    }
+    let pad = (bounds.iter().flatten())
+        .map(|[lo, hi]| hi - lo)
+        .fold(1.0f64, f64::max)
+        * 0.03;
+    bounds.map(|view| view.map(|[lo, hi]| [lo - pad, hi + pad]))
+}
+
+/// Draw `view` into `rect`, showing `bounds` at `k` points per atlas unit.
+fn panel(
+    figure: &mut Figure,
+    (view, rect, bounds, k): (View, Rect, [[f64; 2]; 2], f32),
+    settings: &FigureSettings,
+    content: &FigureContent<'_>,
+    scale_bar: bool,
+) {
+    let style = &content.plot_styles[view.index()];
+    let ink = style.axis_color.unwrap_or(Color32::BLACK);
+    let [u, v] = bounds;
+    let to_figure = |[x, y]: [f64; 2]| {
+        Pos2::new(
+            rect.left() + (x - u[0]) as f32 * k,
+            rect.bottom() - (y - v[0]) as f32 * k,
+        )
+    };
+    // Round values of the preferred unit, in atlas units.
+    let factor = content.prefs.distance_factor();
+    let step = nice(TICK_SPACING as f64 / k as f64 * factor) / factor;
+    let tick_format = content.prefs.tick_format();
+    let tick_label = |t: f64, flipped: bool| tick_format.label(if flipped { -t } else { t }, step);
+    let (u_ticks, v_ticks) = (ticks(u, step), ticks(v, step));
+
+    let mut plot = vec![Primitive::Rect {
+        rect,
+        fill: style.background.unwrap_or(Color32::WHITE),
+        stroke: Stroke::NONE,
+    }];
+    if style.show_grid {
+        let grid = Stroke::new(0.5, ink.gamma_multiply(0.15));
+        for &t in &u_ticks {
+            let x = to_figure([t, v[0]]).x;
+            plot.push(Primitive::Line {
+                points: vec![Pos2::new(x, rect.top()), Pos2::new(x, rect.bottom())],
+                stroke: grid,
+            });
+        }
+        for &t in &v_ticks {
+            let y = to_figure([u[0], t]).y;
+            plot.push(Primitive::Line {
+                points: vec![Pos2::new(rect.left(), y), Pos2::new(rect.right(), y)],
+                stroke: grid,
+            });
+        }
+    }
+    // Farther markers first, as seen from the side each view looks from.
+    let mut markers: Vec<&Marker> = content.markers.iter().collect();
+    let depth = |p: &[f32; 3]| match view {
+        View::XY => p[2],
+        View::ZY => -p[0],
+        View::XZ => p[1],
+    };
+    markers.sort_by(|a, b| depth(&a.0).total_cmp(&depth(&b.0)));
+    let outline = (content.prefs.marker_outline(ink))
+        .map_or(Stroke::NONE, |s| Stroke::new(s.width * 0.5, s.color));
+    let radius = settings.marker_radius;
+    for (p, color, _) in &markers {
+        plot.push(Primitive::Circle {
+            center: to_figure(view.project(*p)),
+            radius,
+            fill: *color,
+            stroke: outline,
+        });
+    }
+    let ring = Stroke::new(1.0, content.prefs.selection_color());
+    for (p, _, name) in &markers {
+        if content.selection.contains(&**name) {
+            plot.push(Primitive::Circle {
+                center: to_figure(view.project(*p)),
+                radius: radius + 1.5,
+                fill: Color32::TRANSPARENT,
+                stroke: ring,
+            });
+        }
+    }
+    for annotation in (content.annotations.items.iter()).filter(|a| a.view == view) {
+        let stroke = Stroke::new(annotation.width, annotation.color);
+        match annotation.displayed(content.orientation) {
+            Mark::Arrow { from, to } => {
+                let (tail, tip) = (to_figure(from), to_figure(to));
+                let back = (tail - tip).normalized() * (4.0 + 3.0 * annotation.width);
+                let barb = |angle: f32| tip + egui::emath::Rot2::from_angle(angle) * back;
+                plot.push(Primitive::Line {
+                    points: vec![tail, tip],
+                    stroke,
+                });
+                plot.push(Primitive::Line {
+                    points: vec![barb(0.45), tip, barb(-0.45)],
+                    stroke,
+                });
+            }
+            Mark::Text { at, text } => plot.push(Primitive::Text {
+                pos: to_figure(at),
+                anchor: Align2::LEFT_TOP,
+                text,
+                size: 4.0 * annotation.width,
+                color: annotation.color,
+                vertical: false,
+            }),
+            Mark::Freehand(points) => plot.push(Primitive::Line {
+                points: points.into_iter().map(to_figure).collect(),
+                stroke,
+            }),
+        }
+    }
+    if scale_bar {
+        plot.extend(scale_bar_items(rect, k, ink, content.prefs));
+    }
+    figure.groups.push(Group {
+        clip: rect,
+        items: plot,
+    });
+
+    let o = content.orientation;
+    let text = |pos, anchor, text: &str, vertical| Primitive::Text {
+        pos,
+        anchor,
+        text: text.to_owned(),
+        size: FONT_SIZE,
+        color: ink,
+        vertical,
+    };
+    let mut axes = vec![
+        Primitive::Rect {
+            rect,
+            fill: Color32::TRANSPARENT,
+            stroke: Stroke::new(0.75, ink),
+        },
+        text(
+            rect.left_top() - Vec2::new(0.0, 5.0),
+            Align2::LEFT_BOTTOM,
+            view.title(),
+            false,
+        ),
+    ];
+    if style.show_axes {
+        let ((u_label, u_flipped), (v_label, v_flipped)) = match view {
+            View::XY => ((o.ap_label(), o.flip_ap), (o.dv_label(), o.flip_dv)),
+            View::ZY => ((tr("Right–Left"), false), (o.dv_label(), o.flip_dv)),
+            View::XZ => ((o.ap_label(), o.flip_ap), (tr("Left–Right"), false)),
+        };
+        let tick = Stroke::new(0.75, ink);
+        for &t in &u_ticks {
+            let x = to_figure([t, v[0]]).x;
+            axes.push(Primitive::Line {
+                points: vec![
+                    Pos2::new(x, rect.bottom()),
+                    Pos2::new(x, rect.bottom() + 3.0),
+                ],
+                stroke: tick,
+            });
+            let label = tick_label(t, u_flipped);
+            axes.push(text(
+                Pos2::new(x, rect.bottom() + 4.0),
+                Align2::CENTER_TOP,
+                &label,
+                false,
+            ));
+        }
+        for &t in &v_ticks {
+            let y = to_figure([u[0], t]).y;
+            axes.push(Primitive::Line {
+                points: vec![Pos2::new(rect.left() - 3.0, y), Pos2::new(rect.left(), y)],
+                stroke: tick,
+            });
+            let label = tick_label(t, v_flipped);
+            axes.push(text(
+                Pos2::new(rect.left() - 5.0, y),
+                Align2::RIGHT_CENTER,
+                &label,
+                false,
+            ));
+        }
+        let below = Pos2::new(rect.center().x, rect.bottom() + 18.0);
+        let u_label = content.prefs.axis_label(u_label);
+        axes.push(text(below, Align2::CENTER_TOP, &u_label, false));
+        let left = Pos2::new(rect.left() - MARGIN_LEFT + 8.0, rect.center().y);
+        let v_label = content.prefs.axis_label(v_label);
+        axes.push(text(left, Align2::CENTER_CENTER, &v_label, true));
+    }
+    figure.groups.push(Group {
+        clip: Rect::EVERYTHING,
+        items: axes,
+    });
+}
+
+/// A bar of a round length in the bottom right corner of `rect`, labelled in the preferred
+/// unit.
+fn scale_bar_items(rect: Rect, k: f32, ink: Color32, prefs: &Preferences) -> Vec<Primitive> {
+    let factor = prefs.distance_factor();
+    let length = nice(0.25 * (rect.width() / k) as f64 * factor);
+    let end = rect.right_bottom() - Vec2::new(10.0, 10.0);
+    let start = end - Vec2::new((length / factor) as f32 * k, 0.0);
+    let label = match prefs.units {
+        DistanceUnit::Atlas => format!("{length}"),
+        DistanceUnit::Microns => format!("{length} µm"),
+    };
+    vec![
+        Primitive::Line {
+            points: vec![start, end],
+            stroke: Stroke::new(2.0, ink),
+        },
+        Primitive::Text {
+            pos: Pos2::new((start.x + end.x) / 2.0, end.y - 3.0),
+            anchor: Align2::CENTER_BOTTOM,
+            text: label,
+            size: FONT_SIZE,
+            color: ink,
+            vertical: false,
+        },
+    ]
+}
+
+/// The legend with its top left corner at `origin`, and where it ends.
+fn legend_items(legend: &Legend, origin: Pos2) -> (Vec<Primitive>, f32) {
+    let text = |pos, anchor, text: &str| Primitive::Text {
+        pos,
+        anchor,
+        text: text.to_owned(),
+        size: FONT_SIZE,
+        color: Color32::BLACK,
+        vertical: false,
+    };
+    let mut items = Vec::new();
+    match legend {
+        Legend::Colorbar(colorbar) => {
+            const TICKS: usize = 5;
+            items.push(text(origin, Align2::LEFT_TOP, &colorbar.label));
+            let bar = Rect::from_min_size(origin + Vec2::new(0.0, 16.0), Vec2::new(10.0, 120.0));
+            let colors = (0..=32).map(|i| colorbar.colormap.color(i as f32 / 32.0));
+            items.push(Primitive::Gradient {
+                rect: bar,
+                colors: colors.collect(),
+            });
+            items.push(Primitive::Rect {
+                rect: bar,
+                fill: Color32::TRANSPARENT,
+                stroke: Stroke::new(0.75, Color32::BLACK),
+            });
+            for i in 0..TICKS {
+                let t = i as f32 / (TICKS - 1) as f32;
+                let y = bar.bottom() - t * bar.height();
+                let value = colorbar.min + t * (colorbar.max - colorbar.min);
+                items.push(Primitive::Line {
+                    points: vec![Pos2::new(bar.right(), y), Pos2::new(bar.right() + 3.0, y)],
+                    stroke: Stroke::new(0.75, Color32::BLACK),
+                });
+                let pos = Pos2::new(bar.right() + 5.0, y);
+                items.push(text(pos, Align2::LEFT_CENTER, &format!("{value:.1}")));
+            }
+            (items, bar.bottom() + FONT_SIZE)
+        }
+        Legend::Swatches(title, swatches) => {
+            items.push(text(origin, Align2::LEFT_TOP, title));
+            let mut y = origin.y + 22.0;
+            for (color, label) in swatches {
+                items.push(Primitive::Circle {
+                    center: Pos2::new(origin.x + 4.0, y),
+                    radius: 3.5,
+                    fill: *color,
+                    stroke: Stroke::NONE,
+                });
+                items.push(text(
+                    Pos2::new(origin.x + 12.0, y),
+                    Align2::LEFT_CENTER,
+                    label,
+                ));
+                y += 13.0;
+            }
+            (items, y)
+        }
+    }
+}
+
+/// 1, 2 or 5 times a power of ten, close to `x`.
+fn nice(x: f64) -> f64 {
+    if !(x > 0.0 && x.is_finite()) {
+        return 1.0;
+    }
+    let power = 10f64.powf(x.log10().floor());
+    let f = x / power;
+    let step = if f < 1.5 {
+        1.0
+    } else if f < 3.5 {
+        2.0
+    } else if f < 7.5 {
+        5.0
+    } else {
+        10.0
+    };
+    step * power
+}
+
+/// Multiples of `step` within `range`.
+fn ticks([lo, hi]: [f64; 2], step: f64) -> Vec<f64> {
+    let first = (lo / step).ceil() as i64;
+    let last = (hi / step).floor() as i64;
+    (first..=last).map(|i| i as f64 * step).collect()
+}
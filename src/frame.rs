@@ -0,0 +1,134 @@
+use std::collections::HashMap;
+
+use crate::dataset::Neuron;
+use crate::i18n::tr;
+use crate::midline::Midline;
+
+/// Coordinate frame in which a dataset's positions are expressed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub enum CoordinateFrame {
+    /// Straightened worm coordinates in atlas units, as used by the bundled atlas.
+    Atlas,
+    /// Straightened worm coordinates in microns, with an arbitrary origin.
+    StraightenedMicrons,
+    /// Raw microscope (lab-frame) microns of a curved worm; needs straightening first.
+    LabMicrons,
+}
+
+impl CoordinateFrame {
+    pub const ALL: [Self; 3] = [Self::Atlas, Self::StraightenedMicrons, Self::LabMicrons];
+
+    /// Parse a `# frame: <name>` declaration from the comment lines at the top of a file.
+    pub fn declared_in(text: &str) -> Option<Self> {
+        text.lines()
+            .take_while(|l| l.trim_start().starts_with('#'))
+            .filter_map(|l| {
+                let (key, value) = l.trim_start_matches('#').split_once([':', '='])?;
+                (key.trim().eq_ignore_ascii_case("frame")).then(|| value.trim().to_lowercase())
+            })
+            .find_map(|value| match value.as_str() {
+                "atlas" => Some(Self::Atlas),
+                "straightened" | "straightened_um" | "straightened-microns" => {
+                    Some(Self::StraightenedMicrons)
+                }
+                "lab" | "raw" | "lab_um" | "lab-microns" => Some(Self::LabMicrons),
+                _ => None,
+            })
+    }
+}
+
+impl std::fmt::Display for CoordinateFrame {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Atlas => f.write_str(tr("Atlas units")),
+            Self::StraightenedMicrons => f.write_str(tr("Straightened (µm)")),
+            Self::LabMicrons => f.write_str(tr("Lab frame (µm)")),
+        }
+    }
+}
+
+/// Similarity transform from a dataset frame into atlas units:
+/// `atlas = (p - origin) / microns_per_unit`.
+#[derive(Clone, Copy, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct FrameTransform {
+    pub origin: [f32; 3],
+    pub microns_per_unit: f32,
+}
+
+impl Default for FrameTransform {
+    fn default() -> Self {
+        Self {
+            origin: [0.0; 3],
+            microns_per_unit: 1.0,
+        }
+    }
+}
+
+impl FrameTransform {
+    pub fn apply(&self, p: [f32; 3]) -> [f32; 3] {
+        let s = self.microns_per_unit.max(f32::EPSILON);
+        [
+            (p[0] - self.origin[0]) / s,
+            (p[1] - self.origin[1]) / s,
+            (p[2] - self.origin[2]) / s,
+        ]
+    }
+
+    pub fn invert(&self, p: [f32; 3]) -> [f32; 3] {
+        let s = self.microns_per_unit.max(f32::EPSILON);
+        [
+            p[0] * s + self.origin[0],
+            p[1] * s + self.origin[1],
+            p[2] * s + self.origin[2],
+        ]
+    }
+
+    pub fn ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label(tr("Origin:"));
+            for v in &mut self.origin {
+                ui.add(egui::DragValue::new(v).speed(0.1));
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.label(tr("µm per atlas unit:"));
+            ui.add(
+                egui::DragValue::new(&mut self.microns_per_unit)
+                    .speed(0.01)
+                    .range(0.01..=100.0),
+            );
+        });
+    }
+}
+
+/// Convert `raw` positions given in `frame` into the atlas frame.
+///
+/// Lab-frame positions are straightened along `midline` first; without one they are passed
+/// through the transform only and will not line up with the atlas.
+pub fn convert(
+    raw: &HashMap<String, Neuron>,
+    frame: CoordinateFrame,
+    transform: &FrameTransform,
+    midline: Option<&Midline>,
+) -> HashMap<String, Neuron> {
+    let midline = midline.filter(|m| m.points.len() >= 2);
+    let samples = midline.map(|m| m.sample(16));
+    raw.iter()
+        .map(|(name, neuron)| {
+            let [x, y, z] = match (frame, midline.zip(samples.as_deref())) {
+                (CoordinateFrame::Atlas, _) => neuron.position(),
+                (CoordinateFrame::LabMicrons, Some((midline, samples))) => {
+                    transform.apply(midline.straighten(samples, neuron.position()))
+                }
+                _ => transform.apply(neuron.position()),
+            };
+            let converted = Neuron {
+                x,
+                y,
+                z,
+                ..neuron.clone()
+            };
+            (name.clone(), converted)
+        })
+        .collect()
+}
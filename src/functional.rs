@@ -0,0 +1,120 @@
+use egui::Color32;
+
+use crate::dataset::Neuron;
+use crate::i18n::tr;
+
+/// Metadata columns that may give the functional type of a neuron, in order of preference.
+const COLUMNS: [&str; 3] = ["function", "functional_type", "neuron_type"];
+
+/// Neuron classes of each type, after the categories of WormAtlas. Classes with a sensory
+/// or motor role besides another one are polymodal. Motor neurons of the ventral cord are
+/// numbered after their class.
+const TYPES: [(FunctionalType, &[&str]); 4] = [
+    (
+        FunctionalType::Sensory,
+        &[
+            "ADE", "ADF", "ADL", "AFD", "ALM", "ALN", "AQR", "ASE", "ASG", "ASH", "ASI", "ASJ",
+            "ASK", "AVM", "AWA", "AWB", "AWC", "BAG", "CEPD", "CEPV", "FLP", "IL2", "OLL", "OLQ",
+            "PDE", "PHA", "PHB", "PHC", "PLM", "PLN", "PQR", "PVD", "PVM", "SDQ", "URB", "URX",
+            "URY",
+        ],
+    ),
+    (
+        FunctionalType::Interneuron,
+        &[
+            "ADA", "AIA", "AIB", "AIM", "AIN", "AIY", "AIZ", "ALA", "AUA", "AVA", "AVB", "AVD",
+            "AVE", "AVF", "AVG", "AVH", "AVJ", "AVK", "BDU", "DVA", "DVC", "I1", "I2", "I3", "I4",
+            "I5", "I6", "LUA", "PVC", "PVN", "PVP", "PVQ", "PVR", "PVT", "PVW", "RIA", "RIB",
+            "RIC", "RIF", "RIG", "RIH", "RIP", "RIR", "RIS", "SAA",
+        ],
+    ),
+    (
+        FunctionalType::Motor,
+        &[
+            "AS", "DA", "DB", "DD", "HSN", "M1", "M2", "M3", "M4", "M5", "MC", "MI", "PDA", "PDB",
+            "RID", "RIV", "RMD", "RME", "RMF", "RMH", "SAB", "SIA", "SIB", "SMB", "SMD", "URA",
+            "VA", "VB", "VC", "VD",
+        ],
+    ),
+    (
+        FunctionalType::Polymodal,
+        &["AVL", "DVB", "IL1", "NSM", "RIM", "RMG"],
+    ),
+];
+
+/// Broad role of a neuron in the circuit.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, serde::Deserialize, serde::Serialize)]
+pub enum FunctionalType {
+    Sensory,
+    Interneuron,
+    Motor,
+    /// More than one of the others, such as sensory and motor.
+    Polymodal,
+}
+
+impl FunctionalType {
+    pub const ALL: [Self; 4] = [
+        Self::Sensory,
+        Self::Interneuron,
+        Self::Motor,
+        Self::Polymodal,
+    ];
+
+    pub fn color(&self) -> Color32 {
+        match self {
+            Self::Sensory => Color32::from_rgb(230, 97, 1),
+            Self::Interneuron => Color32::from_rgb(94, 60, 153),
+            Self::Motor => Color32::from_rgb(27, 158, 119),
+            Self::Polymodal => Color32::from_rgb(230, 171, 2),
+        }
+    }
+
+    /// The type a metadata value names, by its first letters and ignoring case.
+    fn parse(text: &str) -> Option<Self> {
+        let text = text.trim().to_ascii_lowercase();
+        [
+            ("sens", Self::Sensory),
+            ("inter", Self::Interneuron),
+            ("motor", Self::Motor),
+            ("poly", Self::Polymodal),
+        ]
+        .into_iter()
+        .find_map(|(prefix, kind)| text.starts_with(prefix).then_some(kind))
+    }
+}
+
+impl std::fmt::Display for FunctionalType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Sensory => f.write_str(tr("Sensory")),
+            Self::Interneuron => f.write_str(tr("Interneuron")),
+            Self::Motor => f.write_str(tr("Motor")),
+            Self::Polymodal => f.write_str(tr("Polymodal")),
+        }
+    }
+}
+
+/// Functional type of `neuron`, from a metadata column of its file or else from its class.
+pub fn of(neuron: &Neuron) -> Option<FunctionalType> {
+    let column = COLUMNS.iter().find_map(|column| {
+        let (_, value) = (neuron.meta.iter()).find(|(key, _)| key.eq_ignore_ascii_case(column))?;
+        FunctionalType::parse(value)
+    });
+    if column.is_some() {
+        return column;
+    }
+    let name = neuron.name.as_str();
+    let is_suffix = |rest: &str| {
+        matches!(rest, "L" | "R" | "D" | "V" | "DL" | "DR" | "VL" | "VR")
+            || (!rest.is_empty() && rest.bytes().all(|b| b.is_ascii_digit()))
+    };
+    // Whole names first, then classes followed by a side or a number.
+    (TYPES.iter())
+        .find(|(_, classes)| classes.contains(&name))
+        .or_else(|| {
+            TYPES.iter().find(|(_, classes)| {
+                (classes.iter()).any(|class| name.strip_prefix(class).is_some_and(is_suffix))
+            })
+        })
+        .map(|(kind, _)| *kind)
+}
@@ -0,0 +1,89 @@
+use egui::Color32;
+
+use crate::region;
+
+/// Ganglia of the nervous system, with the neuron classes whose cell bodies lie in each.
+/// Pharyngeal neurons are told apart by [`region::is_pharyngeal`] instead.
+const GANGLIA: [(&str, &[&str]); 9] = [
+    (
+        "Anterior",
+        &[
+            "BAG", "CEPD", "CEPV", "IL1", "IL2", "OLL", "OLQ", "RIP", "URA", "URB", "URY",
+        ],
+    ),
+    (
+        "Lateral",
+        &[
+            "ADA", "ADE", "ADF", "ADL", "AFD", "AIA", "AIB", "AIM", "AIY", "AIZ", "ASE", "ASG",
+            "ASH", "ASI", "ASJ", "ASK", "AUA", "AVD", "AVH", "AVJ", "AWA", "AWB", "AWC", "FLP",
+            "RIB", "RIC", "RMG", "SAAD", "SAAV", "SMB", "URX",
+        ],
+    ),
+    (
+        "Dorsal",
+        &[
+            "ALA", "AVE", "RIA", "RID", "RMED", "RMD", "SIAD", "SIBV", "URAD",
+        ],
+    ),
+    (
+        "Ventral",
+        &[
+            "AIN", "AVA", "AVB", "AVK", "AVL", "RIG", "RIH", "RIR", "RIS", "RIV", "RME", "RMEV",
+            "RMF", "RMH", "SIAV", "SIBD", "SMD",
+        ],
+    ),
+    (
+        "Retrovesicular",
+        &[
+            "AVF", "AVG", "RIF", "SABD", "SABV", "AS1", "DA1", "DB1", "DB2", "DD1", "VA1", "VB1",
+            "VB2", "VD1", "VD2",
+        ],
+    ),
+    (
+        "Preanal",
+        &[
+            "AS11", "DA8", "DA9", "PDA", "PDB", "PVP", "PVT", "VA12", "VD13",
+        ],
+    ),
+    ("Dorsorectal", &["DVA", "DVB", "DVC"]),
+    (
+        "Lumbar",
+        &[
+            "ALN", "LUA", "PHA", "PHB", "PHC", "PLM", "PLN", "PQR", "PVC", "PVN", "PVQ", "PVR",
+            "PVW",
+        ],
+    ),
+    ("Pharynx", &[]),
+];
+
+/// Name of the ganglion holding the cell body of `neuron`, if it is known.
+pub fn of(neuron: &crate::dataset::Neuron) -> Option<&'static str> {
+    if region::is_pharyngeal(neuron) {
+        return Some("Pharynx");
+    }
+    let name = neuron.name.as_str();
+    // Numbered motor neurons are listed one by one, so try whole names before classes.
+    GANGLIA
+        .iter()
+        .find(|(_, members)| members.contains(&name))
+        .or_else(|| {
+            GANGLIA.iter().find(|(_, members)| {
+                members.iter().any(|class| {
+                    name.strip_prefix(class).is_some_and(|rest| {
+                        matches!(rest, "L" | "R" | "D" | "V" | "DL" | "DR" | "VL" | "VR")
+                    })
+                })
+            })
+        })
+        .map(|(ganglion, _)| *ganglion)
+}
+
+/// Color of the outline of `ganglion`, distinct for each.
+pub fn color(ganglion: &str) -> Color32 {
+    let index = GANGLIA
+        .iter()
+        .position(|(g, _)| *g == ganglion)
+        .unwrap_or(0);
+    let hue = index as f32 / GANGLIA.len() as f32;
+    egui::ecolor::Hsva::new(hue, 0.6, 0.8, 1.0).into()
+}
@@ -0,0 +1,1142 @@
+use std::borrow::Cow;
+
+/// Signature at the start of the superblock of every HDF5 file.
+const SIGNATURE: &[u8; 8] = b"\x89HDF\r\n\x1a\n";
+/// Address of something that was never written.
+const UNDEFINED: u64 = u64::MAX;
+/// Most objects visited through continuation blocks, B-tree nodes and the like before a file
+/// is taken to be damaged.
+const MAX_NODES: usize = 1_000_000;
+
+/// Whether `bytes` start with an HDF5 superblock, as NWB files do.
+pub fn is_hdf5(bytes: &[u8]) -> bool {
+    bytes.starts_with(SIGNATURE)
+}
+
+/// The values of a dataset or attribute, in row-major order.
+pub struct Array {
+    pub shape: Vec<usize>,
+    pub data: Data,
+}
+
+pub enum Data {
+    Numbers(Vec<f64>),
+    Strings(Vec<String>),
+    /// Addresses of the objects referred to.
+    References(Vec<u64>),
+}
+
+impl Array {
+    pub fn numbers(&self) -> Option<&[f64]> {
+        match &self.data {
+            Data::Numbers(values) => Some(values),
+            _ => None,
+        }
+    }
+
+    pub fn strings(&self) -> Option<&[String]> {
+        match &self.data {
+            Data::Strings(values) => Some(values),
+            _ => None,
+        }
+    }
+
+    pub fn references(&self) -> Option<&[u64]> {
+        match &self.data {
+            Data::References(values) => Some(values),
+            _ => None,
+        }
+    }
+}
+
+/// A read-only HDF5 file held in memory.
+///
+/// Only what files written with the default settings of h5py need is read: groups indexed
+/// by symbol tables or holding their links in the object header, contiguous, compact and
+/// chunked datasets compressed with deflate, and numbers, strings and object references.
+pub struct File<'a> {
+    bytes: &'a [u8],
+    base: u64,
+    offset_size: usize,
+    length_size: usize,
+    root: u64,
+}
+
+impl<'a> File<'a> {
+    pub fn open(bytes: &'a [u8]) -> Result<Self, String> {
+        if !is_hdf5(bytes) {
+            return Err("not an HDF5 file".to_owned());
+        }
+        let version = *bytes.get(8).ok_or("truncated superblock")?;
+        let mut file = Self {
+            bytes,
+            base: 0,
+            offset_size: 8,
+            length_size: 8,
+            root: 0,
+        };
+        match version {
+            0 | 1 => {
+                file.offset_size = *bytes.get(13).ok_or("truncated superblock")? as usize;
+                file.length_size = *bytes.get(14).ok_or("truncated superblock")? as usize;
+                let mut r = file.reader_at(if version == 0 { 24 } else { 28 })?;
+                file.base = r.offset()?;
+                // Free space, end of file and driver information addresses.
+                r.skip(3 * file.offset_size)?;
+                // The root group's symbol table entry: its name, then its object header.
+                r.offset()?;
+                file.root = r.offset()?;
+            }
+            2 | 3 => {
+                file.offset_size = *bytes.get(9).ok_or("truncated superblock")? as usize;
+                file.length_size = *bytes.get(10).ok_or("truncated superblock")? as usize;
+                let mut r = file.reader_at(12)?;
+                file.base = r.offset()?;
+                // Superblock extension and end of file addresses.
+                r.skip(2 * file.offset_size)?;
+                file.root = r.offset()?;
+            }
+            _ => return Err(format!("unsupported HDF5 superblock version {version}")),
+        }
+        if ![2, 4, 8].contains(&file.offset_size) || ![2, 4, 8].contains(&file.length_size) {
+            return Err("unsupported HDF5 address size".to_owned());
+        }
+        Ok(file)
+    }
+
+    /// Address of the object header of the root group.
+    pub fn root(&self) -> u64 {
+        self.root
+    }
+
+    /// Names and object header addresses of the members of the group at `group`, sorted by
+    /// name.
+    pub fn members(&self, group: u64) -> Result<Vec<(String, u64)>, String> {
+        let mut members = Vec::new();
+        for message in self.messages(group)? {
+            match message.kind {
+                LINK_INFO => {
+                    let mut r = self.reader(message.data);
+                    r.u8()?;
+                    let flags = r.u8()?;
+                    if flags & 1 != 0 {
+                        r.skip(8)?;
+                    }
+                    if r.offset()? != UNDEFINED {
+                        return Err("groups with dense link storage are not supported".to_owned());
+                    }
+                }
+                LINK => {
+                    if let Some(member) = self.link(message.data)? {
+                        members.push(member);
+                    }
+                }
+                SYMBOL_TABLE => {
+                    let mut r = self.reader(message.data);
+                    let (tree, heap) = (r.offset()?, r.offset()?);
+                    self.symbol_table(tree, heap, &mut members, &mut 0)?;
+                }
+                _ => {}
+            }
+        }
+        members.sort();
+        Ok(members)
+    }
+
+    /// Address of the member `name` of the group at `group`.
+    pub fn member(&self, group: u64, name: &str) -> Result<Option<u64>, String> {
+        let members = self.members(group)?;
+        Ok(members.into_iter().find(|(n, _)| n == name).map(|(_, a)| a))
+    }
+
+    pub fn is_dataset(&self, object: u64) -> Result<bool, String> {
+        Ok(self.messages(object)?.iter().any(|m| m.kind == LAYOUT))
+    }
+
+    /// Values of the dataset at `dataset`.
+    pub fn read(&self, dataset: u64) -> Result<Array, String> {
+        let messages = self.messages(dataset)?;
+        let find = |kind| messages.iter().find(|m| m.kind == kind).map(|m| m.data);
+        let shape = self.dataspace(find(DATASPACE).ok_or("dataset without a dataspace")?)?;
+        let datatype = self.datatype(&mut self.reader(find(DATATYPE).ok_or("untyped dataset")?))?;
+        let layout = find(LAYOUT).ok_or("not a dataset")?;
+        let filters = find(FILTERS).map(|f| self.filters(f)).transpose()?;
+        let count = byte_size(&shape, 1)?;
+        let raw = self.raw_data(layout, &shape, datatype.size, &filters.unwrap_or_default())?;
+        let data = self.decode(&datatype, &raw, count)?;
+        Ok(Array { shape, data })
+    }
+
+    /// Values of the attribute `name` of the object at `object`, if it has one.
+    pub fn attribute(&self, object: u64, name: &str) -> Result<Option<Array>, String> {
+        for message in self.messages(object)? {
+            if message.kind != ATTRIBUTE {
+                continue;
+            }
+            let mut r = self.reader(message.data);
+            let version = r.u8()?;
+            r.u8()?;
+            let name_size = r.u16()? as usize;
+            let datatype_size = r.u16()? as usize;
+            let dataspace_size = r.u16()? as usize;
+            if version >= 3 {
+                r.u8()?;
+            }
+            // Version 1 pads each part to a multiple of eight bytes.
+            let padded = |size: usize| {
+                if version == 1 {
+                    size.next_multiple_of(8)
+                } else {
+                    size
+                }
+            };
+            let attribute = r.bytes(padded(name_size))?;
+            let attribute = &attribute[..name_size.min(attribute.len())];
+            let attribute = attribute.split(|&b| b == 0).next().unwrap_or_default();
+            let datatype = r.bytes(padded(datatype_size))?;
+            let dataspace = r.bytes(padded(dataspace_size))?;
+            if attribute != name.as_bytes() {
+                continue;
+            }
+            let datatype = self.datatype(&mut self.reader(datatype))?;
+            let shape = self.dataspace(dataspace)?;
+            let count = byte_size(&shape, 1)?;
+            let raw = r.bytes(byte_size(&shape, datatype.size)?)?;
+            let data = self.decode(&datatype, raw, count)?;
+            return Ok(Some(Array { shape, data }));
+        }
+        Ok(None)
+    }
+
+    fn reader(&self, data: &'a [u8]) -> Reader<'a> {
+        Reader {
+            data,
+            pos: 0,
+            offset_size: self.offset_size,
+            length_size: self.length_size,
+        }
+    }
+
+    /// A reader of the file from the absolute byte `pos`.
+    fn reader_at(&self, pos: u64) -> Result<Reader<'a>, String> {
+        let data = usize::try_from(pos)
+            .ok()
+            .and_then(|pos| self.bytes.get(pos..))
+            .ok_or("address beyond the end of the file")?;
+        Ok(self.reader(data))
+    }
+
+    /// A reader from the file address `address`, which is relative to the base address.
+    fn reader_from(&self, address: u64) -> Result<Reader<'a>, String> {
+        self.reader_at(self.base.checked_add(address).ok_or("invalid address")?)
+    }
+
+    /// The `len` bytes at the file address `address`.
+    fn slice(&self, address: u64, len: u64) -> Result<&'a [u8], String> {
+        let len = usize::try_from(len).map_err(|_| "block too large")?;
+        self.reader_from(address)?.bytes(len)
+    }
+
+    /// Header messages of the object at `address`, following continuation blocks.
+    fn messages(&self, address: u64) -> Result<Vec<Message<'a>>, String> {
+        let mut r = self.reader_from(address)?;
+        let mut messages = Vec::new();
+        if r.data.starts_with(b"OHDR") {
+            r.skip(4)?;
+            r.u8()?;
+            let flags = r.u8()?;
+            if flags & 0x20 != 0 {
+                r.skip(16)?;
+            }
+            if flags & 0x10 != 0 {
+                r.skip(4)?;
+            }
+            let size = r.uint(1 << (flags & 3))? as usize;
+            let mut blocks = vec![r.bytes(size)?];
+            let ordered = flags & 0x04 != 0;
+            while let Some(block) = blocks.pop() {
+                let mut r = self.reader(block);
+                let header = if ordered { 6 } else { 4 };
+                while r.remaining() >= header {
+                    let kind = r.u8()? as u16;
+                    let size = r.u16()? as usize;
+                    r.u8()?;
+                    if ordered {
+                        r.u16()?;
+                    }
+                    let data = r.bytes(size)?;
+                    if kind == CONTINUATION {
+                        let mut c = self.reader(data);
+                        let (at, len) = (c.offset()?, c.length()?);
+                        let chunk = self.slice(at, len)?;
+                        // A signature before the messages and a checksum after them.
+                        let end = chunk.len().checked_sub(4).ok_or("truncated header")?;
+                        blocks.push(chunk.get(4..end).ok_or("truncated header")?);
+                    } else {
+                        messages.push(Message { kind, data });
+                    }
+                    if messages.len() > MAX_NODES {
+                        return Err("object header too long".to_owned());
+                    }
+                }
+            }
+            return Ok(messages);
+        }
+        let version = r.u8()?;
+        if version != 1 {
+            return Err(format!("unsupported object header version {version}"));
+        }
+        r.skip(3)?;
+        r.u32()?;
+        let size = r.u32()? as usize;
+        // The messages are aligned to eight bytes from the start of the header.
+        r.skip(4)?;
+        let mut blocks = vec![r.bytes(size)?];
+        while let Some(block) = blocks.pop() {
+            let mut r = self.reader(block);
+            while r.remaining() >= 8 {
+                let kind = r.u16()?;
+                let size = r.u16()? as usize;
+                r.skip(4)?;
+                let data = r.bytes(size)?;
+                if kind == CONTINUATION {
+                    let mut c = self.reader(data);
+                    let (at, len) = (c.offset()?, c.length()?);
+                    blocks.push(self.slice(at, len)?);
+                } else {
+                    messages.push(Message { kind, data });
+                }
+                if messages.len() > MAX_NODES {
+                    return Err("object header too long".to_owned());
+                }
+            }
+        }
+        Ok(messages)
+    }
+
+    /// Name and address of the hard link in a link message; other links are skipped.
+    fn link(&self, data: &'a [u8]) -> Result<Option<(String, u64)>, String> {
+        let mut r = self.reader(data);
+        r.u8()?;
+        let flags = r.u8()?;
+        let kind = if flags & 0x08 != 0 { r.u8()? } else { 0 };
+        if flags & 0x04 != 0 {
+            r.skip(8)?;
+        }
+        if flags & 0x10 != 0 {
+            r.u8()?;
+        }
+        let len = r.uint(1 << (flags & 3))? as usize;
+        let name = String::from_utf8_lossy(r.bytes(len)?).into_owned();
+        Ok((kind == 0)
+            .then(|| r.offset())
+            .transpose()?
+            .map(|a| (name, a)))
+    }
+
+    /// Collect the members of a group indexed by the version 1 B-tree at `tree`, their names
+    /// kept in the local heap at `heap`.
+    fn symbol_table(
+        &self,
+        tree: u64,
+        heap: u64,
+        members: &mut Vec<(String, u64)>,
+        visited: &mut usize,
+    ) -> Result<(), String> {
+        *visited += 1;
+        if *visited > MAX_NODES {
+            return Err("group index too deep".to_owned());
+        }
+        let mut r = self.reader_from(heap)?;
+        if r.bytes(4)? != b"HEAP" {
+            return Err("missing local heap".to_owned());
+        }
+        r.skip(4)?;
+        r.length()?;
+        r.length()?;
+        let names = self.reader_from(r.offset()?)?.data;
+        let name_at = |offset: u64| {
+            let start = usize::try_from(offset).unwrap_or(usize::MAX);
+            let name = names.get(start..).unwrap_or_default();
+            let end = name.iter().position(|&b| b == 0).unwrap_or(name.len());
+            String::from_utf8_lossy(&name[..end]).into_owned()
+        };
+        let mut r = self.reader_from(tree)?;
+        if r.bytes(4)? != b"TREE" {
+            return Err("missing group B-tree".to_owned());
+        }
+        r.u8()?;
+        let level = r.u8()?;
+        let entries = r.u16()? as usize;
+        r.skip(2 * self.offset_size)?;
+        for _ in 0..entries {
+            r.length()?;
+            let child = r.offset()?;
+            if level > 0 {
+                self.symbol_table(child, heap, members, visited)?;
+                continue;
+            }
+            let mut node = self.reader_from(child)?;
+            if node.bytes(4)? != b"SNOD" {
+                return Err("missing symbol table node".to_owned());
+            }
+            node.skip(2)?;
+            let symbols = node.u16()?;
+            for _ in 0..symbols {
+                let name = node.offset()?;
+                let address = node.offset()?;
+                node.skip(24)?;
+                members.push((name_at(name), address));
+            }
+        }
+        Ok(())
+    }
+
+    /// Dimensions of a dataspace message; empty for a scalar.
+    fn dataspace(&self, data: &'a [u8]) -> Result<Vec<usize>, String> {
+        let mut r = self.reader(data);
+        let version = r.u8()?;
+        let rank = r.u8()? as usize;
+        r.u8()?;
+        let null = match version {
+            1 => {
+                r.skip(5)?;
+                false
+            }
+            _ => r.u8()? == 2,
+        };
+        if null {
+            return Ok(vec![0]);
+        }
+        (0..rank)
+            .map(|_| {
+                let dim = r.length()?;
+                usize::try_from(dim).map_err(|_| "dataset too large".to_owned())
+            })
+            .collect()
+    }
+
+    fn datatype(&self, r: &mut Reader<'a>) -> Result<Datatype, String> {
+        let class = r.u8()?;
+        let bits = [r.u8()?, r.u8()?, r.u8()?];
+        let size = r.u32()? as usize;
+        let big = bits[0] & 1 != 0;
+        let kind = match class & 0x0F {
+            0 => {
+                r.skip(4)?;
+                Kind::Int {
+                    signed: bits[0] & 0x08 != 0,
+                    big,
+                }
+            }
+            1 => {
+                r.skip(12)?;
+                Kind::Float { big }
+            }
+            3 => Kind::FixedString,
+            7 if size == self.offset_size => Kind::Reference,
+            9 if bits[0] & 0x0F == 1 => {
+                self.datatype(r)?;
+                Kind::VarString
+            }
+            class => Kind::Other(class),
+        };
+        Ok(Datatype { kind, size })
+    }
+
+    /// Filters of a pipeline message, as their identifiers and client values.
+    fn filters(&self, data: &'a [u8]) -> Result<Vec<(u16, Vec<u32>)>, String> {
+        let mut r = self.reader(data);
+        let version = r.u8()?;
+        let count = r.u8()?;
+        if version == 1 {
+            r.skip(6)?;
+        }
+        let mut filters = Vec::new();
+        for _ in 0..count {
+            let id = r.u16()?;
+            let name_len = if version == 1 || id >= 256 {
+                r.u16()? as usize
+            } else {
+                0
+            };
+            r.u16()?;
+            let values = r.u16()? as usize;
+            r.skip(if version == 1 {
+                name_len.next_multiple_of(8)
+            } else {
+                name_len
+            })?;
+            let values = (0..values)
+                .map(|_| r.u32())
+                .collect::<Result<Vec<_>, _>>()?;
+            if version == 1 && values.len() % 2 == 1 {
+                r.skip(4)?;
+            }
+            filters.push((id, values));
+        }
+        Ok(filters)
+    }
+
+    /// Raw bytes of every element of a dataset, in row-major order.
+    fn raw_data(
+        &self,
+        layout: &'a [u8],
+        shape: &[usize],
+        element: usize,
+        filters: &[(u16, Vec<u32>)],
+    ) -> Result<Cow<'a, [u8]>, String> {
+        let total = byte_size(shape, element)?;
+        let mut r = self.reader(layout);
+        let version = r.u8()?;
+        let (class, dims) = match version {
+            1 | 2 => {
+                let rank = r.u8()? as usize;
+                let class = r.u8()?;
+                r.skip(5)?;
+                let address = if class == 0 { UNDEFINED } else { r.offset()? };
+                let dims = (0..rank).map(|_| r.u32()).collect::<Result<Vec<_>, _>>()?;
+                if class == 0 {
+                    r.u32()?;
+                    return Ok(Cow::Borrowed(r.bytes(total)?));
+                }
+                (class, Some((address, dims)))
+            }
+            3 | 4 => (r.u8()?, None),
+            _ => return Err(format!("unsupported data layout version {version}")),
+        };
+        match class {
+            0 => {
+                let size = r.u16()? as usize;
+                Ok(Cow::Borrowed(r.bytes(size.min(total))?))
+            }
+            1 => {
+                let address = match &dims {
+                    Some((address, _)) => *address,
+                    None => r.offset()?,
+                };
+                if address == UNDEFINED {
+                    return Ok(Cow::Owned(vec![0; total]));
+                }
+                Ok(Cow::Borrowed(self.slice(address, total as u64)?))
+            }
+            2 => {
+                let mut out = vec![0; total];
+                if version >= 4 {
+                    self.chunks_v4(&mut r, shape, element, filters, &mut out)?;
+                    return Ok(Cow::Owned(out));
+                }
+                let (tree, chunk) = match dims {
+                    Some((address, dims)) => (address, dims),
+                    None => {
+                        let rank = r.u8()? as usize;
+                        let address = r.offset()?;
+                        let dims = (0..rank).map(|_| r.u32()).collect::<Result<Vec<_>, _>>()?;
+                        (address, dims)
+                    }
+                };
+                // The last dimension of a chunk is the size of an element.
+                let chunk: Vec<usize> = chunk.iter().map(|&d| d as usize).collect();
+                let chunk = &chunk[..chunk.len().saturating_sub(1)];
+                // Positions within a chunk are computed unchecked from here on.
+                byte_size(chunk, element)?;
+                if tree != UNDEFINED {
+                    let mut visited = 0;
+                    self.chunk_tree(tree, shape, chunk, element, filters, &mut out, &mut visited)?;
+                }
+                Ok(Cow::Owned(out))
+            }
+            _ => Err(format!("unsupported data layout class {class}")),
+        }
+    }
+
+    /// Read the chunks of a dataset whose layout message is version 4, which h5py writes only
+    /// when asked for the latest file format.
+    fn chunks_v4(
+        &self,
+        r: &mut Reader<'a>,
+        shape: &[usize],
+        element: usize,
+        filters: &[(u16, Vec<u32>)],
+        out: &mut [u8],
+    ) -> Result<(), String> {
+        let flags = r.u8()?;
+        let rank = r.u8()? as usize;
+        let encoded = r.u8()? as usize;
+        let dims = (0..rank)
+            .map(|_| r.uint(encoded))
+            .collect::<Result<Vec<_>, _>>()?;
+        let chunk: Vec<usize> = dims[..rank.saturating_sub(1)]
+            .iter()
+            .map(|&d| d as usize)
+            .collect();
+        let chunk_bytes = byte_size(&chunk, element)?;
+        match r.u8()? {
+            // A single chunk holding the whole dataset.
+            1 => {
+                let (size, mask) = if flags & 0x02 != 0 {
+                    (r.length()?, r.u32()?)
+                } else {
+                    (chunk_bytes as u64, 0)
+                };
+                let address = r.offset()?;
+                if address == UNDEFINED {
+                    return Ok(());
+                }
+                let data = unfilter(self.slice(address, size)?, filters, mask, element)?;
+                copy_chunk(&data, &vec![0; chunk.len()], &chunk, shape, element, out);
+                Ok(())
+            }
+            // Unfiltered chunks one after another, in row-major order of the chunk grid.
+            2 => {
+                let address = r.offset()?;
+                if address == UNDEFINED {
+                    return Ok(());
+                }
+                let grid: Vec<usize> = (shape.iter().zip(&chunk))
+                    .map(|(&d, &c)| d.div_ceil(c.max(1)))
+                    .collect();
+                let count = byte_size(&grid, 1)?;
+                for index in 0..count {
+                    let mut rest = index;
+                    let mut origin = vec![0; grid.len()];
+                    for k in (0..grid.len()).rev() {
+                        origin[k] = (rest % grid[k]) * chunk[k];
+                        rest /= grid[k];
+                    }
+                    let at = (index.checked_mul(chunk_bytes))
+                        .and_then(|offset| address.checked_add(offset as u64))
+                        .ok_or("invalid chunk address")?;
+                    let data = self.slice(at, chunk_bytes as u64)?;
+                    copy_chunk(data, &origin, &chunk, shape, element, out);
+                }
+                Ok(())
+            }
+            index => Err(format!(
+                "unsupported chunk index type {index}; save the file with h5py's default \
+                 format to read it"
+            )),
+        }
+    }
+
+    /// Read the chunks indexed by the version 1 B-tree at `tree` into `out`.
+    #[allow(clippy::too_many_arguments)]
+    fn chunk_tree(
+        &self,
+        tree: u64,
+        shape: &[usize],
+        chunk: &[usize],
+        element: usize,
+        filters: &[(u16, Vec<u32>)],
+        out: &mut [u8],
+        visited: &mut usize,
+    ) -> Result<(), String> {
+        *visited += 1;
+        if *visited > MAX_NODES {
+            return Err("chunk index too deep".to_owned());
+        }
+        let mut r = self.reader_from(tree)?;
+        if r.bytes(4)? != b"TREE" {
+            return Err("missing chunk B-tree".to_owned());
+        }
+        r.u8()?;
+        let level = r.u8()?;
+        let entries = r.u16()? as usize;
+        r.skip(2 * self.offset_size)?;
+        for _ in 0..entries {
+            let size = r.u32()?;
+            let mask = r.u32()?;
+            let origin = (0..=chunk.len())
+                .map(|_| r.u64().map(|v| v as usize))
+                .collect::<Result<Vec<_>, _>>()?;
+            let child = r.offset()?;
+            if level > 0 {
+                self.chunk_tree(child, shape, chunk, element, filters, out, visited)?;
+                continue;
+            }
+            let data = unfilter(self.slice(child, size as u64)?, filters, mask, element)?;
+            copy_chunk(&data, &origin[..chunk.len()], chunk, shape, element, out);
+        }
+        Ok(())
+    }
+
+    /// Values of `count` elements of type `datatype` stored in `raw`.
+    fn decode(&self, datatype: &Datatype, raw: &[u8], count: usize) -> Result<Data, String> {
+        let size = datatype.size;
+        if raw.len() < count.checked_mul(size).ok_or("dataset too large")? {
+            return Err("dataset shorter than its dataspace".to_owned());
+        }
+        let elements = raw.chunks_exact(size.max(1)).take(count);
+        Ok(match datatype.kind {
+            Kind::Int { signed, big } => Data::Numbers(
+                elements
+                    .map(|e| {
+                        let mut bytes = [0u8; 8];
+                        if big {
+                            for (b, v) in bytes.iter_mut().zip(e.iter().rev()) {
+                                *b = *v;
+                            }
+                        } else {
+                            bytes[..e.len().min(8)].copy_from_slice(&e[..e.len().min(8)]);
+                        }
+                        let unsigned = u64::from_le_bytes(bytes);
+                        let shift = 64 - 8 * e.len().min(8) as u32;
+                        match signed {
+                            true => ((unsigned << shift) as i64 >> shift) as f64,
+                            false => unsigned as f64,
+                        }
+                    })
+                    .collect(),
+            ),
+            Kind::Float { big } => Data::Numbers(
+                elements
+                    .map(|e| match (e.len(), big) {
+                        (4, false) => f32::from_le_bytes([e[0], e[1], e[2], e[3]]) as f64,
+                        (4, true) => f32::from_be_bytes([e[0], e[1], e[2], e[3]]) as f64,
+                        (8, false) => f64::from_le_bytes(e.try_into().unwrap_or_default()),
+                        (8, true) => f64::from_be_bytes(e.try_into().unwrap_or_default()),
+                        _ => f64::NAN,
+                    })
+                    .collect(),
+            ),
+            Kind::FixedString => Data::Strings(
+                elements
+                    .map(|e| {
+                        let end = e.iter().position(|&b| b == 0).unwrap_or(e.len());
+                        String::from_utf8_lossy(&e[..end]).trim_end().to_owned()
+                    })
+                    .collect(),
+            ),
+            Kind::VarString => Data::Strings(
+                elements
+                    .map(|e| {
+                        let mut r = self.reader(e);
+                        let len = r.u32()? as usize;
+                        let (collection, index) = (r.offset()?, r.u32()?);
+                        let bytes = self.heap_object(collection, index)?;
+                        let bytes = &bytes[..len.min(bytes.len())];
+                        let end = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+                        Ok(String::from_utf8_lossy(&bytes[..end]).into_owned())
+                    })
+                    .collect::<Result<_, String>>()?,
+            ),
+            Kind::Reference => Data::References(
+                elements
+                    .map(|e| self.reader(e).offset())
+                    .collect::<Result<_, _>>()?,
+            ),
+            Kind::Other(class) => return Err(format!("unsupported datatype class {class}")),
+        })
+    }
+
+    /// Object `index` of the global heap collection at `collection`.
+    fn heap_object(&self, collection: u64, index: u32) -> Result<&'a [u8], String> {
+        if collection == 0 && index == 0 {
+            return Ok(&[]);
+        }
+        let mut r = self.reader_from(collection)?;
+        if r.bytes(4)? != b"GCOL" {
+            return Err("missing global heap".to_owned());
+        }
+        r.skip(4)?;
+        let size = r.length()? as usize;
+        let header = 8 + self.length_size;
+        let mut r = self.reader(r.data.get(..size).ok_or("truncated global heap")?);
+        r.skip(header)?;
+        while r.remaining() >= 8 + self.length_size {
+            let id = r.u16()?;
+            if id == 0 {
+                break;
+            }
+            r.skip(6)?;
+            let len = r.length()? as usize;
+            let data = r.bytes(len)?;
+            if id as u32 == index {
+                return Ok(data);
+            }
+            r.skip(len.next_multiple_of(8) - len)?;
+        }
+        Err("missing global heap object".to_owned())
+    }
+}
+
+const DATASPACE: u16 = 0x01;
+const LINK_INFO: u16 = 0x02;
+const DATATYPE: u16 = 0x03;
+const LINK: u16 = 0x06;
+const LAYOUT: u16 = 0x08;
+const FILTERS: u16 = 0x0B;
+const ATTRIBUTE: u16 = 0x0C;
+const CONTINUATION: u16 = 0x10;
+const SYMBOL_TABLE: u16 = 0x11;
+
+struct Message<'a> {
+    kind: u16,
+    data: &'a [u8],
+}
+
+struct Datatype {
+    kind: Kind,
+    size: usize,
+}
+
+enum Kind {
+    Int { signed: bool, big: bool },
+    Float { big: bool },
+    FixedString,
+    VarString,
+    Reference,
+    Other(u8),
+}
+
+/// Bytes taken by an array of dimensions `dims` and elements of `element` bytes, both read
+/// from the file, so a damaged one can't overflow.
+fn byte_size(dims: &[usize], element: usize) -> Result<usize, String> {
+    (dims.iter())
+        .try_fold(element, |size, &d| size.checked_mul(d))
+        .ok_or_else(|| "dataset too large".to_owned())
+}
+
+/// Undo the filters of a chunk, skipping those whose bit is set in `mask`.
+fn unfilter(
+    data: &[u8],
+    filters: &[(u16, Vec<u32>)],
+    mask: u32,
+    element: usize,
+) -> Result<Vec<u8>, String> {
+    // The mask has a bit for each filter, of at most 32.
+    if filters.len() > 32 {
+        return Err("too many filters".to_owned());
+    }
+    let mut data = data.to_vec();
+    for (i, (id, values)) in filters.iter().enumerate().rev() {
+        if mask & (1 << i) != 0 {
+            continue;
+        }
+        data = match id {
+            1 => miniz_oxide::inflate::decompress_to_vec_zlib(&data)
+                .map_err(|err| format!("corrupt compressed chunk: {err:?}"))?,
+            2 => {
+                let size = values.first().map_or(element, |&s| s as usize).max(1);
+                let count = data.len() / size;
+                let mut out = data.clone();
+                for i in 0..count {
+                    for b in 0..size {
+                        out[i * size + b] = data[b * count + i];
+                    }
+                }
+                out
+            }
+            // Fletcher-32 appends a checksum, which is not verified.
+            3 => {
+                data.truncate(data.len().saturating_sub(4));
+                data
+            }
+            id => return Err(format!("unsupported HDF5 filter {id}")),
+        };
+    }
+    Ok(data)
+}
+
+/// Copy the chunk `data` of dimensions `chunk`, starting at `origin` in a dataset of
+/// dimensions `shape`, into `out`, leaving out the part beyond the edges of the dataset.
+fn copy_chunk(
+    data: &[u8],
+    origin: &[usize],
+    chunk: &[usize],
+    shape: &[usize],
+    element: usize,
+    out: &mut [u8],
+) {
+    let rank = shape.len();
+    if rank == 0 {
+        let n = element.min(data.len()).min(out.len());
+        out[..n].copy_from_slice(&data[..n]);
+        return;
+    }
+    if chunk.len() != rank || origin.len() != rank || origin.iter().zip(shape).any(|(o, d)| o >= d)
+    {
+        return;
+    }
+    let last = rank - 1;
+    let run = chunk[last].min(shape[last] - origin[last]) * element;
+    // Position within the chunk of each dimension but the last.
+    let mut at = vec![0usize; last];
+    loop {
+        let inside = (0..last).all(|k| origin[k] + at[k] < shape[k]);
+        if inside {
+            let (mut src, mut dst) = (0, 0);
+            for k in 0..rank {
+                let (i, o) = if k < last {
+                    (at[k], origin[k] + at[k])
+                } else {
+                    (0, origin[k])
+                };
+                src = src * chunk[k] + i;
+                dst = dst * shape[k] + o;
+            }
+            let (src, dst) = (src * element, dst * element);
+            if let (Some(from), Some(to)) = (data.get(src..src + run), out.get_mut(dst..dst + run))
+            {
+                to.copy_from_slice(from);
+            }
+        }
+        let mut k = last;
+        loop {
+            if k == 0 {
+                return;
+            }
+            k -= 1;
+            at[k] += 1;
+            // The rest of the chunk along `k` lies beyond the edge of the dataset.
+            if at[k] < chunk[k] && origin[k] + at[k] < shape[k] {
+                break;
+            }
+            at[k] = 0;
+        }
+    }
+}
+
+/// Little-endian reader of the structures of the file.
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+    offset_size: usize,
+    length_size: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn remaining(&self) -> usize {
+        self.data.len().saturating_sub(self.pos)
+    }
+
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8], String> {
+        let end = self.pos.checked_add(len).ok_or("invalid length")?;
+        let bytes = self
+            .data
+            .get(self.pos..end)
+            .ok_or("unexpected end of the file")?;
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    fn skip(&mut self, len: usize) -> Result<(), String> {
+        self.bytes(len).map(|_| ())
+    }
+
+    /// An unsigned integer of `len` bytes.
+    fn uint(&mut self, len: usize) -> Result<u64, String> {
+        let bytes = self.bytes(len)?;
+        if len > 8 {
+            return Err("integer too wide".to_owned());
+        }
+        Ok(bytes.iter().rev().fold(0, |v, &b| (v << 8) | b as u64))
+    }
+
+    fn u8(&mut self) -> Result<u8, String> {
+        self.uint(1).map(|v| v as u8)
+    }
+
+    fn u16(&mut self) -> Result<u16, String> {
+        self.uint(2).map(|v| v as u16)
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        self.uint(4).map(|v| v as u32)
+    }
+
+    fn u64(&mut self) -> Result<u64, String> {
+        self.uint(8)
+    }
+
+    /// A file address; all ones for an undefined address, whatever its size.
+    fn offset(&mut self) -> Result<u64, String> {
+        let size = self.offset_size;
+        let v = self.uint(size)?;
+        Ok(if size < 8 && v == (1 << (8 * size)) - 1 {
+            UNDEFINED
+        } else {
+            v
+        })
+    }
+
+    fn length(&mut self) -> Result<u64, String> {
+        self.uint(self.length_size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Size of a version 2 superblock, which comes first.
+    const SUPERBLOCK: usize = 48;
+
+    /// A file of 8-byte addresses whose root group links `/data` to the dataset object header
+    /// made of `messages`. `blocks` are written before it; `messages` gets their addresses.
+    fn file(blocks: &[&[u8]], messages: impl FnOnce(&[u64]) -> Vec<(u16, Vec<u8>)>) -> Vec<u8> {
+        let mut bytes = vec![0; SUPERBLOCK];
+        let put = |bytes: &mut Vec<u8>, block: &[u8]| {
+            let address = bytes.len() as u64;
+            bytes.extend_from_slice(block);
+            address
+        };
+        let addresses: Vec<u64> = blocks.iter().map(|b| put(&mut bytes, b)).collect();
+        let dataset = put(&mut bytes, &object_header(messages(&addresses)));
+        let mut link = vec![1, 0, 4];
+        link.extend_from_slice(b"data");
+        link.extend_from_slice(&dataset.to_le_bytes());
+        let root = put(&mut bytes, &object_header(vec![(LINK, link)]));
+        bytes[..8].copy_from_slice(SIGNATURE);
+        bytes[8..12].copy_from_slice(&[2, 8, 8, 0]);
+        bytes[36..44].copy_from_slice(&root.to_le_bytes());
+        bytes
+    }
+
+    /// A version 2 object header, with a 4-byte size and no checksum check.
+    fn object_header(messages: Vec<(u16, Vec<u8>)>) -> Vec<u8> {
+        let mut body = Vec::new();
+        for (kind, data) in messages {
+            body.push(kind as u8);
+            body.extend_from_slice(&(data.len() as u16).to_le_bytes());
+            body.push(0);
+            body.extend(data);
+        }
+        let mut header = b"OHDR".to_vec();
+        header.extend_from_slice(&[2, 0x02]);
+        header.extend_from_slice(&(body.len() as u32).to_le_bytes());
+        header.extend(body);
+        header.extend_from_slice(&[0; 4]);
+        header
+    }
+
+    fn dataspace(dims: &[u64]) -> (u16, Vec<u8>) {
+        let mut data = vec![1, dims.len() as u8, 0, 0, 0, 0, 0, 0];
+        dims.iter()
+            .for_each(|d| data.extend_from_slice(&d.to_le_bytes()));
+        (DATASPACE, data)
+    }
+
+    /// Little-endian IEEE double.
+    fn float64() -> (u16, Vec<u8>) {
+        let mut data = vec![0x11, 0x20, 0x3f, 0, 8, 0, 0, 0];
+        data.extend_from_slice(&[0, 0, 64, 0, 52, 11, 0, 52, 0xff, 0x03, 0, 0]);
+        (DATATYPE, data)
+    }
+
+    fn float64_bytes(values: impl IntoIterator<Item = f64>) -> Vec<u8> {
+        values.into_iter().flat_map(f64::to_le_bytes).collect()
+    }
+
+    /// Version 1 B-tree node indexing `chunks` (size, origin, address) of a 2D dataset.
+    fn chunk_tree(chunks: &[(u32, [u64; 2], u64)]) -> Vec<u8> {
+        let mut tree = b"TREE".to_vec();
+        tree.extend_from_slice(&[1, 0]);
+        tree.extend_from_slice(&(chunks.len() as u16).to_le_bytes());
+        tree.extend_from_slice(&[0xff; 16]);
+        for (size, origin, address) in chunks {
+            tree.extend_from_slice(&size.to_le_bytes());
+            tree.extend_from_slice(&0u32.to_le_bytes());
+            for v in [origin[0], origin[1], 0, *address] {
+                tree.extend_from_slice(&v.to_le_bytes());
+            }
+        }
+        tree.extend_from_slice(&[0; 32]);
+        tree
+    }
+
+    /// A 5 x 3 dataset of `t * 10 + n`, stored in 2 x 2 chunks written by `store`.
+    fn chunked(store: impl Fn(&[u8]) -> Vec<u8>, filters: Option<Vec<u8>>) -> Vec<u8> {
+        let (rows, columns) = (5, 3);
+        let mut chunks = Vec::new();
+        for t in (0..rows).step_by(2) {
+            for n in (0..columns).step_by(2) {
+                let values = (t..t + 2)
+                    .flat_map(|t| (n..n + 2).map(move |n| (t, n)))
+                    .map(|(t, n)| (t * 10 + n) as f64);
+                chunks.push(([t as u64, n as u64], store(&float64_bytes(values))));
+            }
+        }
+        // The tree holds the addresses of the chunks, so it is written after them, last.
+        let addresses = chunks.iter().scan(SUPERBLOCK as u64, |at, (_, data)| {
+            let address = *at;
+            *at += data.len() as u64;
+            Some(address)
+        });
+        let entries: Vec<_> = (chunks.iter().zip(addresses))
+            .map(|((origin, data), address)| (data.len() as u32, *origin, address))
+            .collect();
+        let tree = chunk_tree(&entries);
+        let mut blocks: Vec<&[u8]> = chunks.iter().map(|(_, data)| &data[..]).collect();
+        blocks.push(&tree);
+        file(&blocks, |addresses| {
+            let mut layout = vec![3, 2, 3];
+            layout.extend_from_slice(&addresses[addresses.len() - 1].to_le_bytes());
+            for d in [2u32, 2, 8] {
+                layout.extend_from_slice(&d.to_le_bytes());
+            }
+            let mut messages = vec![dataspace(&[rows as u64, columns as u64]), float64()];
+            messages.extend(filters.map(|f| (FILTERS, f)));
+            messages.push((LAYOUT, layout));
+            messages
+        })
+    }
+
+    fn read_data(bytes: &[u8]) -> Result<Array, String> {
+        let file = File::open(bytes)?;
+        let dataset = file.member(file.root(), "data")?.ok_or("no /data")?;
+        file.read(dataset)
+    }
+
+    fn expected() -> Vec<f64> {
+        (0..5)
+            .flat_map(|t| (0..3).map(move |n| (t * 10 + n) as f64))
+            .collect()
+    }
+
+    #[test]
+    fn contiguous() {
+        let values = float64_bytes([1.0, 2.5, -3.0, 4.0, 5.0, 6.0]);
+        let bytes = file(&[&values], |addresses| {
+            let mut layout = vec![3, 1];
+            layout.extend_from_slice(&addresses[0].to_le_bytes());
+            layout.extend_from_slice(&(values.len() as u64).to_le_bytes());
+            vec![dataspace(&[2, 3]), float64(), (LAYOUT, layout)]
+        });
+        let array = read_data(&bytes).unwrap();
+        assert_eq!(array.shape, [2, 3]);
+        assert_eq!(array.numbers().unwrap(), [1.0, 2.5, -3.0, 4.0, 5.0, 6.0]);
+    }
+
+    #[test]
+    fn chunked_with_partial_edge_chunks() {
+        let array = read_data(&chunked(<[u8]>::to_vec, None)).unwrap();
+        assert_eq!(array.shape, [5, 3]);
+        assert_eq!(array.numbers().unwrap(), expected());
+    }
+
+    #[test]
+    fn deflate() {
+        let compress = |data: &[u8]| miniz_oxide::deflate::compress_to_vec_zlib(data, 6);
+        // Version 2 pipeline with deflate at level 6.
+        let pipeline = vec![2, 1, 1, 0, 0, 0, 1, 0, 6, 0, 0, 0];
+        let array = read_data(&chunked(compress, Some(pipeline))).unwrap();
+        assert_eq!(array.numbers().unwrap(), expected());
+    }
+
+    #[test]
+    fn oversized_dataspace_is_an_error() {
+        let bytes = file(&[], |_| {
+            let mut layout = vec![3, 1];
+            layout.extend_from_slice(&[0xff; 16]);
+            vec![dataspace(&[u64::MAX / 4, 8]), float64(), (LAYOUT, layout)]
+        });
+        assert_eq!(read_data(&bytes).err().unwrap(), "dataset too large");
+    }
+
+    #[test]
+    fn too_many_filters_is_an_error() {
+        let filters = vec![(3, Vec::new()); 33];
+        assert!(unfilter(&[0; 8], &filters, 0, 8).is_err());
+    }
+}
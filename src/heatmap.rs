@@ -0,0 +1,116 @@
+use std::hash::{Hash, Hasher};
+
+use egui::{Color32, ColorImage, Sense, Stroke, TextureHandle, TextureOptions};
+
+use crate::activity::Activity;
+use crate::i18n::tr;
+
+/// Widest image drawn; longer recordings are sampled down to this many columns.
+const MAX_COLUMNS: usize = 4096;
+
+/// Neuron×time image of an activity matrix, kept as a texture until its rows change.
+#[derive(Default)]
+pub struct Heatmap {
+    key: u64,
+    rows: Vec<String>,
+    texture: Option<TextureHandle>,
+}
+
+impl Heatmap {
+    /// Draw a row for each of `names` with activity, in their order, and a column for each
+    /// frame, with `frame` marked and the row of `focused` outlined. A click moves `frame` to
+    /// the column clicked and returns the neuron of the row.
+    pub fn ui<'n>(
+        &mut self,
+        ui: &mut egui::Ui,
+        activity: &Activity,
+        names: impl Iterator<Item = &'n str>,
+        frame: &mut usize,
+        focused: Option<&str>,
+    ) -> Option<String> {
+        let rows: Vec<&str> = names.filter(|n| activity.traces.contains_key(*n)).collect();
+        if rows.is_empty() {
+            ui.weak(tr("No neuron shown has activity"));
+            return None;
+        }
+        let key = {
+            let mut hasher = std::collections::hash_map::DefaultHasher::new();
+            let range = (activity.range.0.to_bits(), activity.range.1.to_bits());
+            (&rows, &activity.name, activity.frames(), range).hash(&mut hasher);
+            hasher.finish()
+        };
+        if self.texture.is_none() || self.key != key {
+            self.key = key;
+            self.rows = rows.iter().map(|&n| n.to_owned()).collect();
+            let image = self.image(activity);
+            self.texture = Some(
+                ui.ctx()
+                    .load_texture("heatmap", image, TextureOptions::NEAREST),
+            );
+        }
+        let texture = self.texture.as_ref()?;
+        let height = (self.rows.len() as f32 * 6.0).clamp(80.0, 480.0);
+        let size = egui::vec2(ui.available_width().max(200.0), height);
+        let (rect, response) = ui.allocate_exact_size(size, Sense::click());
+        let painter = ui.painter_at(rect);
+        let uv = egui::Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(1.0, 1.0));
+        painter.image(texture.id(), rect, uv, Color32::WHITE);
+        let row_height = rect.height() / self.rows.len() as f32;
+        let column_width = rect.width() / activity.frames() as f32;
+        if let Some(row) = focused.and_then(|f| self.rows.iter().position(|n| n == f)) {
+            let top = rect.top() + row as f32 * row_height;
+            let row_rect = egui::Rect::from_x_y_ranges(rect.x_range(), top..=top + row_height);
+            painter.rect_stroke(row_rect, 0.0, Stroke::new(1.5, Color32::WHITE));
+        }
+        let x = rect.left() + (*frame as f32 + 0.5) * column_width;
+        painter.vline(
+            x,
+            rect.y_range(),
+            Stroke::new(1.5, ui.visuals().warn_fg_color),
+        );
+
+        let cell = |pos: egui::Pos2| {
+            let row = ((pos.y - rect.top()) / row_height) as usize;
+            let column = ((pos.x - rect.left()) / column_width) as usize;
+            Some((
+                row.min(self.rows.len() - 1),
+                column.min(activity.frames() - 1),
+            ))
+        };
+        let hovered = response.hover_pos().and_then(cell);
+        let clicked = response.clicked();
+        if let Some((row, column)) = hovered {
+            let name = &self.rows[row];
+            let value = activity
+                .value(name, column)
+                .map_or("–".to_owned(), |v| format!("{v:.3}"));
+            response.on_hover_text_at_pointer(format!(
+                "{name}\nt = {}\n{value}",
+                activity.times[column]
+            ));
+        }
+        let (row, column) = hovered.filter(|_| clicked)?;
+        *frame = column;
+        Some(self.rows[row].clone())
+    }
+
+    fn image(&self, activity: &Activity) -> ColorImage {
+        let colorbar = activity.colorbar();
+        let width = activity.frames().min(MAX_COLUMNS);
+        let mut pixels = Vec::with_capacity(width * self.rows.len());
+        for name in &self.rows {
+            for column in 0..width {
+                let frame = column * activity.frames() / width;
+                pixels.push(
+                    activity
+                        .value(name, frame)
+                        .map_or(Color32::from_gray(60), |v| colorbar.color(v)),
+                );
+            }
+        }
+        ColorImage {
+            size: [width, self.rows.len()],
+            pixels,
+        }
+    }
+}
@@ -0,0 +1,51 @@
+/// Convex hull of `points` in counter-clockwise order, without repeating the first point.
+/// Fewer than three distinct points are returned as they are.
+pub fn convex_hull(points: &[[f64; 2]]) -> Vec<[f64; 2]> {
+    let mut points = points.to_vec();
+    points.sort_unstable_by(|a, b| a[0].total_cmp(&b[0]).then(a[1].total_cmp(&b[1])));
+    points.dedup();
+    if points.len() < 3 {
+        return points;
+    }
+    let cross = |o: [f64; 2], a: [f64; 2], b: [f64; 2]| {
+        (a[0] - o[0]) * (b[1] - o[1]) - (a[1] - o[1]) * (b[0] - o[0])
+    };
+    // Andrew's monotone chain: the lower hull left to right, then the upper one back.
+    let mut hull: Vec<[f64; 2]> = Vec::with_capacity(points.len() + 1);
+    for &p in &points {
+        while hull.len() >= 2 && cross(hull[hull.len() - 2], hull[hull.len() - 1], p) <= 0.0 {
+            hull.pop();
+        }
+        hull.push(p);
+    }
+    let lower = hull.len() + 1;
+    for &p in points.iter().rev().skip(1) {
+        while hull.len() >= lower && cross(hull[hull.len() - 2], hull[hull.len() - 1], p) <= 0.0 {
+            hull.pop();
+        }
+        hull.push(p);
+    }
+    hull.pop();
+    hull
+}
+
+/// Area enclosed by `polygon` (shoelace formula), whatever its orientation.
+pub fn area(polygon: &[[f64; 2]]) -> f64 {
+    let n = polygon.len();
+    let twice: f64 = (0..n)
+        .map(|i| {
+            let (a, b) = (polygon[i], polygon[(i + 1) % n]);
+            a[0] * b[1] - b[0] * a[1]
+        })
+        .sum();
+    twice.abs() / 2.0
+}
+
+/// Mean of the vertices of `polygon`, where its label goes.
+pub fn center(polygon: &[[f64; 2]]) -> [f64; 2] {
+    let n = polygon.len().max(1) as f64;
+    let [x, y] = polygon
+        .iter()
+        .fold([0.0, 0.0], |[x, y], p| [x + p[0], y + p[1]]);
+    [x / n, y / n]
+}
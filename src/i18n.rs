@@ -0,0 +1,575 @@
+use std::sync::atomic::{AtomicU8, Ordering};
+
+/// Language of the interface text.
+#[derive(Clone, Copy, Default, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub enum Language {
+    #[default]
+    English,
+    Japanese,
+}
+
+impl Language {
+    pub const ALL: [Self; 2] = [Self::English, Self::Japanese];
+}
+
+/// Each language is named in itself, so it can be found whatever is selected.
+impl std::fmt::Display for Language {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::English => write!(f, "English"),
+            Self::Japanese => write!(f, "日本語"),
+        }
+    }
+}
+
+static CURRENT: AtomicU8 = AtomicU8::new(0);
+
+pub fn set_language(language: Language) {
+    CURRENT.store(language as u8, Ordering::Relaxed);
+}
+
+pub fn language() -> Language {
+    match CURRENT.load(Ordering::Relaxed) {
+        1 => Language::Japanese,
+        _ => Language::English,
+    }
+}
+
+/// Interface string in the current language. The English text is the key of the string
+/// table; strings without a translation are shown in English.
+pub fn tr(text: &'static str) -> &'static str {
+    match language() {
+        Language::English => text,
+        Language::Japanese => japanese(text).unwrap_or(text),
+    }
+}
+
+fn japanese(text: &str) -> Option<&'static str> {
+    Some(match text {
+        // Menus and top bar
+        "File" => "ファイル",
+        "Open dataset…" => "データセットを開く…",
+        "Quit" => "終了",
+        "Debug" => "デバッグ",
+        "Timing overlay" => "処理時間を表示",
+        "Preferences" => "環境設定",
+        "Filter Panel" => "フィルタ",
+        "Selection" => "選択",
+        "Datasets" => "データセット",
+        "Plot Style" => "プロットの書式",
+        "Compare Lists" => "リスト比較",
+        "Principal axes" => "主軸",
+        "Axes of largest to smallest spread of the neurons shown, two standard deviations long" => "表示中のニューロンの広がりが大きい順の軸（長さは標準偏差の2倍）",
+        "Rotate into principal frame" => "主軸座標系へ回転",
+        "Turn the positions about their mean so the axes of largest spread become x, y and z" => "広がりの大きい軸が x, y, z になるよう平均の周りに位置を回転",
+        "Symmetry" => "対称性",
+        "Bilateral Symmetry" => "左右対称性",
+        "Mark most asymmetric" => "非対称の大きい対を表示",
+        "Pairs joined by a dashed line on the canvas" => "キャンバス上で破線で結ばれる対",
+        "left/right pairs, the right one mirrored" => "組の左右対（右側を鏡映）",
+        "Class" => "クラス",
+        "Position" => "位置",
+        "Color" => "色",
+        "Edit atlas" => "アトラス編集",
+        "Edit Atlas" => "アトラス編集",
+        "Drag neurons on the canvas to move them, or select them to edit them here." => "キャンバス上でニューロンをドラッグして移動するか、選択してここで編集します。",
+        "No neurons selected" => "ニューロンが選択されていません",
+        "Revert all edits" => "すべての編集を元に戻す",
+        "Add neuron" => "ニューロンを追加",
+        "Enter a name" => "名前を入力してください",
+        "Names cannot contain spaces, commas or semicolons" => "名前に空白・カンマ・セミコロンは使えません",
+        "The atlas already has" => "アトラスに既にあります:",
+        "Coordinates must be numbers" => "座標は数値である必要があります",
+        "From position" => "位置から判定",
+        "From name" => "名前から判定",
+        "Yes" => "はい",
+        "No" => "いいえ",
+        "Added" => "追加",
+        "Removed" => "削除",
+        "Changed" => "変更",
+        "Review changes…" => "変更を確認…",
+        "Atlas Changes" => "アトラスの変更",
+        "The atlas has not been edited" => "アトラスは編集されていません",
+        "Arrows from original to edited positions" => "元の位置から編集後の位置への矢印",
+        "moved" => "移動",
+        "recolored" => "色変更",
+        "region changed" => "領域変更",
+        "Save atlas as…" => "アトラスを名前を付けて保存…",
+        "Moved" => "移動",
+        "Recolored" => "色変更",
+        "Edited" => "編集",
+        "Undo" => "元に戻す",
+        "Redo" => "やり直す",
+        "History" => "履歴",
+        "Undo or redo up to this edit" => "この編集まで元に戻す／やり直す",
+        "Remove from the atlas" => "アトラスから削除",
+        "Warnings" => "警告",
+        "Problems found in the file" => "ファイルで見つかった問題",
+        "No problems found in the loaded datasets" => "読み込んだデータセットに問題は見つかりませんでした",
+        "line" => "行",
+        "Focus this neuron" => "このニューロンにフォーカス",
+        "Duplicate name, first used on line" => "名前の重複、最初の使用は行",
+        "Coordinate is not a finite number" => "座標が有限の数値ではありません",
+        "Color channel outside 0–1 and 0–255" => "色チャンネルが 0–1 にも 0–255 にも収まりません",
+        "Or a column of #RRGGBB colors" => "または #RRGGBB 形式の色の列",
+        "Row could not be read and was skipped" => "行を読み取れず、スキップしました",
+        "Coordinates reach" => "座標の最大値",
+        "check that the frame and its scale match the units of the file" => "座標系と縮尺がファイルの単位に合っているか確認してください",
+        "Map columns" => "列の割り当て",
+        "does not name its columns. Choose the column of each field:" => "には列名がありません。各項目の列を選んでください:",
+        "Use for files named like" => "次の名前のファイルにも使う:",
+        "Load" => "読み込む",
+        "Name, x, y and z each need a column of their own" => "name、x、y、z にはそれぞれ別の列が必要です",
+        "Column" => "列",
+        "None" => "なし",
+        "First row names the columns" => "1行目は列名",
+        "Delimiter:" => "区切り文字:",
+        "Detect" => "自動検出",
+        "Comma" => "カンマ",
+        "Semicolon" => "セミコロン",
+        "Tab" => "タブ",
+        "Whitespace" => "空白",
+        "Metadata" => "メタデータ",
+        "Metadata column shown in the list" => "リストに表示するメタデータの列",
+        "Column:" => "列:",
+        "Range:" => "範囲:",
+        "Fit" => "合わせる",
+        "Metadata column" => "メタデータ列",
+        "Span the values of the column" => "列の値の範囲に合わせる",
+        "Activity" => "活動",
+        "Open activity…" => "活動データを開く…",
+        "A table with a row per neuron: its name, then its activity at each frame; or the fluorescence traces of an NWB file" => "ニューロンごとに一行、名前と各フレームの活動を並べた表、またはNWBファイルの蛍光トレース",
+        "no rows with a neuron name and its activity" => "ニューロン名と活動を含む行がありません",
+        "no numeric activity values" => "数値の活動データがありません",
+        "neurons" => "ニューロン",
+        "frames" => "フレーム",
+        "Close the activity" => "活動データを閉じる",
+        "Activity Traces" => "活動トレース",
+        "Pin" => "固定",
+        "Keep this trace in the plot" => "このトレースをプロットに残す",
+        "Hover a neuron to show its trace" => "ニューロンにカーソルを合わせるとトレースを表示",
+        "Pin selection" => "選択を固定",
+        "Keep the traces of the selected neurons" => "選択したニューロンのトレースを残す",
+        "Pinned:" => "固定:",
+        "Not in the activity data" => "活動データにありません",
+        "Unpin" => "固定を解除",
+        "Traces" => "トレース",
+        "Time" => "時間",
+        "Activity Heatmap" => "活動ヒートマップ",
+        "Heatmap" => "ヒートマップ",
+        "No neuron shown has activity" => "表示中のニューロンに活動データがありません",
+        "Neurons shown, by time; click a row to find the neuron, a column to go to its time" => "表示中のニューロンの時間変化。行をクリックでニューロンへ、列をクリックでその時刻へ移動",
+        "no fluorescence traces (ROI response series) in the file" => "蛍光トレース（ROI response series）がファイルにありません",
+        "Correlation network" => "相関ネットワーク",
+        "Join the shown neurons whose activity correlates, positive in red and negative in blue" => "活動が相関する表示中のニューロンを結ぶ（正の相関は赤、負の相関は青）",
+        "Slice sweep" => "スライス掃引",
+        "Stop sweeping" => "掃引を停止",
+        "Sweep" => "掃引",
+        "Move the slices from one end of the shown neurons to the other with the playback controls" => "再生コントロールで表示中のニューロンの端から端までスライスを移動",
+        "First frame" => "最初のフレーム",
+        "Previous frame" => "前のフレーム",
+        "Next frame" => "次のフレーム",
+        "Pause" => "一時停止",
+        "Play" => "再生",
+        "Space" => "スペース",
+        "Loop" => "ループ",
+        "Playback speed in frames per second" => "再生速度（フレーム毎秒）",
+        "xy view" => "xy ビュー",
+        "All views" => "全ビュー",
+        "Format" => "形式",
+        "Record" => "記録範囲",
+        "Frame rate" => "フレームレート",
+        "Width" => "幅",
+        "As shown" => "表示サイズ",
+        "Width in pixels; the height keeps the proportions" => "幅(ピクセル)。高さは縦横比を保ちます",
+        "the window was resized while recording" => "記録中にウィンドウのサイズが変わりました",
+        "no frames were recorded" => "フレームが記録されませんでした",
+        "ffmpeg was not found; install it and put it on the PATH to export videos" => "ffmpeg が見つかりません。動画を書き出すにはインストールして PATH に追加してください",
+        "Export Video" => "動画の書き出し",
+        "Frames are encoded by ffmpeg, which must be installed." => "フレームは ffmpeg でエンコードされます(要インストール)。",
+        "Export…" => "書き出し…",
+        "Saved" => "保存しました:",
+        "Recording" => "記録中",
+        "Export video…" => "動画を書き出し…",
+        "Head | tail split" => "頭部 | 尾部 分割",
+        "Show the pharyngeal nervous system (I1–I6, M, MC, MI, NSM) alone, then the other neurons, then all" => "咽頭神経系 (I1–I6, M, MC, MI, NSM) のみ、次にそれ以外、次に全てを表示",
+        "Type:" => "種類:",
+        "Sensory" => "感覚",
+        "Interneuron" => "介在",
+        "Motor" => "運動",
+        "Polymodal" => "多機能",
+        "Functional type" => "機能分類",
+        "Unknown" => "不明",
+        "Paired" => "対あり",
+        "Unpaired" => "対なし",
+        "Neurons with a left/right partner" => "左右の対をもつニューロン",
+        "Neurons without a partner, such as AVL, RID and AQR" => "AVL、RID、AQR など対をもたないニューロン",
+        "of" => "/",
+        "midline" => "正中",
+        "What the search being typed would show" => "入力中の検索で表示されるもの",
+        "Style preset" => "スタイルプリセット",
+        "Preset" => "プリセット",
+        "Save current style…" => "現在のスタイルを保存…",
+        "Coloring, depth cue, marker size and plot styles saved under a name; an asterisk marks changes since the preset was applied" => "色分け・奥行き表現・マーカーサイズ・プロットスタイルを名前付きで保存します。アスタリスクは適用後の変更を示します",
+        "Save the current style from the toolbar to make a preset." => "ツールバーから現在のスタイルを保存してプリセットを作成します。",
+        "Update" => "更新",
+        "Save the current settings into this preset" => "現在の設定をこのプリセットに保存",
+        "Revert" => "元に戻す",
+        "Export project bundle…" => "プロジェクトバンドルを書き出し…",
+        "One file with the datasets, their transforms and midlines, the edited atlas, the selections, the workspaces and the settings, to hand the project to a collaborator" => "データセットとその変換・正中線、編集したアトラス、選択、ワークスペース、設定を1つのファイルにまとめ、共同研究者に渡せるようにします",
+        "Project bundle" => "プロジェクトバンドル",
+        "not a bundle of this version" => "このバージョンのバンドルではありません",
+        "Annotate" => "注釈",
+        "Arrow" => "矢印",
+        "Text" => "テキスト",
+        "Freehand" => "フリーハンド",
+        "Text, then click a plot" => "テキストを入力してプロットをクリック",
+        "Line width; text is four times as tall" => "線の太さ。テキストの高さはその4倍",
+        "Remove the callouts of every view" => "すべてのビューの注釈を削除",
+        "Export figure…" => "図を書き出し…",
+        "The views laid out in one image with a shared legend and scale bar" => "凡例とスケールバーを共有し、ビューを1枚の画像に配置",
+        "Export Figure" => "図の書き出し",
+        "The neurons shown, with the selection and the callouts, in the current colors and orientation." => "表示中のニューロンを、選択と注釈とともに、現在の色と向きで描きます。",
+        "Orthographic" => "三面図",
+        "Side by side" => "横に並べる",
+        "Stacked" => "縦に並べる",
+        "Views" => "ビュー",
+        "Marker radius" => "マーカーの半径",
+        "Legend" => "凡例",
+        "Scale bar" => "スケールバー",
+        "Tick decimals" => "目盛りの小数桁",
+        "Digits after the decimal point of axis ticks; unchecked, as many as their spacing needs" => "軸の目盛りの小数点以下の桁数。オフのときは目盛りの間隔に応じた桁数",
+        "Thousands separators" => "桁区切り",
+        "Axis ticks such as 1,200 rather than 1200" => "軸の目盛りを 1200 ではなく 1,200 のように表示",
+        "Side:" => "側:",
+        "Selected only" => "選択したものだけ",
+        "Inside ROI" => "ROI の内側",
+        "shown, filtered by:" => "を表示中、フィルタ:",
+        "µm per atlas unit:" => "アトラス単位あたりの µm:",
+        "Copying is not available in this browser" => "このブラウザではコピーできません",
+        "Could not copy to the clipboard" => "クリップボードにコピーできませんでした",
+        "Clusters" => "クラスタ",
+        "Cluster" => "クラスタ",
+        "Noise" => "ノイズ",
+        "Run" => "実行",
+        "Radius" => "半径",
+        "Minimum neighbors" => "最小近傍数",
+        "Include colors" => "色も含める",
+        "Color weight" => "色の重み",
+        "Distance in atlas units that a full change of a color channel counts as" => "色チャンネルの最大変化に相当するアトラス単位の距離",
+        "\u{27F2} Reset views" => "\u{27F2} 表示をリセット",
+        "Restore the default bounds of all plots" => "すべてのプロットを既定の範囲に戻す",
+        "Navigate" => "移動",
+        "Ruler" => "定規",
+        "ROI box" => "ROIボックス",
+        "Neurons inside the box are selected" => "ボックス内のニューロンが選択されています",
+        "Draw a rectangle in another view to bound the remaining axis" => {
+            "別のビューで矩形を描いて残りの軸を指定してください"
+        }
+        "Crop" => "切り抜き",
+        "Clear ROI" => "ROIを解除",
+        "New workspace" => "新しいワークスペース",
+        "Name:" => "名前:",
+        "Close" => "閉じる",
+        // Side panel
+        "Body Side:" => "体側:",
+        "Left" => "左",
+        "Right" => "右",
+        "Both" => "両方",
+        "Color:" => "色:",
+        "List A/B" => "リストA/B",
+        "Depth (z)" => "奥行き (z)",
+        "Search: " => "検索: ",
+        "Press Enter to apply, Esc to discard" => "Enter で適用、Esc で取り消し",
+        "↑/↓ choose, Tab inserts" => "↑/↓ で選択、Tab で挿入",
+        "no matching neuron" => "該当するニューロンなし",
+        "did you mean" => "もしかして",
+        "Region keywords" => "領域キーワード",
+        "Region:" => "領域:",
+        "Head" => "頭部",
+        "Midbody" => "胴体中央",
+        "Tail" => "尾部",
+        "Pharyngeal" => "咽頭",
+        "Extrapharyngeal" => "咽頭外",
+        "Layers" => "レイヤー",
+        "Ganglion outlines" => "神経節の輪郭",
+        "Convex hulls of the neurons of each ganglion" => "各神経節のニューロンの凸包",
+        "Anterior" => "前神経節",
+        "Lateral" => "側神経節",
+        "Dorsal" => "背神経節",
+        "Ventral" => "腹神経節",
+        "Retrovesicular" => "後小胞神経節",
+        "Preanal" => "肛門前神経節",
+        "Dorsorectal" => "背直腸神経節",
+        "Lumbar" => "腰神経節",
+        "Pharynx" => "咽頭",
+        "area" => "面積",
+        "Outline the selection" => "選択範囲の輪郭",
+        "Draw the convex hull of the selected neurons in each view" => "各ビューで選択したニューロンの凸包を描く",
+        "Density contours" => "密度等高線",
+        "Kernel density estimate of the selection or of a class" => "選択範囲またはクラスのカーネル密度推定",
+        "Of:" => "対象:",
+        "selection, or a class like AVA" => "選択範囲、または AVA などのクラス",
+        "Bandwidth" => "バンド幅",
+        "Neurons" => "ニューロン",
+        "Remove filter" => "フィルタを削除",
+        "Nearest:" => "最寄り:",
+        "Right-click to copy" => "右クリックでコピー",
+        "Position copied" => "位置をコピーしました",
+        "copied" => "をコピーしました",
+        "Copy the listed neurons with their positions" => "一覧のニューロンを位置とともにコピー",
+        "neurons copied" => "個のニューロンをコピーしました",
+        "Copy" => "コピー",
+        "Copy the selected names" => "選択した名前をコピー",
+        "names copied" => "個の名前をコピーしました",
+        "Hover a plot to read out coordinates" => "プロットにカーソルを合わせると座標が表示されます",
+        "neurons shown" => "個のニューロンを表示中",
+        "selected" => "選択中",
+        "\u{2191}/\u{2193} move through the list, Enter selects and centers, \
+         \u{2190}/\u{2192} move the slices (without a focus, step through time), \
+         Esc clears the focus, Space plays" => {
+            "\u{2191}/\u{2193} で一覧を移動、Enter で選択して中央に表示、\
+             \u{2190}/\u{2192} でスライスを移動（フォーカスがなければ時間を移動）、\
+             Esc でフォーカスを解除、Space で再生"
+        }
+        // Datasets
+        "Loading" => "読み込み中",
+        "Cancel" => "キャンセル",
+        "NeuroPAL atlas" => "NeuroPALアトラス",
+        "Drop a CSV file with name, x, y, z and optional r, g, b columns to load a dataset." => {
+            "name, x, y, z 列（r, g, b 列は任意）を持つCSVファイルをドロップすると読み込まれます。"
+        }
+        "Overlay on the atlas" => "アトラスに重ねて表示",
+        "Watch" => "監視",
+        "Reload when the file changes on disk" => "ファイルが変更されたら再読み込み",
+        "Origin:" => "原点:",
+        "Atlas units" => "アトラス単位",
+        "Straightened (µm)" => "直線化 (µm)",
+        "Lab frame (µm)" => "実験座標系 (µm)",
+        "Review outliers…" => "外れ値を確認…",
+        "Displacement" => "変位",
+        "From:" => "基準:",
+        "To:" => "対象:",
+        "Show displacement arrows" => "変位の矢印を表示",
+        "Variability atlas" => "ばらつきアトラス",
+        "Build from datasets" => "データセットから作成",
+        "Load at least two datasets" => "データセットを2つ以上読み込んでください",
+        "Clear" => "クリア",
+        "Show positional spread (1 SD)" => "位置のばらつきを表示 (1 SD)",
+        "Add as dataset" => "データセットとして追加",
+        "Export CSV…" => "CSVを書き出す…",
+        "Most variable neurons:" => "ばらつきの大きいニューロン:",
+        "Matched neurons" => "対応したニューロン",
+        "Mean vector (x, y, z)" => "平均ベクトル (x, y, z)",
+        "Mean distance" => "平均距離",
+        "Median distance" => "距離の中央値",
+        "Largest shift" => "最大の変位",
+        "Midline (head to tail, x,y per line)" => "正中線 (頭から尾へ、1行に x,y)",
+        "Tail first" => "尾から",
+        "Dorsal on the right" => "背側を右に",
+        "Straighten" => "直線化",
+        "Load file…" => "ファイルを読み込む…",
+        "Remove" => "削除",
+        "Done editing" => "編集を終了",
+        "Edit on canvas" => "キャンバス上で編集",
+        "Drag points to move, Shift+click to append, right-click to delete" => {
+            "ドラッグで移動、Shift+クリックで追加、右クリックで削除"
+        }
+        "Lab-frame positions are not straightened." => "実験座標系の位置は直線化されません。",
+        // Outliers
+        "Outliers" => "外れ値",
+        "Reference:" => "参照:",
+        "Dataset:" => "データセット:",
+        "Select all flagged" => "検出されたものをすべて選択",
+        "Name" => "名前",
+        "Distance" => "距離",
+        // Plot style
+        "Orientation" => "向き",
+        "Layout" => "レイアウト",
+        "Floating windows" => "フローティングウィンドウ",
+        "Docked panels" => "ドッキングパネル",
+        "Left | right split" => "左右分割",
+        "Orthogonal views in own window" => "直交ビューを別ウィンドウに表示",
+        "Move them to a second monitor; not used when docked" => {
+            "2台目のモニターに移動できます。ドッキング時は使われません"
+        }
+        "Neuron list in own window" => "ニューロン一覧を別ウィンドウに表示",
+        "Apply to all plots" => "すべてのプロットに適用",
+        "Reset" => "リセット",
+        "Grid" => "グリッド",
+        "Axes" => "軸",
+        "Background" => "背景",
+        "Axis color" => "軸の色",
+        "Tick spacing" => "目盛りの間隔",
+        "Flip anterior–posterior" => "前後を反転",
+        "Flip dorsal–ventral" => "背腹を反転",
+        // Selection and comparison
+        "Paste neuron names or drop a text file:" => {
+            "ニューロン名を貼り付けるか、テキストファイルをドロップしてください:"
+        }
+        "Select" => "選択",
+        "Add" => "追加",
+        "Show selected only" => "選択したものだけを表示",
+        "List A" => "リストA",
+        "List B" => "リストB",
+        "Compare" => "比較",
+        "Only A" => "Aのみ",
+        "Only B" => "Bのみ",
+        "Neither" => "どちらでもない",
+        // Views
+        "Lateral (x-y)" => "側面 (x-y)",
+        "Anterior (z-y)" => "前面 (z-y)",
+        "Dorsal (x-z)" => "背面 (x-z)",
+        "Orthogonal views" => "直交ビュー",
+        "Anterior View (z-y)" => "前面ビュー (z-y)",
+        "Dorsal View (x-z)" => "背面ビュー (x-z)",
+        "Right–Left" => "右–左",
+        "Left–Right" => "左–右",
+        "Posterior–Anterior" => "後–前",
+        "Anterior–Posterior" => "前–後",
+        "Dorsal–Ventral" => "背–腹",
+        "Ventral–Dorsal" => "腹–背",
+        "\u{27F2} Reset view" => "\u{27F2} 表示をリセット",
+        "Restore the default bounds (or double-click the plot)" => {
+            "既定の範囲に戻す (プロットのダブルクリックでも可)"
+        }
+        "Restore layout" => "レイアウトを戻す",
+        "Show all views again (Esc)" => "すべてのビューを再表示 (Esc)",
+        "Maximize" => "最大化",
+        "Let this view fill the window" => "このビューをウィンドウいっぱいに表示",
+        // Help and tour
+        "Help" => "ヘルプ",
+        "Guided tour" => "ガイドツアー",
+        "Search" => "検索",
+        "Type part of a neuron name to filter the list and the plots." => {
+            "ニューロン名の一部を入力すると、一覧とプロットが絞り込まれます。"
+        }
+        "Body side" => "体側",
+        "Click to switch between the left side, the right side and both sides of the worm." => {
+            "クリックすると、線虫の左側・右側・両側を切り替えます。"
+        }
+        "Lateral view" => "側面ビュー",
+        "Scroll to zoom, drag to pan and hover a neuron to see its name. \
+         Double-click restores the view." => {
+            "スクロールで拡大縮小、ドラッグで移動、ニューロンにカーソルを合わせると名前が表示されます。\
+             ダブルクリックで表示が元に戻ります。"
+        }
+        "Slices" => "スライス",
+        "The anterior and dorsal views show a thin slab through the position hovered in \
+         the lateral view, so they follow the cursor." => {
+            "前面ビューと背面ビューには、側面ビューでカーソルを合わせた位置を通る薄い層が表示され、\
+             カーソルに追従します。"
+        }
+        "Back" => "戻る",
+        "Next" => "次へ",
+        "Finish" => "完了",
+        "Skip tour" => "ツアーを終了",
+        // Preferences
+        "Language" => "言語",
+        "Theme" => "テーマ",
+        "Display" => "表示",
+        "UI scale" => "UIの大きさ",
+        "Also Ctrl+Plus / Ctrl+Minus" => "Ctrl+プラス / Ctrl+マイナスでも変更できます",
+        "List font size" => "一覧の文字サイズ",
+        "Limit frame rate" => "フレームレートを制限",
+        "The window is only redrawn on input and while animating; this caps the animations" => {
+            "画面は入力があったときとアニメーション中だけ再描画されます。この設定はアニメーションの再描画を制限します"
+        }
+        "Markers" => "マーカー",
+        "Point size" => "点の大きさ",
+        "Outline width" => "輪郭の太さ",
+        "Contrasting outline keeping dim neurons visible; 0 draws none" => {
+            "暗いニューロンも見えるようにする対比色の輪郭。0で描画しません"
+        }
+        "Depth cue" => "奥行きの表現",
+        "Brightness of right-side neurons; 1 disables the cue" => {
+            "右側のニューロンの明るさ。1で無効になります"
+        }
+        "Hover tolerance" => "ホバーの許容距離",
+        "Slab half thickness" => "スラブの厚さ (半分)",
+        "Hover" => "ホバー",
+        "Slab" => "スラブ",
+        "Hover radius in atlas units" => "ホバー半径 (アトラス単位)",
+        "Half thickness of the anterior and dorsal slices in atlas units" => "前方・背側ビューのスライスの半分の厚さ (アトラス単位)",
+        "Hover ring" => "ホバーの円",
+        "Guide lines" => "ガイド線",
+        "Units" => "単位",
+        "Export directory:" => "書き出し先:",
+        "Restore defaults" => "既定値に戻す",
+        "High contrast" => "ハイコントラスト",
+        "Standard colors" => "標準の配色",
+        "Untitled" => "無題",
+        "New" => "新規",
+        "Delete" => "削除",
+        "Colors" => "配色",
+        "Accent" => "アクセント",
+        "Highlight" => "強調色",
+        "Plot background" => "プロットの背景",
+        "Marker outline" => "マーカーの輪郭",
+        "Outline color" => "輪郭の色",
+        "Thicker outlines, stronger highlights and larger hover targets" => {
+            "輪郭を太く、強調色を濃く、ホバーの対象を大きくします"
+        }
+        _ => return None,
+    })
+}
+
+/// Where the web app looks for a font with Japanese glyphs, relative to the page. None is
+/// bundled, as one covering the kanji is several megabytes.
+#[cfg(target_arch = "wasm32")]
+const WEB_JAPANESE_FONT: &str = "assets/japanese.ttf";
+
+/// Add a font with Japanese glyphs to the fonts of `ctx` as a fallback the first time
+/// Japanese is selected in it, since the fonts bundled with egui only cover Latin scripts;
+/// without one, Japanese text is drawn as boxes. It is read in the background, from the
+/// system on native and from `assets/japanese.ttf` next to the page on the web, and used
+/// from the frame after it arrives.
+pub fn load_fonts(ctx: &egui::Context, language: Language) {
+    let id = egui::Id::new("japanese_font_requested");
+    if language == Language::English
+        || ctx.data_mut(|d| std::mem::replace(d.get_temp_mut_or_default(id), true))
+    {
+        return;
+    }
+    let ctx = ctx.clone();
+    #[cfg(not(target_arch = "wasm32"))]
+    std::thread::spawn(move || {
+        const CANDIDATES: [&str; 7] = [
+            "/usr/share/fonts/opentype/noto/NotoSansCJK-Regular.ttc",
+            "/usr/share/fonts/noto-cjk/NotoSansCJK-Regular.ttc",
+            "/usr/share/fonts/google-noto-cjk/NotoSansCJK-Regular.ttc",
+            "/System/Library/Fonts/ヒラギノ角ゴシック W3.ttc",
+            "/System/Library/Fonts/Hiragino Sans GB.ttc",
+            "C:\\Windows\\Fonts\\YuGothM.ttc",
+            "C:\\Windows\\Fonts\\meiryo.ttc",
+        ];
+        match CANDIDATES.iter().find_map(|path| std::fs::read(path).ok()) {
+            Some(bytes) => add_japanese_font(&ctx, bytes),
+            None => log::warn!("No font with Japanese glyphs found"),
+        }
+    });
+    #[cfg(target_arch = "wasm32")]
+    wasm_bindgen_futures::spawn_local(async move {
+        match crate::web::fetch_bytes(WEB_JAPANESE_FONT).await {
+            Ok(bytes) => add_japanese_font(&ctx, bytes),
+            Err(err) => log::warn!("Failed to load {WEB_JAPANESE_FONT}: {err}"),
+        }
+    });
+}
+
+/// Add `bytes` after the fonts `ctx` already has, keeping any the host app installed.
+fn add_japanese_font(ctx: &egui::Context, bytes: Vec<u8>) {
+    use egui::epaint::text::{FontInsert, FontPriority, InsertFontFamily};
+    let families = [egui::FontFamily::Proportional, egui::FontFamily::Monospace];
+    ctx.add_font(FontInsert::new(
+        "japanese",
+        egui::FontData::from_owned(bytes),
+        (families.into_iter())
+            .map(|family| InsertFontFamily {
+                family,
+                priority: FontPriority::Lowest,
+            })
+            .collect(),
+    ));
+    ctx.request_repaint();
+}
@@ -0,0 +1,109 @@
+/// Neuron names in sorted order, so the names starting with a prefix form a contiguous run
+/// found by binary search instead of by testing every name.
+#[derive(Clone, Debug, Default)]
+pub struct NameIndex {
+    names: Vec<String>,
+}
+
+impl NameIndex {
+    pub fn new<'a>(names: impl IntoIterator<Item = &'a String>) -> Self {
+        let mut names: Vec<String> = names.into_iter().cloned().collect();
+        names.sort_unstable();
+        Self { names }
+    }
+
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+
+    /// Names starting with `prefix`, in order.
+    pub fn with_prefix(&self, prefix: &str) -> &[String] {
+        let start = self.names.partition_point(|n| n.as_str() < prefix);
+        let len = self.names[start..].partition_point(|n| n.starts_with(prefix));
+        &self.names[start..start + len]
+    }
+
+    /// Classes and names starting with `prefix`, in order, with the number of names each
+    /// one matches as a search prefix.
+    pub fn completions(&self, prefix: &str) -> Vec<(&str, usize)> {
+        let names = self.with_prefix(prefix);
+        let mut terms: Vec<&str> = names
+            .iter()
+            .filter_map(|n| class_of(n))
+            .filter(|c| c.starts_with(prefix))
+            .chain(names.iter().map(String::as_str))
+            .collect();
+        terms.sort_unstable();
+        terms.dedup();
+        terms
+            .into_iter()
+            .map(|term| (term, self.with_prefix(term).len()))
+            // A class of a single neuron adds nothing to its name.
+            .filter(|&(term, count)| {
+                count > 1
+                    || self
+                        .names
+                        .binary_search_by(|n| n.as_str().cmp(term))
+                        .is_ok()
+            })
+            .collect()
+    }
+
+    /// Up to `limit` names closest to `term` by edit distance, closest first, for a term
+    /// that matches nothing. Names further than half the length of `term` are left out.
+    pub fn suggestions(&self, term: &str, limit: usize) -> Vec<&str> {
+        let term = term.to_uppercase();
+        let max = (term.chars().count() / 2).max(1);
+        let mut close: Vec<(usize, &str)> = self
+            .names
+            .iter()
+            .map(|n| (edit_distance(&term, &n.to_uppercase()), n.as_str()))
+            .filter(|&(distance, _)| distance <= max)
+            .collect();
+        close.sort_unstable();
+        close.into_iter().take(limit).map(|(_, n)| n).collect()
+    }
+}
+
+/// Levenshtein distance between `a` and `b`, counting characters.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitute = diagonal + usize::from(ca != *cb);
+            diagonal = row[j + 1];
+            row[j + 1] = substitute.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+    row[b.len()]
+}
+
+/// `prefixes` in order without those covered by a shorter one, so that their runs in a
+/// [`NameIndex`] neither overlap nor repeat.
+pub fn disjoint_prefixes<'a>(prefixes: impl IntoIterator<Item = &'a str>) -> Vec<&'a str> {
+    let mut prefixes: Vec<&str> = prefixes.into_iter().collect();
+    prefixes.sort_unstable();
+    let mut disjoint: Vec<&str> = Vec::with_capacity(prefixes.len());
+    for prefix in prefixes {
+        if disjoint
+            .last()
+            .map_or(true, |last| !prefix.starts_with(last))
+        {
+            disjoint.push(prefix);
+        }
+    }
+    disjoint
+}
+
+/// The class of a bilateral neuron: its name without the final L or R (`AVAL` → `AVA`).
+pub fn class_of(name: &str) -> Option<&str> {
+    let class = name.strip_suffix(['L', 'R'])?;
+    (class.len() >= 3).then_some(class)
+}
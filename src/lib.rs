@@ -1,4 +1,68 @@
 #![warn(clippy::all, rust_2018_idioms)]
 
+mod action;
+mod activity;
+mod annotate;
 mod app;
+mod atlas;
+mod batch;
+mod builder;
+mod bundle;
+mod cluster;
+mod colormap;
+mod completion;
+mod dataset;
+mod density;
+mod edit;
+mod events;
+#[cfg(not(target_arch = "wasm32"))]
+mod figure;
+mod frame;
+mod functional;
+mod ganglion;
+mod hdf5;
+mod heatmap;
+mod hull;
+mod i18n;
+mod index;
+mod loader;
+mod mapping;
+mod metadata;
+mod midline;
+mod nwb;
+mod outlier;
+mod pca;
+#[cfg(not(target_arch = "wasm32"))]
+mod pdf;
+mod playback;
+mod prefs;
+mod profile;
+#[cfg(not(target_arch = "wasm32"))]
+mod raster;
+mod region;
+mod roi;
+mod selection;
+mod style;
+mod symmetry;
+mod theme;
+mod toast;
+mod tools;
+mod touch;
+mod tour;
+mod validate;
+mod variability;
+mod video;
+mod view;
+#[cfg(target_arch = "wasm32")]
+mod web;
+mod widget;
+pub use action::Action;
 pub use app::MyApp;
+pub use atlas::{Atlas, AtlasQuery, WormSide};
+pub use builder::AppBuilder;
+pub use dataset::Neuron;
+pub use events::{AppEvent, FilterState};
+pub use functional::FunctionalType;
+pub use index::NameIndex;
+pub use region::{BodyRegion, RegionFilter};
+pub use widget::NeuropalLensWidget;
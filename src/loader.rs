@@ -0,0 +1,127 @@
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::{mpsc, Arc};
+
+#[cfg(target_arch = "wasm32")]
+use crate::dataset::{self, TableParser};
+use crate::dataset::{Dataset, Neuron};
+use crate::mapping::TableFormat;
+
+/// Files at least this large are parsed on a background thread.
+pub const BACKGROUND_THRESHOLD: usize = 256 * 1024;
+/// Number of parsed rows handed to the UI at a time while a file is still loading, and on
+/// the web, parsed between two frames.
+const CHUNK_ROWS: usize = 5000;
+
+/// Name and contents of a file that arrives later, such as one picked in the browser's file
+/// dialog; dropped without a message if the user cancels.
+pub type PendingFile = mpsc::Receiver<Result<(String, Vec<u8>), String>>;
+
+enum Message {
+    /// Positions of newly parsed rows, as found in the file.
+    Chunk(Vec<[f32; 3]>),
+    Done(Result<Box<Dataset>, String>),
+}
+
+/// A dataset being parsed on a background thread, so the UI stays responsive and the user
+/// can follow the progress or cancel. Rows are streamed to the UI in chunks and can be
+/// drawn before the whole file is read. On the web, where there are no threads, a table is
+/// parsed a chunk at a time, yielding to the browser in between; JSON is parsed at once.
+pub struct Loading {
+    pub name: String,
+    /// File being read, attached to the dataset once loaded.
+    pub path: Option<std::path::PathBuf>,
+    /// Positions of the rows parsed so far, as found in the file.
+    pub partial: Vec<[f32; 3]>,
+    /// Fraction of the file parsed, as `f32` bits.
+    progress: Arc<AtomicU32>,
+    cancel: Arc<AtomicBool>,
+    messages: mpsc::Receiver<Message>,
+}
+
+impl Loading {
+    pub fn start(ctx: &egui::Context, name: String, bytes: Vec<u8>, format: TableFormat) -> Self {
+        let progress = Arc::new(AtomicU32::new(0));
+        let cancel = Arc::new(AtomicBool::new(false));
+        let (tx, messages) = mpsc::channel();
+        let on_row = {
+            let (progress, cancel, ctx, tx) =
+                (progress.clone(), cancel.clone(), ctx.clone(), tx.clone());
+            let mut chunk = Vec::with_capacity(CHUNK_ROWS);
+            move |fraction: f32, neuron: Option<&Neuron>| {
+                progress.store(fraction.to_bits(), Ordering::Relaxed);
+                if let Some(neuron) = neuron {
+                    chunk.push(neuron.position());
+                    if chunk.len() == CHUNK_ROWS {
+                        let _ = tx.send(Message::Chunk(std::mem::take(&mut chunk)));
+                        ctx.request_repaint();
+                    }
+                }
+                !cancel.load(Ordering::Relaxed)
+            }
+        };
+        let done = {
+            let ctx = ctx.clone();
+            move |dataset: Result<Dataset, String>| {
+                let _ = tx.send(Message::Done(dataset.map(Box::new)));
+                ctx.request_repaint();
+            }
+        };
+        let dataset_name = name.clone();
+        #[cfg(not(target_arch = "wasm32"))]
+        std::thread::spawn(move || {
+            done(Dataset::from_bytes_with_progress(
+                dataset_name,
+                &bytes,
+                format,
+                on_row,
+            ));
+        });
+        #[cfg(target_arch = "wasm32")]
+        wasm_bindgen_futures::spawn_local(async move {
+            let mut on_row = on_row;
+            let parsed = if dataset::is_json(&bytes) {
+                dataset::parse_neurons_with_progress(&bytes, &format, on_row)
+            } else {
+                let mut parser = TableParser::new(&bytes, &format);
+                loop {
+                    if let Some(parsed) = parser.step(CHUNK_ROWS, &mut on_row) {
+                        break parsed;
+                    }
+                    crate::web::yield_now().await;
+                }
+            };
+            done(parsed.map(|parsed| Dataset::from_parsed(dataset_name, &bytes, format, parsed)));
+        });
+        Self {
+            name,
+            path: None,
+            partial: Vec::new(),
+            progress,
+            cancel,
+            messages,
+        }
+    }
+
+    pub fn progress(&self) -> f32 {
+        f32::from_bits(self.progress.load(Ordering::Relaxed))
+    }
+
+    pub fn cancel(&self) {
+        self.cancel.store(true, Ordering::Relaxed);
+    }
+
+    /// Collect the rows parsed since the last call; returns the parsed dataset or error once
+    /// the background work is done.
+    pub fn poll(&mut self) -> Option<Result<Dataset, String>> {
+        loop {
+            match self.messages.try_recv() {
+                Ok(Message::Chunk(points)) => self.partial.extend(points),
+                Ok(Message::Done(result)) => return Some(result.map(|d| *d)),
+                Err(mpsc::TryRecvError::Empty) => return None,
+                Err(mpsc::TryRecvError::Disconnected) => {
+                    return Some(Err("loader stopped".to_owned()))
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,438 @@
+use std::borrow::Cow;
+
+use csv::StringRecord;
+
+use crate::dataset;
+use crate::i18n::tr;
+
+/// Fields of [`Neuron`](crate::dataset::Neuron) a column can be assigned to; the colors are
+/// optional.
+pub const FIELDS: [&str; 7] = ["name", "x", "y", "z", "r", "g", "b"];
+/// Rows of a file shown in the mapping dialog and checked before asking for a mapping.
+const PREVIEW_ROWS: usize = 8;
+/// Lines at the beginning of a file the delimiter is detected from.
+const DETECT_LINES: usize = 20;
+
+/// Character separating the fields of a table.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub enum Delimiter {
+    Comma,
+    Semicolon,
+    Tab,
+    /// Any run of spaces and tabs.
+    Whitespace,
+}
+
+impl Delimiter {
+    pub const ALL: [Self; 4] = [Self::Comma, Self::Semicolon, Self::Tab, Self::Whitespace];
+
+    /// The delimiter splitting the first lines of `bytes` into the same number of fields most
+    /// consistently, then into the most fields; whitespace if none of them splits the lines.
+    pub fn detect(bytes: &[u8]) -> Self {
+        let lines: Vec<&[u8]> = bytes
+            .split(|&b| b == b'\n')
+            .map(<[u8]>::trim_ascii)
+            .filter(|l| !l.is_empty() && !l.starts_with(b"#"))
+            .take(DETECT_LINES)
+            .collect();
+        let mut best = None;
+        // On a tie, a semicolon or tab wins over a comma, which may be a decimal comma.
+        for delimiter in [Self::Tab, Self::Semicolon, Self::Comma] {
+            let counts: Vec<usize> = lines
+                .iter()
+                .map(|l| l.iter().filter(|&&b| b == delimiter.byte()).count())
+                .collect();
+            let Some(common) = counts
+                .iter()
+                .filter(|&&c| c > 0)
+                .max_by_key(|&&c| counts.iter().filter(|&&d| d == c).count())
+            else {
+                continue;
+            };
+            let score = (counts.iter().filter(|&&c| c == *common).count(), *common);
+            if best.map_or(true, |(_, best)| score > best) {
+                best = Some((delimiter, score));
+            }
+        }
+        match best {
+            Some((delimiter, _)) => delimiter,
+            None if lines.iter().any(|l| l.contains(&b' ')) => Self::Whitespace,
+            None => Self::Comma,
+        }
+    }
+
+    /// The byte fields are split at once the table is [prepared](Self::prepare).
+    pub fn byte(self) -> u8 {
+        match self {
+            Self::Comma => b',',
+            Self::Semicolon => b';',
+            Self::Tab | Self::Whitespace => b'\t',
+        }
+    }
+
+    /// `bytes` ready to be split at [`byte`](Self::byte): runs of whitespace become single
+    /// tabs. Decimal commas are left to [`decimal_point`], field by field.
+    pub fn prepare(self, bytes: &[u8]) -> Cow<'_, [u8]> {
+        match self {
+            Self::Comma | Self::Semicolon | Self::Tab => Cow::Borrowed(bytes),
+            Self::Whitespace => {
+                let mut out = Vec::with_capacity(bytes.len());
+                for (i, line) in bytes.split(|&b| b == b'\n').enumerate() {
+                    if i > 0 {
+                        out.push(b'\n');
+                    }
+                    let fields = line
+                        .split(|b| b.is_ascii_whitespace())
+                        .filter(|f| !f.is_empty());
+                    for (j, field) in fields.enumerate() {
+                        if j > 0 {
+                            out.push(b'\t');
+                        }
+                        out.extend_from_slice(field);
+                    }
+                }
+                Cow::Owned(out)
+            }
+        }
+    }
+}
+
+/// A field of a numeric column with its decimal comma, as spreadsheets write them in many
+/// European locales, made a point. Fields with more than one comma or with a point already
+/// are left as they are.
+pub fn decimal_point(field: &str) -> Cow<'_, str> {
+    match field.matches(',').count() {
+        1 if !field.contains('.') => Cow::Owned(field.replacen(',', ".", 1)),
+        _ => Cow::Borrowed(field),
+    }
+}
+
+impl std::fmt::Display for Delimiter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Comma => f.write_str(tr("Comma")),
+            Self::Semicolon => f.write_str(tr("Semicolon")),
+            Self::Tab => f.write_str(tr("Tab")),
+            Self::Whitespace => f.write_str(tr("Whitespace")),
+        }
+    }
+}
+
+/// How a table is read: its delimiter, detected unless one is set, and its columns, named
+/// by its header unless they are mapped.
+#[derive(Clone, Debug, Default, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct TableFormat {
+    pub delimiter: Option<Delimiter>,
+    pub columns: Option<ColumnMapping>,
+}
+
+impl TableFormat {
+    pub fn delimiter_of(&self, bytes: &[u8]) -> Delimiter {
+        self.delimiter.unwrap_or_else(|| Delimiter::detect(bytes))
+    }
+}
+
+/// Combo box choosing a delimiter, or detecting it for `None`, showing the one `detected`
+/// where it is known; returns whether the choice changed.
+pub fn delimiter_combo(
+    ui: &mut egui::Ui,
+    id: &str,
+    delimiter: &mut Option<Delimiter>,
+    detected: Option<Delimiter>,
+) -> bool {
+    let before = *delimiter;
+    let detect = match detected {
+        Some(detected) => format!("{} ({detected})", tr("Detect")),
+        None => tr("Detect").to_owned(),
+    };
+    egui::ComboBox::from_id_salt(id)
+        .selected_text(delimiter.map_or(detect.clone(), |d| d.to_string()))
+        .show_ui(ui, |ui| {
+            ui.selectable_value(delimiter, None, detect);
+            for d in Delimiter::ALL {
+                ui.selectable_value(delimiter, Some(d), d.to_string());
+            }
+        });
+    *delimiter != before
+}
+
+/// Which column of a table holds each field of a neuron, for tables whose header does not
+/// name them.
+#[derive(Clone, Debug, Default, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct ColumnMapping {
+    /// Column of each of [`FIELDS`], counted from 0.
+    pub columns: [Option<usize>; 7],
+    /// The first row names the columns and is skipped.
+    pub header: bool,
+}
+
+impl ColumnMapping {
+    /// A first guess from the beginning of a table: columns named like a field are taken for
+    /// it, and the name and coordinates otherwise go to the first text column and the
+    /// numeric columns after it.
+    pub fn guess(rows: &[StringRecord]) -> Self {
+        let header = rows.first().is_some_and(|row| {
+            row.iter()
+                .skip(1)
+                .any(|f| !f.trim().is_empty() && f.trim().parse::<f32>().is_err())
+        });
+        let mut columns = [None; 7];
+        if header {
+            for (column, title) in rows[0].iter().enumerate() {
+                let words: Vec<String> = title
+                    .split(|c: char| !c.is_ascii_alphanumeric())
+                    .map(|w| w.to_ascii_lowercase())
+                    .collect();
+                let field = FIELDS.iter().position(|&f| {
+                    words.iter().any(|w| {
+                        w == f
+                            || (f == "name" && ["neuron", "label", "cell"].contains(&&w[..]))
+                            || (f == "r" && ["red", "color", "colour", "hex"].contains(&&w[..]))
+                            || (f == "g" && w == "green")
+                            || (f == "b" && w == "blue")
+                    })
+                });
+                if let Some(field) = field.filter(|&f| columns[f].is_none()) {
+                    columns[field] = Some(column);
+                }
+            }
+        }
+        let Some(sample) = rows.get(header as usize) else {
+            return Self { columns, header };
+        };
+        let numeric = |f: &str| f.trim().parse::<f32>().is_ok();
+        if columns[0].is_none() {
+            columns[0] = sample.iter().position(|f| !numeric(f));
+        }
+        let numbers: Vec<usize> = (0..sample.len())
+            .filter(|&c| Some(c) > columns[0] && sample.get(c).is_some_and(numeric))
+            .collect();
+        for field in 1..FIELDS.len() {
+            if columns[field].is_none() {
+                columns[field] = numbers
+                    .iter()
+                    .copied()
+                    .find(|&c| !columns.contains(&Some(c)));
+            }
+        }
+        Self { columns, header }
+    }
+
+    /// The name and the three coordinates each have a column of their own.
+    pub fn is_complete(&self) -> bool {
+        let mut used: Vec<usize> = self.columns.iter().flatten().copied().collect();
+        used.sort_unstable();
+        used.dedup();
+        self.columns[..4].iter().all(Option::is_some)
+            && used.len() == self.columns.iter().flatten().count()
+    }
+
+    /// A header record naming the mapped columns after the fields of a neuron, and leaving
+    /// the others unnamed so they are ignored.
+    pub fn header_record(&self) -> StringRecord {
+        let width = self.columns.iter().flatten().max().map_or(0, |c| c + 1);
+        let mut names = vec![""; width];
+        for (field, column) in FIELDS.iter().zip(self.columns) {
+            if let Some(column) = column {
+                names[column] = field;
+            }
+        }
+        StringRecord::from(names)
+    }
+
+    /// Editors assigning a column of `rows` to each field.
+    pub fn ui(&mut self, ui: &mut egui::Ui, rows: &[StringRecord]) {
+        let width = rows.iter().map(StringRecord::len).max().unwrap_or(0);
+        let title = |column: usize| match rows.first() {
+            Some(row) if self.header => {
+                format!("{}: {}", column + 1, row.get(column).unwrap_or(""))
+            }
+            _ => format!("{} {}", tr("Column"), column + 1),
+        };
+        egui::Grid::new("column_mapping")
+            .num_columns(2)
+            .show(ui, |ui| {
+                for (field, name) in FIELDS.iter().enumerate() {
+                    let label = ui.label(*name);
+                    if *name == "r" {
+                        label.on_hover_text(tr("Or a column of #RRGGBB colors"));
+                    }
+                    let mut column = self.columns[field];
+                    egui::ComboBox::from_id_salt(("column_mapping", field))
+                        .selected_text(column.map_or(tr("None").to_owned(), title))
+                        .show_ui(ui, |ui| {
+                            if field >= 4 {
+                                ui.selectable_value(&mut column, None, tr("None"));
+                            }
+                            for c in 0..width {
+                                ui.selectable_value(&mut column, Some(c), title(c));
+                            }
+                        });
+                    self.columns[field] = column;
+                    ui.end_row();
+                }
+            });
+        ui.checkbox(&mut self.header, tr("First row names the columns"));
+    }
+
+    /// The first rows of `rows` as a table, with the field each mapped column goes to above
+    /// it.
+    pub fn preview_ui(&self, ui: &mut egui::Ui, rows: &[StringRecord]) {
+        let width = rows.iter().map(StringRecord::len).max().unwrap_or(0);
+        egui::ScrollArea::horizontal().show(ui, |ui| {
+            egui::Grid::new("column_mapping_preview")
+                .striped(true)
+                .show(ui, |ui| {
+                    for c in 0..width {
+                        let field = self.columns.iter().position(|&m| m == Some(c));
+                        ui.label(egui::RichText::new(field.map_or("", |f| FIELDS[f])).strong());
+                    }
+                    ui.end_row();
+                    for (i, row) in rows.iter().enumerate() {
+                        for c in 0..width {
+                            let text = row.get(c).unwrap_or("");
+                            if i == 0 && self.header {
+                                ui.weak(text);
+                            } else {
+                                ui.label(text);
+                            }
+                        }
+                        ui.end_row();
+                    }
+                });
+        });
+    }
+}
+
+/// A file waiting for its columns to be assigned before it is loaded.
+pub struct PendingMapping {
+    pub name: String,
+    pub bytes: Vec<u8>,
+    pub path: Option<std::path::PathBuf>,
+    /// First rows of the file, as split with the delimiter of `format`.
+    pub rows: Vec<StringRecord>,
+    /// The format to read the file with; its columns are always mapped.
+    pub format: TableFormat,
+    /// Keep the format for files named after the same [`file_pattern`].
+    pub remember: bool,
+}
+
+impl PendingMapping {
+    pub fn new(
+        name: String,
+        bytes: Vec<u8>,
+        path: Option<std::path::PathBuf>,
+        delimiter: Option<Delimiter>,
+    ) -> Self {
+        let mut pending = Self {
+            name,
+            bytes,
+            path,
+            rows: Vec::new(),
+            format: TableFormat {
+                delimiter,
+                columns: None,
+            },
+            remember: true,
+        };
+        pending.split();
+        pending
+    }
+
+    /// Split the first rows again after the delimiter changed, and guess their columns.
+    pub fn split(&mut self) {
+        let delimiter = self.format.delimiter_of(&self.bytes);
+        self.rows = dataset::preview_rows(&self.bytes, delimiter, PREVIEW_ROWS);
+        self.format.columns = Some(ColumnMapping::guess(&self.rows));
+    }
+}
+
+/// Whether `bytes` hold a table whose columns cannot be told without a mapping: none of its
+/// first rows is read as a neuron, yet they have numbers in them.
+pub fn needs_mapping(bytes: &[u8], format: &TableFormat) -> bool {
+    if dataset::is_json(bytes) {
+        return false;
+    }
+    // The first lines, with room for comments and rows that cannot be read.
+    let end = (bytes.iter().enumerate())
+        .filter(|(_, &b)| b == b'\n')
+        .nth(4 * PREVIEW_ROWS)
+        .map_or(bytes.len(), |(i, _)| i);
+    let head = &bytes[..end];
+    let rows = dataset::preview_rows(head, format.delimiter_of(head), PREVIEW_ROWS);
+    let tabular = rows
+        .iter()
+        .any(|r| r.iter().filter(|f| f.trim().parse::<f32>().is_ok()).count() >= 3);
+    tabular && dataset::parse_neurons_with_progress(head, format, |_, _| true).is_err()
+}
+
+/// Name of a file with every run of digits replaced by `#`, so the files of one series,
+/// such as `worm_3.csv` and `worm_12.csv`, share a pattern.
+pub fn file_pattern(name: &str) -> String {
+    let mut pattern = String::with_capacity(name.len());
+    for c in name.chars() {
+        if !c.is_ascii_digit() {
+            pattern.push(c);
+        } else if !pattern.ends_with('#') {
+            pattern.push('#');
+        }
+    }
+    pattern
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detect() {
+        assert_eq!(
+            Delimiter::detect(b"name,x,y,z\nAVAL,1,2,3\n"),
+            Delimiter::Comma
+        );
+        assert_eq!(
+            Delimiter::detect(b"name\tx\ty\tz\nAVAL\t1\t2\t3\n"),
+            Delimiter::Tab
+        );
+        assert_eq!(
+            Delimiter::detect(b"name x  y z\nAVAL 1 2   3\n"),
+            Delimiter::Whitespace
+        );
+        // A decimal comma in every row does not outvote the semicolons.
+        let semicolon = b"name;x;y;z\nAVAL;1,5;2,5;3\nAVAR;1,5;2;3,5\n";
+        assert_eq!(Delimiter::detect(semicolon), Delimiter::Semicolon);
+        // Comment lines are left out.
+        assert_eq!(
+            Delimiter::detect(b"# a; b; c\nAVAL,1,2,3\n"),
+            Delimiter::Comma
+        );
+    }
+
+    #[test]
+    fn prepare() {
+        let semicolon = b"AVAL;1,5;x, y";
+        assert_eq!(&*Delimiter::Semicolon.prepare(semicolon), semicolon);
+        assert_eq!(&*Delimiter::Comma.prepare(b"AVAL,1,2"), b"AVAL,1,2");
+        let whitespace = Delimiter::Whitespace.prepare(b"  AVAL  1\t 2\nAVAR 3 4");
+        assert_eq!(&*whitespace, b"AVAL\t1\t2\nAVAR\t3\t4");
+    }
+
+    #[test]
+    fn decimal_point() {
+        assert_eq!(super::decimal_point("1,5"), "1.5");
+        assert_eq!(super::decimal_point("-0,25e3"), "-0.25e3");
+        assert_eq!(super::decimal_point("2"), "2");
+        assert_eq!(super::decimal_point("1,234.5"), "1,234.5");
+        assert_eq!(super::decimal_point("1,2,3"), "1,2,3");
+    }
+
+    #[test]
+    fn semicolon_table_keeps_commas_outside_numbers() {
+        let table = b"name;x;y;z;note\nAVAL, left;1,5;-2,25;3;ventral, anterior\n";
+        let neurons = dataset::parse_neurons(table).unwrap();
+        let neuron = &neurons["AVAL, left"];
+        assert_eq!([neuron.x, neuron.y, neuron.z], [1.5, -2.25, 3.0]);
+        assert_eq!(neuron.meta["note"], "ventral, anterior");
+    }
+}
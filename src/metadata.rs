@@ -0,0 +1,188 @@
+use std::collections::{BTreeSet, HashMap};
+
+use crate::colormap::{Colorbar, Colormap};
+use crate::dataset::{self, Dataset, Neuron};
+use crate::i18n::tr;
+
+/// Metadata of the neurons of the atlas and of the loaded datasets, looked up by name: the
+/// extra columns of the files they were read from.
+#[derive(Clone, Copy)]
+pub struct Metadata<'a> {
+    atlas: &'a HashMap<String, Neuron>,
+    datasets: &'a [Dataset],
+}
+
+impl<'a> Metadata<'a> {
+    pub fn new(atlas: &'a HashMap<String, Neuron>, datasets: &'a [Dataset]) -> Self {
+        Self { atlas, datasets }
+    }
+
+    /// Names of every metadata column, sorted.
+    pub fn keys(&self) -> BTreeSet<String> {
+        let mut keys = dataset::meta_keys(self.atlas);
+        for dataset in self.datasets {
+            keys.extend(dataset.columns.iter().cloned());
+        }
+        keys
+    }
+
+    /// Names of the columns with a number among their values, sorted.
+    pub fn numeric_keys(&self) -> BTreeSet<String> {
+        let sources = std::iter::once(self.atlas).chain(self.datasets.iter().map(|d| &d.raw));
+        let mut keys = BTreeSet::new();
+        for neurons in sources {
+            for neuron in neurons.values() {
+                for (key, value) in &neuron.meta {
+                    if !keys.contains(key) && value.trim().parse::<f32>().is_ok() {
+                        keys.insert(key.clone());
+                    }
+                }
+            }
+        }
+        keys
+    }
+
+    /// Every value given for the neuron `name`, with its column and the dataset it is from,
+    /// `None` for the atlas.
+    pub fn of(&self, name: &str) -> Vec<(&'a str, &'a str, Option<&'a str>)> {
+        let atlas = self.atlas.get(name).map(|n| (n, None));
+        let datasets =
+            (self.datasets.iter()).filter_map(|d| Some((d.raw.get(name)?, Some(d.name.as_str()))));
+        atlas
+            .into_iter()
+            .chain(datasets)
+            .flat_map(|(n, source)| {
+                (n.meta.iter()).map(move |(k, v)| (k.as_str(), v.as_str(), source))
+            })
+            .collect()
+    }
+
+    /// The value of the column `key` for the neuron `name`, from the atlas or else from the
+    /// first dataset that has one.
+    pub fn get(&self, name: &str, key: &str) -> Option<&'a str> {
+        let atlas = self.atlas.get(name);
+        let datasets = self.datasets.iter().filter_map(|d| d.raw.get(name));
+        atlas
+            .into_iter()
+            .chain(datasets)
+            .find_map(|n| n.meta.get(key))
+            .map(String::as_str)
+    }
+
+    /// Whether the atlas or any dataset holds `value` in the column `key` for the neuron
+    /// `name`, both compared ignoring case.
+    pub fn matches(&self, name: &str, key: &str, value: &str) -> bool {
+        let atlas = self.atlas.get(name);
+        let datasets = self.datasets.iter().filter_map(|d| d.raw.get(name));
+        atlas.into_iter().chain(datasets).any(|n| {
+            (n.meta.iter())
+                .any(|(k, v)| k.eq_ignore_ascii_case(key) && v.eq_ignore_ascii_case(value))
+        })
+    }
+}
+
+/// Coloring of the neurons by a numeric metadata column mapped through a colormap.
+#[derive(Clone, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct ColumnColoring {
+    pub column: Option<String>,
+    pub colormap: Colormap,
+    /// Values at the ends of the colormap; values beyond are clamped.
+    pub min: f32,
+    pub max: f32,
+}
+
+impl Default for ColumnColoring {
+    fn default() -> Self {
+        Self {
+            column: None,
+            colormap: Colormap::Viridis,
+            min: 0.0,
+            max: 1.0,
+        }
+    }
+}
+
+impl std::hash::Hash for ColumnColoring {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        (&self.column, self.colormap).hash(state);
+        (self.min.to_bits(), self.max.to_bits()).hash(state);
+    }
+}
+
+impl ColumnColoring {
+    /// Value of the column for the neuron `name`, if it is a number.
+    pub fn value(&self, meta: &Metadata<'_>, name: &str) -> Option<f32> {
+        let value = meta.get(name, self.column.as_deref()?)?;
+        value.trim().parse().ok().filter(|v: &f32| v.is_finite())
+    }
+
+    pub fn colorbar(&self) -> Option<Colorbar> {
+        Some(Colorbar {
+            label: self.column.clone()?,
+            min: self.min,
+            max: self.max,
+            colormap: self.colormap,
+        })
+    }
+
+    /// Set the range to the values of the column over the neurons `names`.
+    pub fn fit<'n>(&mut self, meta: &Metadata<'_>, names: impl Iterator<Item = &'n String>) {
+        let (min, max) = names
+            .filter_map(|name| self.value(meta, name))
+            .fold((f32::MAX, f32::MIN), |(lo, hi), v| (lo.min(v), hi.max(v)));
+        if min <= max {
+            (self.min, self.max) = (min, max);
+        }
+    }
+
+    /// Editors for the column, the colormap and its range, fitted to the values over the
+    /// neurons `names` when another column is chosen.
+    pub fn ui<'n>(
+        &mut self,
+        ui: &mut egui::Ui,
+        meta: Metadata<'_>,
+        names: impl Iterator<Item = &'n String> + Clone,
+    ) {
+        let before = self.column.clone();
+        ui.horizontal(|ui| {
+            ui.label(tr("Column:"));
+            egui::ComboBox::from_id_salt("color_column")
+                .selected_text(self.column.as_deref().unwrap_or(tr("None")))
+                .show_ui(ui, |ui| {
+                    for key in meta.numeric_keys() {
+                        ui.selectable_value(&mut self.column, Some(key.clone()), key);
+                    }
+                });
+            egui::ComboBox::from_id_salt("color_colormap")
+                .selected_text(self.colormap.to_string())
+                .show_ui(ui, |ui| {
+                    for colormap in Colormap::ALL {
+                        ui.selectable_value(&mut self.colormap, colormap, colormap.to_string());
+                    }
+                });
+        });
+        if self.column != before {
+            self.fit(&meta, names.clone());
+        }
+        ui.horizontal(|ui| {
+            let speed = ((self.max - self.min).abs() / 100.0).max(1e-3);
+            ui.label(tr("Range:"));
+            ui.add(egui::DragValue::new(&mut self.min).speed(speed));
+            ui.label("–");
+            ui.add(egui::DragValue::new(&mut self.max).speed(speed));
+            if ui
+                .small_button(tr("Fit"))
+                .on_hover_text(tr("Span the values of the column"))
+                .clicked()
+            {
+                self.fit(&meta, names);
+            }
+        });
+    }
+}
+
+/// The column and value of a `column=value` search term.
+pub fn search_term(term: &str) -> Option<(&str, &str)> {
+    term.split_once('=').filter(|(key, _)| !key.is_empty())
+}
@@ -0,0 +1,191 @@
+use egui::Color32;
+use egui_plot::{Line, MarkerShape, PlotPoint, PlotPoints, PlotUi, Points};
+
+const MIDLINE_COLOR: Color32 = Color32::from_rgb(255, 112, 67);
+
+/// Midline (backbone) of a curved worm, given as spline control points in the lab-frame
+/// xy plane, ordered from head to tail.
+#[derive(Clone, Default, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct Midline {
+    pub points: Vec<[f32; 2]>,
+    /// The control points run from tail to head.
+    pub reversed: bool,
+    /// The dorsal side lies to the right of the head-to-tail direction.
+    pub flip_dv: bool,
+}
+
+impl Midline {
+    /// Parse one `x,y` (or whitespace/semicolon separated) control point per line.
+    /// Lines that don't start with two numbers, such as a header, are skipped.
+    pub fn parse(text: &str) -> Result<Vec<[f32; 2]>, String> {
+        let points: Vec<[f32; 2]> = text
+            .lines()
+            .filter_map(|line| {
+                let mut fields = line
+                    .split(|c: char| c == ',' || c == ';' || c.is_whitespace())
+                    .filter(|x| !x.is_empty())
+                    .map(|x| x.parse::<f32>());
+                match (fields.next(), fields.next()) {
+                    (Some(Ok(x)), Some(Ok(y))) => Some([x, y]),
+                    _ => None,
+                }
+            })
+            .collect();
+        if points.len() < 2 {
+            return Err("a midline needs at least two x,y points".to_owned());
+        }
+        Ok(points)
+    }
+
+    pub fn to_text(&self) -> String {
+        self.points
+            .iter()
+            .map(|[x, y]| format!("{x},{y}"))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Points along a Catmull-Rom spline through the control points, head first.
+    pub fn sample(&self, steps_per_span: usize) -> Vec<[f32; 2]> {
+        let mut pts = self.points.clone();
+        if self.reversed {
+            pts.reverse();
+        }
+        if pts.len() < 3 {
+            return pts;
+        }
+        let at = |i: isize| pts[i.clamp(0, pts.len() as isize - 1) as usize];
+        let mut out = Vec::with_capacity(pts.len() * steps_per_span + 1);
+        for i in 0..pts.len() as isize - 1 {
+            let (p0, p1, p2, p3) = (at(i - 1), at(i), at(i + 1), at(i + 2));
+            for step in 0..steps_per_span {
+                let t = step as f32 / steps_per_span as f32;
+                let (t2, t3) = (t * t, t * t * t);
+                let c = |k: usize| {
+                    0.5 * (2.0 * p1[k]
+                        + (-p0[k] + p2[k]) * t
+                        + (2.0 * p0[k] - 5.0 * p1[k] + 4.0 * p2[k] - p3[k]) * t2
+                        + (-p0[k] + 3.0 * p1[k] - 3.0 * p2[k] + p3[k]) * t3)
+                };
+                out.push([c(0), c(1)]);
+            }
+        }
+        out.push(*pts.last().unwrap_or(&[0.0, 0.0]));
+        out
+    }
+
+    /// Map a lab-frame position into straightened coordinates: arc length from the head
+    /// along the midline, signed distance from it (dorsal positive) and the unchanged depth.
+    pub fn straighten(&self, samples: &[[f32; 2]], p: [f32; 3]) -> [f32; 3] {
+        let mut best = (f32::INFINITY, 0.0, 0.0);
+        let mut arc = 0.0;
+        for w in samples.windows(2) {
+            let (a, b) = (w[0], w[1]);
+            let d = [b[0] - a[0], b[1] - a[1]];
+            let len = (d[0] * d[0] + d[1] * d[1]).sqrt();
+            if len <= f32::EPSILON {
+                continue;
+            }
+            let t = (((p[0] - a[0]) * d[0] + (p[1] - a[1]) * d[1]) / (len * len)).clamp(0.0, 1.0);
+            let q = [a[0] + t * d[0], a[1] + t * d[1]];
+            let off = [p[0] - q[0], p[1] - q[1]];
+            let dist2 = off[0] * off[0] + off[1] * off[1];
+            if dist2 < best.0 {
+                // Positive to the left of the head-to-tail direction.
+                let side = (d[0] * off[1] - d[1] * off[0]) / len;
+                best = (dist2, arc + t * len, side);
+            }
+            arc += len;
+        }
+        let (_, s, side) = best;
+        let dv = if self.flip_dv { -side } else { side };
+        [s, dv, p[2]]
+    }
+}
+
+/// Drag, add and remove midline control points inside a plot.
+///
+/// Drag a control point to move it, Shift+click to append a point at the tail end and
+/// right-click a point to delete it. `to_plot`/`from_plot` map between the stored
+/// coordinates and the plot. Returns `true` if the midline changed.
+pub fn edit_in_plot(
+    plot_ui: &mut PlotUi,
+    midline: &mut Midline,
+    to_plot: impl Fn([f32; 2]) -> [f64; 2],
+    from_plot: impl Fn([f64; 2]) -> [f32; 2],
+) -> bool {
+    const HIT_RADIUS: f32 = 8.0;
+    let response = plot_ui.response().clone();
+    let drag_id = response.id.with("midline_drag");
+    let mut dragging: Option<usize> = plot_ui.ctx().data(|d| d.get_temp(drag_id)).flatten();
+    let dragging = &mut dragging;
+    let pointer = plot_ui.pointer_coordinate();
+    let hovered = response.hover_pos().and_then(|pos| {
+        midline
+            .points
+            .iter()
+            .map(|p| {
+                let screen = plot_ui.screen_from_plot(PlotPoint::from(to_plot(*p)));
+                screen.distance(pos)
+            })
+            .enumerate()
+            .filter(|(_, d)| *d < HIT_RADIUS)
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(i, _)| i)
+    });
+
+    let mut changed = false;
+    if response.drag_started() {
+        *dragging = hovered;
+    }
+    if let (Some(i), Some(pointer)) = (*dragging, pointer) {
+        if response.dragged() && i < midline.points.len() {
+            midline.points[i] = from_plot([pointer.x, pointer.y]);
+            changed = true;
+        }
+    }
+    if response.drag_stopped() {
+        *dragging = None;
+    }
+    let current = *dragging;
+    plot_ui.ctx().data_mut(|d| d.insert_temp(drag_id, current));
+    if response.secondary_clicked() {
+        if let Some(i) = hovered {
+            midline.points.remove(i);
+            changed = true;
+        }
+    }
+    if response.clicked() && plot_ui.ctx().input(|i| i.modifiers.shift) {
+        if let Some(pointer) = pointer {
+            midline.points.push(from_plot([pointer.x, pointer.y]));
+            changed = true;
+        }
+    }
+
+    let curve: Vec<_> = midline.sample(16).into_iter().map(&to_plot).collect();
+    plot_ui.line(
+        Line::new(PlotPoints::new(curve))
+            .color(MIDLINE_COLOR)
+            .width(2.0),
+    );
+    let handles: Vec<_> = midline.points.iter().map(|p| to_plot(*p)).collect();
+    plot_ui.points(
+        Points::new(PlotPoints::new(handles))
+            .shape(MarkerShape::Square)
+            .color(MIDLINE_COLOR)
+            .radius(4.0),
+    );
+    if let Some(i) = hovered.or(*dragging) {
+        if let Some(p) = midline.points.get(i) {
+            plot_ui.points(
+                Points::new(PlotPoints::new(vec![to_plot(*p)]))
+                    .shape(MarkerShape::Square)
+                    .color(Color32::WHITE)
+                    .filled(false)
+                    .radius(6.0),
+            );
+        }
+    }
+    changed
+}
@@ -0,0 +1,152 @@
+use std::collections::{BTreeMap, HashSet};
+
+use crate::activity::Activity;
+use crate::hdf5::File;
+use crate::i18n::tr;
+
+/// Columns of a segmentation table that may hold the neuron identity of each ROI, in order
+/// of preference.
+const LABEL_COLUMNS: [&str; 6] = [
+    "ID_labels",
+    "neuron_name",
+    "labels",
+    "label",
+    "names",
+    "name",
+];
+/// Most groups searched for traces, so files with many objects open quickly.
+const MAX_GROUPS: usize = 10_000;
+
+/// Read the first ROI response series of an NWB file, such as the `DfOverF` or
+/// `Fluorescence` traces of the `ophys` module, as activity.
+///
+/// Each ROI is named by the label of its row in the segmentation table it refers to, or
+/// `ROI` and its row where the table has no label column.
+pub fn read_activity(name: &str, bytes: &[u8]) -> Result<Activity, String> {
+    let file = File::open(bytes)?;
+    let (path, series) = find_series(&file)?
+        .ok_or_else(|| tr("no fluorescence traces (ROI response series) in the file").to_owned())?;
+    let data_address = file.member(series, "data")?.ok_or("series without data")?;
+    let data = file.read(data_address)?;
+    let values = data.numbers().ok_or("traces are not numbers")?;
+    let (frames, columns) = match data.shape[..] {
+        [frames] => (frames, 1),
+        [frames, columns] => (frames, columns),
+        _ => return Err(format!("traces of unexpected shape {:?}", data.shape)),
+    };
+    let (rows, labels) = match file.member(series, "rois")? {
+        Some(rois) => {
+            let rows = (file.read(rois)?.numbers().unwrap_or_default().iter())
+                .map(|&r| r as usize)
+                .collect();
+            (rows, table_labels(&file, rois)?)
+        }
+        None => (Vec::new(), Vec::new()),
+    };
+    let mut traces = BTreeMap::new();
+    for column in 0..columns {
+        let row = rows.get(column).copied().unwrap_or(column);
+        let label = (labels.get(row))
+            .map(|l| l.trim())
+            .filter(|l| !l.is_empty())
+            .map_or_else(|| format!("ROI {row}"), str::to_owned);
+        let trace = (0..frames)
+            .map(|t| values[t * columns + column] as f32)
+            .collect();
+        // Two ROIs given the same identity are both kept.
+        let key = match traces.contains_key(&label) {
+            true => format!("{label} (ROI {row})"),
+            false => label,
+        };
+        traces.insert(key, trace);
+    }
+    let times = series_times(&file, series, frames)?;
+    let series = path.rsplit('/').next().unwrap_or(&path);
+    Activity::new(&format!("{name} ({series})"), times, traces)
+}
+
+/// Path and address of the first group, in order of names, holding `data` and the `rois` it
+/// covers.
+fn find_series(file: &File<'_>) -> Result<Option<(String, u64)>, String> {
+    let mut stack = vec![(String::new(), file.root())];
+    let mut visited = HashSet::new();
+    while let Some((path, group)) = stack.pop() {
+        if !visited.insert(group) || visited.len() > MAX_GROUPS {
+            continue;
+        }
+        let members = file.members(group)?;
+        let has = |name: &str| members.iter().any(|(n, _)| n == name);
+        if has("data") && has("rois") {
+            return Ok(Some((path, group)));
+        }
+        // Reversed, so the first member is searched first.
+        for (name, address) in members.into_iter().rev() {
+            if !file.is_dataset(address)? {
+                stack.push((format!("{path}/{name}"), address));
+            }
+        }
+    }
+    Ok(None)
+}
+
+/// Labels of the rows of the segmentation table that the `rois` dataset refers to.
+fn table_labels(file: &File<'_>, rois: u64) -> Result<Vec<String>, String> {
+    let table = file.attribute(rois, "table")?;
+    let Some(&table) = table.as_ref().and_then(|t| t.references()?.first()) else {
+        return Ok(Vec::new());
+    };
+    for column in LABEL_COLUMNS {
+        let Some(address) = file.member(table, column)? else {
+            continue;
+        };
+        let Ok(values) = file.read(address) else {
+            continue;
+        };
+        let Some(strings) = values.strings() else {
+            continue;
+        };
+        // A ragged column lists several labels per row, ending at each index.
+        let Some(index) = file.member(table, &format!("{column}_index"))? else {
+            return Ok(strings.to_vec());
+        };
+        let ends = file.read(index)?;
+        let mut start = 0;
+        let labels = (ends.numbers().unwrap_or_default().iter())
+            .map(|&end| {
+                let end = (end as usize).min(strings.len());
+                let row = strings.get(start..end).unwrap_or_default();
+                start = end;
+                row.iter()
+                    .find(|l| !l.trim().is_empty())
+                    .cloned()
+                    .unwrap_or_default()
+            })
+            .collect();
+        return Ok(labels);
+    }
+    Ok(Vec::new())
+}
+
+/// Time of each frame of a series, from its `timestamps` or from its `starting_time` and
+/// sampling `rate`.
+fn series_times(file: &File<'_>, series: u64, frames: usize) -> Result<Option<Vec<f32>>, String> {
+    if let Some(timestamps) = file.member(series, "timestamps")? {
+        let timestamps = file.read(timestamps)?;
+        let times = timestamps.numbers().unwrap_or_default();
+        return Ok(Some(times.iter().map(|&t| t as f32).collect()));
+    }
+    let Some(start) = file.member(series, "starting_time")? else {
+        return Ok(None);
+    };
+    let rate = file.attribute(start, "rate")?;
+    let rate = rate.as_ref().and_then(|r| r.numbers()?.first().copied());
+    let start = file.read(start)?.numbers().and_then(|s| s.first().copied());
+    let (Some(start), Some(rate)) = (start, rate.filter(|&r| r > 0.0)) else {
+        return Ok(None);
+    };
+    Ok(Some(
+        (0..frames)
+            .map(|i| (start + i as f64 / rate) as f32)
+            .collect(),
+    ))
+}
@@ -0,0 +1,70 @@
+use std::collections::HashMap;
+
+use crate::dataset::Neuron;
+
+/// A neuron whose annotated position or color deviates strongly from the reference.
+pub struct Outlier {
+    pub name: String,
+    /// Distance to the reference position, in atlas units.
+    pub distance: f32,
+    pub position_z: f32,
+    pub color_z: f32,
+}
+
+impl Outlier {
+    pub fn score(&self) -> f32 {
+        self.position_z.max(self.color_z)
+    }
+}
+
+fn color_distance(a: &Neuron, b: &Neuron) -> f32 {
+    ((a.r - b.r).powi(2) + (a.g - b.g).powi(2) + (a.b - b.b).powi(2)).sqrt()
+}
+
+fn z_scores(values: &[f32]) -> Vec<f32> {
+    let n = values.len().max(1) as f32;
+    let mean = values.iter().sum::<f32>() / n;
+    let std = (values.iter().map(|v| (v - mean).powi(2)).sum::<f32>() / n).sqrt();
+    values
+        .iter()
+        .map(|v| if std > 0.0 { (v - mean) / std } else { 0.0 })
+        .collect()
+}
+
+/// Flag neurons of `dataset` whose position or color z-score against `reference` exceeds `threshold`.
+///
+/// Deviations are standardized across all neurons shared by both tables, so a globally shifted
+/// dataset is not reported as a whole; the result is sorted with the strongest outlier first.
+pub fn find_outliers(
+    reference: &HashMap<String, Neuron>,
+    dataset: &HashMap<String, Neuron>,
+    threshold: f32,
+) -> Vec<Outlier> {
+    let pairs: Vec<_> = dataset
+        .values()
+        .filter_map(|n| reference.get(&n.name).map(|r| (r, n)))
+        .collect();
+    let distances: Vec<f32> = pairs
+        .iter()
+        .map(|(r, n)| {
+            let [dx, dy, dz] = [n.x - r.x, n.y - r.y, n.z - r.z];
+            (dx * dx + dy * dy + dz * dz).sqrt()
+        })
+        .collect();
+    let colors: Vec<f32> = pairs.iter().map(|(r, n)| color_distance(r, n)).collect();
+
+    let mut out: Vec<_> = pairs
+        .iter()
+        .zip(distances.iter().zip(z_scores(&distances)))
+        .zip(z_scores(&colors))
+        .filter(|((_, (_, pz)), cz)| *pz > threshold || *cz > threshold)
+        .map(|(((_, n), (d, pz)), cz)| Outlier {
+            name: n.name.clone(),
+            distance: *d,
+            position_z: pz,
+            color_z: cz,
+        })
+        .collect();
+    out.sort_unstable_by(|a, b| b.score().total_cmp(&a.score()));
+    out
+}
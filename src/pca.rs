@@ -0,0 +1,136 @@
+use egui::Color32;
+
+/// Colors of the first, second and third principal axis.
+pub const AXIS_COLORS: [Color32; 3] = [
+    Color32::from_rgb(229, 57, 53),
+    Color32::from_rgb(67, 160, 71),
+    Color32::from_rgb(30, 136, 229),
+];
+
+/// Principal axes of a point cloud: its mean and the eigenvectors of its covariance, in
+/// order of decreasing variance.
+#[derive(Clone, Copy, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct PrincipalAxes {
+    pub mean: [f32; 3],
+    /// Unit axes forming a right-handed frame.
+    pub axes: [[f32; 3]; 3],
+    /// Standard deviation of the points along each axis.
+    pub spread: [f32; 3],
+}
+
+impl PrincipalAxes {
+    /// Principal axes of `points`, or `None` for fewer than three points.
+    ///
+    /// The sign of each axis is chosen so that it points the way of the coordinate axis it
+    /// is closest to, so a roughly aligned cloud is not flipped.
+    pub fn of(points: &[[f32; 3]]) -> Option<Self> {
+        if points.len() < 3 {
+            return None;
+        }
+        let n = points.len() as f64;
+        let mut mean = [0.0f64; 3];
+        for p in points {
+            for (m, v) in mean.iter_mut().zip(p) {
+                *m += *v as f64 / n;
+            }
+        }
+        let mut cov = [[0.0f64; 3]; 3];
+        for p in points {
+            let d = [0, 1, 2].map(|i| p[i] as f64 - mean[i]);
+            for i in 0..3 {
+                for j in 0..3 {
+                    cov[i][j] += d[i] * d[j] / n;
+                }
+            }
+        }
+        let (values, vectors) = jacobi_eigen(cov);
+        let mut order = [0, 1, 2];
+        order.sort_unstable_by(|&a, &b| values[b].total_cmp(&values[a]));
+        let mut axes = order.map(|k| [0, 1, 2].map(|i| vectors[i][k]));
+        for axis in &mut axes {
+            let dominant = (0..3)
+                .max_by(|&a, &b| axis[a].abs().total_cmp(&axis[b].abs()))
+                .unwrap_or(0);
+            if axis[dominant] < 0.0 {
+                *axis = axis.map(|v| -v);
+            }
+        }
+        if dot(cross(axes[0], axes[1]), axes[2]) < 0.0 {
+            axes[2] = axes[2].map(|v| -v);
+        }
+        Some(Self {
+            mean: mean.map(|v| v as f32),
+            axes: axes.map(|a| a.map(|v| v as f32)),
+            spread: order.map(|k| values[k].max(0.0).sqrt() as f32),
+        })
+    }
+
+    /// `p` rotated about the mean so that the principal axes become the x, y and z axes.
+    pub fn rotate(&self, p: [f32; 3]) -> [f32; 3] {
+        let d = [0, 1, 2].map(|i| p[i] - self.mean[i]);
+        [0, 1, 2].map(|i| {
+            let a = self.axes[i];
+            self.mean[i] + a[0] * d[0] + a[1] * d[1] + a[2] * d[2]
+        })
+    }
+
+    /// Ends of each axis two standard deviations either side of the mean.
+    pub fn segments(&self) -> [([f32; 3], [f32; 3]); 3] {
+        [0, 1, 2].map(|i| {
+            let reach = self.axes[i].map(|v| v * 2.0 * self.spread[i]);
+            (
+                [0, 1, 2].map(|k| self.mean[k] - reach[k]),
+                [0, 1, 2].map(|k| self.mean[k] + reach[k]),
+            )
+        })
+    }
+}
+
+fn dot(a: [f64; 3], b: [f64; 3]) -> f64 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn cross(a: [f64; 3], b: [f64; 3]) -> [f64; 3] {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+/// Eigenvalues and eigenvectors (as columns) of the symmetric matrix `m`, by cyclic Jacobi
+/// rotations.
+fn jacobi_eigen(mut m: [[f64; 3]; 3]) -> ([f64; 3], [[f64; 3]; 3]) {
+    let mut v = [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]];
+    for _ in 0..50 {
+        let off = m[0][1].powi(2) + m[0][2].powi(2) + m[1][2].powi(2);
+        if off < 1e-20 {
+            break;
+        }
+        for (p, q) in [(0, 1), (0, 2), (1, 2)] {
+            if m[p][q].abs() < 1e-30 {
+                continue;
+            }
+            // Rotation angle that zeroes m[p][q].
+            let theta = (m[q][q] - m[p][p]) / (2.0 * m[p][q]);
+            let t = theta.signum() / (theta.abs() + (theta * theta + 1.0).sqrt());
+            let c = 1.0 / (t * t + 1.0).sqrt();
+            let s = t * c;
+            // m = Jᵀ m J, with J the rotation in the (p, q) plane.
+            for row in &mut m {
+                let (mp, mq) = (row[p], row[q]);
+                row[p] = c * mp - s * mq;
+                row[q] = s * mp + c * mq;
+            }
+            let (rp, rq) = (m[p], m[q]);
+            m[p] = [0, 1, 2].map(|k| c * rp[k] - s * rq[k]);
+            m[q] = [0, 1, 2].map(|k| s * rp[k] + c * rq[k]);
+            for row in &mut v {
+                let (vp, vq) = (row[p], row[q]);
+                row[p] = c * vp - s * vq;
+                row[q] = s * vp + c * vq;
+            }
+        }
+    }
+    ([m[0][0], m[1][1], m[2][2]], v)
+}
@@ -0,0 +1,452 @@
+use std::collections::BTreeSet;
+use std::fmt::Write as _;
+
+use ab_glyph::{Font as _, FontRef, OutlineCurve, Point, ScaleFont as _};
+use egui::{Color32, FontDefinitions, FontFamily, Pos2, Rect, Stroke, Vec2};
+
+use crate::figure::{Figure, Primitive};
+
+/// Advance of the printable ASCII characters in Helvetica, in thousandths of the font size,
+/// from its font metrics.
+const HELVETICA_ASCII: [u16; 95] = [
+    278, 278, 355, 556, 556, 889, 667, 191, 333, 333, 389, 584, 278, 333, 278, 278, 556, 556, 556,
+    556, 556, 556, 556, 556, 556, 556, 278, 278, 584, 584, 584, 556, 1015, 667, 667, 722, 722, 667,
+    611, 778, 722, 278, 500, 667, 556, 833, 722, 778, 667, 778, 722, 667, 611, 722, 667, 944, 667,
+    667, 611, 278, 278, 278, 469, 556, 333, 556, 556, 500, 556, 556, 278, 556, 556, 222, 222, 500,
+    222, 833, 556, 556, 556, 556, 333, 500, 278, 556, 500, 722, 500, 500, 500, 334, 260, 334, 584,
+];
+
+/// Fraction of the circle radius at which the control points of a quarter arc lie.
+const KAPPA: f32 = 0.552_284_8;
+
+/// Write `figure` as a one-page PDF, one point of the figure to one point of the page.
+/// Markers, lines and gradients stay vector shapes. Text is set in Helvetica, so it can be
+/// edited, unless it has characters Helvetica lacks, such as Japanese; then it is drawn as
+/// the outlines of its glyphs in `fonts`, the fonts of the interface.
+pub fn encode(figure: &Figure, fonts: &FontDefinitions) -> Vec<u8> {
+    let outline_fonts: Vec<FontRef<'_>> = (fonts.families.get(&FontFamily::Proportional))
+        .into_iter()
+        .flatten()
+        .filter_map(|name| fonts.font_data.get(name))
+        .filter_map(|data| FontRef::try_from_slice_and_index(&data.font, data.index).ok())
+        .collect();
+    let mut page = Page {
+        height: figure.size.y,
+        content: String::new(),
+        opacities: BTreeSet::new(),
+        shadings: Vec::new(),
+        fonts: outline_fonts,
+    };
+    // Round caps and joins, as egui draws lines.
+    page.content.push_str("1 J 1 j\n");
+    let background = Rect::from_min_size(Pos2::ZERO, figure.size);
+    page.draw(&Primitive::Rect {
+        rect: background,
+        fill: figure.background,
+        stroke: Stroke::NONE,
+    });
+    for group in &figure.groups {
+        let clipped = group.clip.is_finite();
+        if clipped {
+            page.content.push_str("q ");
+            page.rect(group.clip);
+            page.content.push_str("W n\n");
+        }
+        for item in &group.items {
+            page.draw(item);
+        }
+        if clipped {
+            page.content.push_str("Q\n");
+        }
+    }
+    page.finish(figure.size)
+}
+
+/// The page being written, and the resources its content uses.
+struct Page<'a> {
+    /// Height in points, to turn the figure's downward y axis upwards.
+    height: f32,
+    content: String,
+    /// Opacities of fill and stroke used, each pair named in the resources of the page.
+    opacities: BTreeSet<(u8, u8)>,
+    /// Gradients, as the shading dictionaries they are painted with.
+    shadings: Vec<String>,
+    /// Fonts glyphs are outlined from, in the order egui falls back through them.
+    fonts: Vec<FontRef<'a>>,
+}
+
+impl Page<'_> {
+    fn draw(&mut self, item: &Primitive) {
+        match item {
+            Primitive::Circle {
+                center,
+                radius,
+                fill,
+                stroke,
+            } => {
+                let Some(op) = self.paint(*fill, *stroke) else {
+                    return;
+                };
+                let (x, y) = self.point(*center);
+                let (r, k) = (*radius, KAPPA * radius);
+                let c = &mut self.content;
+                let _ = writeln!(c, "{} {} m", num(x + r), num(y));
+                for [a, b, d] in [
+                    [(x + r, y + k), (x + k, y + r), (x, y + r)],
+                    [(x - k, y + r), (x - r, y + k), (x - r, y)],
+                    [(x - r, y - k), (x - k, y - r), (x, y - r)],
+                    [(x + k, y - r), (x + r, y - k), (x + r, y)],
+                ] {
+                    let _ = writeln!(
+                        c,
+                        "{} {} {} {} {} {} c",
+                        num(a.0),
+                        num(a.1),
+                        num(b.0),
+                        num(b.1),
+                        num(d.0),
+                        num(d.1)
+                    );
+                }
+                let _ = writeln!(c, "h {op}");
+            }
+            Primitive::Line { points, stroke } => {
+                if points.len() < 2 {
+                    return;
+                }
+                let Some(op) = self.paint(Color32::TRANSPARENT, *stroke) else {
+                    return;
+                };
+                for (i, p) in points.iter().enumerate() {
+                    let (x, y) = self.point(*p);
+                    let verb = if i == 0 { "m" } else { "l" };
+                    let _ = writeln!(self.content, "{} {} {verb}", num(x), num(y));
+                }
+                let _ = writeln!(self.content, "{op}");
+            }
+            Primitive::Rect { rect, fill, stroke } => {
+                let Some(op) = self.paint(*fill, *stroke) else {
+                    return;
+                };
+                self.rect(*rect);
+                let _ = writeln!(self.content, "{op}");
+            }
+            Primitive::Text {
+                pos,
+                anchor,
+                text,
+                size,
+                color,
+                vertical,
+            } => {
+                if text.is_empty() || self.paint(*color, Stroke::NONE).is_none() {
+                    return;
+                }
+                let helvetica: Option<Vec<(u8, u16)>> = text.chars().map(helvetica).collect();
+                let width = match &helvetica {
+                    Some(codes) => codes.iter().map(|(_, w)| *w as f32).sum::<f32>() / 1000.0,
+                    None => self.outline_width(text),
+                } * size;
+                let ascent = self.ascent() * size;
+                // Text runs along the first column of the matrix and its up is the second,
+                // with the origin on the baseline.
+                let matrix = if *vertical {
+                    let (x, y) =
+                        self.point(Pos2::new(pos.x - size / 2.0 + ascent, pos.y + width / 2.0));
+                    [0.0, 1.0, -1.0, 0.0, x, y]
+                } else {
+                    let corner = anchor.anchor_size(*pos, Vec2::new(width, *size)).min;
+                    let (x, y) = self.point(corner + Vec2::new(0.0, ascent));
+                    [1.0, 0.0, 0.0, 1.0, x, y]
+                };
+                let matrix = matrix.map(num).join(" ");
+                match helvetica {
+                    Some(codes) => {
+                        let mut string = String::new();
+                        for (code, _) in codes {
+                            match code {
+                                b'(' | b')' | b'\\' => {
+                                    string.push('\\');
+                                    string.push(code as char);
+                                }
+                                32..=126 => string.push(code as char),
+                                _ => {
+                                    let _ = write!(string, "\\{code:03o}");
+                                }
+                            }
+                        }
+                        let _ = writeln!(
+                            self.content,
+                            "BT /F1 {} Tf {matrix} Tm ({string}) Tj ET",
+                            num(*size)
+                        );
+                    }
+                    None => {
+                        let _ = writeln!(self.content, "q {matrix} cm");
+                        self.outline(text, *size);
+                        self.content.push_str("Q\n");
+                    }
+                }
+            }
+            Primitive::Gradient { rect, colors } => {
+                if colors.is_empty() {
+                    return;
+                }
+                let rgb = |color: &Color32| {
+                    let [r, g, b, _] = color.to_srgba_unmultiplied();
+                    [r, g, b].map(|v| num(v as f32 / 255.0)).join(" ")
+                };
+                // Linear pieces from each color to the next, stitched along the bar.
+                let pieces = colors.len().saturating_sub(1).max(1);
+                let mut functions = String::new();
+                for i in 0..pieces {
+                    let (a, b) = (&colors[i], &colors[(i + 1).min(colors.len() - 1)]);
+                    let _ = write!(
+                        functions,
+                        "<< /FunctionType 2 /Domain [0 1] /C0 [{}] /C1 [{}] /N 1 >> ",
+                        rgb(a),
+                        rgb(b)
+                    );
+                }
+                let bounds: Vec<String> =
+                    (1..pieces).map(|i| num(i as f32 / pieces as f32)).collect();
+                let encode = vec!["0 1"; pieces].join(" ");
+                let (left, bottom) = self.point(rect.left_bottom());
+                let top = self.point(rect.left_top()).1;
+                self.shadings.push(format!(
+                    "<< /ShadingType 2 /ColorSpace /DeviceRGB /Coords [{} {} {} {}] \
+                     /Function << /FunctionType 3 /Domain [0 1] /Functions [{functions}] \
+                     /Bounds [{}] /Encode [{encode}] >> /Extend [true true] >>",
+                    num(left),
+                    num(bottom),
+                    num(left),
+                    num(top),
+                    bounds.join(" ")
+                ));
+                self.content.push_str("q ");
+                self.rect(*rect);
+                let _ = writeln!(self.content, "W n /S{} sh Q", self.shadings.len() - 1);
+            }
+        }
+    }
+
+    /// Page coordinates of a point of the figure.
+    fn point(&self, p: Pos2) -> (f32, f32) {
+        (p.x, self.height - p.y)
+    }
+
+    fn rect(&mut self, rect: Rect) {
+        let (x, y) = self.point(rect.left_bottom());
+        let _ = write!(
+            self.content,
+            "{} {} {} {} re ",
+            num(x),
+            num(y),
+            num(rect.width()),
+            num(rect.height())
+        );
+    }
+
+    /// Set the colors and line width of the next path, and return the operator painting it,
+    /// or `None` if it would be invisible.
+    fn paint(&mut self, fill: Color32, stroke: Stroke) -> Option<&'static str> {
+        let [fr, fg, fb, fa] = fill.to_srgba_unmultiplied();
+        let [sr, sg, sb, sa] = stroke.color.to_srgba_unmultiplied();
+        let stroked = sa > 0 && stroke.width > 0.0;
+        let op = match (fa > 0, stroked) {
+            (false, false) => return None,
+            (true, false) => "f",
+            (false, true) => "S",
+            (true, true) => "B",
+        };
+        let c = &mut self.content;
+        let channel = |v: u8| num(v as f32 / 255.0);
+        if fa > 0 {
+            let _ = write!(c, "{} {} {} rg ", channel(fr), channel(fg), channel(fb));
+        }
+        if stroked {
+            let _ = write!(
+                c,
+                "{} {} {} RG {} w ",
+                channel(sr),
+                channel(sg),
+                channel(sb),
+                num(stroke.width)
+            );
+        }
+        let opacity = (
+            if fa > 0 { fa } else { 255 },
+            if stroked { sa } else { 255 },
+        );
+        self.opacities.insert(opacity);
+        let _ = write!(c, "/A{}_{} gs ", opacity.0, opacity.1);
+        Some(op)
+    }
+
+    /// Distance from the top of a line of text to its baseline, as a fraction of the font
+    /// size, after the first font of the interface as egui lays text out.
+    fn ascent(&self) -> f32 {
+        (self.fonts.first())
+            .map(|font| font.as_scaled(1.0).ascent())
+            .unwrap_or(0.8)
+    }
+
+    /// The font `c` is drawn with, as egui picks it: the first one that has it.
+    fn font_of(&self, c: char) -> Option<&FontRef<'_>> {
+        (self.fonts.iter())
+            .find(|font| font.glyph_id(c).0 != 0)
+            .or(self.fonts.first())
+    }
+
+    /// Width of `text` drawn with the fonts of the interface, as a fraction of its size.
+    fn outline_width(&self, text: &str) -> f32 {
+        (text.chars())
+            .filter_map(|c| {
+                let font = self.font_of(c)?;
+                Some(font.as_scaled(1.0).h_advance(font.glyph_id(c)))
+            })
+            .sum()
+    }
+
+    /// Fill the outlines of the glyphs of `text`, starting at the origin on the baseline.
+    fn outline(&mut self, text: &str, size: f32) {
+        let mut path = String::new();
+        let mut x = 0.0;
+        for c in text.chars() {
+            let Some(font) = self.font_of(c) else {
+                continue;
+            };
+            let scaled = font.as_scaled(size);
+            let id = font.glyph_id(c);
+            let factor = scaled.scale_factor();
+            let at = |p: Point| {
+                format!(
+                    "{} {}",
+                    num(x + p.x * factor.horizontal),
+                    num(p.y * factor.vertical)
+                )
+            };
+            let mut last = None;
+            for curve in font.outline(id).map(|o| o.curves).unwrap_or_default() {
+                let (start, end) = match curve {
+                    OutlineCurve::Line(a, b)
+                    | OutlineCurve::Quad(a, _, b)
+                    | OutlineCurve::Cubic(a, _, _, b) => (a, b),
+                };
+                if last != Some(start) {
+                    let _ = writeln!(path, "{} m", at(start));
+                }
+                let _ = match curve {
+                    OutlineCurve::Line(_, b) => writeln!(path, "{} l", at(b)),
+                    OutlineCurve::Quad(a, q, b) => {
+                        // The same curve as a cubic one.
+                        let toward = |p: Point| Point {
+                            x: p.x + (q.x - p.x) * 2.0 / 3.0,
+                            y: p.y + (q.y - p.y) * 2.0 / 3.0,
+                        };
+                        let (c1, c2) = (toward(a), toward(b));
+                        writeln!(path, "{} {} {} c", at(c1), at(c2), at(b))
+                    }
+                    OutlineCurve::Cubic(_, c1, c2, b) => {
+                        writeln!(path, "{} {} {} c", at(c1), at(c2), at(b))
+                    }
+                };
+                last = Some(end);
+            }
+            x += scaled.h_advance(id);
+        }
+        if !path.is_empty() {
+            self.content.push_str(&path);
+            self.content.push_str("f\n");
+        }
+    }
+
+    /// The whole file, with the content compressed.
+    fn finish(self, size: Vec2) -> Vec<u8> {
+        let mut states = String::new();
+        for (fill, stroke) in &self.opacities {
+            let _ = write!(
+                states,
+                "/A{fill}_{stroke} << /ca {} /CA {} >> ",
+                num(*fill as f32 / 255.0),
+                num(*stroke as f32 / 255.0)
+            );
+        }
+        let mut shadings = String::new();
+        for (i, shading) in self.shadings.iter().enumerate() {
+            let _ = write!(shadings, "/S{i} {shading} ");
+        }
+        let content = miniz_oxide::deflate::compress_to_vec_zlib(self.content.as_bytes(), 6);
+
+        let mut pdf = b"%PDF-1.4\n%\xE2\xE3\xCF\xD3\n".to_vec();
+        let mut offsets = Vec::new();
+        let mut object = |pdf: &mut Vec<u8>, body: &[u8]| {
+            offsets.push(pdf.len());
+            pdf.extend(format!("{} 0 obj\n", offsets.len()).as_bytes());
+            pdf.extend(body);
+            pdf.extend(b"\nendobj\n");
+        };
+        object(&mut pdf, b"<< /Type /Catalog /Pages 2 0 R >>");
+        object(&mut pdf, b"<< /Type /Pages /Kids [3 0 R] /Count 1 >>");
+        let page = format!(
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}] /Contents 4 0 R \
+             /Resources << /Font << /F1 5 0 R >> /ExtGState << {states}>> \
+             /Shading << {shadings}>> >> >>",
+            num(size.x),
+            num(size.y)
+        );
+        object(&mut pdf, page.as_bytes());
+        let mut stream = format!(
+            "<< /Length {} /Filter /FlateDecode >>\nstream\n",
+            content.len()
+        )
+        .into_bytes();
+        stream.extend(&content);
+        stream.extend(b"\nendstream");
+        object(&mut pdf, &stream);
+        object(
+            &mut pdf,
+            b"<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica /Encoding /WinAnsiEncoding >>",
+        );
+
+        let xref = pdf.len();
+        let mut table = format!("xref\n0 {}\n0000000000 65535 f \n", offsets.len() + 1);
+        for offset in &offsets {
+            let _ = writeln!(table, "{offset:010} 00000 n ");
+        }
+        let _ = write!(
+            table,
+            "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{xref}\n%%EOF\n",
+            offsets.len() + 1
+        );
+        pdf.extend(table.as_bytes());
+        pdf
+    }
+}
+
+/// Code of `c` in the WinAnsi encoding and its advance in Helvetica, if it has one that is
+/// likely in a figure.
+fn helvetica(c: char) -> Option<(u8, u16)> {
+    match c {
+        ' '..='~' => Some((c as u8, HELVETICA_ASCII[c as usize - 32])),
+        '\u{2026}' => Some((0x85, 1000)),
+        '\u{2013}' => Some((0x96, 556)),
+        '\u{2014}' => Some((0x97, 1000)),
+        '\u{B0}' => Some((0xB0, 400)),
+        '\u{B1}' => Some((0xB1, 584)),
+        '\u{B5}' => Some((0xB5, 556)),
+        '\u{D7}' => Some((0xD7, 584)),
+        _ => None,
+    }
+}
+
+/// A number of the content, to a hundredth of a point.
+fn num(v: f32) -> String {
+    let text = format!("{v:.2}");
+    let text = text.trim_end_matches('0').trim_end_matches('.');
+    if text == "-0" {
+        "0".to_owned()
+    } else {
+        text.to_owned()
+    }
+}
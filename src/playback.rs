@@ -0,0 +1,149 @@
+use crate::i18n::tr;
+
+/// Positions of the slice sweep, from one end of the shown neurons to the other.
+pub const SWEEP_STEPS: usize = 200;
+
+/// A move of the time-dependent layers asked for by the playback controls or the keyboard.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Seek {
+    /// Move by this many frames.
+    By(isize),
+    /// Go back to the first frame.
+    Start,
+}
+
+/// Play state shared by the layers that change over time: the activity frames and the
+/// slice sweep.
+#[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct Playback {
+    #[serde(skip)]
+    pub playing: bool,
+    /// Start over after the last frame instead of stopping.
+    pub looping: bool,
+    /// Frames played per second.
+    pub fps: f32,
+    /// Time of the last frame played, and the part of a frame due since.
+    #[serde(skip)]
+    last: Option<f64>,
+    #[serde(skip)]
+    carry: f64,
+}
+
+impl Default for Playback {
+    fn default() -> Self {
+        Self {
+            playing: false,
+            looping: true,
+            fps: 10.0,
+            last: None,
+            carry: 0.0,
+        }
+    }
+}
+
+impl Playback {
+    /// Frames due since the last call while playing; the caller keeps repainting until paused.
+    pub fn tick(&mut self, ctx: &egui::Context) -> Option<Seek> {
+        if !self.playing {
+            self.last = None;
+            return None;
+        }
+        let now = ctx.input(|i| i.time);
+        let elapsed = now - self.last.unwrap_or(now);
+        self.last = Some(now);
+        self.carry += elapsed * self.fps as f64;
+        let frames = self.carry.floor();
+        self.carry -= frames;
+        (frames >= 1.0).then_some(Seek::By(frames as isize))
+    }
+
+    /// Apply `seek` to `frame`, one of `count`: past the last frame playback starts over
+    /// when looping and otherwise stops there.
+    pub fn seek(&mut self, frame: &mut usize, count: usize, seek: Seek) {
+        let Some(last) = count.checked_sub(1) else {
+            return;
+        };
+        let target = match seek {
+            Seek::Start => 0,
+            Seek::By(delta) => *frame as isize + delta,
+        };
+        *frame = if target > last as isize {
+            if self.looping && self.playing {
+                (target as usize) % count
+            } else {
+                self.playing = false;
+                last
+            }
+        } else {
+            target.max(0) as usize
+        };
+    }
+
+    /// Play, pause and step buttons, the loop toggle and the speed.
+    pub fn controls_ui(&mut self, ui: &mut egui::Ui) -> Option<Seek> {
+        let mut seek = None;
+        if ui
+            .small_button("\u{23EE}")
+            .on_hover_text(tr("First frame"))
+            .clicked()
+        {
+            seek = Some(Seek::Start);
+        }
+        if ui
+            .small_button("\u{23F4}")
+            .on_hover_text(format!("{} (\u{2190})", tr("Previous frame")))
+            .clicked()
+        {
+            seek = Some(Seek::By(-1));
+        }
+        let (icon, hint) = match self.playing {
+            true => ("\u{23F8}", tr("Pause")),
+            false => ("\u{25B6}", tr("Play")),
+        };
+        if ui
+            .small_button(icon)
+            .on_hover_text(format!("{hint} ({})", tr("Space")))
+            .clicked()
+        {
+            self.playing = !self.playing;
+        }
+        if ui
+            .small_button("\u{23F5}")
+            .on_hover_text(format!("{} (\u{2192})", tr("Next frame")))
+            .clicked()
+        {
+            seek = Some(Seek::By(1));
+        }
+        ui.toggle_value(&mut self.looping, "\u{1F501}")
+            .on_hover_text(tr("Loop"));
+        ui.add(
+            egui::DragValue::new(&mut self.fps)
+                .range(0.5..=120.0)
+                .speed(0.1)
+                .max_decimals(1)
+                .suffix(" fps"),
+        )
+        .on_hover_text(tr("Playback speed in frames per second"));
+        seek
+    }
+
+    /// Space toggles playing. The arrow keys step through the frames when `arrows`, as they
+    /// move the slices while a neuron is focused.
+    pub fn keys(&mut self, ctx: &egui::Context, arrows: bool) -> Option<Seek> {
+        if ctx.wants_keyboard_input() {
+            return None;
+        }
+        if ctx.input_mut(|i| i.consume_key(egui::Modifiers::NONE, egui::Key::Space)) {
+            self.playing = !self.playing;
+        }
+        if !arrows {
+            return None;
+        }
+        let delta = ctx.input(|i| {
+            i.num_presses(egui::Key::ArrowRight) as isize
+                - i.num_presses(egui::Key::ArrowLeft) as isize
+        });
+        (delta != 0).then_some(Seek::By(delta))
+    }
+}
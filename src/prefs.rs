@@ -0,0 +1,520 @@
+use egui::{ecolor::Hsva, Color32, Stroke, Theme, Visuals};
+use egui_plot::{GridInput, GridMark};
+
+use crate::i18n::{tr, Language};
+use crate::theme::ColorTheme;
+
+/// Unit in which measured distances are reported.
+#[derive(Clone, Copy, Default, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub enum DistanceUnit {
+    #[default]
+    Atlas,
+    Microns,
+}
+
+impl DistanceUnit {
+    pub const ALL: [Self; 2] = [Self::Atlas, Self::Microns];
+}
+
+impl std::fmt::Display for DistanceUnit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Atlas => f.write_str(tr("Atlas units")),
+            Self::Microns => write!(f, "µm"),
+        }
+    }
+}
+
+/// How axis ticks print positions, given in atlas units, in the preferred unit.
+#[derive(Clone, Copy)]
+pub struct TickFormat {
+    /// Factor converting atlas units into the preferred unit.
+    factor: f64,
+    /// Digits after the decimal point; `None` gives as many as the tick spacing needs.
+    decimals: Option<usize>,
+    thousands_separators: bool,
+}
+
+impl TickFormat {
+    /// Label of the tick at `value`, `step` away from the next one, both in atlas units.
+    pub fn label(&self, value: f64, step: f64) -> String {
+        let (value, step) = (value * self.factor, step * self.factor);
+        let decimals = self.decimals.unwrap_or_else(|| {
+            let decimals = -step.log10().floor();
+            if decimals.is_finite() {
+                decimals.max(0.0) as usize
+            } else {
+                0
+            }
+        });
+        // Rounded first, so that values just below zero print as 0; adding zero turns -0
+        // into 0.
+        let scale = 10f64.powi(decimals as i32);
+        let text = format!("{:.*}", decimals, (value * scale).round() / scale + 0.0);
+        if self.thousands_separators {
+            group_thousands(&text)
+        } else {
+            text
+        }
+    }
+
+    /// Grid lines at round values of the preferred unit rather than of atlas units, one
+    /// tenth of the next larger step apart as egui_plot spaces them.
+    pub fn grid_spacer(self) -> impl Fn(GridInput) -> Vec<GridMark> {
+        let spacer = egui_plot::log_grid_spacer(10);
+        move |input| {
+            let f = self.factor;
+            let scaled = GridInput {
+                bounds: (input.bounds.0 * f, input.bounds.1 * f),
+                base_step_size: input.base_step_size * f,
+            };
+            (spacer(scaled).into_iter())
+                .map(|mark| GridMark {
+                    value: mark.value / f,
+                    step_size: mark.step_size / f,
+                })
+                .collect()
+        }
+    }
+}
+
+/// `number` with a comma between each group of three digits of its whole part.
+fn group_thousands(number: &str) -> String {
+    let (sign, digits) = match number.strip_prefix('-') {
+        Some(digits) => ("-", digits),
+        None => ("", number),
+    };
+    let (whole, fraction) = digits.split_at(digits.find('.').unwrap_or(digits.len()));
+    let mut grouped = String::from(sign);
+    for (i, digit) in whole.chars().enumerate() {
+        if i > 0 && (whole.len() - i) % 3 == 0 {
+            grouped.push(',');
+        }
+        grouped.push(digit);
+    }
+    grouped + fraction
+}
+
+/// Display defaults edited in the Preferences window and persisted with the app state.
+#[derive(Clone, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct Preferences {
+    pub language: Language,
+    /// Zoom factor of the whole interface, also changed with Ctrl+Plus and Ctrl+Minus.
+    pub ui_scale: f32,
+    /// Size of the monospace font of the neuron list, in points.
+    pub list_font_size: f32,
+    /// Upper bound on frames per second while animating, such as during activity playback.
+    pub fps_limit: Option<u32>,
+    /// Marker radius in points when zoomed in; markers shrink as the view zooms out.
+    pub point_size: f32,
+    /// Width of the outline around every marker, in points, drawn in the text color of the
+    /// theme so dark markers show on a dark background and light ones on a light one.
+    pub outline_width: f32,
+    /// Brightness of right-side (negative z) markers relative to the left side; 1 disables
+    /// the depth cue.
+    pub depth_cue: f32,
+    /// Distance from the hovered position, in atlas units, within which a neuron is picked
+    /// out in the orthogonal views.
+    pub hover_tolerance: f64,
+    /// Half thickness of the slab shown in the orthogonal views, in atlas units.
+    pub slab_thickness: f64,
+    /// Ring around the hovered neuron in the orthogonal views and a long-pressed one.
+    pub hover_ring: Stroke,
+    /// Lines through the hovered position and neuron in the orthogonal views.
+    pub guide_line: Stroke,
+    pub units: DistanceUnit,
+    pub microns_per_unit: f32,
+    /// Digits after the decimal point of axis ticks; `None` gives as many as the tick
+    /// spacing needs.
+    pub tick_decimals: Option<usize>,
+    /// Commas between groups of three digits of axis ticks, as in 1,200.
+    pub thousands_separators: bool,
+    /// Directory offered first when exporting; empty uses the system default.
+    pub export_dir: String,
+    /// Stronger outlines and colors and larger hover targets, on top of the dark or light
+    /// theme.
+    pub high_contrast: bool,
+    /// Saved color themes.
+    pub color_themes: Vec<ColorTheme>,
+    /// Index into `color_themes` of the one in use; `None` keeps the stock colors.
+    pub color_theme: Option<usize>,
+}
+
+impl Default for Preferences {
+    fn default() -> Self {
+        Self {
+            language: Language::default(),
+            ui_scale: 1.0,
+            list_font_size: 16.0,
+            fps_limit: None,
+            point_size: 6.0,
+            outline_width: 1.0,
+            depth_cue: 0.8,
+            hover_tolerance: 0.35,
+            slab_thickness: 1.5,
+            hover_ring: Stroke::new(1.5, Color32::LIGHT_RED),
+            guide_line: Stroke::new(1.0, Color32::LIGHT_RED),
+            units: DistanceUnit::default(),
+            microns_per_unit: 1.0,
+            tick_decimals: None,
+            thousands_separators: false,
+            export_dir: String::new(),
+            high_contrast: false,
+            color_themes: Vec::new(),
+            color_theme: None,
+        }
+    }
+}
+
+/// Range of [`Preferences::ui_scale`].
+pub const UI_SCALE_RANGE: std::ops::RangeInclusive<f32> = 0.5..=3.0;
+
+impl Preferences {
+    /// Apply the interface scale to egui if it changed.
+    pub fn apply_scale(&self, ctx: &egui::Context) {
+        if (ctx.zoom_factor() - self.ui_scale).abs() > 1e-3 {
+            ctx.set_zoom_factor(self.ui_scale);
+        }
+    }
+
+    /// Step the interface scale by `delta`, in tenths like a browser zoom.
+    pub fn step_scale(&mut self, delta: f32) {
+        let scale = ((self.ui_scale + delta) * 10.0).round() / 10.0;
+        self.ui_scale = scale.clamp(*UI_SCALE_RANGE.start(), *UI_SCALE_RANGE.end());
+    }
+
+    /// Ask for the next frame of an animation, no sooner than the frame rate limit allows.
+    pub fn request_repaint(&self, ctx: &egui::Context) {
+        match self.fps_limit.filter(|fps| *fps > 0) {
+            Some(fps) => {
+                ctx.request_repaint_after(std::time::Duration::from_secs_f64(1.0 / fps as f64))
+            }
+            None => ctx.request_repaint(),
+        }
+    }
+
+    fn active_theme(&self) -> Option<&ColorTheme> {
+        self.color_themes.get(self.color_theme?)
+    }
+
+    /// Patch the visuals of both themes with the color theme and for high contrast, or
+    /// restore the stock ones. Only does work when those settings changed.
+    pub fn apply_visuals(&self, ctx: &egui::Context) {
+        let id = egui::Id::new("applied_visuals");
+        let applied = (self.high_contrast, self.active_theme().cloned());
+        if ctx
+            .data(|d| d.get_temp::<(bool, Option<ColorTheme>)>(id))
+            .as_ref()
+            == Some(&applied)
+        {
+            return;
+        }
+        for theme in [Theme::Dark, Theme::Light] {
+            let mut visuals = theme.default_visuals();
+            if let Some(colors) = &applied.1 {
+                colors.apply(&mut visuals);
+            }
+            if self.high_contrast {
+                high_contrast(&mut visuals);
+            }
+            ctx.set_visuals_of(theme, visuals);
+        }
+        ctx.data_mut(|d| d.insert_temp(id, applied));
+    }
+
+    /// Outline of selected neurons in the list and the lateral view.
+    pub fn selection_color(&self) -> Color32 {
+        if let Some(theme) = self.active_theme() {
+            theme.highlight
+        } else if self.high_contrast {
+            Color32::from_rgb(255, 255, 0)
+        } else {
+            Color32::from_rgb(255, 200, 0)
+        }
+    }
+
+    /// Outline of the neuron focused with the arrow keys.
+    pub fn focus_color(&self) -> Color32 {
+        if self.high_contrast {
+            Color32::from_rgb(0, 255, 255)
+        } else {
+            Color32::from_rgb(0, 200, 255)
+        }
+    }
+
+    pub fn hover_stroke(&self) -> Stroke {
+        self.emphasize(self.hover_ring)
+    }
+
+    pub fn guide_stroke(&self) -> Stroke {
+        self.emphasize(self.guide_line)
+    }
+
+    /// In high contrast, `stroke` fully saturated and at least 2 points wide.
+    fn emphasize(&self, stroke: Stroke) -> Stroke {
+        if !self.high_contrast {
+            return stroke;
+        }
+        let mut color = Hsva::from(stroke.color);
+        color.s = 1.0;
+        color.v = 1.0;
+        Stroke::new(stroke.width.max(2.0), color)
+    }
+
+    /// Distance in atlas units within which a hovered neuron is picked out.
+    pub fn pick_tolerance(&self) -> f64 {
+        if self.high_contrast {
+            self.hover_tolerance * 2.0
+        } else {
+            self.hover_tolerance
+        }
+    }
+
+    /// Outline drawn around every marker; `text_color` is the default color.
+    pub fn marker_outline(&self, text_color: Color32) -> Option<Stroke> {
+        let theme = self.active_theme().filter(|t| t.outline_width > 0.0);
+        match theme {
+            Some(t) => Some(Stroke::new(
+                t.outline_width,
+                t.outline_color.unwrap_or(text_color),
+            )),
+            None => {
+                let width = if self.high_contrast {
+                    self.outline_width.max(2.0)
+                } else {
+                    self.outline_width
+                };
+                (width > 0.0).then_some(Stroke::new(width, text_color))
+            }
+        }
+    }
+
+    /// Pick, create, edit and delete color themes.
+    fn color_theme_ui(&mut self, ui: &mut egui::Ui) {
+        let name = |theme: Option<&ColorTheme>| match theme {
+            Some(theme) if !theme.name.is_empty() => theme.name.clone(),
+            Some(_) => tr("Untitled").to_owned(),
+            None => tr("Standard colors").to_owned(),
+        };
+        ui.horizontal(|ui| {
+            egui::ComboBox::from_id_salt("color_theme")
+                .selected_text(name(self.active_theme()))
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut self.color_theme, None, name(None));
+                    for (i, theme) in self.color_themes.iter().enumerate() {
+                        ui.selectable_value(&mut self.color_theme, Some(i), name(Some(theme)));
+                    }
+                });
+            if ui.button(tr("New")).clicked() {
+                let mut theme = self.active_theme().cloned().unwrap_or_default();
+                theme.name = format!("{} {}", tr("Colors"), self.color_themes.len() + 1);
+                self.color_themes.push(theme);
+                self.color_theme = Some(self.color_themes.len() - 1);
+            }
+            if let Some(i) = self.color_theme {
+                if ui.button(tr("Delete")).clicked() {
+                    self.color_themes.remove(i);
+                    self.color_theme = None;
+                }
+            }
+        });
+        if let Some(theme) = self.color_theme.and_then(|i| self.color_themes.get_mut(i)) {
+            theme.ui(ui);
+        }
+    }
+
+    pub fn list_font(&self) -> egui::FontId {
+        egui::FontId::monospace(self.list_font_size)
+    }
+
+    /// Marker radius for a plot spanning `scale` atlas units horizontally.
+    pub fn marker_radius(&self, scale: f64) -> f64 {
+        let size = self.point_size as f64;
+        (scale * -0.01 + size).clamp(1.0, size.max(1.0))
+    }
+
+    /// Factor converting atlas units into the preferred unit.
+    pub fn distance_factor(&self) -> f64 {
+        match self.units {
+            DistanceUnit::Atlas => 1.0,
+            DistanceUnit::Microns => self.microns_per_unit as f64,
+        }
+    }
+
+    /// A distance given in atlas units, in the preferred unit.
+    pub fn format_distance(&self, atlas_units: f64) -> String {
+        let value = atlas_units * self.distance_factor();
+        match self.units {
+            DistanceUnit::Atlas => format!("{value:.2}"),
+            DistanceUnit::Microns => format!("{value:.2} µm"),
+        }
+    }
+
+    /// How axis ticks print positions in the preferred unit.
+    pub fn tick_format(&self) -> TickFormat {
+        TickFormat {
+            factor: self.distance_factor(),
+            decimals: self.tick_decimals,
+            thousands_separators: self.thousands_separators,
+        }
+    }
+
+    /// Label of an axis along `direction`, naming the preferred unit; atlas units have no
+    /// name.
+    pub fn axis_label(&self, direction: &str) -> String {
+        match self.units {
+            DistanceUnit::Atlas => direction.to_owned(),
+            DistanceUnit::Microns => format!("{direction} (µm)"),
+        }
+    }
+
+    /// An area given in square atlas units, in the square of the preferred unit.
+    pub fn format_area(&self, atlas_units: f64) -> String {
+        let value = atlas_units * self.distance_factor().powi(2);
+        match self.units {
+            DistanceUnit::Atlas => format!("{value:.1}"),
+            DistanceUnit::Microns => format!("{value:.1} µm²"),
+        }
+    }
+
+    pub fn ui(&mut self, ui: &mut egui::Ui) {
+        ui.label(egui::RichText::new(tr("Language")).strong());
+        ui.horizontal(|ui| {
+            for language in Language::ALL {
+                ui.radio_value(&mut self.language, language, language.to_string());
+            }
+        });
+        ui.separator();
+        ui.label(egui::RichText::new(tr("Theme")).strong());
+        egui::widgets::global_theme_preference_buttons(ui);
+        ui.checkbox(&mut self.high_contrast, tr("High contrast"))
+            .on_hover_text(tr(
+                "Thicker outlines, stronger highlights and larger hover targets",
+            ));
+        self.color_theme_ui(ui);
+        ui.separator();
+        ui.label(egui::RichText::new(tr("Display")).strong());
+        ui.add(
+            egui::Slider::new(&mut self.ui_scale, UI_SCALE_RANGE)
+                .step_by(0.1)
+                .text(tr("UI scale")),
+        )
+        .on_hover_text(tr("Also Ctrl+Plus / Ctrl+Minus"));
+        ui.add(egui::Slider::new(&mut self.list_font_size, 8.0..=32.0).text(tr("List font size")));
+        ui.horizontal(|ui| {
+            let mut limited = self.fps_limit.is_some();
+            if ui.checkbox(&mut limited, tr("Limit frame rate")).changed() {
+                self.fps_limit = limited.then_some(30);
+            }
+            if let Some(fps) = &mut self.fps_limit {
+                ui.add(egui::DragValue::new(fps).range(5..=240).suffix(" fps"));
+            }
+        })
+        .response
+        .on_hover_text(tr(
+            "The window is only redrawn on input and while animating; this caps the animations",
+        ));
+        ui.separator();
+        ui.label(egui::RichText::new(tr("Markers")).strong());
+        ui.add(egui::Slider::new(&mut self.point_size, 1.0..=16.0).text(tr("Point size")));
+        ui.add(egui::Slider::new(&mut self.outline_width, 0.0..=4.0).text(tr("Outline width")))
+            .on_hover_text(tr(
+                "Contrasting outline keeping dim neurons visible; 0 draws none",
+            ));
+        ui.add(egui::Slider::new(&mut self.depth_cue, 0.2..=1.0).text(tr("Depth cue")))
+            .on_hover_text(tr("Brightness of right-side neurons; 1 disables the cue"));
+        ui.separator();
+        ui.label(egui::RichText::new(tr("Orthogonal views")).strong());
+        ui.add(
+            egui::Slider::new(&mut self.hover_tolerance, 0.05..=3.0).text(tr("Hover tolerance")),
+        )
+        .on_hover_text(tr("Hover radius in atlas units"));
+        ui.add(
+            egui::Slider::new(&mut self.slab_thickness, 0.1..=10.0).text(tr("Slab half thickness")),
+        )
+        .on_hover_text(tr(
+            "Half thickness of the anterior and dorsal slices in atlas units",
+        ));
+        ui.horizontal(|ui| {
+            ui.label(tr("Hover ring"));
+            ui.add(&mut self.hover_ring);
+        });
+        ui.horizontal(|ui| {
+            ui.label(tr("Guide lines"));
+            ui.add(&mut self.guide_line);
+        });
+        ui.separator();
+        ui.label(egui::RichText::new(tr("Units")).strong());
+        ui.horizontal(|ui| {
+            for unit in DistanceUnit::ALL {
+                ui.radio_value(&mut self.units, unit, unit.to_string());
+            }
+        });
+        if self.units == DistanceUnit::Microns {
+            ui.add(
+                egui::DragValue::new(&mut self.microns_per_unit)
+                    .speed(0.01)
+                    .range(0.01..=100.0)
+                    .prefix("µm per atlas unit: "),
+            );
+        }
+        ui.horizontal(|ui| {
+            let mut fixed = self.tick_decimals.is_some();
+            if ui.checkbox(&mut fixed, tr("Tick decimals")).changed() {
+                self.tick_decimals = fixed.then_some(1);
+            }
+            if let Some(decimals) = &mut self.tick_decimals {
+                ui.add(egui::DragValue::new(decimals).range(0..=6));
+            }
+        })
+        .response
+        .on_hover_text(tr(
+            "Digits after the decimal point of axis ticks; unchecked, as many as their spacing needs",
+        ));
+        ui.checkbox(&mut self.thousands_separators, tr("Thousands separators"))
+            .on_hover_text(tr("Axis ticks such as 1,200 rather than 1200"));
+        if !cfg!(target_arch = "wasm32") {
+            ui.separator();
+            ui.horizontal(|ui| {
+                ui.label(tr("Export directory:"));
+                ui.text_edit_singleline(&mut self.export_dir);
+            });
+        }
+        ui.separator();
+        if ui.button(tr("Restore defaults")).clicked() {
+            // Saved color themes are kept, only no longer used.
+            *self = Self {
+                color_themes: std::mem::take(&mut self.color_themes),
+                ..Self::default()
+            };
+        }
+    }
+}
+
+fn high_contrast(visuals: &mut Visuals) {
+    let fg = if visuals.dark_mode {
+        Color32::WHITE
+    } else {
+        Color32::BLACK
+    };
+    visuals.override_text_color = Some(fg);
+    visuals.window_stroke = Stroke::new(2.0, fg);
+    visuals.selection.stroke = Stroke::new(2.0, fg);
+    visuals.hyperlink_color = if visuals.dark_mode {
+        Color32::from_rgb(120, 200, 255)
+    } else {
+        Color32::from_rgb(0, 60, 200)
+    };
+    let widgets = &mut visuals.widgets;
+    for widget in [
+        &mut widgets.noninteractive,
+        &mut widgets.inactive,
+        &mut widgets.hovered,
+        &mut widgets.active,
+        &mut widgets.open,
+    ] {
+        widget.bg_stroke = Stroke::new(widget.bg_stroke.width.max(1.5), fg);
+        widget.fg_stroke = Stroke::new(widget.fg_stroke.width.max(1.5), fg);
+    }
+}
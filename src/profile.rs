@@ -0,0 +1,111 @@
+use std::collections::VecDeque;
+
+use egui::{Align2, Color32, FontId, Id, RichText};
+
+/// Number of frames the timings are averaged over.
+#[cfg(not(target_arch = "wasm32"))]
+const HISTORY: usize = 120;
+
+/// Rolling per-scope timings in milliseconds, kept in egui memory so any code with access
+/// to the [`egui::Context`] can record into it.
+#[derive(Clone, Default)]
+struct Timings {
+    scopes: Vec<(&'static str, VecDeque<f32>)>,
+}
+
+impl Timings {
+    #[cfg(not(target_arch = "wasm32"))]
+    fn record(&mut self, name: &'static str, ms: f32) {
+        let samples = match self.scopes.iter().position(|(n, _)| *n == name) {
+            Some(i) => &mut self.scopes[i].1,
+            None => {
+                self.scopes.push((name, VecDeque::with_capacity(HISTORY)));
+                &mut self.scopes.last_mut().expect("just pushed").1
+            }
+        };
+        if samples.len() == HISTORY {
+            samples.pop_front();
+        }
+        samples.push_back(ms);
+    }
+}
+
+fn timings_id() -> Id {
+    Id::new("profile_timings")
+}
+
+/// A running timing scope; call [`Scope::end`] to record it.
+pub struct Scope {
+    name: &'static str,
+    #[cfg(not(target_arch = "wasm32"))]
+    start: std::time::Instant,
+}
+
+/// Start timing the scope `name`. Timings are only taken on native builds.
+pub fn scope(name: &'static str) -> Scope {
+    Scope {
+        name,
+        #[cfg(not(target_arch = "wasm32"))]
+        start: std::time::Instant::now(),
+    }
+}
+
+impl Scope {
+    pub fn end(self, ctx: &egui::Context) {
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let ms = self.start.elapsed().as_secs_f32() * 1000.0;
+            ctx.data_mut(|d| {
+                d.get_temp_mut_or_default::<Timings>(timings_id())
+                    .record(self.name, ms)
+            });
+        }
+        #[cfg(target_arch = "wasm32")]
+        let _ = (self.name, ctx);
+    }
+}
+
+/// Small overlay in the bottom right corner with the last, mean and worst time of each
+/// recorded scope over the recent frames.
+pub fn overlay(ctx: &egui::Context) {
+    let timings = ctx.data(|d| d.get_temp::<Timings>(timings_id()).unwrap_or_default());
+    egui::Area::new(Id::new("profile_overlay"))
+        .anchor(Align2::RIGHT_BOTTOM, [-8.0, -8.0])
+        .interactable(false)
+        .show(ctx, |ui| {
+            egui::Frame::popup(ui.style()).show(ui, |ui| {
+                let font = FontId::monospace(11.0);
+                ui.label(
+                    RichText::new(format!(
+                        "{:<12}{:>7}{:>7}{:>7}",
+                        "ms", "last", "mean", "max"
+                    ))
+                    .font(font.clone())
+                    .strong(),
+                );
+                // Stays put while the app is idle, since frames are only drawn on input.
+                ui.label(
+                    RichText::new(format!("{:<12}{:>7}", "frame", ctx.cumulative_pass_nr()))
+                        .font(font.clone()),
+                );
+                if timings.scopes.is_empty() {
+                    ui.label(RichText::new("no timings on this platform").font(font.clone()));
+                }
+                for (name, samples) in &timings.scopes {
+                    let last = samples.back().copied().unwrap_or_default();
+                    let mean = samples.iter().sum::<f32>() / samples.len().max(1) as f32;
+                    let max = samples.iter().copied().fold(0.0, f32::max);
+                    let color = if max > 16.0 {
+                        Color32::from_rgb(255, 112, 67)
+                    } else {
+                        ui.visuals().text_color()
+                    };
+                    ui.label(
+                        RichText::new(format!("{name:<12}{last:>7.2}{mean:>7.2}{max:>7.2}"))
+                            .font(font.clone())
+                            .color(color),
+                    );
+                }
+            });
+        });
+}
@@ -0,0 +1,238 @@
+use egui::epaint::text::Fonts;
+use egui::epaint::{
+    self, CircleShape, ClippedShape, Mesh, RectShape, Shape, TessellationOptions, Tessellator,
+    TextShape,
+};
+use egui::{Color32, ColorImage, FontDefinitions, FontId, Pos2, Rect};
+
+use crate::figure::{Figure, Primitive};
+
+/// Largest side of the glyph atlas, as on most GPUs.
+const MAX_TEXTURE_SIDE: usize = 8192;
+
+/// Paint `figure` into an image with `scale` pixels per point. The figure is tessellated
+/// like the interface is, then filled on the CPU, so its size is not bound by the screen.
+pub fn render(figure: &Figure, scale: f32, fonts: FontDefinitions) -> ColorImage {
+    let fonts = Fonts::new(scale, MAX_TEXTURE_SIDE, fonts);
+    let mut shapes = Vec::new();
+    for group in &figure.groups {
+        for item in &group.items {
+            shapes.push(ClippedShape {
+                clip_rect: group.clip,
+                shape: to_shape(item, &fonts),
+            });
+        }
+    }
+    let font_image = fonts.image();
+    let discs = fonts.texture_atlas().lock().prepared_discs();
+    let options = TessellationOptions::default();
+    let mut tessellator = Tessellator::new(scale, options, font_image.size, discs);
+    // Coverage of the glyph atlas as egui shows it, see `FontImage::srgba_pixels`.
+    let coverage: Vec<f32> = font_image.pixels.iter().map(|c| c.powf(0.55)).collect();
+
+    let size = [
+        (figure.size.x * scale).ceil() as usize,
+        (figure.size.y * scale).ceil() as usize,
+    ];
+    let mut canvas = Canvas {
+        size,
+        pixels: vec![rgba(figure.background); size[0] * size[1]],
+    };
+    for primitive in tessellator.tessellate_shapes(shapes) {
+        if let epaint::Primitive::Mesh(mesh) = &primitive.primitive {
+            let clip = primitive.clip_rect * scale;
+            canvas.fill(mesh, scale, clip, (&coverage, font_image.size));
+        }
+    }
+    ColorImage {
+        size,
+        pixels: (canvas.pixels.into_iter())
+            .map(|[r, g, b, a]| {
+                let byte = |v: f32| (v * 255.0).round().clamp(0.0, 255.0) as u8;
+                Color32::from_rgba_premultiplied(byte(r), byte(g), byte(b), byte(a))
+            })
+            .collect(),
+    }
+}
+
+fn to_shape(item: &Primitive, fonts: &Fonts) -> Shape {
+    match item {
+        Primitive::Circle {
+            center,
+            radius,
+            fill,
+            stroke,
+        } => Shape::Circle(CircleShape {
+            center: *center,
+            radius: *radius,
+            fill: *fill,
+            stroke: *stroke,
+        }),
+        Primitive::Line { points, stroke } => Shape::line(points.clone(), *stroke),
+        Primitive::Rect { rect, fill, stroke } => {
+            Shape::Rect(RectShape::new(*rect, 0.0, *fill, *stroke))
+        }
+        Primitive::Text {
+            pos,
+            anchor,
+            text,
+            size,
+            color,
+            vertical,
+        } => {
+            let galley = fonts.layout_no_wrap(text.clone(), FontId::proportional(*size), *color);
+            let extent = galley.size();
+            if *vertical {
+                // Turned a quarter counterclockwise about its top left corner.
+                let corner = Pos2::new(pos.x - extent.y / 2.0, pos.y + extent.x / 2.0);
+                let angle = -std::f32::consts::FRAC_PI_2;
+                Shape::Text(TextShape::new(corner, galley, *color).with_angle(angle))
+            } else {
+                let corner = anchor.anchor_size(*pos, extent).min;
+                Shape::galley(corner, galley, *color)
+            }
+        }
+        Primitive::Gradient { rect, colors } => {
+            let mut mesh = Mesh::default();
+            let steps = colors.len().saturating_sub(1).max(1);
+            for (i, color) in colors.iter().enumerate() {
+                let y = rect.bottom() - i as f32 / steps as f32 * rect.height();
+                mesh.colored_vertex(Pos2::new(rect.left(), y), *color);
+                mesh.colored_vertex(Pos2::new(rect.right(), y), *color);
+                if i > 0 {
+                    let k = 2 * i as u32;
+                    mesh.add_triangle(k - 2, k - 1, k);
+                    mesh.add_triangle(k - 1, k, k + 1);
+                }
+            }
+            Shape::mesh(mesh)
+        }
+    }
+}
+
+/// Premultiplied color with components from 0 to 1.
+fn rgba(color: Color32) -> [f32; 4] {
+    color.to_array().map(|v| v as f32 / 255.0)
+}
+
+/// Pixels being painted, premultiplied, row by row from the top.
+struct Canvas {
+    size: [usize; 2],
+    pixels: Vec<[f32; 4]>,
+}
+
+impl Canvas {
+    /// Blend the triangles of `mesh`, given in points, over the pixels inside `clip`. All
+    /// meshes of a figure are textured by the glyph atlas, whose `coverage` they multiply.
+    fn fill(
+        &mut self,
+        mesh: &Mesh,
+        scale: f32,
+        clip: Rect,
+        (coverage, size): (&[f32], [usize; 2]),
+    ) {
+        let [width, height] = self.size;
+        let texel = |uv: Pos2| {
+            let x = ((uv.x * size[0] as f32) as usize).min(size[0] - 1);
+            let y = ((uv.y * size[1] as f32) as usize).min(size[1] - 1);
+            coverage[y * size[0] + x]
+        };
+        for triangle in mesh.indices.chunks_exact(3) {
+            let [a, b, c] = [0, 1, 2].map(|i| &mesh.vertices[triangle[i] as usize]);
+            let [pa, pb, pc] = [a, b, c].map(|v| v.pos * scale);
+            let area = edge(pa, pb, pc);
+            if area.abs() < 1e-6 {
+                continue;
+            }
+            let min = pa.min(pb).min(pc).max(clip.min).max(Pos2::ZERO);
+            let max = pa.max(pb).max(pc).min(clip.max);
+            let (x0, y0) = (min.x.floor() as usize, min.y.floor() as usize);
+            let x1 = (max.x.ceil().max(0.0) as usize).min(width);
+            let y1 = (max.y.ceil().max(0.0) as usize).min(height);
+            let [ca, cb, cc] = [a, b, c].map(|v| rgba(v.color));
+            for y in y0..y1 {
+                for x in x0..x1 {
+                    let p = Pos2::new(x as f32 + 0.5, y as f32 + 0.5);
+                    if !clip.contains(p) {
+                        continue;
+                    }
+                    let wa = edge(pb, pc, p) / area;
+                    let wb = edge(pc, pa, p) / area;
+                    let wc = 1.0 - wa - wb;
+                    if wa < 0.0 || wb < 0.0 || wc < 0.0 {
+                        continue;
+                    }
+                    let uv = Pos2::new(
+                        wa * a.uv.x + wb * b.uv.x + wc * c.uv.x,
+                        wa * a.uv.y + wb * b.uv.y + wc * c.uv.y,
+                    );
+                    let t = texel(uv);
+                    let src: [f32; 4] =
+                        std::array::from_fn(|i| (wa * ca[i] + wb * cb[i] + wc * cc[i]) * t);
+                    let dst = &mut self.pixels[y * width + x];
+                    for i in 0..4 {
+                        dst[i] = src[i] + dst[i] * (1.0 - src[3]);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Twice the signed area of the triangle `a`, `b`, `p`.
+fn edge(a: Pos2, b: Pos2, p: Pos2) -> f32 {
+    (b.x - a.x) * (p.y - a.y) - (b.y - a.y) * (p.x - a.x)
+}
+
+/// Encode `image` as an RGBA PNG.
+pub fn encode_png(image: &ColorImage) -> Vec<u8> {
+    let [width, height] = image.size;
+    let mut rows = Vec::with_capacity((4 * width + 1) * height);
+    for row in image.pixels.chunks_exact(width.max(1)) {
+        // Each row starts with its filter type, none here.
+        rows.push(0);
+        for color in row {
+            rows.extend(color.to_srgba_unmultiplied());
+        }
+    }
+    let mut header = Vec::with_capacity(13);
+    header.extend((width as u32).to_be_bytes());
+    header.extend((height as u32).to_be_bytes());
+    // 8 bits per channel, RGBA, deflate, no filtering method, not interlaced.
+    header.extend([8, 6, 0, 0, 0]);
+
+    let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+    chunk(&mut png, b"IHDR", &header);
+    chunk(
+        &mut png,
+        b"IDAT",
+        &miniz_oxide::deflate::compress_to_vec_zlib(&rows, 4),
+    );
+    chunk(&mut png, b"IEND", &[]);
+    png
+}
+
+fn chunk(png: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    png.extend((data.len() as u32).to_be_bytes());
+    let start = png.len();
+    png.extend(kind);
+    png.extend(data);
+    let crc = crc32(&png[start..]);
+    png.extend(crc.to_be_bytes());
+}
+
+/// CRC-32 of the PNG chunks, bit by bit; images are written rarely.
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in bytes {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
@@ -0,0 +1,311 @@
+use std::collections::HashMap;
+
+use serde::Deserialize;
+
+use crate::dataset::Neuron;
+use crate::functional::{self, FunctionalType};
+use crate::ganglion;
+use crate::i18n::tr;
+use crate::symmetry;
+
+/// Neurons of the pharyngeal nervous system.
+const PHARYNGEAL: [&str; 20] = [
+    "I1L", "I1R", "I2L", "I2R", "I3", "I4", "I5", "I6", "M1", "M2L", "M2R", "M3L", "M3R", "M4",
+    "M5", "MCL", "MCR", "MI", "NSML", "NSMR",
+];
+
+/// Classes of the motor neurons along the ventral nerve cord, numbered from the head.
+const VENTRAL_CORD: [&str; 8] = ["AS", "DA", "DB", "DD", "VA", "VB", "VC", "VD"];
+
+/// Where along the body a neuron sits.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, serde::Deserialize, serde::Serialize)]
+pub enum BodyRegion {
+    Head,
+    Midbody,
+    Tail,
+}
+
+impl BodyRegion {
+    pub const ALL: [Self; 3] = [Self::Head, Self::Midbody, Self::Tail];
+
+    fn parse(text: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|r| format!("{r:?}").eq_ignore_ascii_case(text.trim()))
+    }
+}
+
+impl std::fmt::Display for BodyRegion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Head => write!(f, "{}", tr("Head")),
+            Self::Midbody => write!(f, "{}", tr("Midbody")),
+            Self::Tail => write!(f, "{}", tr("Tail")),
+        }
+    }
+}
+
+/// Read a body region column, leaving out values that name none.
+pub fn deserialize_body_region<'de, D>(deserializer: D) -> Result<Option<BodyRegion>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let text = Option::<String>::deserialize(deserializer)?;
+    Ok(text.as_deref().and_then(BodyRegion::parse))
+}
+
+/// Read a yes/no column, leaving out values that are neither. Booleans and 0/1 numbers, as
+/// in JSON or as inferred from CSV fields, are accepted as well as text.
+pub fn deserialize_flag<'de, D>(deserializer: D) -> Result<Option<bool>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    deserializer.deserialize_option(FlagVisitor)
+}
+
+struct FlagVisitor;
+
+impl<'de> serde::de::Visitor<'de> for FlagVisitor {
+    type Value = Option<bool>;
+
+    fn expecting(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("a yes/no flag")
+    }
+
+    fn visit_none<E>(self) -> Result<Self::Value, E> {
+        Ok(None)
+    }
+
+    fn visit_unit<E>(self) -> Result<Self::Value, E> {
+        Ok(None)
+    }
+
+    fn visit_some<D: serde::Deserializer<'de>>(self, d: D) -> Result<Self::Value, D::Error> {
+        d.deserialize_any(self)
+    }
+
+    fn visit_bool<E>(self, v: bool) -> Result<Self::Value, E> {
+        Ok(Some(v))
+    }
+
+    fn visit_u64<E>(self, v: u64) -> Result<Self::Value, E> {
+        Ok(match v {
+            0 => Some(false),
+            1 => Some(true),
+            _ => None,
+        })
+    }
+
+    fn visit_i64<E>(self, v: i64) -> Result<Self::Value, E> {
+        Ok(match v {
+            0 => Some(false),
+            1 => Some(true),
+            _ => None,
+        })
+    }
+
+    fn visit_f64<E>(self, _: f64) -> Result<Self::Value, E> {
+        Ok(None)
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Self::Value, E> {
+        Ok(match v.trim().to_ascii_lowercase().as_str() {
+            "1" | "true" | "yes" | "pharyngeal" => Some(true),
+            "0" | "false" | "no" | "extrapharyngeal" => Some(false),
+            _ => None,
+        })
+    }
+}
+
+/// The body region of `neuron`, as given by its file or else by the third of the
+/// anterior–posterior `extent` of the animal it falls in.
+pub fn body_region(neuron: &Neuron, (anterior, posterior): (f32, f32)) -> BodyRegion {
+    if let Some(region) = neuron.region {
+        return region;
+    }
+    let third = (posterior - anterior) / 3.0;
+    if neuron.x <= anterior + third {
+        BodyRegion::Head
+    } else if neuron.x < posterior - third {
+        BodyRegion::Midbody
+    } else {
+        BodyRegion::Tail
+    }
+}
+
+/// Whether `neuron` belongs to the pharyngeal nervous system, as given by its file or else
+/// by its name.
+pub fn is_pharyngeal(neuron: &Neuron) -> bool {
+    neuron
+        .pharyngeal
+        .unwrap_or_else(|| PHARYNGEAL.contains(&neuron.name.as_str()))
+}
+
+/// Anatomical regions that can be searched for by keyword instead of by neuron names.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Region {
+    Head,
+    Tail,
+    /// Motor neurons of the ventral nerve cord.
+    VentralCord,
+    /// Neurons of the pharyngeal nervous system.
+    Pharynx,
+    /// Neurons of the ganglia of the tail, as given by [`ganglion::of`].
+    PreanalGanglion,
+    /// See [`Region::PreanalGanglion`].
+    DorsorectalGanglion,
+    /// See [`Region::PreanalGanglion`].
+    LumbarGanglion,
+}
+
+impl Region {
+    pub const ALL: [Self; 7] = [
+        Self::Head,
+        Self::Tail,
+        Self::VentralCord,
+        Self::Pharynx,
+        Self::PreanalGanglion,
+        Self::DorsorectalGanglion,
+        Self::LumbarGanglion,
+    ];
+
+    /// The keyword standing for the region in a search.
+    pub fn keyword(&self) -> &'static str {
+        match self {
+            Self::Head => "head",
+            Self::Tail => "tail",
+            Self::VentralCord => "ventral-cord",
+            Self::Pharynx => "pharynx",
+            Self::PreanalGanglion => "preanal",
+            Self::DorsorectalGanglion => "dorsorectal",
+            Self::LumbarGanglion => "lumbar",
+        }
+    }
+
+    /// The region a search term names, ignoring case.
+    pub fn from_keyword(term: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|r| r.keyword().eq_ignore_ascii_case(term))
+    }
+
+    /// Whether `neuron` lies in the region, for an animal spanning `extent` along the
+    /// anterior–posterior axis.
+    pub fn contains(&self, neuron: &Neuron, extent: (f32, f32)) -> bool {
+        match self {
+            Self::Head => body_region(neuron, extent) == BodyRegion::Head,
+            Self::Tail => body_region(neuron, extent) == BodyRegion::Tail,
+            Self::VentralCord => VENTRAL_CORD.iter().any(|class| {
+                neuron.name.strip_prefix(class).is_some_and(|number| {
+                    !number.is_empty() && number.bytes().all(|b| b.is_ascii_digit())
+                })
+            }),
+            Self::Pharynx => is_pharyngeal(neuron),
+            Self::PreanalGanglion => ganglion::of(neuron) == Some("Preanal"),
+            Self::DorsorectalGanglion => ganglion::of(neuron) == Some("Dorsorectal"),
+            Self::LumbarGanglion => ganglion::of(neuron) == Some("Lumbar"),
+        }
+    }
+}
+
+/// Smallest and largest anterior–posterior coordinate of `neurons`.
+pub fn extent<'a>(neurons: impl IntoIterator<Item = &'a Neuron>) -> (f32, f32) {
+    neurons
+        .into_iter()
+        .fold((f32::INFINITY, f32::NEG_INFINITY), |(min, max), n| {
+            (min.min(n.x), max.max(n.x))
+        })
+}
+
+/// Quick filters on the region columns, set from the buttons of the side panel.
+#[derive(
+    Clone, Copy, Debug, Default, PartialEq, Eq, Hash, serde::Deserialize, serde::Serialize,
+)]
+pub struct RegionFilter {
+    pub body: Option<BodyRegion>,
+    /// Keep only pharyngeal (`true`) or extrapharyngeal (`false`) neurons.
+    pub pharyngeal: Option<bool>,
+    /// Keep only neurons of this functional type.
+    pub function: Option<FunctionalType>,
+    /// Keep only neurons with (`true`) or without (`false`) a left/right partner.
+    pub paired: Option<bool>,
+}
+
+impl RegionFilter {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Whether `neuron`, one of `neurons` spanning `extent`, passes the filters.
+    pub fn contains(
+        &self,
+        neuron: &Neuron,
+        extent: (f32, f32),
+        neurons: &HashMap<String, Neuron>,
+    ) -> bool {
+        self.body.map_or(true, |b| body_region(neuron, extent) == b)
+            && self.pharyngeal.map_or(true, |p| is_pharyngeal(neuron) == p)
+            && (self.function).map_or(true, |f| functional::of(neuron) == Some(f))
+            && (self.paired).map_or(true, |p| symmetry::is_paired(&neuron.name, neurons) == p)
+    }
+
+    /// Go from all neurons to the pharyngeal nervous system alone, then to the rest of the
+    /// neurons, and back.
+    pub fn cycle_pharyngeal(&mut self) {
+        self.pharyngeal = match self.pharyngeal {
+            None => Some(true),
+            Some(true) => Some(false),
+            Some(false) => None,
+        };
+    }
+
+    /// Toggle buttons for each body region, for pharyngeal or extrapharyngeal neurons, for
+    /// paired or unpaired neurons and for each functional type; clicking the active one turns
+    /// it off again.
+    pub fn ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal_wrapped(|ui| {
+            ui.label(tr("Region:"));
+            for region in BodyRegion::ALL {
+                let active = self.body == Some(region);
+                if ui.selectable_label(active, region.to_string()).clicked() {
+                    self.body = (!active).then_some(region);
+                }
+            }
+            ui.separator();
+            for (pharyngeal, text) in [(true, "Pharyngeal"), (false, "Extrapharyngeal")] {
+                let active = self.pharyngeal == Some(pharyngeal);
+                if ui.selectable_label(active, tr(text)).clicked() {
+                    self.pharyngeal = (!active).then_some(pharyngeal);
+                }
+            }
+            ui.separator();
+            for (paired, text, hint) in [
+                (true, "Paired", "Neurons with a left/right partner"),
+                (
+                    false,
+                    "Unpaired",
+                    "Neurons without a partner, such as AVL, RID and AQR",
+                ),
+            ] {
+                let active = self.paired == Some(paired);
+                if ui
+                    .selectable_label(active, tr(text))
+                    .on_hover_text(tr(hint))
+                    .clicked()
+                {
+                    self.paired = (!active).then_some(paired);
+                }
+            }
+        });
+        ui.horizontal_wrapped(|ui| {
+            ui.label(tr("Type:"));
+            for function in FunctionalType::ALL {
+                let active = self.function == Some(function);
+                let text = egui::RichText::new(function.to_string()).color(function.color());
+                if ui.selectable_label(active, text).clicked() {
+                    self.function = (!active).then_some(function);
+                }
+            }
+        });
+    }
+}
@@ -0,0 +1,94 @@
+use egui::Color32;
+use egui_plot::{Line, PlotPoints, PlotUi};
+
+use crate::style::Orientation;
+use crate::view::View;
+
+pub const ROI_COLOR: Color32 = Color32::from_rgb(0, 230, 118);
+
+/// Axis-aligned 3D region of interest in atlas coordinates, built from rectangles drawn in
+/// two (or three) projections. An unset axis is unbounded.
+#[derive(Clone, Debug, Default, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct Roi {
+    /// Ranges along x, y and z.
+    pub ranges: [Option<[f32; 2]>; 3],
+}
+
+impl Roi {
+    pub fn is_empty(&self) -> bool {
+        self.ranges.iter().all(Option::is_none)
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.ranges.iter().all(Option::is_some)
+    }
+
+    pub fn contains(&self, p: [f32; 3]) -> bool {
+        self.ranges
+            .iter()
+            .zip(p)
+            .all(|(range, v)| range.map_or(true, |[lo, hi]| (lo..=hi).contains(&v)))
+    }
+
+    /// Constrain the two axes of `view` to the rectangle spanned by `a` and `b`, given in
+    /// displayed (possibly flipped) plot coordinates.
+    pub fn set_from_view(&mut self, view: View, a: [f64; 2], b: [f64; 2], o: Orientation) {
+        let to_atlas = |p: [f64; 2]| {
+            let mut atlas = [0.0; 3];
+            for (axis, v) in view.unproject(p) {
+                atlas[axis] = v;
+            }
+            o.apply(atlas)
+        };
+        let (pa, pb) = (to_atlas(a), to_atlas(b));
+        for (axis, _) in view.unproject(a) {
+            let (lo, hi) = (pa[axis].min(pb[axis]), pa[axis].max(pb[axis]));
+            self.ranges[axis] = Some([lo, hi]);
+        }
+    }
+
+    /// Outline the box in `view`; unbounded axes extend to the visible plot area.
+    pub fn draw(&self, plot_ui: &mut PlotUi, view: View, o: Orientation) {
+        if self.is_empty() {
+            return;
+        }
+        let bounds = plot_ui.plot_bounds();
+        let (min, max) = (bounds.min(), bounds.max());
+        // Corners of the box in atlas coordinates, with unbounded axes filled from the plot.
+        let mut corners = Vec::with_capacity(2);
+        for (k, v) in [(0, [min[0], min[1]]), (1, [max[0], max[1]])] {
+            let mut atlas = [0.0f32; 3];
+            for (axis, value) in view.unproject(v) {
+                atlas[axis] = value;
+            }
+            let atlas = o.apply(atlas);
+            let mut corner = atlas;
+            for (axis, range) in self.ranges.iter().enumerate() {
+                if let Some(r) = range {
+                    corner[axis] = r[k];
+                }
+            }
+            corners.push(view.project(o.apply(corner)));
+        }
+        let [a, b] = [corners[0], corners[1]];
+        let outline = vec![a, [b[0], a[1]], b, [a[0], b[1]], a];
+        plot_ui.line(
+            Line::new(PlotPoints::new(outline))
+                .color(ROI_COLOR)
+                .width(1.5)
+                .style(egui_plot::LineStyle::dashed_loose()),
+        );
+    }
+
+    pub fn describe(&self) -> String {
+        ["x", "y", "z"]
+            .iter()
+            .zip(&self.ranges)
+            .map(|(axis, range)| match range {
+                Some([lo, hi]) => format!("{axis}: {lo:.1}..{hi:.1}"),
+                None => format!("{axis}: any"),
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
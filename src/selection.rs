@@ -0,0 +1,102 @@
+use std::collections::{BTreeSet, HashMap};
+
+use crate::i18n::tr;
+
+/// Characters accepted between names in a pasted or imported list.
+const NAME_SEPARATORS: [char; 6] = [' ', ';', ',', '\t', '\n', '\r'];
+
+/// Result of matching a free-form name list against the loaded atlas.
+#[derive(Default, serde::Deserialize, serde::Serialize)]
+pub struct NameListMatch {
+    pub matched: BTreeSet<String>,
+    pub unmatched: Vec<String>,
+}
+
+/// Split a pasted list (or the content of a text/CSV file) into candidate neuron names.
+///
+/// Numeric fields are dropped so that a CSV with coordinates can be imported as-is.
+pub fn parse_name_list(text: &str) -> Vec<&str> {
+    text.split(&NAME_SEPARATORS[..])
+        .map(|x| x.trim_matches(|c: char| c == '"' || c == '\''))
+        .filter(|x| !x.is_empty() && x.parse::<f64>().is_err())
+        .collect()
+}
+
+/// Match `names` against the keys of `data`, ignoring case.
+pub fn match_names<V>(names: &[&str], data: &HashMap<String, V>) -> NameListMatch {
+    let lookup: HashMap<String, &String> = data.keys().map(|k| (k.to_uppercase(), k)).collect();
+    let mut result = NameListMatch::default();
+    for name in names {
+        match lookup.get(&name.to_uppercase()) {
+            Some(key) => {
+                result.matched.insert((*key).clone());
+            }
+            None => {
+                if !result.unmatched.iter().any(|x| x == name) {
+                    result.unmatched.push(name.to_string());
+                }
+            }
+        }
+    }
+    result
+}
+
+/// Membership of a neuron in two compared name lists.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Membership {
+    OnlyA,
+    OnlyB,
+    Both,
+    Neither,
+}
+
+impl Membership {
+    pub const ALL: [Self; 4] = [Self::OnlyA, Self::OnlyB, Self::Both, Self::Neither];
+
+    pub fn color(&self) -> egui::Color32 {
+        match self {
+            Self::OnlyA => egui::Color32::from_rgb(30, 136, 229),
+            Self::OnlyB => egui::Color32::from_rgb(255, 143, 0),
+            Self::Both => egui::Color32::from_rgb(67, 160, 71),
+            Self::Neither => egui::Color32::from_gray(120),
+        }
+    }
+}
+
+impl std::fmt::Display for Membership {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::OnlyA => f.write_str(tr("Only A")),
+            Self::OnlyB => f.write_str(tr("Only B")),
+            Self::Both => f.write_str(tr("Both")),
+            Self::Neither => f.write_str(tr("Neither")),
+        }
+    }
+}
+
+/// Two name lists (e.g. cells detected in two animals) compared against the atlas.
+#[derive(Default, serde::Deserialize, serde::Serialize)]
+pub struct ListComparison {
+    pub text_a: String,
+    pub text_b: String,
+    #[serde(skip)]
+    pub a: NameListMatch,
+    #[serde(skip)]
+    pub b: NameListMatch,
+}
+
+impl ListComparison {
+    pub fn update<V>(&mut self, data: &HashMap<String, V>) {
+        self.a = match_names(&parse_name_list(&self.text_a), data);
+        self.b = match_names(&parse_name_list(&self.text_b), data);
+    }
+
+    pub fn membership(&self, name: &str) -> Membership {
+        match (self.a.matched.contains(name), self.b.matched.contains(name)) {
+            (true, true) => Membership::Both,
+            (true, false) => Membership::OnlyA,
+            (false, true) => Membership::OnlyB,
+            (false, false) => Membership::Neither,
+        }
+    }
+}
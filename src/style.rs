@@ -0,0 +1,188 @@
+use egui::{Color32, Rangef, Stroke};
+use egui_plot::{GridMark, Line, Plot, PlotPoints, PlotResponse, PlotUi};
+
+use std::ops::RangeInclusive;
+
+use crate::dataset::Neuron;
+use crate::i18n::tr;
+use crate::prefs::TickFormat;
+
+/// Figure styling of a single plot, independent of the global egui theme.
+#[derive(Clone, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct PlotStyle {
+    pub show_grid: bool,
+    pub show_axes: bool,
+    /// Plot background; `None` follows the theme.
+    pub background: Option<Color32>,
+    /// Color of axis labels, ticks and grid lines; `None` follows the theme.
+    pub axis_color: Option<Color32>,
+    /// Minimum distance between grid lines in points; larger values give fewer ticks.
+    pub tick_spacing: f32,
+}
+
+impl Default for PlotStyle {
+    fn default() -> Self {
+        Self {
+            show_grid: true,
+            show_axes: true,
+            background: None,
+            axis_color: None,
+            tick_spacing: 8.0,
+        }
+    }
+}
+
+impl PlotStyle {
+    pub fn apply<'a>(&self, plot: Plot<'a>) -> Plot<'a> {
+        plot.show_grid(self.show_grid)
+            .show_axes(self.show_axes)
+            .grid_spacing(Rangef::new(self.tick_spacing, self.tick_spacing.max(300.0)))
+    }
+
+    /// Show `plot` with this style applied.
+    pub fn show<'a, R>(
+        &self,
+        ui: &mut egui::Ui,
+        plot: Plot<'a>,
+        build_fn: impl FnOnce(&mut PlotUi) -> R + 'a,
+    ) -> PlotResponse<R> {
+        ui.scope(|ui| {
+            self.apply_visuals(ui.visuals_mut());
+            self.apply(plot).show(ui, build_fn)
+        })
+        .inner
+    }
+
+    /// Patch the visuals egui_plot reads its background and axis colors from.
+    pub fn apply_visuals(&self, visuals: &mut egui::Visuals) {
+        if let Some(background) = self.background {
+            visuals.extreme_bg_color = background;
+        }
+        if let Some(color) = self.axis_color {
+            visuals.override_text_color = Some(color);
+        }
+    }
+
+    pub fn ui(&mut self, ui: &mut egui::Ui) {
+        ui.checkbox(&mut self.show_grid, tr("Grid"));
+        ui.checkbox(&mut self.show_axes, tr("Axes"));
+        optional_color(ui, tr("Background"), &mut self.background, Color32::WHITE);
+        optional_color(ui, tr("Axis color"), &mut self.axis_color, Color32::GRAY);
+        ui.add(egui::Slider::new(&mut self.tick_spacing, 4.0..=120.0).text(tr("Tick spacing")));
+    }
+}
+
+fn optional_color(ui: &mut egui::Ui, label: &str, color: &mut Option<Color32>, initial: Color32) {
+    ui.horizontal(|ui| {
+        let mut custom = color.is_some();
+        if ui.checkbox(&mut custom, label).changed() {
+            *color = custom.then_some(initial);
+        }
+        if let Some(color) = color {
+            ui.color_edit_button_srgba(color);
+        }
+    });
+}
+
+/// Display direction of the body axes, since labs mount and display worms with opposite
+/// conventions. Flipping is applied to the displayed coordinates only; tick labels keep
+/// showing atlas values.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Hash, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct Orientation {
+    pub flip_ap: bool,
+    pub flip_dv: bool,
+}
+
+impl Orientation {
+    pub fn is_identity(&self) -> bool {
+        !self.flip_ap && !self.flip_dv
+    }
+
+    fn signs(&self) -> [f32; 3] {
+        [
+            if self.flip_ap { -1.0 } else { 1.0 },
+            if self.flip_dv { -1.0 } else { 1.0 },
+            1.0,
+        ]
+    }
+
+    pub fn apply(&self, p: [f32; 3]) -> [f32; 3] {
+        let s = self.signs();
+        [p[0] * s[0], p[1] * s[1], p[2] * s[2]]
+    }
+
+    pub fn apply_cov(&self, cov: [[f32; 3]; 3]) -> [[f32; 3]; 3] {
+        let s = self.signs();
+        let mut out = cov;
+        for (i, row) in out.iter_mut().enumerate() {
+            for (j, v) in row.iter_mut().enumerate() {
+                *v *= s[i] * s[j];
+            }
+        }
+        out
+    }
+
+    pub fn apply_neuron(&self, neuron: &Neuron) -> Neuron {
+        let [x, y, z] = self.apply(neuron.position());
+        Neuron {
+            x,
+            y,
+            z,
+            ..neuron.clone()
+        }
+    }
+
+    pub fn ap_label(&self) -> &'static str {
+        if self.flip_ap {
+            tr("Posterior–Anterior")
+        } else {
+            tr("Anterior–Posterior")
+        }
+    }
+
+    pub fn dv_label(&self) -> &'static str {
+        if self.flip_dv {
+            tr("Dorsal–Ventral")
+        } else {
+            tr("Ventral–Dorsal")
+        }
+    }
+
+    pub fn ui(&mut self, ui: &mut egui::Ui) {
+        ui.checkbox(&mut self.flip_ap, tr("Flip anterior–posterior"));
+        ui.checkbox(&mut self.flip_dv, tr("Flip dorsal–ventral"));
+    }
+}
+
+/// Tick formatter printing positions in the preferred unit on an axis that may be displayed
+/// mirrored.
+pub fn tick_formatter(
+    flipped: bool,
+    ticks: TickFormat,
+) -> impl Fn(GridMark, &RangeInclusive<f64>) -> String {
+    move |mark, _range| {
+        let value = if flipped { -mark.value } else { mark.value };
+        ticks.label(value, mark.step_size)
+    }
+}
+
+/// Circle of `radius` screen points around `center`, drawn as a line so its width can be
+/// set, unlike the outline of an unfilled marker.
+pub fn ring(plot_ui: &mut PlotUi, center: [f64; 2], radius: f32, stroke: Stroke) {
+    const SEGMENTS: usize = 32;
+    let [dx, dy] = plot_ui.transform().dvalue_dpos();
+    let (rx, ry) = (radius as f64 * dx.abs(), radius as f64 * dy.abs());
+    let points: Vec<[f64; 2]> = (0..=SEGMENTS)
+        .map(|i| {
+            let angle = i as f64 / SEGMENTS as f64 * std::f64::consts::TAU;
+            [center[0] + rx * angle.cos(), center[1] + ry * angle.sin()]
+        })
+        .collect();
+    plot_ui.line(
+        Line::new(PlotPoints::new(points))
+            .color(stroke.color)
+            .width(stroke.width),
+    );
+}
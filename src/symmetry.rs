@@ -0,0 +1,109 @@
+use std::collections::HashMap;
+
+use egui::Color32;
+
+use crate::dataset::Neuron;
+use crate::i18n::tr;
+use crate::index;
+
+/// Color of the most asymmetric pairs on the canvas.
+pub const ASYMMETRY_COLOR: Color32 = Color32::from_rgb(255, 160, 0);
+
+/// How far a left/right pair of neurons is from mirror symmetry.
+pub struct PairAsymmetry {
+    pub class: String,
+    pub left: [f32; 3],
+    pub right: [f32; 3],
+    /// Left position minus the mirrored right position, in atlas units.
+    pub offset: [f32; 3],
+    /// Length of `offset`.
+    pub distance: f32,
+    /// Distance between the two NeuroPAL colors, each channel in `0..=1`.
+    pub color: f32,
+}
+
+/// Reflect `p` across the midsagittal plane; left and right are told apart by the sign of z.
+pub fn mirror(p: [f32; 3]) -> [f32; 3] {
+    [p[0], p[1], -p[2]]
+}
+
+/// Name of the neuron on the other side of `name`, such as `AVAR` for `AVAL`.
+pub fn partner(name: &str) -> Option<String> {
+    if let Some(class) = name.strip_suffix('L') {
+        return Some(format!("{class}R"));
+    }
+    name.strip_suffix('R').map(|class| format!("{class}L"))
+}
+
+/// Whether the neuron `name` has its partner on the other side among `neurons`. Unpaired
+/// neurons, such as AVL, RID and AQR, lie on the midline.
+pub fn is_paired(name: &str, neurons: &HashMap<String, Neuron>) -> bool {
+    partner(name).is_some_and(|partner| neurons.contains_key(&partner))
+}
+
+/// Every class with both an `L` and an `R` neuron in `data`, compared with the right one
+/// mirrored onto the left side. Sorted by class.
+pub fn pairs(data: &HashMap<String, Neuron>) -> Vec<PairAsymmetry> {
+    let mut pairs: Vec<_> = data
+        .values()
+        .filter(|n| n.name.ends_with('L'))
+        .filter_map(|left| {
+            let class = index::class_of(&left.name)?;
+            let right = data.get(&format!("{class}R"))?;
+            let mirrored = mirror(right.position());
+            let offset = [0, 1, 2].map(|i| left.position()[i] - mirrored[i]);
+            Some(PairAsymmetry {
+                class: class.to_owned(),
+                left: left.position(),
+                right: right.position(),
+                offset,
+                distance: offset.iter().map(|v| v * v).sum::<f32>().sqrt(),
+                color: ((left.r - right.r).powi(2)
+                    + (left.g - right.g).powi(2)
+                    + (left.b - right.b).powi(2))
+                .sqrt(),
+            })
+        })
+        .collect();
+    pairs.sort_unstable_by(|a, b| a.class.cmp(&b.class));
+    pairs
+}
+
+/// Column the symmetry table is sorted by.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub enum SymmetryColumn {
+    Class,
+    #[default]
+    Position,
+    Color,
+}
+
+impl SymmetryColumn {
+    pub const ALL: [Self; 3] = [Self::Class, Self::Position, Self::Color];
+
+    /// Sort `pairs` by this column, the largest asymmetry first unless `ascending`.
+    pub fn sort(&self, pairs: &mut [PairAsymmetry], ascending: bool) {
+        pairs.sort_by(|a, b| {
+            let order = match self {
+                Self::Class => a.class.cmp(&b.class),
+                Self::Position => b.distance.total_cmp(&a.distance),
+                Self::Color => b.color.total_cmp(&a.color),
+            };
+            if ascending == (*self == Self::Class) {
+                order
+            } else {
+                order.reverse()
+            }
+        });
+    }
+}
+
+impl std::fmt::Display for SymmetryColumn {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Class => f.write_str(tr("Class")),
+            Self::Position => f.write_str(tr("Position")),
+            Self::Color => f.write_str(tr("Color")),
+        }
+    }
+}
@@ -0,0 +1,80 @@
+use egui::{Color32, Stroke, Visuals};
+
+use crate::i18n::tr;
+
+/// A named set of interface colors made in the Preferences window, applied on top of the
+/// dark or light theme.
+#[derive(Clone, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct ColorTheme {
+    pub name: String,
+    /// Selected text and widgets, and links.
+    pub accent: Color32,
+    /// Outline of selected neurons.
+    pub highlight: Color32,
+    /// Background of the plots without a background of their own; `None` follows the theme.
+    pub plot_background: Option<Color32>,
+    /// Width of the outline around each marker, in points; 0 keeps the one of the Markers
+    /// preferences.
+    pub outline_width: f32,
+    /// `None` uses the text color of the theme.
+    pub outline_color: Option<Color32>,
+}
+
+impl Default for ColorTheme {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            accent: Color32::from_rgb(22, 131, 240),
+            highlight: Color32::from_rgb(255, 200, 0),
+            plot_background: None,
+            outline_width: 0.0,
+            outline_color: None,
+        }
+    }
+}
+
+impl ColorTheme {
+    pub fn apply(&self, visuals: &mut Visuals) {
+        visuals.selection.bg_fill = self.accent.gamma_multiply(0.6);
+        visuals.hyperlink_color = self.accent;
+        visuals.widgets.hovered.bg_stroke = Stroke::new(1.0, self.accent);
+        if let Some(background) = self.plot_background {
+            visuals.extreme_bg_color = background;
+        }
+    }
+
+    pub fn ui(&mut self, ui: &mut egui::Ui) {
+        egui::Grid::new("color_theme")
+            .num_columns(2)
+            .show(ui, |ui| {
+                ui.label(tr("Name:"));
+                ui.text_edit_singleline(&mut self.name);
+                ui.end_row();
+                ui.label(tr("Accent"));
+                ui.color_edit_button_srgba(&mut self.accent);
+                ui.end_row();
+                ui.label(tr("Highlight"));
+                ui.color_edit_button_srgba(&mut self.highlight);
+                ui.end_row();
+                optional_color(ui, tr("Plot background"), &mut self.plot_background);
+                ui.label(tr("Marker outline"));
+                ui.add(egui::Slider::new(&mut self.outline_width, 0.0..=4.0).suffix(" pt"));
+                ui.end_row();
+                if self.outline_width > 0.0 {
+                    optional_color(ui, tr("Outline color"), &mut self.outline_color);
+                }
+            });
+    }
+}
+
+fn optional_color(ui: &mut egui::Ui, label: &str, color: &mut Option<Color32>) {
+    let mut custom = color.is_some();
+    if ui.checkbox(&mut custom, label).changed() {
+        *color = custom.then_some(Color32::GRAY);
+    }
+    if let Some(color) = color {
+        ui.color_edit_button_srgba(color);
+    }
+    ui.end_row();
+}
@@ -0,0 +1,58 @@
+use egui::{Align2, Id, Order, RichText};
+
+/// Seconds a toast stays on screen.
+const DURATION: f64 = 2.0;
+
+#[derive(Clone)]
+struct Toast {
+    message: String,
+    shown_at: f64,
+}
+
+fn toast_id() -> Id {
+    Id::new("toast")
+}
+
+/// Show a short message at the bottom of the window, replacing any current one.
+pub fn show(ctx: &egui::Context, message: impl Into<String>) {
+    let toast = Toast {
+        message: message.into(),
+        shown_at: ctx.input(|i| i.time),
+    };
+    ctx.data_mut(|d| d.insert_temp(toast_id(), toast));
+}
+
+/// Put `text` on the clipboard and confirm it with a toast. On the web the browser's
+/// asynchronous clipboard API may refuse, without focus or permission, and the toast comes
+/// once it has answered, saying whether the text was copied.
+pub fn copy(ctx: &egui::Context, text: String, message: impl Into<String>) {
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        ctx.copy_text(text);
+        show(ctx, message);
+    }
+    #[cfg(target_arch = "wasm32")]
+    crate::web::copy_text(ctx, text, message.into());
+}
+
+/// Draw the current toast, if it has not expired.
+pub fn draw(ctx: &egui::Context) {
+    let Some(toast) = ctx.data(|d| d.get_temp::<Toast>(toast_id())) else {
+        return;
+    };
+    let age = ctx.input(|i| i.time) - toast.shown_at;
+    if age > DURATION {
+        ctx.data_mut(|d| d.remove::<Toast>(toast_id()));
+        return;
+    }
+    ctx.request_repaint_after(std::time::Duration::from_secs_f64(DURATION - age));
+    egui::Area::new(Id::new("toast_area"))
+        .order(Order::Tooltip)
+        .anchor(Align2::CENTER_BOTTOM, [0.0, -48.0])
+        .interactable(false)
+        .show(ctx, |ui| {
+            egui::Frame::popup(ui.style()).show(ui, |ui| {
+                ui.label(RichText::new(&toast.message).strong());
+            });
+        });
+}
@@ -0,0 +1,136 @@
+use egui::{Align2, Color32};
+use egui_plot::{Line, PlotPoint, PlotPoints, PlotUi, Points, Text};
+
+use crate::i18n::tr;
+use crate::prefs::Preferences;
+
+const RULER_COLOR: Color32 = Color32::from_rgb(255, 235, 59);
+
+/// What a primary drag on the plots does.
+#[derive(Clone, Copy, Default, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub enum CanvasTool {
+    /// Pan, zoom and hover as usual.
+    #[default]
+    Navigate,
+    /// Click-drag to measure a distance.
+    Ruler,
+    /// Click-drag rectangles in two projections to define a 3D box.
+    Roi,
+    /// Drag neurons of the atlas to move them.
+    Edit,
+    /// Draw arrows, text boxes and freehand lines as figure callouts.
+    Annotate,
+}
+
+impl CanvasTool {
+    pub const ALL: [Self; 5] = [
+        Self::Navigate,
+        Self::Ruler,
+        Self::Roi,
+        Self::Edit,
+        Self::Annotate,
+    ];
+
+    /// Whether the plots may use a primary drag for panning.
+    pub fn allows_pan(&self) -> bool {
+        *self == Self::Navigate
+    }
+
+    /// Whether clicking a neuron selects it.
+    pub fn selects_on_click(&self) -> bool {
+        matches!(self, Self::Navigate | Self::Edit)
+    }
+}
+
+impl std::fmt::Display for CanvasTool {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Navigate => write!(f, "\u{1F5D0} {}", tr("Navigate")),
+            Self::Ruler => write!(f, "\u{1F4CF} {}", tr("Ruler")),
+            Self::Roi => write!(f, "\u{2B1A} {}", tr("ROI box")),
+            Self::Edit => write!(f, "\u{270F} {}", tr("Edit atlas")),
+            Self::Annotate => write!(f, "\u{1F58A} {}", tr("Annotate")),
+        }
+    }
+}
+
+/// Measure an arbitrary distance by click-dragging in a plot; the last measurement stays
+/// visible until a new one starts or it is cleared with a right click.
+pub fn ruler(plot_ui: &mut PlotUi, prefs: &Preferences) {
+    let response = plot_ui.response().clone();
+    let id = response.id.with("ruler");
+    let mut line: Option<([f64; 2], [f64; 2])> = plot_ui.ctx().data(|d| d.get_temp(id)).flatten();
+
+    if let Some(p) = plot_ui.pointer_coordinate() {
+        if response.drag_started() {
+            line = Some(([p.x, p.y], [p.x, p.y]));
+        } else if response.dragged() {
+            if let Some((_, end)) = &mut line {
+                *end = [p.x, p.y];
+            }
+        }
+    }
+    if response.secondary_clicked() {
+        line = None;
+    }
+    plot_ui.ctx().data_mut(|d| d.insert_temp(id, line));
+
+    let Some((a, b)) = line else {
+        return;
+    };
+    let (dx, dy) = (b[0] - a[0], b[1] - a[1]);
+    let distance = (dx * dx + dy * dy).sqrt();
+    plot_ui.line(
+        Line::new(PlotPoints::new(vec![a, b]))
+            .color(RULER_COLOR)
+            .width(2.0),
+    );
+    plot_ui.points(
+        Points::new(PlotPoints::new(vec![a, b]))
+            .color(RULER_COLOR)
+            .radius(3.0),
+    );
+    let mid = PlotPoint::new((a[0] + b[0]) / 2.0, (a[1] + b[1]) / 2.0);
+    plot_ui.text(
+        Text::new(
+            mid,
+            format!(
+                "{}  (Δ {:.2}, {:.2})",
+                prefs.format_distance(distance),
+                dx * prefs.distance_factor(),
+                dy * prefs.distance_factor()
+            ),
+        )
+        .color(RULER_COLOR)
+        .anchor(Align2::LEFT_BOTTOM),
+    );
+}
+
+/// Track a rectangle dragged in a plot. Returns its corners once the drag ends.
+pub fn drag_rect(plot_ui: &mut PlotUi, color: Color32) -> Option<([f64; 2], [f64; 2])> {
+    let response = plot_ui.response().clone();
+    let id = response.id.with("drag_rect");
+    let mut rect: Option<([f64; 2], [f64; 2])> = plot_ui.ctx().data(|d| d.get_temp(id)).flatten();
+
+    if let Some(p) = plot_ui.pointer_coordinate() {
+        if response.drag_started() {
+            rect = Some(([p.x, p.y], [p.x, p.y]));
+        } else if response.dragged() {
+            if let Some((_, end)) = &mut rect {
+                *end = [p.x, p.y];
+            }
+        }
+    }
+    let finished = if response.drag_stopped() {
+        rect.take()
+    } else {
+        None
+    };
+    plot_ui.ctx().data_mut(|d| d.insert_temp(id, rect));
+
+    if let Some((a, b)) = rect {
+        let outline = vec![a, [b[0], a[1]], b, [a[0], b[1]], a];
+        plot_ui.line(Line::new(PlotPoints::new(outline)).color(color).width(1.0));
+    }
+    finished
+}
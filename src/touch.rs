@@ -0,0 +1,67 @@
+use egui_plot::{PlotUi, Text};
+
+use crate::dataset::Neuron;
+use crate::prefs::Preferences;
+use crate::style;
+use crate::view::View;
+
+/// How far from a long press, in points, a neuron is still picked; about a fingertip.
+const PRESS_RADIUS: f32 = 40.0;
+
+/// Touch gestures on a plot beyond the pinch zoom egui_plot already reads: two-finger pan,
+/// and a long press naming the neuron under the finger until the next tap.
+pub fn gestures(plot_ui: &mut PlotUi, view: View, data: &[&Neuron], prefs: &Preferences) {
+    let response = plot_ui.response().clone();
+    if response.contains_pointer() {
+        if let Some(touch) = plot_ui.ctx().multi_touch() {
+            if touch.translation_delta != egui::Vec2::ZERO {
+                // The bounds move in plot units, whose y axis points up unlike the screen's.
+                let [dx, dy] = plot_ui.transform().dvalue_dpos();
+                let delta = touch.translation_delta;
+                plot_ui.translate_bounds(egui::vec2(
+                    -(delta.x as f64 * dx) as f32,
+                    -(delta.y as f64 * dy) as f32,
+                ));
+            }
+        }
+    }
+
+    let id = response.id.with("long_press");
+    let mut pressed: Option<String> = plot_ui.ctx().data(|d| d.get_temp(id)).flatten();
+    if response.clicked() || response.drag_started() {
+        pressed = None;
+    }
+    let press_radius = PRESS_RADIUS * if prefs.high_contrast { 1.5 } else { 1.0 };
+    if response.long_touched() {
+        if let Some(pos) = response.interact_pointer_pos() {
+            pressed = data
+                .iter()
+                .map(|n| {
+                    let [x, y] = view.project(n.position());
+                    let on_screen = plot_ui.screen_from_plot([x, y].into());
+                    (n, on_screen.distance(pos))
+                })
+                .filter(|(_, distance)| *distance < press_radius)
+                .min_by(|a, b| a.1.total_cmp(&b.1))
+                .map(|(n, _)| n.name.clone());
+        }
+    }
+    plot_ui
+        .ctx()
+        .data_mut(|d| d.insert_temp(id, pressed.clone()));
+
+    let Some(neuron) = pressed.and_then(|name| data.iter().find(|n| n.name == name)) else {
+        return;
+    };
+    let [x, y] = view.project(neuron.position());
+    style::ring(plot_ui, [x, y], 8.0, prefs.hover_stroke());
+    let [px, py, pz] = neuron.position();
+    plot_ui.text(
+        Text::new(
+            [x, y].into(),
+            format!("{}\n({px:.1}, {py:.1}, {pz:.1})", neuron.name),
+        )
+        .anchor(egui::Align2::LEFT_BOTTOM)
+        .highlight(true),
+    );
+}
@@ -0,0 +1,145 @@
+use egui::{Color32, Id, LayerId, Order, Rect, RichText, Stroke};
+
+use crate::i18n::tr;
+
+const HIGHLIGHT: Color32 = Color32::from_rgb(255, 179, 0);
+const CARD_WIDTH: f32 = 280.0;
+
+/// Parts of the interface the tour points at.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Target {
+    Search,
+    Side,
+    Plots,
+    Slices,
+}
+
+struct Step {
+    target: Target,
+    title: &'static str,
+    text: &'static str,
+}
+
+const STEPS: [Step; 4] = [
+    Step {
+        target: Target::Search,
+        title: "Search",
+        text: "Type part of a neuron name to filter the list and the plots.",
+    },
+    Step {
+        target: Target::Side,
+        title: "Body side",
+        text: "Click to switch between the left side, the right side and both sides of the worm.",
+    },
+    Step {
+        target: Target::Plots,
+        title: "Lateral view",
+        text: "Scroll to zoom, drag to pan and hover a neuron to see its name. \
+               Double-click restores the view.",
+    },
+    Step {
+        target: Target::Slices,
+        title: "Slices",
+        text: "The anterior and dorsal views show a thin slab through the position hovered in \
+               the lateral view, so they follow the cursor.",
+    },
+];
+
+fn target_id(target: Target) -> Id {
+    Id::new(("tour_target", target))
+}
+
+/// Remember where `target` was drawn this frame, so the tour can point at it.
+pub fn mark(ctx: &egui::Context, target: Target, rect: Rect) {
+    ctx.data_mut(|d| d.insert_temp(target_id(target), rect));
+}
+
+/// Guided tour of the main controls, started on the first run and from the Help menu.
+#[derive(Default, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct Tour {
+    /// Whether the tour was offered once already.
+    pub seen: bool,
+    #[serde(skip)]
+    step: Option<usize>,
+}
+
+impl Tour {
+    pub fn start(&mut self) {
+        self.seen = true;
+        self.step = Some(0);
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.step.is_some()
+    }
+
+    pub fn stop(&mut self) {
+        self.step = None;
+    }
+
+    /// Outline the current target and show the explanation card next to it.
+    pub fn show(&mut self, ctx: &egui::Context) {
+        let Some(index) = self.step else {
+            return;
+        };
+        let step = &STEPS[index];
+        let screen = ctx.screen_rect();
+        let target = ctx.data(|d| d.get_temp::<Rect>(target_id(step.target)));
+
+        let pos = match target {
+            Some(rect) => {
+                ctx.layer_painter(LayerId::new(Order::Foreground, Id::new("tour_highlight")))
+                    .rect_stroke(rect.expand(4.0), 4.0, Stroke::new(2.5, HIGHLIGHT));
+                let right = rect.right() + 12.0;
+                let x = if right + CARD_WIDTH < screen.right() {
+                    right
+                } else {
+                    (rect.left() - CARD_WIDTH - 24.0).max(screen.left())
+                };
+                egui::pos2(x, rect.top().clamp(screen.top(), screen.bottom() - 160.0))
+            }
+            None => screen.center() - egui::vec2(CARD_WIDTH / 2.0, 60.0),
+        };
+
+        egui::Area::new(Id::new("tour_card"))
+            .order(Order::Foreground)
+            .fixed_pos(pos)
+            .show(ctx, |ui| {
+                egui::Frame::popup(ui.style())
+                    .stroke(Stroke::new(1.5, HIGHLIGHT))
+                    .show(ui, |ui| {
+                        ui.set_max_width(CARD_WIDTH);
+                        ui.label(
+                            RichText::new(format!(
+                                "{}  ({}/{})",
+                                tr(step.title),
+                                index + 1,
+                                STEPS.len()
+                            ))
+                            .strong(),
+                        );
+                        ui.label(tr(step.text));
+                        ui.separator();
+                        ui.horizontal(|ui| {
+                            if ui
+                                .add_enabled(index > 0, egui::Button::new(tr("Back")))
+                                .clicked()
+                            {
+                                self.step = Some(index - 1);
+                            }
+                            let last = index + 1 == STEPS.len();
+                            if ui
+                                .button(tr(if last { "Finish" } else { "Next" }))
+                                .clicked()
+                            {
+                                self.step = (!last).then_some(index + 1);
+                            }
+                            if !last && ui.button(tr("Skip tour")).clicked() {
+                                self.step = None;
+                            }
+                        });
+                    });
+            });
+    }
+}
@@ -0,0 +1,133 @@
+use std::collections::HashMap;
+
+use crate::dataset::Neuron;
+use crate::frame::CoordinateFrame;
+use crate::i18n::tr;
+
+/// Coordinates beyond this many atlas units suggest positions given in other units, such as
+/// nanometers or pixels.
+const UNIT_LIMIT: f32 = 1000.0;
+
+/// Something in a dataset file that is likely a mistake.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Problem {
+    /// The name was already used on `first_line`; the later row wins.
+    Duplicate {
+        first_line: u64,
+    },
+    NotFinite,
+    /// A color channel outside `0..=1`, once channels given in `0..=255` are scaled.
+    ColorRange,
+    /// The row does not match the expected columns and was skipped.
+    Unreadable,
+    /// Coordinates reach `max` in magnitude, far beyond the size of the atlas.
+    Units {
+        max: f32,
+    },
+}
+
+impl std::fmt::Display for Problem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Duplicate { first_line } => {
+                write!(
+                    f,
+                    "{} {first_line}",
+                    tr("Duplicate name, first used on line")
+                )
+            }
+            Self::NotFinite => f.write_str(tr("Coordinate is not a finite number")),
+            Self::ColorRange => f.write_str(tr("Color channel outside 0–1 and 0–255")),
+            Self::Unreadable => f.write_str(tr("Row could not be read and was skipped")),
+            Self::Units { max } => write!(
+                f,
+                "{} {max:.0}; {}",
+                tr("Coordinates reach"),
+                tr("check that the frame and its scale match the units of the file")
+            ),
+        }
+    }
+}
+
+/// A problem found in a dataset, with the line of the file it is on and the neuron it
+/// concerns, where they apply.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Warning {
+    pub line: Option<u64>,
+    pub name: Option<String>,
+    pub problem: Problem,
+}
+
+/// Checks the rows of a file one at a time as it is parsed, and then the neurons read.
+#[derive(Default)]
+pub struct Validator {
+    first_lines: HashMap<String, u64>,
+    warnings: Vec<Warning>,
+}
+
+impl Validator {
+    /// Check the neuron read from `line`, or note that the line could not be read. A header
+    /// line is not reported.
+    pub fn row(&mut self, line: u64, neuron: Option<&Neuron>, first_field: Option<&str>) {
+        let Some(neuron) = neuron else {
+            if !first_field.is_some_and(|f| f.trim().eq_ignore_ascii_case("name")) {
+                self.warn(line, None, Problem::Unreadable);
+            }
+            return;
+        };
+        let name = Some(neuron.name.clone());
+        match self.first_lines.get(&neuron.name) {
+            Some(&first_line) => self.warn(line, name.clone(), Problem::Duplicate { first_line }),
+            None => {
+                self.first_lines.insert(neuron.name.clone(), line);
+            }
+        }
+        if !neuron.position().iter().all(|v| v.is_finite()) {
+            self.warn(line, name, Problem::NotFinite);
+        }
+    }
+
+    /// Check the colors of `neurons` once they are normalized, and return every warning in
+    /// the order of the lines.
+    pub fn finish(mut self, neurons: &HashMap<String, Neuron>) -> Vec<Warning> {
+        for neuron in neurons.values() {
+            let channels = [neuron.r, neuron.g, neuron.b];
+            if !channels.iter().all(|c| (0.0..=1.0).contains(c)) {
+                let line = self.first_lines.get(&neuron.name).copied();
+                self.warnings.push(Warning {
+                    line,
+                    name: Some(neuron.name.clone()),
+                    problem: Problem::ColorRange,
+                });
+            }
+        }
+        self.warnings.sort_by_key(|w| w.line);
+        self.warnings
+    }
+
+    fn warn(&mut self, line: u64, name: Option<String>, problem: Problem) {
+        self.warnings.push(Warning {
+            line: Some(line),
+            name,
+            problem,
+        });
+    }
+}
+
+/// A warning if the coordinates of `neurons` are far too large for `frame`; frames in
+/// microns are scaled by the user and are not checked.
+pub fn units(neurons: &HashMap<String, Neuron>, frame: CoordinateFrame) -> Option<Warning> {
+    if frame != CoordinateFrame::Atlas {
+        return None;
+    }
+    let max = neurons
+        .values()
+        .flat_map(|n| n.position())
+        .filter(|v| v.is_finite())
+        .fold(0.0f32, |max, v| max.max(v.abs()));
+    (max > UNIT_LIMIT).then_some(Warning {
+        line: None,
+        name: None,
+        problem: Problem::Units { max },
+    })
+}
@@ -0,0 +1,125 @@
+use std::collections::{BTreeMap, HashMap};
+#[cfg(not(target_arch = "wasm32"))]
+use std::fmt::Write as _;
+
+use crate::dataset::Neuron;
+
+/// Positional statistics of one neuron across several annotated animals.
+pub struct NeuronStats {
+    pub name: String,
+    /// Number of animals in which the neuron was annotated.
+    pub n: usize,
+    pub mean: [f32; 3],
+    /// Sample covariance of the position (zero when `n < 2`).
+    pub cov: [[f32; 3]; 3],
+    pub color: [f32; 3],
+}
+
+impl NeuronStats {
+    pub fn to_neuron(&self) -> Neuron {
+        let [x, y, z] = self.mean;
+        let [r, g, b] = self.color;
+        Neuron {
+            name: self.name.clone(),
+            x,
+            y,
+            z,
+            r,
+            g,
+            b,
+            region: None,
+            pharyngeal: None,
+            meta: Default::default(),
+        }
+    }
+
+    /// Root of the mean positional variance, a single-number summary of the spread.
+    pub fn rms_spread(&self) -> f32 {
+        ((self.cov[0][0] + self.cov[1][1] + self.cov[2][2]) / 3.0).sqrt()
+    }
+}
+
+/// Per-neuron mean position, covariance and mean color across `animals`, sorted by name.
+pub fn variability(animals: &[&HashMap<String, Neuron>]) -> Vec<NeuronStats> {
+    let mut grouped: BTreeMap<&str, Vec<&Neuron>> = BTreeMap::new();
+    for animal in animals {
+        for neuron in animal.values() {
+            grouped.entry(&neuron.name).or_default().push(neuron);
+        }
+    }
+    grouped
+        .into_iter()
+        .map(|(name, neurons)| {
+            let n = neurons.len();
+            let mut mean = [0.0f32; 3];
+            let mut color = [0.0f32; 3];
+            for neuron in &neurons {
+                for (m, v) in mean.iter_mut().zip(neuron.position()) {
+                    *m += v / n as f32;
+                }
+                for (c, v) in color.iter_mut().zip([neuron.r, neuron.g, neuron.b]) {
+                    *c += v / n as f32;
+                }
+            }
+            let mut cov = [[0.0f32; 3]; 3];
+            if n > 1 {
+                for neuron in &neurons {
+                    let p = neuron.position();
+                    for i in 0..3 {
+                        for j in 0..3 {
+                            cov[i][j] += (p[i] - mean[i]) * (p[j] - mean[j]) / (n - 1) as f32;
+                        }
+                    }
+                }
+            }
+            NeuronStats {
+                name: name.to_owned(),
+                n,
+                mean,
+                cov,
+                color,
+            }
+        })
+        .collect()
+}
+
+/// Serialize a statistical atlas. The first seven columns keep the bundled atlas layout.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn to_csv(stats: &[NeuronStats]) -> String {
+    let mut out = String::from("name,x,y,z,r,g,b,n,sxx,syy,szz,sxy,sxz,syz\n");
+    for s in stats {
+        let [x, y, z] = s.mean;
+        let [r, g, b] = s.color;
+        let c = &s.cov;
+        let _ = writeln!(
+            out,
+            "{},{x},{y},{z},{r},{g},{b},{},{},{},{},{},{},{}",
+            s.name, s.n, c[0][0], c[1][1], c[2][2], c[0][1], c[0][2], c[1][2]
+        );
+    }
+    out
+}
+
+/// Outline of the 1-sigma ellipse of a 2D covariance `[[a, b], [b, c]]` around `center`.
+pub fn ellipse(center: [f64; 2], cov: [[f64; 2]; 2], segments: usize) -> Vec<[f64; 2]> {
+    let (a, b, c) = (cov[0][0], cov[0][1], cov[1][1]);
+    let half_trace = (a + c) / 2.0;
+    let det_term = (((a - c) / 2.0).powi(2) + b * b).sqrt();
+    let (l1, l2) = (half_trace + det_term, (half_trace - det_term).max(0.0));
+    let angle = if b == 0.0 && a >= c {
+        0.0
+    } else if b == 0.0 {
+        std::f64::consts::FRAC_PI_2
+    } else {
+        (l1 - a).atan2(b)
+    };
+    let (sin, cos) = angle.sin_cos();
+    let (r1, r2) = (l1.sqrt(), l2.sqrt());
+    (0..=segments)
+        .map(|i| {
+            let t = i as f64 / segments as f64 * std::f64::consts::TAU;
+            let (u, v) = (r1 * t.cos(), r2 * t.sin());
+            [center[0] + u * cos - v * sin, center[1] + u * sin + v * cos]
+        })
+        .collect()
+}
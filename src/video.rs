@@ -0,0 +1,262 @@
+use std::io::{Read, Write};
+use std::path::PathBuf;
+use std::process::{Child, Command, Stdio};
+
+use egui::ColorImage;
+
+use crate::i18n::tr;
+
+/// Container and codec of an exported video.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub enum VideoFormat {
+    /// H.264 in MP4, which presentation software plays everywhere.
+    #[default]
+    Mp4,
+    /// VP9 in WebM, for browsers and slides on the web.
+    WebM,
+}
+
+impl VideoFormat {
+    pub const ALL: [Self; 2] = [Self::Mp4, Self::WebM];
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Mp4 => "mp4",
+            Self::WebM => "webm",
+        }
+    }
+
+    /// Encoder arguments of ffmpeg for the format.
+    fn codec(self) -> &'static [&'static str] {
+        match self {
+            Self::Mp4 => &["-c:v", "libx264", "-crf", "18", "-movflags", "+faststart"],
+            Self::WebM => &["-c:v", "libvpx-vp9", "-crf", "31", "-b:v", "0"],
+        }
+    }
+}
+
+impl std::fmt::Display for VideoFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Mp4 => "MP4 (H.264)",
+            Self::WebM => "WebM (VP9)",
+        })
+    }
+}
+
+/// Part of the window recorded.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub enum VideoScope {
+    /// The lateral view only.
+    #[default]
+    XyView,
+    /// All the views, as they are laid out on the canvas.
+    Canvas,
+}
+
+impl std::fmt::Display for VideoScope {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::XyView => tr("xy view"),
+            Self::Canvas => tr("All views"),
+        })
+    }
+}
+
+/// How the activity playback is exported as a video.
+#[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct VideoSettings {
+    pub format: VideoFormat,
+    pub scope: VideoScope,
+    /// Frames per second of the video, independent of the playback speed.
+    pub fps: f32,
+    /// Width of the video in pixels, its height following the recorded area; `0` keeps the
+    /// size on screen.
+    pub width: u32,
+}
+
+impl Default for VideoSettings {
+    fn default() -> Self {
+        Self {
+            format: VideoFormat::Mp4,
+            scope: VideoScope::XyView,
+            fps: 10.0,
+            width: 1920,
+        }
+    }
+}
+
+impl VideoSettings {
+    pub fn ui(&mut self, ui: &mut egui::Ui) {
+        egui::Grid::new("video_settings")
+            .num_columns(2)
+            .show(ui, |ui| {
+                ui.label(tr("Format"));
+                egui::ComboBox::from_id_salt("video_format")
+                    .selected_text(self.format.to_string())
+                    .show_ui(ui, |ui| {
+                        for format in VideoFormat::ALL {
+                            ui.selectable_value(&mut self.format, format, format.to_string());
+                        }
+                    });
+                ui.end_row();
+                ui.label(tr("Record"));
+                ui.horizontal(|ui| {
+                    for scope in [VideoScope::XyView, VideoScope::Canvas] {
+                        ui.radio_value(&mut self.scope, scope, scope.to_string());
+                    }
+                });
+                ui.end_row();
+                ui.label(tr("Frame rate"));
+                ui.add(
+                    egui::DragValue::new(&mut self.fps)
+                        .range(1.0..=120.0)
+                        .speed(0.1)
+                        .max_decimals(1)
+                        .suffix(" fps"),
+                );
+                ui.end_row();
+                ui.label(tr("Width"));
+                ui.horizontal(|ui| {
+                    for width in [1280, 1920, 3840] {
+                        ui.selectable_value(&mut self.width, width, width.to_string());
+                    }
+                    ui.selectable_value(&mut self.width, 0, tr("As shown"));
+                    // Encoders want an even number of pixels.
+                    ui.add(
+                        egui::DragValue::new(&mut self.width)
+                            .range(0..=7680)
+                            .speed(2),
+                    )
+                    .on_hover_text(tr("Width in pixels; the height keeps the proportions"));
+                    self.width -= self.width % 2;
+                });
+                ui.end_row();
+            });
+    }
+}
+
+/// A video being written by ffmpeg, one frame of the activity at a time.
+pub struct Recording {
+    pub settings: VideoSettings,
+    pub path: PathBuf,
+    /// Activity frame recorded next, of `frames`.
+    pub frame: usize,
+    pub frames: usize,
+    /// Part of the window recorded, in points, fixed by the first frame.
+    pub region: Option<egui::Rect>,
+    /// A screenshot of the frame shown was asked for and has not come yet.
+    pub waiting: bool,
+    /// The ffmpeg process, writing what comes on its input, and the size of the frames it
+    /// takes, once the first frame came.
+    encoder: Option<(Child, [usize; 2])>,
+}
+
+impl Recording {
+    pub fn new(settings: VideoSettings, path: PathBuf, frames: usize) -> Self {
+        Self {
+            settings,
+            path,
+            frame: 0,
+            frames,
+            region: None,
+            waiting: false,
+            encoder: None,
+        }
+    }
+
+    /// Fraction of the frames recorded.
+    pub fn progress(&self) -> f32 {
+        self.frame as f32 / self.frames.max(1) as f32
+    }
+
+    /// Encode `image` as the next frame; ffmpeg starts with the first one.
+    pub fn push(&mut self, image: &ColorImage) -> Result<(), String> {
+        if self.encoder.is_none() {
+            self.encoder = Some(self.spawn(image.size)?);
+        }
+        let Some((child, size)) = &mut self.encoder else {
+            return Ok(());
+        };
+        if image.size != *size {
+            return Err(tr("the window was resized while recording").to_owned());
+        }
+        let stdin = child.stdin.as_mut();
+        if !stdin.is_some_and(|stdin| stdin.write_all(image.as_raw()).is_ok()) {
+            // ffmpeg quit; its own message says why.
+            let (child, _) = self.encoder.take().expect("encoder");
+            return Err(finish(child).err().unwrap_or_default());
+        }
+        self.frame += 1;
+        Ok(())
+    }
+
+    /// Close the video once all frames are in, waiting for ffmpeg to write it.
+    pub fn finish(self) -> Result<PathBuf, String> {
+        let Some((child, _)) = self.encoder else {
+            return Err(tr("no frames were recorded").to_owned());
+        };
+        finish(child)?;
+        Ok(self.path)
+    }
+
+    /// Stop ffmpeg and remove the partly written video.
+    pub fn cancel(self) {
+        if let Some((mut child, _)) = self.encoder {
+            child.kill().ok();
+            child.wait().ok();
+            std::fs::remove_file(&self.path).ok();
+        }
+    }
+
+    fn spawn(&self, [width, height]: [usize; 2]) -> Result<(Child, [usize; 2]), String> {
+        // Scale to the width asked for, or to even sizes at the size on screen.
+        let scale = match self.settings.width {
+            0 => "scale=trunc(iw/2)*2:trunc(ih/2)*2".to_owned(),
+            width => format!("scale={width}:-2:flags=lanczos"),
+        };
+        let child = Command::new("ffmpeg")
+            .args([
+                "-y",
+                "-loglevel",
+                "error",
+                "-f",
+                "rawvideo",
+                "-pix_fmt",
+                "rgba",
+            ])
+            .args(["-s", &format!("{width}x{height}")])
+            .args(["-framerate", &self.settings.fps.to_string(), "-i", "-"])
+            .args(["-vf", &format!("{scale},format=yuv420p")])
+            .args(self.settings.format.codec())
+            .arg(&self.path)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|err| match err.kind() {
+                std::io::ErrorKind::NotFound => {
+                    tr("ffmpeg was not found; install it and put it on the PATH to export videos")
+                        .to_owned()
+                }
+                _ => format!("ffmpeg: {err}"),
+            })?;
+        Ok((child, [width, height]))
+    }
+}
+
+/// Wait for ffmpeg to exit, with its error output if it failed. Waiting closes its input,
+/// which tells it the last frame is in.
+fn finish(mut child: Child) -> Result<(), String> {
+    let status = child.wait().map_err(|e| e.to_string())?;
+    if status.success() {
+        return Ok(());
+    }
+    let mut message = String::new();
+    if let Some(stderr) = &mut child.stderr {
+        stderr.read_to_string(&mut message).ok();
+    }
+    Err(format!("ffmpeg: {}", message.trim()))
+}
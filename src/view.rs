@@ -0,0 +1,187 @@
+use crate::i18n::tr;
+
+/// The three orthogonal projections of the atlas.
+#[derive(Clone, Copy, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub enum View {
+    /// Lateral view, anterior–posterior against ventral–dorsal.
+    XY,
+    /// Anterior view, right–left against ventral–dorsal.
+    ZY,
+    /// Dorsal view, anterior–posterior against left–right.
+    XZ,
+}
+
+impl View {
+    pub const ALL: [Self; 3] = [Self::XY, Self::ZY, Self::XZ];
+
+    pub fn index(&self) -> usize {
+        *self as usize
+    }
+
+    pub fn title(&self) -> &'static str {
+        match self {
+            Self::XY => tr("Lateral (x-y)"),
+            Self::ZY => tr("Anterior (z-y)"),
+            Self::XZ => tr("Dorsal (x-z)"),
+        }
+    }
+
+    pub fn project(&self, [x, y, z]: [f32; 3]) -> [f64; 2] {
+        match self {
+            Self::XY => [x as f64, y as f64],
+            Self::ZY => [z as f64, y as f64],
+            Self::XZ => [x as f64, -z as f64],
+        }
+    }
+
+    /// Project a 3D position covariance onto the plane of this view.
+    pub fn project_cov(&self, c: &[[f32; 3]; 3]) -> [[f64; 2]; 2] {
+        let (i, j, sign) = match self {
+            Self::XY => (0, 1, 1.0),
+            Self::ZY => (2, 1, 1.0),
+            Self::XZ => (0, 2, -1.0),
+        };
+        let cross = sign * c[i][j] as f64;
+        [[c[i][i] as f64, cross], [cross, c[j][j] as f64]]
+    }
+
+    /// Inverse of [`View::project`] for the two axes shown in this view.
+    pub fn unproject(&self, [u, v]: [f64; 2]) -> [(usize, f32); 2] {
+        match self {
+            Self::XY => [(0, u as f32), (1, v as f32)],
+            Self::ZY => [(2, u as f32), (1, v as f32)],
+            Self::XZ => [(0, u as f32), (2, -v as f32)],
+        }
+    }
+}
+
+/// Position, content size and collapsed state of a floating view window, kept with the app
+/// state so the layout survives restarts.
+#[derive(Clone, Copy, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct WindowGeometry {
+    pub pos: [f32; 2],
+    pub size: [f32; 2],
+    pub collapsed: bool,
+}
+
+impl WindowGeometry {
+    /// Show `window` where it was last left, or at `default_pos` the first time, and report
+    /// its geometry after this frame.
+    pub fn show(
+        ctx: &egui::Context,
+        window: egui::Window<'_>,
+        stored: Option<Self>,
+        default_pos: egui::Pos2,
+        add_contents: impl FnOnce(&mut egui::Ui),
+    ) -> Option<Self> {
+        let window = match stored {
+            Some(g) => window
+                .default_pos(g.pos)
+                .default_size(g.size)
+                .default_open(!g.collapsed),
+            None => window.default_pos(default_pos),
+        };
+        let mut size = stored.map(|g| g.size);
+        let response = window.show(ctx, |ui| {
+            size = Some(ui.available_size().into());
+            add_contents(ui);
+        })?;
+        Some(Self {
+            pos: response.response.rect.min.into(),
+            size: size?,
+            collapsed: response.inner.is_none(),
+        })
+    }
+}
+
+/// Show `add_contents` in a separate OS window where the backend supports multiple
+/// viewports, or in a floating window otherwise. Returns `false` once the user closes it.
+pub fn show_in_viewport(
+    ctx: &egui::Context,
+    id: &str,
+    title: &str,
+    size: [f32; 2],
+    mut add_contents: impl FnMut(&mut egui::Ui),
+) -> bool {
+    let mut open = true;
+    ctx.show_viewport_immediate(
+        egui::ViewportId::from_hash_of(id),
+        egui::ViewportBuilder::default()
+            .with_title(title)
+            .with_inner_size(size),
+        |ctx, class| {
+            if class == egui::ViewportClass::Embedded {
+                egui::Window::new(title)
+                    .id(egui::Id::new(id))
+                    .open(&mut open)
+                    .show(ctx, |ui| add_contents(ui));
+            } else {
+                egui::CentralPanel::default().show(ctx, |ui| add_contents(ui));
+                if ctx.input(|i| i.viewport().close_requested()) {
+                    open = false;
+                }
+            }
+        },
+    );
+    open
+}
+
+/// Where the anterior and dorsal views are placed relative to the lateral view.
+#[derive(Clone, Copy, Default, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub enum ViewLayout {
+    /// Movable windows on top of the central area.
+    #[default]
+    Floating,
+    /// Panels to the right of and below the lateral view, never covering it.
+    Docked,
+    /// Left- and right-side neurons in two linked lateral views, to check bilateral symmetry.
+    SplitSides,
+    /// Head and tail neurons in two lateral views linked along the dorsoventral axis, each
+    /// framing its end of the body at its true position along it.
+    HeadTail,
+}
+
+impl ViewLayout {
+    pub const ALL: [Self; 4] = [
+        Self::Floating,
+        Self::Docked,
+        Self::SplitSides,
+        Self::HeadTail,
+    ];
+}
+
+impl std::fmt::Display for ViewLayout {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Floating => f.write_str(tr("Floating windows")),
+            Self::Docked => f.write_str(tr("Docked panels")),
+            Self::SplitSides => f.write_str(tr("Left | right split")),
+            Self::HeadTail => f.write_str(tr("Head | tail split")),
+        }
+    }
+}
+
+/// A panel that can be laid out over the whole window or inside the `Ui` of a host app.
+pub enum Panel {
+    TopBottom(egui::TopBottomPanel),
+    Side(egui::SidePanel),
+    Central(egui::CentralPanel),
+}
+
+impl Panel {
+    pub fn show<R>(
+        self,
+        ctx: &egui::Context,
+        host: Option<&mut egui::Ui>,
+        add_contents: impl FnOnce(&mut egui::Ui) -> R,
+    ) -> R {
+        match (self, host) {
+            (Self::TopBottom(panel), Some(ui)) => panel.show_inside(ui, add_contents).inner,
+            (Self::TopBottom(panel), None) => panel.show(ctx, add_contents).inner,
+            (Self::Side(panel), Some(ui)) => panel.show_inside(ui, add_contents).inner,
+            (Self::Side(panel), None) => panel.show(ctx, add_contents).inner,
+            (Self::Central(panel), Some(ui)) => panel.show_inside(ui, add_contents).inner,
+            (Self::Central(panel), None) => panel.show(ctx, add_contents).inner,
+        }
+    }
+}
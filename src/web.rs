@@ -0,0 +1,213 @@
+use std::sync::mpsc;
+
+use wasm_bindgen::closure::Closure;
+use wasm_bindgen::{JsCast as _, JsValue};
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{IdbDatabase, IdbOpenDbRequest, IdbRequest, IdbTransactionMode};
+
+/// IndexedDB database and object store holding the datasets, annotations and edited atlas of
+/// the last session.
+const DB_NAME: &str = "neuropal_lens";
+const STORE: &str = "session";
+const SESSION_KEY: &str = "datasets";
+
+use crate::bundle;
+use crate::i18n::tr;
+use crate::loader::PendingFile;
+use crate::toast;
+
+/// Open the browser's file picker for a dataset, the web counterpart of the blocking native
+/// dialog; the file arrives once it has been read.
+pub fn pick_file(ctx: &egui::Context) -> PendingFile {
+    let (sender, receiver) = mpsc::channel();
+    let ctx = ctx.clone();
+    wasm_bindgen_futures::spawn_local(async move {
+        let Some(file) = rfd::AsyncFileDialog::new()
+            .add_filter("CSV", &["csv", "txt"])
+            .add_filter(tr("Project bundle"), &[bundle::EXTENSION])
+            .pick_file()
+            .await
+        else {
+            return;
+        };
+        let bytes = file.read().await;
+        let _ = sender.send(Ok((file.file_name(), bytes)));
+        ctx.request_repaint();
+    });
+    receiver
+}
+
+/// Download a dataset, for `?data=<url>` links to the viewer.
+pub fn fetch(ctx: &egui::Context, url: String) -> PendingFile {
+    let (sender, receiver) = mpsc::channel();
+    let ctx = ctx.clone();
+    wasm_bindgen_futures::spawn_local(async move {
+        let name = url
+            .split(['?', '#'])
+            .next()
+            .and_then(|path| path.rsplit('/').next())
+            .filter(|name| !name.is_empty())
+            .unwrap_or(&url)
+            .to_owned();
+        let result = fetch_bytes(&url)
+            .await
+            .map(|bytes| (name, bytes))
+            .map_err(|err| format!("{url}: {err}"));
+        let _ = sender.send(result);
+        ctx.request_repaint();
+    });
+    receiver
+}
+
+pub async fn fetch_bytes(url: &str) -> Result<Vec<u8>, String> {
+    let window = web_sys::window().ok_or("no window")?;
+    let response = JsFuture::from(window.fetch_with_str(url))
+        .await
+        .map_err(js_error)?;
+    let response: web_sys::Response = response.dyn_into().map_err(js_error)?;
+    if !response.ok() {
+        return Err(format!(
+            "HTTP {} {}",
+            response.status(),
+            response.status_text()
+        ));
+    }
+    let buffer = JsFuture::from(response.array_buffer().map_err(js_error)?)
+        .await
+        .map_err(js_error)?;
+    Ok(js_sys::Uint8Array::new(&buffer).to_vec())
+}
+
+/// Put `text` on the clipboard, then show `message`, or that the browser refused.
+pub fn copy_text(ctx: &egui::Context, text: String, message: String) {
+    // The clipboard API is missing outside secure contexts.
+    let clipboard = web_sys::window()
+        .map(|window| window.navigator().clipboard())
+        .filter(|clipboard| !clipboard.is_undefined());
+    let Some(clipboard) = clipboard else {
+        toast::show(ctx, tr("Copying is not available in this browser"));
+        return;
+    };
+    let promise = clipboard.write_text(&text);
+    let ctx = ctx.clone();
+    wasm_bindgen_futures::spawn_local(async move {
+        match JsFuture::from(promise).await {
+            Ok(_) => toast::show(&ctx, message),
+            Err(err) => {
+                log::warn!("Failed to copy to the clipboard: {}", js_error(err));
+                toast::show(&ctx, tr("Could not copy to the clipboard"));
+            }
+        }
+        ctx.request_repaint();
+    });
+}
+
+/// Let the browser handle its events and draw a frame before going on.
+pub async fn yield_now() {
+    let promise = js_sys::Promise::new(&mut |resolve, _| {
+        if let Some(window) = web_sys::window() {
+            let _ = window.set_timeout_with_callback(&resolve);
+        }
+    });
+    let _ = JsFuture::from(promise).await;
+}
+
+fn js_error(err: JsValue) -> String {
+    err.as_string().unwrap_or_else(|| format!("{err:?}"))
+}
+
+/// Save the serialized session in IndexedDB, replacing the previous one.
+pub fn store_session(text: String) {
+    wasm_bindgen_futures::spawn_local(async move {
+        let result = async {
+            let db = open_db().await?;
+            let store = db
+                .transaction_with_str_and_mode(STORE, IdbTransactionMode::Readwrite)
+                .and_then(|tx| tx.object_store(STORE))
+                .map_err(js_error)?;
+            let request = store
+                .put_with_key(&JsValue::from_str(&text), &JsValue::from_str(SESSION_KEY))
+                .map_err(js_error)?;
+            request_result(&request).await
+        };
+        if let Err(err) = result.await {
+            log::warn!("Failed to store the session: {err}");
+        }
+    });
+}
+
+/// Read the session saved by [`store_session`]; nothing arrives if there is none.
+pub fn load_session(ctx: &egui::Context) -> mpsc::Receiver<String> {
+    let (sender, receiver) = mpsc::channel();
+    let ctx = ctx.clone();
+    wasm_bindgen_futures::spawn_local(async move {
+        let result = async {
+            let db = open_db().await?;
+            let store = db
+                .transaction_with_str(STORE)
+                .and_then(|tx| tx.object_store(STORE))
+                .map_err(js_error)?;
+            let request = store
+                .get(&JsValue::from_str(SESSION_KEY))
+                .map_err(js_error)?;
+            request_result(&request).await
+        };
+        match result.await {
+            Ok(value) => {
+                if let Some(text) = value.as_string() {
+                    let _ = sender.send(text);
+                    ctx.request_repaint();
+                }
+            }
+            Err(err) => log::warn!("Failed to load the previous session: {err}"),
+        }
+    });
+    receiver
+}
+
+async fn open_db() -> Result<IdbDatabase, String> {
+    let factory = web_sys::window()
+        .ok_or("no window")?
+        .indexed_db()
+        .map_err(js_error)?
+        .ok_or("IndexedDB is not available")?;
+    let request = factory.open_with_u32(DB_NAME, 1).map_err(js_error)?;
+    let upgrade = Closure::once_into_js(|event: web_sys::Event| {
+        let db = event
+            .target()
+            .and_then(|target| target.dyn_into::<IdbOpenDbRequest>().ok())
+            .and_then(|request| request.result().ok())
+            .and_then(|result| result.dyn_into::<IdbDatabase>().ok());
+        if let Some(db) = db {
+            let _ = db.create_object_store(STORE);
+        }
+    });
+    request.set_onupgradeneeded(Some(upgrade.unchecked_ref()));
+    request_result(&request)
+        .await?
+        .dyn_into::<IdbDatabase>()
+        .map_err(js_error)
+}
+
+/// Wait for an IndexedDB request to finish.
+async fn request_result(request: &IdbRequest) -> Result<JsValue, String> {
+    let promise = js_sys::Promise::new(&mut |resolve, reject| {
+        let done = request.clone();
+        let on_success = Closure::once_into_js(move |_: web_sys::Event| {
+            let _ = resolve.call1(&JsValue::NULL, &done.result().unwrap_or(JsValue::UNDEFINED));
+        });
+        let failed = request.clone();
+        let on_error = Closure::once_into_js(move |_: web_sys::Event| {
+            let error = failed
+                .error()
+                .ok()
+                .flatten()
+                .map(|e| JsValue::from_str(&e.message()))
+                .unwrap_or(JsValue::UNDEFINED);
+            let _ = reject.call1(&JsValue::NULL, &error);
+        });
+        request.set_onsuccess(Some(on_success.unchecked_ref()));
+        request.set_onerror(Some(on_error.unchecked_ref()));
+    });
+    JsFuture::from(promise).await.map_err(js_error)
+}
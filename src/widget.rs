@@ -0,0 +1,45 @@
+use crate::action::Action;
+use crate::app::MyApp;
+use crate::events::AppEvent;
+
+/// The atlas viewer as a widget, for egui apps that show it in one of their own panels
+/// instead of running it as a standalone eframe app.
+///
+/// It lays out its menu, neuron list, status bar and plots inside the given `Ui`; its
+/// windows (orthogonal views, settings) still float over the host window. The interface
+/// scale and the frame rate stay under the control of the host. The state can be
+/// persisted with serde like the app's.
+#[derive(Default, serde::Deserialize, serde::Serialize)]
+#[serde(transparent)]
+pub struct NeuropalLensWidget {
+    app: MyApp,
+}
+
+impl NeuropalLensWidget {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Call `callback` on each hover, selection and filter change; see [`MyApp::on_event`].
+    pub fn on_event(&mut self, callback: impl FnMut(&AppEvent) + 'static) {
+        self.app.on_event(callback);
+    }
+
+    /// Let the viewer add a font with Japanese glyphs to the host's fonts when Japanese is
+    /// selected. Off by default, as the fonts belong to the host, which should then install
+    /// one itself.
+    pub fn load_fonts(&mut self, load: bool) {
+        self.app.set_host_fonts(load);
+    }
+
+    /// Queue `action` to be applied at the end of the next frame, and ask for that frame.
+    pub fn dispatch(&mut self, action: Action) {
+        self.app.dispatch(action);
+    }
+
+    /// Show the viewer, filling the available space of `ui`.
+    pub fn ui(&mut self, ui: &mut egui::Ui) {
+        let ctx = ui.ctx().clone();
+        self.app.show(&ctx, Some(ui));
+    }
+}